@@ -0,0 +1,372 @@
+//! Command-line parsing for `rain-cli`.
+//!
+//! Options may appear anywhere after the program name, as `--name value` or
+//! `--name=value`. Connection settings fall back to `RAIN_*` environment
+//! variables so CI jobs can keep credentials out of the command line.
+
+use std::{path::PathBuf, time::Duration};
+
+pub const DEFAULT_SERVER: &str = "http://127.0.0.1:8080";
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const DEFAULT_UPLOAD_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const FLAGS: [&str; 3] = ["help", "no-wait", "quiet"];
+
+pub const USAGE: &str = "\
+用法: rain-cli [选项] <命令>
+
+命令:
+  login                                  登录并输出会话令牌
+  issue create <CODE> [--name NAME]      创建 Issue
+  upload <CODE> <FILE>...                上传文件并等待处理完成
+      [--archive-password PASSWORD] [--no-wait]
+      [--poll-interval SECONDS] [--timeout SECONDS]
+  search <CODE> <QUERY>                  检索 Issue 日志
+      [--mode content|regex|filename] [--from N] [--size N]
+      [--level LEVEL] [--min-level LEVEL] [--path TEXT]
+      [--start TIME] [--end TIME] [--param KEY=VALUE]...
+  download file <BUNDLE_HASH> <FILE_ID> [--output PATH]
+  download temp-result <ID> [--output PATH]
+      下载文件或临时结果；PATH 为 - 时写到标准输出
+
+选项:
+  --server URL        Rain 地址，默认 RAIN_SERVER 或 http://127.0.0.1:8080
+  --username NAME     登录用户名，默认 RAIN_USERNAME；密码只从 RAIN_PASSWORD 读取
+  --session TOKEN     已有会话令牌，默认 RAIN_SESSION；设置后不再登录
+  --quiet             不在标准错误输出进度
+  --help              显示本帮助
+
+成功时结果以 JSON 写到标准输出；失败时错误以 JSON 写到标准错误，
+退出码为 1，参数错误的退出码为 2。
+";
+
+#[derive(Debug, PartialEq)]
+pub struct Cli {
+    pub server: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub session: Option<String>,
+    pub quiet: bool,
+    pub command: Command,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Help,
+    Login,
+    CreateIssue {
+        code: String,
+        name: Option<String>,
+    },
+    Upload {
+        issue_code: String,
+        files: Vec<PathBuf>,
+        archive_password: Option<String>,
+        wait: bool,
+        poll_interval: Duration,
+        timeout: Duration,
+    },
+    Search {
+        issue_code: String,
+        /// Query string pairs sent as-is to `GET /issues/{code}/search`.
+        params: Vec<(String, String)>,
+    },
+    DownloadFile {
+        bundle_hash: String,
+        file_id: String,
+        output: Option<PathBuf>,
+    },
+    DownloadTempResult {
+        id: String,
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Default)]
+struct RawArgs {
+    positional: Vec<String>,
+    options: Vec<(String, Option<String>)>,
+}
+
+impl RawArgs {
+    fn split(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut raw = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let Some(option) = arg.strip_prefix("--") else {
+                raw.positional.push(arg);
+                continue;
+            };
+            if option.is_empty() {
+                raw.positional.extend(args.by_ref());
+                break;
+            }
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None if FLAGS.contains(&option) => (option.to_string(), None),
+                None => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("选项 --{option} 缺少取值"))?;
+                    (option.to_string(), Some(value))
+                }
+            };
+            if FLAGS.contains(&name.as_str()) && value.is_some() {
+                return Err(format!("选项 --{name} 不接受取值"));
+            }
+            raw.options.push((name, value));
+        }
+        Ok(raw)
+    }
+
+    fn flag(&mut self, name: &str) -> bool {
+        let before = self.options.len();
+        self.options.retain(|(option, _)| option != name);
+        self.options.len() != before
+    }
+
+    /// Removes every occurrence of `name`, returning their values in order.
+    fn values(&mut self, name: &str) -> Vec<String> {
+        let mut values = Vec::new();
+        self.options.retain(|(option, value)| {
+            if option != name {
+                return true;
+            }
+            values.extend(value.clone());
+            false
+        });
+        values
+    }
+
+    fn value(&mut self, name: &str) -> Result<Option<String>, String> {
+        let mut values = self.values(name);
+        if values.len() > 1 {
+            return Err(format!("选项 --{name} 只能指定一次"));
+        }
+        Ok(values.pop())
+    }
+
+    fn seconds(&mut self, name: &str, default: Duration) -> Result<Duration, String> {
+        match self.value(name)? {
+            None => Ok(default),
+            Some(value) => match value.parse::<u64>() {
+                Ok(seconds) if seconds > 0 => Ok(Duration::from_secs(seconds)),
+                _ => Err(format!("选项 --{name} 必须是正整数秒数")),
+            },
+        }
+    }
+
+    fn finish(self, expected: usize) -> Result<(), String> {
+        if let Some((name, _)) = self.options.first() {
+            return Err(format!("未知选项 --{name}"));
+        }
+        if self.positional.len() > expected {
+            return Err(format!("多余的参数 {}", self.positional[expected]));
+        }
+        Ok(())
+    }
+}
+
+/// Parses the arguments after the program name. `env` looks up connection
+/// defaults such as `RAIN_SERVER`.
+pub fn parse(
+    args: impl IntoIterator<Item = String>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<Cli, String> {
+    let mut raw = RawArgs::split(args)?;
+    let non_empty = |value: Option<String>| value.filter(|value| !value.trim().is_empty());
+    let server = non_empty(raw.value("server")?)
+        .or_else(|| non_empty(env("RAIN_SERVER")))
+        .unwrap_or_else(|| DEFAULT_SERVER.to_string());
+    let username = non_empty(raw.value("username")?).or_else(|| non_empty(env("RAIN_USERNAME")));
+    let session = non_empty(raw.value("session")?).or_else(|| non_empty(env("RAIN_SESSION")));
+    let password = env("RAIN_PASSWORD").filter(|value| !value.is_empty());
+    let quiet = raw.flag("quiet");
+    let help = raw.flag("help");
+
+    let command = if help || raw.positional.is_empty() {
+        Command::Help
+    } else {
+        parse_command(&mut raw)?
+    };
+    Ok(Cli {
+        server,
+        username,
+        password,
+        session,
+        quiet,
+        command,
+    })
+}
+
+fn parse_command(raw: &mut RawArgs) -> Result<Command, String> {
+    let positional = raw.positional.clone();
+    let words: Vec<&str> = positional.iter().map(String::as_str).collect();
+    let (command, consumed) = match words.as_slice() {
+        ["login", ..] => (Command::Login, 1),
+        ["issue", "create", code, ..] => (
+            Command::CreateIssue {
+                code: code.to_string(),
+                name: raw.value("name")?,
+            },
+            3,
+        ),
+        ["upload", issue_code, files @ ..] if !files.is_empty() => {
+            let wait = !raw.flag("no-wait");
+            (
+                Command::Upload {
+                    issue_code: issue_code.to_string(),
+                    files: files.iter().map(PathBuf::from).collect(),
+                    archive_password: raw.value("archive-password")?,
+                    wait,
+                    poll_interval: raw.seconds("poll-interval", DEFAULT_POLL_INTERVAL)?,
+                    timeout: raw.seconds("timeout", DEFAULT_UPLOAD_TIMEOUT)?,
+                },
+                words.len(),
+            )
+        }
+        ["search", issue_code, query, ..] => (
+            Command::Search {
+                issue_code: issue_code.to_string(),
+                params: search_params(raw, query)?,
+            },
+            3,
+        ),
+        ["download", "file", bundle_hash, file_id, ..] => (
+            Command::DownloadFile {
+                bundle_hash: bundle_hash.to_string(),
+                file_id: file_id.to_string(),
+                output: raw.value("output")?.map(PathBuf::from),
+            },
+            4,
+        ),
+        ["download", "temp-result", id, ..] => (
+            Command::DownloadTempResult {
+                id: id.to_string(),
+                output: raw.value("output")?.map(PathBuf::from),
+            },
+            3,
+        ),
+        _ => return Err(format!("无法识别的命令: {}", words.join(" "))),
+    };
+    std::mem::take(raw).finish(consumed)?;
+    Ok(command)
+}
+
+/// Maps the search shorthands onto the endpoint's query names; `--param`
+/// passes any other parameter through unchanged.
+fn search_params(raw: &mut RawArgs, query: &str) -> Result<Vec<(String, String)>, String> {
+    let mut params = vec![("q".to_string(), query.to_string())];
+    for (option, param) in [
+        ("mode", "mode"),
+        ("from", "from"),
+        ("size", "size"),
+        ("level", "level"),
+        ("min-level", "min_level"),
+        ("path", "path_like"),
+        ("start", "start"),
+        ("end", "end"),
+    ] {
+        if let Some(value) = raw.value(option)? {
+            params.push((param.to_string(), value));
+        }
+    }
+    for pair in raw.values("param") {
+        let (key, value) = pair
+            .split_once('=')
+            .filter(|(key, _)| !key.is_empty())
+            .ok_or_else(|| format!("--param 需要 KEY=VALUE 形式: {pair}"))?;
+        params.push((key.to_string(), value.to_string()));
+    }
+    Ok(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Cli, String> {
+        parse(args.iter().map(|arg| arg.to_string()), |name| match name {
+            "RAIN_SERVER" => Some("http://rain.test".into()),
+            "RAIN_PASSWORD" => Some("secret".into()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn connection_settings_fall_back_to_the_environment() {
+        let cli = parse_args(&["login", "--username=alice"]).unwrap();
+        assert_eq!(cli.server, "http://rain.test");
+        assert_eq!(cli.username.as_deref(), Some("alice"));
+        assert_eq!(cli.password.as_deref(), Some("secret"));
+        assert_eq!(cli.command, Command::Login);
+
+        let cli = parse_args(&["--server", "http://other", "--quiet"]).unwrap();
+        assert_eq!(cli.server, "http://other");
+        assert!(cli.quiet);
+        assert_eq!(cli.command, Command::Help);
+    }
+
+    #[test]
+    fn upload_collects_files_and_wait_options() {
+        let cli = parse_args(&[
+            "upload",
+            "BUG-1",
+            "a.log",
+            "--no-wait",
+            "b.zip",
+            "--timeout",
+            "60",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Upload {
+                issue_code: "BUG-1".into(),
+                files: vec!["a.log".into(), "b.zip".into()],
+                archive_password: None,
+                wait: false,
+                poll_interval: DEFAULT_POLL_INTERVAL,
+                timeout: Duration::from_secs(60),
+            }
+        );
+        assert!(parse_args(&["upload", "BUG-1"]).is_err());
+        assert!(parse_args(&["upload", "BUG-1", "a.log", "--timeout", "0"]).is_err());
+    }
+
+    #[test]
+    fn search_maps_shorthands_and_passes_raw_params() {
+        let cli = parse_args(&[
+            "search",
+            "BUG-1",
+            "timeout",
+            "--min-level",
+            "WARN",
+            "--param",
+            "before=2",
+            "--path=app",
+        ])
+        .unwrap();
+        let Command::Search { params, .. } = cli.command else {
+            panic!("expected search");
+        };
+        assert_eq!(
+            params,
+            [
+                ("q".to_string(), "timeout".to_string()),
+                ("min_level".into(), "WARN".into()),
+                ("path_like".into(), "app".into()),
+                ("before".into(), "2".into()),
+            ]
+        );
+        assert!(parse_args(&["search", "BUG-1", "x", "--param", "=1"]).is_err());
+    }
+
+    #[test]
+    fn unknown_options_and_extra_arguments_are_rejected() {
+        assert!(parse_args(&["login", "--nope", "1"]).is_err());
+        assert!(parse_args(&["issue", "create", "A", "B"]).is_err());
+        assert!(parse_args(&["download", "file", "hash"]).is_err());
+        assert!(parse_args(&["issue", "create", "A", "--name"]).is_err());
+        assert!(parse_args(&["login", "--quiet=yes"]).is_err());
+    }
+}
@@ -0,0 +1,385 @@
+//! Thin HTTP client over the Rain `/api` routes used by `rain-cli`.
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use actix_web::http::header::{ContentDisposition, HeaderValue};
+use futures_util::StreamExt;
+use reqwest::{
+    Method, RequestBuilder, Response, StatusCode, Url,
+    header::{CONTENT_DISPOSITION, COOKIE, SET_COOKIE},
+    multipart::{Form, Part},
+};
+use serde_json::{Value, json};
+use tokio::{fs, io::AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+
+const SESSION_COOKIE_NAME: &str = "rain_session";
+
+#[derive(Debug, thiserror::Error)]
+pub enum CliError {
+    #[error("{0}")]
+    Usage(String),
+    #[error("{message}")]
+    Api {
+        status: u16,
+        code: String,
+        message: String,
+    },
+    /// An upload task that failed on the server or did not finish in time.
+    #[error("{message}")]
+    Task { code: String, message: String },
+    #[error("请求失败: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("{path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+impl CliError {
+    fn io(path: &Path, source: std::io::Error) -> Self {
+        Self::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        match self {
+            Self::Usage(_) => "USAGE",
+            Self::Api { code, .. } | Self::Task { code, .. } => code,
+            Self::Http(_) => "HTTP_ERROR",
+            Self::Io { .. } => "IO_ERROR",
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let status = match self {
+            Self::Api { status, .. } => Some(*status),
+            _ => None,
+        };
+        json!({ "error": { "code": self.code(), "message": self.to_string(), "status": status } })
+    }
+}
+
+/// Running byte count of an upload, reported through the callback after
+/// every chunk read from disk.
+pub type ProgressFn = Arc<dyn Fn(u64, u64) + Send + Sync>;
+
+pub struct RainClient {
+    http: reqwest::Client,
+    base: Url,
+    session: Option<String>,
+}
+
+impl RainClient {
+    pub fn new(server: &str) -> Result<Self, CliError> {
+        let base = Url::parse(server)
+            .ok()
+            .filter(|url| !url.cannot_be_a_base())
+            .ok_or_else(|| CliError::Usage(format!("无效的服务地址: {server}")))?;
+        Ok(Self {
+            http: reqwest::Client::builder()
+                .user_agent(concat!("rain-cli/", env!("CARGO_PKG_VERSION")))
+                .build()?,
+            base,
+            session: None,
+        })
+    }
+
+    pub fn set_session(&mut self, token: String) {
+        self.session = Some(token);
+    }
+
+    fn endpoint(&self, segments: &[&str]) -> Url {
+        let mut url = self.base.clone();
+        url.path_segments_mut()
+            .expect("base URL was checked in RainClient::new")
+            .pop_if_empty()
+            .push("api")
+            .extend(segments);
+        url
+    }
+
+    fn request(&self, method: Method, segments: &[&str]) -> RequestBuilder {
+        let request = self.http.request(method, self.endpoint(segments));
+        match &self.session {
+            Some(token) => request.header(COOKIE, format!("{SESSION_COOKIE_NAME}={token}")),
+            None => request,
+        }
+    }
+
+    /// Logs in and keeps the session for later requests. Returns the session
+    /// token together with the signed-in user.
+    pub async fn login(&mut self, username: &str, password: &str) -> Result<Value, CliError> {
+        let response = self
+            .request(Method::POST, &["auth", "login"])
+            .json(&json!({ "username": username, "password": password }))
+            .send()
+            .await?;
+        let response = check(response).await?;
+        let token = response
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find_map(session_token_from_cookie)
+            .ok_or_else(|| CliError::Api {
+                status: response.status().as_u16(),
+                code: "SESSION_MISSING".into(),
+                message: "登录响应中没有会话 Cookie".into(),
+            })?;
+        let user: Value = response.json().await?;
+        self.session = Some(token.clone());
+        Ok(json!({ "session": token, "user": user }))
+    }
+
+    pub async fn create_issue(&self, code: &str, name: Option<&str>) -> Result<Value, CliError> {
+        let response = self
+            .request(Method::POST, &["issues"])
+            .json(&json!({ "code": code, "name": name }))
+            .send()
+            .await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    /// Streams `files` as one multipart upload and returns the queued task.
+    pub async fn upload(
+        &self,
+        issue_code: &str,
+        files: &[PathBuf],
+        archive_password: Option<&str>,
+        progress: ProgressFn,
+    ) -> Result<Value, CliError> {
+        let mut sizes = Vec::with_capacity(files.len());
+        for path in files {
+            let metadata = fs::metadata(path)
+                .await
+                .map_err(|error| CliError::io(path, error))?;
+            if !metadata.is_file() {
+                return Err(CliError::Usage(format!("不是普通文件: {}", path.display())));
+            }
+            sizes.push(metadata.len());
+        }
+        let total: u64 = sizes.iter().sum();
+        let sent = Arc::new(AtomicU64::new(0));
+
+        let mut form = Form::new();
+        if let Some(password) = archive_password {
+            form = form.text("archive_password", password.to_string());
+        }
+        for (path, size) in files.iter().zip(sizes) {
+            let file = fs::File::open(path)
+                .await
+                .map_err(|error| CliError::io(path, error))?;
+            let sent = sent.clone();
+            let progress = progress.clone();
+            let stream = ReaderStream::new(file).inspect(move |chunk| {
+                if let Ok(chunk) = chunk {
+                    let done =
+                        sent.fetch_add(chunk.len() as u64, Ordering::Relaxed) + chunk.len() as u64;
+                    progress(done, total);
+                }
+            });
+            let filename = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "upload.log".into());
+            let part = Part::stream_with_length(reqwest::Body::wrap_stream(stream), size)
+                .file_name(filename);
+            form = form.part("files", part);
+        }
+
+        let response = self
+            .request(Method::POST, &["issues", issue_code, "uploads"])
+            .multipart(form)
+            .send()
+            .await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    pub async fn upload_task(&self, task_id: &str) -> Result<Value, CliError> {
+        let response = self
+            .request(Method::GET, &["uploads", task_id])
+            .send()
+            .await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    pub async fn search(
+        &self,
+        issue_code: &str,
+        params: &[(String, String)],
+    ) -> Result<Value, CliError> {
+        let response = self
+            .request(Method::GET, &["issues", issue_code, "search"])
+            .query(params)
+            .send()
+            .await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    /// Downloads `segments` to `output`, to the server-suggested file name in
+    /// the working directory when `output` is `None`, or to stdout for `-`.
+    /// Files are written under a `.part` name and renamed once complete.
+    pub async fn download(
+        &self,
+        segments: &[&str],
+        output: Option<&Path>,
+    ) -> Result<Value, CliError> {
+        let response = check(self.request(Method::GET, segments).send().await?).await?;
+        let target = match output {
+            Some(path) => path.to_path_buf(),
+            None => PathBuf::from(
+                response
+                    .headers()
+                    .get(CONTENT_DISPOSITION)
+                    .and_then(|value| attachment_filename(value.as_bytes()))
+                    .unwrap_or_else(|| "download".into()),
+            ),
+        };
+        let mut body = response.bytes_stream();
+
+        if target == Path::new("-") {
+            let mut stdout = std::io::stdout().lock();
+            let mut bytes = 0_u64;
+            while let Some(chunk) = body.next().await {
+                let chunk = chunk?;
+                bytes += chunk.len() as u64;
+                stdout
+                    .write_all(&chunk)
+                    .map_err(|error| CliError::io(&target, error))?;
+            }
+            stdout
+                .flush()
+                .map_err(|error| CliError::io(&target, error))?;
+            return Ok(json!({ "path": "-", "bytes": bytes }));
+        }
+
+        let mut partial_name = target.file_name().unwrap_or_default().to_os_string();
+        partial_name.push(".part");
+        let partial = target.with_file_name(partial_name);
+        let written = async {
+            let mut file = fs::File::create(&partial)
+                .await
+                .map_err(|error| CliError::io(&partial, error))?;
+            let mut bytes = 0_u64;
+            while let Some(chunk) = body.next().await {
+                let chunk = chunk?;
+                bytes += chunk.len() as u64;
+                file.write_all(&chunk)
+                    .await
+                    .map_err(|error| CliError::io(&partial, error))?;
+            }
+            file.sync_all()
+                .await
+                .map_err(|error| CliError::io(&partial, error))?;
+            fs::rename(&partial, &target)
+                .await
+                .map_err(|error| CliError::io(&target, error))?;
+            Ok(bytes)
+        }
+        .await;
+        let bytes = match written {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                let _ = fs::remove_file(&partial).await;
+                return Err(error);
+            }
+        };
+        Ok(json!({ "path": target.display().to_string(), "bytes": bytes }))
+    }
+}
+
+/// Turns a non-success response into [`CliError::Api`], keeping the
+/// server's `{code, message}` body when there is one.
+async fn check(response: Response) -> Result<Response, CliError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let text = response.text().await.unwrap_or_default();
+    let body: Value = serde_json::from_str(&text).unwrap_or(Value::Null);
+    let field = |name: &str| body.get(name).and_then(Value::as_str).map(str::to_string);
+    Err(CliError::Api {
+        status: status.as_u16(),
+        code: field("code").unwrap_or_else(|| format!("HTTP_{}", status.as_u16())),
+        message: field("message").unwrap_or_else(|| fallback_message(status, &text)),
+    })
+}
+
+fn fallback_message(status: StatusCode, text: &str) -> String {
+    let text = text.trim();
+    if text.is_empty() {
+        status.to_string()
+    } else {
+        format!("{status}: {text}")
+    }
+}
+
+fn session_token_from_cookie(cookie: &str) -> Option<String> {
+    let (name, value) = cookie.split(';').next()?.split_once('=')?;
+    (name.trim() == SESSION_COOKIE_NAME && !value.trim().is_empty())
+        .then(|| value.trim().to_string())
+}
+
+/// File name suggested by a `Content-Disposition` header, reduced to its
+/// last path component so a download never leaves the working directory.
+fn attachment_filename(header: &[u8]) -> Option<String> {
+    let disposition = ContentDisposition::from_raw(&HeaderValue::from_bytes(header).ok()?).ok()?;
+    let name = disposition
+        .get_filename_ext()
+        .and_then(|ext| String::from_utf8(ext.value.clone()).ok())
+        .or_else(|| disposition.get_filename().map(str::to_string))?;
+    let name = name.rsplit(['/', '\\']).next()?.trim();
+    (!name.is_empty() && name != "." && name != "..").then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_cookie_value_is_extracted() {
+        assert_eq!(
+            session_token_from_cookie("rain_session=abc; HttpOnly; Path=/").as_deref(),
+            Some("abc")
+        );
+        assert_eq!(session_token_from_cookie("other=abc; Path=/"), None);
+        assert_eq!(session_token_from_cookie("rain_session=; Max-Age=0"), None);
+    }
+
+    #[test]
+    fn attachment_names_prefer_the_utf8_form_and_stay_local() {
+        assert_eq!(
+            attachment_filename(
+                b"attachment; filename*=UTF-8''%E6%97%A5%E5%BF%97.log; filename=\"__.log\""
+            )
+            .as_deref(),
+            Some("日志.log")
+        );
+        assert_eq!(
+            attachment_filename(b"attachment; filename=\"../../etc/passwd\"").as_deref(),
+            Some("passwd")
+        );
+        assert_eq!(attachment_filename(b"attachment; filename=\"..\""), None);
+    }
+
+    #[test]
+    fn api_paths_are_appended_to_the_server_prefix() {
+        let client = RainClient::new("http://rain.test/logs/").unwrap();
+        assert_eq!(
+            client.endpoint(&["issues", "A B", "search"]).as_str(),
+            "http://rain.test/logs/api/issues/A%20B/search"
+        );
+        assert!(RainClient::new("not a url").is_err());
+    }
+}
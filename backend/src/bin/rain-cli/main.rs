@@ -0,0 +1,181 @@
+//! `rain-cli`: scriptable client for a running Rain server.
+//!
+//! Every command prints one JSON document on stdout; progress goes to
+//! stderr so pipelines can parse the output directly.
+
+mod args;
+mod client;
+
+use std::{
+    process::ExitCode,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Instant,
+};
+
+use args::{Cli, Command, USAGE};
+use client::{CliError, ProgressFn, RainClient};
+use serde_json::Value;
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = match args::parse(std::env::args().skip(1), |name| std::env::var(name).ok()) {
+        Ok(cli) => cli,
+        Err(message) => {
+            eprintln!("rain-cli: {message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    if cli.command == Command::Help {
+        print!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    match run(cli).await {
+        Ok(output) => {
+            if let Some(output) = output {
+                println!("{output}");
+            }
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{}", error.to_json());
+            match error {
+                CliError::Usage(_) => ExitCode::from(2),
+                _ => ExitCode::FAILURE,
+            }
+        }
+    }
+}
+
+/// Runs the command, returning the JSON to print. Downloads to stdout
+/// return `None` because the file content already went there.
+async fn run(cli: Cli) -> Result<Option<Value>, CliError> {
+    let mut client = RainClient::new(&cli.server)?;
+    let credentials = cli.username.as_deref().zip(cli.password.as_deref());
+    if cli.command == Command::Login {
+        let (username, password) = credentials.ok_or_else(|| {
+            CliError::Usage("login 需要 --username 或 RAIN_USERNAME，以及 RAIN_PASSWORD".into())
+        })?;
+        return client.login(username, password).await.map(Some);
+    }
+    match (cli.session, credentials) {
+        (Some(token), _) => client.set_session(token),
+        (None, Some((username, password))) => {
+            client.login(username, password).await?;
+        }
+        // Read-only routes also serve guests; the server rejects the rest.
+        (None, None) => {}
+    }
+
+    let output = match cli.command {
+        Command::Help | Command::Login => unreachable!("handled before authentication"),
+        Command::CreateIssue { code, name } => client.create_issue(&code, name.as_deref()).await?,
+        Command::Upload {
+            issue_code,
+            files,
+            archive_password,
+            wait,
+            poll_interval,
+            timeout,
+        } => {
+            let task = client
+                .upload(
+                    &issue_code,
+                    &files,
+                    archive_password.as_deref(),
+                    upload_progress(cli.quiet),
+                )
+                .await?;
+            if !wait {
+                return Ok(Some(task));
+            }
+            let task_id = task["task_id"].as_str().unwrap_or_default().to_string();
+            let started = Instant::now();
+            let mut last_stage = String::new();
+            loop {
+                let task = client.upload_task(&task_id).await?;
+                let stage = task["stage"].as_str().unwrap_or_default();
+                if !cli.quiet && stage != last_stage {
+                    eprintln!("处理阶段: {stage}");
+                    last_stage = stage.to_string();
+                }
+                match task["status"].as_str() {
+                    Some("READY") => break task,
+                    Some("FAILED") => {
+                        println!("{task}");
+                        return Err(CliError::Task {
+                            code: task["failure_code"]
+                                .as_str()
+                                .unwrap_or("UPLOAD_FAILED")
+                                .to_string(),
+                            message: task["failure_reason"]
+                                .as_str()
+                                .unwrap_or("上传处理失败")
+                                .to_string(),
+                        });
+                    }
+                    _ if started.elapsed() >= timeout => {
+                        println!("{task}");
+                        return Err(CliError::Task {
+                            code: "UPLOAD_WAIT_TIMEOUT".into(),
+                            message: format!(
+                                "等待上传处理超过 {} 秒，任务仍在进行: {task_id}",
+                                timeout.as_secs()
+                            ),
+                        });
+                    }
+                    _ => tokio::time::sleep(poll_interval).await,
+                }
+            }
+        }
+        Command::Search { issue_code, params } => client.search(&issue_code, &params).await?,
+        Command::DownloadFile {
+            bundle_hash,
+            file_id,
+            output,
+        } => {
+            let segments = ["files", "v1", &bundle_hash, "files", &file_id, "download"];
+            return download(&client, &segments, output.as_deref()).await;
+        }
+        Command::DownloadTempResult { id, output } => {
+            let segments = ["temp-results", &id, "download"];
+            return download(&client, &segments, output.as_deref()).await;
+        }
+    };
+    Ok(Some(output))
+}
+
+async fn download(
+    client: &RainClient,
+    segments: &[&str],
+    output: Option<&std::path::Path>,
+) -> Result<Option<Value>, CliError> {
+    let summary = client.download(segments, output).await?;
+    // The file body is the stdout payload, so report the summary on stderr.
+    if summary["path"] == "-" {
+        eprintln!("{summary}");
+        return Ok(None);
+    }
+    Ok(Some(summary))
+}
+
+/// Reports upload progress on stderr in 10% steps, which keeps CI logs
+/// short even for large files.
+fn upload_progress(quiet: bool) -> ProgressFn {
+    let reported = Arc::new(AtomicU64::new(0));
+    Arc::new(move |sent, total| {
+        if quiet || total == 0 {
+            return;
+        }
+        let step = sent.saturating_mul(10) / total;
+        if reported.fetch_max(step + 1, Ordering::Relaxed) <= step {
+            eprintln!(
+                "已上传 {}% ({sent}/{total} 字节)",
+                sent.saturating_mul(100) / total
+            );
+        }
+    })
+}
@@ -13,6 +13,8 @@ use uuid::Uuid;
 
 use crate::error::AppError;
 
+const BLOB_INDEX_DELETE_BATCH_SIZE: i64 = 500;
+
 pub type BlobReader = Pin<Box<dyn tokio::io::AsyncRead + Send>>;
 
 pub struct StoredBlob {
@@ -548,6 +550,10 @@ pub async fn garbage_collect_unreferenced_blobs_with_grace(
     store: &dyn BlobStore,
     grace_hours: u64,
 ) -> Result<u64, AppError> {
+    let released = release_unlinked_blob_indexes(pool).await?;
+    if released > 0 {
+        tracing::info!(released, "released unused blob indexes");
+    }
     let anomalies: Vec<(i64, String, String)> = sqlx::query_as(
         r#"
         SELECT id, storage_key, state
@@ -688,6 +694,56 @@ pub async fn garbage_collect_unreferenced_blobs_with_grace(
     Ok(removed)
 }
 
+/// Deletes shared index content that no file links to and no segment reads
+/// any more. Returns the number of blob indexes removed.
+pub async fn release_unlinked_blob_indexes(pool: &SqlitePool) -> Result<u64, AppError> {
+    let claimed: Vec<i64> = sqlx::query_scalar(
+        r#"
+        UPDATE blob_indexes SET state = 'DELETING'
+        WHERE state = 'DELETING'
+           OR (NOT EXISTS (SELECT 1 FROM files f WHERE f.blob_index_id = blob_indexes.id)
+               AND NOT EXISTS (
+                   SELECT 1 FROM log_segment_contents c
+                   JOIN log_segments s ON s.content_id = c.id
+                   WHERE c.blob_index_id = blob_indexes.id
+               ))
+        RETURNING id
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(AppError::Database)?;
+    let mut released = 0u64;
+    for id in claimed {
+        // Content and line offsets go in batches so one large index does not
+        // hold the write lock; the FTS entries follow through triggers.
+        for statement in [
+            "DELETE FROM log_segment_contents WHERE id IN (SELECT id FROM log_segment_contents WHERE blob_index_id = ? LIMIT ?)",
+            "DELETE FROM blob_line_offsets WHERE rowid IN (SELECT rowid FROM blob_line_offsets WHERE blob_index_id = ? LIMIT ?)",
+        ] {
+            loop {
+                let deleted = sqlx::query(statement)
+                    .bind(id)
+                    .bind(BLOB_INDEX_DELETE_BATCH_SIZE)
+                    .execute(pool)
+                    .await
+                    .map_err(AppError::Database)?
+                    .rows_affected();
+                if deleted == 0 {
+                    break;
+                }
+            }
+        }
+        released += sqlx::query("DELETE FROM blob_indexes WHERE id = ? AND state = 'DELETING'")
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(AppError::Database)?
+            .rows_affected();
+    }
+    Ok(released)
+}
+
 pub fn spawn_blob_gc(
     pool: SqlitePool,
     store: std::sync::Arc<dyn BlobStore>,
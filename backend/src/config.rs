@@ -1,4 +1,4 @@
-use std::{env, path::PathBuf};
+use std::{env, path::PathBuf, time::Duration};
 
 use base64::{Engine as _, engine::general_purpose::STANDARD};
 
@@ -196,6 +196,36 @@ impl Default for SkillRunLimits {
     }
 }
 
+/// Server-side directory scanned for log files to ingest. Files are picked up
+/// from `<root>/<ISSUE_CODE>/`.
+#[derive(Debug, Clone)]
+pub struct DropFolderConfig {
+    pub root: PathBuf,
+    pub poll_interval: Duration,
+    /// How long a file must go unmodified before it counts as complete.
+    pub settle: Duration,
+}
+
+impl DropFolderConfig {
+    fn from_env() -> Result<Option<Self>, AppError> {
+        let Some(root) = optional_env("RAIN_DROP_FOLDER")? else {
+            return Ok(None);
+        };
+        let poll_seconds: u64 = env_value("RAIN_DROP_FOLDER_POLL_SECONDS", 30)?;
+        if poll_seconds == 0 {
+            return Err(AppError::Config(
+                "RAIN_DROP_FOLDER_POLL_SECONDS must be positive".into(),
+            ));
+        }
+        let settle_seconds: u64 = env_value("RAIN_DROP_FOLDER_SETTLE_SECONDS", 60)?;
+        Ok(Some(Self {
+            root: PathBuf::from(root),
+            poll_interval: Duration::from_secs(poll_seconds),
+            settle: Duration::from_secs(settle_seconds),
+        }))
+    }
+}
+
 #[derive(Clone)]
 pub struct BootstrapAdminConfig {
     pub username: String,
@@ -289,6 +319,12 @@ pub struct ApiConfig {
     pub concurrent_line_reads_per_client: usize,
     pub default_search_results: i64,
     pub max_search_results: i64,
+    pub max_regex_scan_bytes: u64,
+    pub max_export_scan_bytes: u64,
+    pub max_global_search_scan_bytes: u64,
+    pub max_histogram_scan_bytes: u64,
+    pub max_template_scan_bytes: u64,
+    pub max_diff_file_size: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -328,6 +364,12 @@ impl Default for ApiConfig {
             concurrent_line_reads_per_client: 2,
             default_search_results: 50,
             max_search_results: 100,
+            max_regex_scan_bytes: 256 * MIB,
+            max_export_scan_bytes: GIB,
+            max_global_search_scan_bytes: 512 * MIB,
+            max_histogram_scan_bytes: 512 * MIB,
+            max_template_scan_bytes: 256 * MIB,
+            max_diff_file_size: 8 * MIB,
         }
     }
 }
@@ -560,6 +602,30 @@ impl AppLimits {
                     "RAIN_API_MAX_SEARCH_RESULTS",
                     defaults.api.max_search_results,
                 )?,
+                max_regex_scan_bytes: env_size(
+                    "RAIN_API_MAX_REGEX_SCAN_BYTES",
+                    defaults.api.max_regex_scan_bytes,
+                )?,
+                max_export_scan_bytes: env_size(
+                    "RAIN_API_MAX_EXPORT_SCAN_BYTES",
+                    defaults.api.max_export_scan_bytes,
+                )?,
+                max_global_search_scan_bytes: env_size(
+                    "RAIN_API_MAX_GLOBAL_SEARCH_SCAN_BYTES",
+                    defaults.api.max_global_search_scan_bytes,
+                )?,
+                max_histogram_scan_bytes: env_size(
+                    "RAIN_API_MAX_HISTOGRAM_SCAN_BYTES",
+                    defaults.api.max_histogram_scan_bytes,
+                )?,
+                max_template_scan_bytes: env_size(
+                    "RAIN_API_MAX_TEMPLATE_SCAN_BYTES",
+                    defaults.api.max_template_scan_bytes,
+                )?,
+                max_diff_file_size: env_size(
+                    "RAIN_API_MAX_DIFF_FILE_SIZE",
+                    defaults.api.max_diff_file_size,
+                )?,
             },
             temp_results: TempResultConfig {
                 max_result_size: env_size(
@@ -645,6 +711,27 @@ impl AppLimits {
             "RAIN_API_DEFAULT_SEARCH_RESULTS"
         );
         positive!(self.api.max_search_results, "RAIN_API_MAX_SEARCH_RESULTS");
+        positive!(
+            self.api.max_regex_scan_bytes,
+            "RAIN_API_MAX_REGEX_SCAN_BYTES"
+        );
+        positive!(
+            self.api.max_export_scan_bytes,
+            "RAIN_API_MAX_EXPORT_SCAN_BYTES"
+        );
+        positive!(
+            self.api.max_global_search_scan_bytes,
+            "RAIN_API_MAX_GLOBAL_SEARCH_SCAN_BYTES"
+        );
+        positive!(
+            self.api.max_histogram_scan_bytes,
+            "RAIN_API_MAX_HISTOGRAM_SCAN_BYTES"
+        );
+        positive!(
+            self.api.max_template_scan_bytes,
+            "RAIN_API_MAX_TEMPLATE_SCAN_BYTES"
+        );
+        positive!(self.api.max_diff_file_size, "RAIN_API_MAX_DIFF_FILE_SIZE");
         positive!(
             self.temp_results.max_result_size,
             "RAIN_TEMP_RESULT_MAX_SIZE"
@@ -758,6 +845,7 @@ pub struct AppConfig {
     pub ai_provider: AiProviderEnv,
     pub skill_run_limits: SkillRunLimits,
     pub bootstrap_admin: BootstrapAdminConfig,
+    pub drop_folder: Option<DropFolderConfig>,
 }
 
 impl AppConfig {
@@ -803,6 +891,7 @@ impl AppConfig {
         let limits = AppLimits::from_env()?;
         let auth = AuthConfig::from_env()?;
         let ai_provider = AiProviderEnv::from_env()?;
+        let drop_folder = DropFolderConfig::from_env()?;
         let bootstrap_admin = BootstrapAdminConfig {
             username: env::var("RAIN_BOOTSTRAP_ADMIN_USERNAME").unwrap_or_else(|_| "admin".into()),
             password: env::var("RAIN_BOOTSTRAP_ADMIN_PASSWORD").unwrap_or_default(),
@@ -827,6 +916,7 @@ impl AppConfig {
             ai_provider,
             skill_run_limits: SkillRunLimits::default(),
             bootstrap_admin,
+            drop_folder,
         })
     }
 }
@@ -978,6 +1068,15 @@ mod tests {
         assert_eq!(limits.api.max_line_page_bytes, 16 * 1024_u64.pow(2));
         assert_eq!(limits.api.concurrent_line_reads, 8);
         assert_eq!(limits.api.concurrent_line_reads_per_client, 2);
+        assert_eq!(limits.api.max_regex_scan_bytes, 256 * 1024_u64.pow(2));
+        assert_eq!(limits.api.max_export_scan_bytes, 1024_u64.pow(3));
+        assert_eq!(
+            limits.api.max_global_search_scan_bytes,
+            512 * 1024_u64.pow(2)
+        );
+        assert_eq!(limits.api.max_histogram_scan_bytes, 512 * 1024_u64.pow(2));
+        assert_eq!(limits.api.max_template_scan_bytes, 256 * 1024_u64.pow(2));
+        assert_eq!(limits.api.max_diff_file_size, 8 * 1024_u64.pow(2));
     }
 
     #[test]
@@ -18,6 +18,14 @@ use crate::error::AppError;
 pub const CLEANUP_BATCH_SIZE: u64 = 1_000;
 const LARGE_CLEANUP_CHECKPOINT_ROWS: u64 = 10_000;
 const LOG_SEGMENT_BACKFILL_BATCH_SIZE: i64 = 500;
+const LOG_SEGMENTS_FTS_TABLE: &str = r#"
+    CREATE VIRTUAL TABLE IF NOT EXISTS log_segments_fts USING fts5(
+        content,
+        content='log_segment_contents',
+        content_rowid='id',
+        tokenize='trigram'
+    )
+"#;
 static HEAVY_CLEANUP_WRITER: Lazy<Semaphore> = Lazy::new(|| Semaphore::new(1));
 static QUEUED_CLEANUPS: AtomicUsize = AtomicUsize::new(0);
 static ACTIVE_CLEANUPS: AtomicUsize = AtomicUsize::new(0);
@@ -147,6 +155,7 @@ pub struct BundleCleanupStats {
     pub line_offsets: CleanupPhaseStats,
     pub fts_segments: CleanupPhaseStats,
     pub segments: CleanupPhaseStats,
+    pub templates: CleanupPhaseStats,
     pub files: CleanupPhaseStats,
 }
 
@@ -159,7 +168,11 @@ struct InactiveCleanupLease<'a> {
 
 impl BundleCleanupStats {
     pub fn total_rows(self) -> u64 {
-        self.line_offsets.rows + self.fts_segments.rows + self.segments.rows + self.files.rows
+        self.line_offsets.rows
+            + self.fts_segments.rows
+            + self.segments.rows
+            + self.templates.rows
+            + self.files.rows
     }
 }
 
@@ -221,6 +234,16 @@ async fn cleanup_bundle_content_batched_inner(
         ));
     }
 
+    // Files being emptied batch by batch must not be copied from by new
+    // files of the same blob.
+    sqlx::query(
+        "UPDATE files SET blob_index_id = NULL WHERE bundle_id = ? AND blob_index_id IS NOT NULL",
+    )
+    .bind(bundle_id)
+    .execute(pool)
+    .await
+    .map_err(AppError::Database)?;
+
     let stats = BundleCleanupStats {
         line_offsets: delete_bundle_rows_in_batches(
             pool,
@@ -231,7 +254,8 @@ async fn cleanup_bundle_content_batched_inner(
             lease,
         )
         .await?,
-        // The external-content FTS index is maintained by log_segments triggers.
+        // The external-content FTS index follows log_segment_contents through
+        // triggers; shared content stays until its blob index is collected.
         fts_segments: CleanupPhaseStats::default(),
         segments: delete_bundle_rows_in_batches(
             pool,
@@ -242,6 +266,15 @@ async fn cleanup_bundle_content_batched_inner(
             lease,
         )
         .await?,
+        templates: delete_bundle_rows_in_batches(
+            pool,
+            bundle_id,
+            batch_size,
+            "log_templates",
+            "DELETE FROM log_templates WHERE rowid IN (SELECT rowid FROM log_templates WHERE bundle_id = ? LIMIT ?)",
+            lease,
+        )
+        .await?,
         files: delete_bundle_rows_in_batches(
             pool,
             bundle_id,
@@ -518,6 +551,7 @@ async fn reset_schema(pool: &SqlitePool) -> Result<(), AppError> {
         "DROP TABLE IF EXISTS skill_reviews",
         "DROP TABLE IF EXISTS user_skills",
         "DROP TABLE IF EXISTS ai_provider_settings",
+        "DROP TABLE IF EXISTS timestamp_formats",
         "DROP TABLE IF EXISTS admin_audit_logs",
         "DROP TABLE IF EXISTS system_settings",
         "DROP TABLE IF EXISTS saved_searches",
@@ -526,8 +560,14 @@ async fn reset_schema(pool: &SqlitePool) -> Result<(), AppError> {
         "DROP TABLE IF EXISTS temp_results",
         "DROP TABLE IF EXISTS rain_ready_probe",
         "DROP TABLE IF EXISTS log_line_offsets",
+        "DROP TABLE IF EXISTS blob_line_offsets",
+        "DROP TABLE IF EXISTS log_event_spans",
+        "DROP TABLE IF EXISTS log_templates",
         "DROP TABLE IF EXISTS log_segments",
+        "DROP TABLE IF EXISTS log_segment_contents",
+        "DROP TABLE IF EXISTS event_time_settings",
         "DROP TABLE IF EXISTS files",
+        "DROP TABLE IF EXISTS blob_indexes",
         "DROP TABLE IF EXISTS blobs",
         "DROP TABLE IF EXISTS bundles",
         "DROP TABLE IF EXISTS issues",
@@ -621,6 +661,16 @@ async fn create_schema(pool: &SqlitePool) -> Result<(), AppError> {
         )
         "#,
         r#"
+        CREATE TABLE IF NOT EXISTS timestamp_formats (
+            id TEXT PRIMARY KEY,
+            name TEXT COLLATE NOCASE NOT NULL UNIQUE,
+            pattern TEXT NOT NULL,
+            format TEXT NOT NULL,
+            created_by_user_id TEXT REFERENCES users(id) ON DELETE SET NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+        r#"
         CREATE TABLE IF NOT EXISTS issues (
             code TEXT PRIMARY KEY,
             name TEXT NOT NULL,
@@ -653,7 +703,20 @@ async fn create_schema(pool: &SqlitePool) -> Result<(), AppError> {
             uploader_user_id TEXT REFERENCES users(id) ON DELETE SET NULL,
             size_bytes INTEGER,
             content_size_bytes INTEGER NOT NULL DEFAULT 0 CHECK (content_size_bytes >= 0),
-            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            event_time_rekey_pending INTEGER NOT NULL DEFAULT 0,
+            template_state TEXT NOT NULL DEFAULT 'NONE',
+            source_path TEXT
+        )
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS event_time_settings (
+            bundle_id TEXT NOT NULL REFERENCES bundles(id) ON DELETE CASCADE,
+            file_id INTEGER REFERENCES files(id) ON DELETE CASCADE,
+            utc_offset_minutes INTEGER NOT NULL CHECK (utc_offset_minutes BETWEEN -840 AND 840),
+            clock_skew_ms INTEGER NOT NULL DEFAULT 0,
+            updated_by_user_id TEXT REFERENCES users(id) ON DELETE SET NULL,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
         )
         "#,
         r#"
@@ -670,6 +733,17 @@ async fn create_schema(pool: &SqlitePool) -> Result<(), AppError> {
         )
         "#,
         r#"
+        CREATE TABLE IF NOT EXISTS blob_indexes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            blob_id INTEGER NOT NULL REFERENCES blobs(id) ON DELETE CASCADE,
+            index_key TEXT NOT NULL,
+            state TEXT NOT NULL DEFAULT 'BUILDING' CHECK (state IN ('BUILDING', 'READY', 'DELETING')),
+            reference_sensitive INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE (blob_id, index_key)
+        )
+        "#,
+        r#"
         CREATE TABLE IF NOT EXISTS files (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             bundle_id TEXT NOT NULL REFERENCES bundles(id) ON DELETE CASCADE,
@@ -684,22 +758,35 @@ async fn create_schema(pool: &SqlitePool) -> Result<(), AppError> {
             status TEXT,
             meta TEXT,
             created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            event_time_shift_ms INTEGER NOT NULL DEFAULT 0,
+            blob_index_id INTEGER REFERENCES blob_indexes(id),
             CONSTRAINT files_bundle_path UNIQUE (bundle_id, path)
         )
         "#,
         r#"
-        CREATE TABLE IF NOT EXISTS log_segments (
+        CREATE TABLE IF NOT EXISTS log_segment_contents (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
-            bundle_id TEXT NOT NULL REFERENCES bundles(id) ON DELETE CASCADE,
-            file_id INTEGER REFERENCES files(id) ON DELETE CASCADE,
+            blob_index_id INTEGER REFERENCES blob_indexes(id) ON DELETE CASCADE,
             timeline TEXT,
-            content TEXT NOT NULL,
             line_offset INTEGER,
             line_end INTEGER,
             chunk_index INTEGER,
+            level_mask INTEGER,
+            content TEXT NOT NULL
+        )
+        "#,
+        // Only what differs between files sharing content: their bundle and
+        // their shifted event times.
+        r#"
+        CREATE TABLE IF NOT EXISTS log_segments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            bundle_id TEXT NOT NULL REFERENCES bundles(id) ON DELETE CASCADE,
+            file_id INTEGER REFERENCES files(id) ON DELETE CASCADE,
+            content_id INTEGER NOT NULL REFERENCES log_segment_contents(id),
             event_time_start_ms INTEGER,
             event_time_end_ms INTEGER,
             event_time_indexed INTEGER NOT NULL DEFAULT 0,
+            event_time_shift_ms INTEGER NOT NULL DEFAULT 0,
             created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
         )
         "#,
@@ -712,6 +799,40 @@ async fn create_schema(pool: &SqlitePool) -> Result<(), AppError> {
         )
         "#,
         r#"
+        CREATE TABLE IF NOT EXISTS blob_line_offsets (
+            blob_index_id INTEGER NOT NULL REFERENCES blob_indexes(id) ON DELETE CASCADE,
+            line_number INTEGER NOT NULL,
+            byte_offset INTEGER NOT NULL,
+            PRIMARY KEY (blob_index_id, line_number)
+        )
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS log_event_spans (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            segment_id INTEGER NOT NULL REFERENCES log_segments(id) ON DELETE CASCADE,
+            file_id INTEGER NOT NULL,
+            line_start INTEGER NOT NULL,
+            line_end INTEGER NOT NULL,
+            content_start INTEGER NOT NULL,
+            content_end INTEGER NOT NULL,
+            event_time_ms INTEGER,
+            event_time_shift_ms INTEGER NOT NULL DEFAULT 0
+        )
+        "#,
+        r#"
+        CREATE TABLE IF NOT EXISTS log_templates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            bundle_id TEXT NOT NULL REFERENCES bundles(id) ON DELETE CASCADE,
+            template_key TEXT NOT NULL,
+            template TEXT NOT NULL,
+            line_count INTEGER NOT NULL CHECK (line_count > 0),
+            level_mask INTEGER NOT NULL DEFAULT 0,
+            first_event_time_ms INTEGER,
+            last_event_time_ms INTEGER,
+            examples TEXT NOT NULL
+        )
+        "#,
+        r#"
         CREATE TABLE IF NOT EXISTS temp_results (
             id TEXT PRIMARY KEY,
             status TEXT NOT NULL DEFAULT 'ACTIVE' CHECK (status IN ('STAGING', 'ACTIVE', 'DELETING')),
@@ -812,35 +933,46 @@ async fn create_schema(pool: &SqlitePool) -> Result<(), AppError> {
             value INTEGER NOT NULL
         )
         "#,
+    ];
+
+    for statement in statements {
+        sqlx::query(statement)
+            .execute(pool)
+            .await
+            .map_err(AppError::Database)?;
+    }
+
+    split_log_segment_content(pool).await?;
+    // Segment text lives in log_segment_contents so files with identical
+    // blobs can share one copy of it and of its full-text index.
+    let search_statements = [
+        LOG_SEGMENTS_FTS_TABLE,
         r#"
-        CREATE VIRTUAL TABLE IF NOT EXISTS log_segments_fts USING fts5(
-            content,
-            content='log_segments',
-            content_rowid='id',
-            tokenize='trigram'
-        )
-        "#,
-        r#"
-        CREATE TRIGGER IF NOT EXISTS log_segments_fts_ai AFTER INSERT ON log_segments BEGIN
+        CREATE TRIGGER IF NOT EXISTS log_segment_contents_fts_ai AFTER INSERT ON log_segment_contents BEGIN
             INSERT INTO log_segments_fts(rowid, content) VALUES (new.id, new.content);
         END
         "#,
         r#"
-        CREATE TRIGGER IF NOT EXISTS log_segments_fts_ad AFTER DELETE ON log_segments BEGIN
+        CREATE TRIGGER IF NOT EXISTS log_segment_contents_fts_ad AFTER DELETE ON log_segment_contents BEGIN
             INSERT INTO log_segments_fts(log_segments_fts, rowid, content)
             VALUES ('delete', old.id, old.content);
         END
         "#,
         r#"
-        CREATE TRIGGER IF NOT EXISTS log_segments_fts_au AFTER UPDATE OF content ON log_segments BEGIN
+        CREATE TRIGGER IF NOT EXISTS log_segment_contents_fts_au AFTER UPDATE OF content ON log_segment_contents BEGIN
             INSERT INTO log_segments_fts(log_segments_fts, rowid, content)
             VALUES ('delete', old.id, old.content);
             INSERT INTO log_segments_fts(rowid, content) VALUES (new.id, new.content);
         END
         "#,
+        // Content outside a blob index belongs to exactly one segment.
+        r#"
+        CREATE TRIGGER IF NOT EXISTS log_segments_private_content_ad AFTER DELETE ON log_segments BEGIN
+            DELETE FROM log_segment_contents WHERE id = old.content_id AND blob_index_id IS NULL;
+        END
+        "#,
     ];
-
-    for statement in statements {
+    for statement in search_statements {
         sqlx::query(statement)
             .execute(pool)
             .await
@@ -849,8 +981,15 @@ async fn create_schema(pool: &SqlitePool) -> Result<(), AppError> {
 
     ensure_skill_run_optional_columns(pool).await?;
     ensure_log_segment_optional_columns(pool).await?;
+    ensure_event_time_shift_columns(pool).await?;
+    migrate_event_time_setting_scope(pool).await?;
+    ensure_bundle_template_state_column(pool).await?;
+    ensure_bundle_source_path_column(pool).await?;
+    ensure_file_blob_index_column(pool).await?;
     ensure_log_segment_event_time_indexes(pool).await?;
     backfill_log_segment_event_times(pool).await?;
+    ensure_log_segment_level_index(pool).await?;
+    backfill_log_segment_levels(pool).await?;
 
     let index_statements = [
         "CREATE UNIQUE INDEX IF NOT EXISTS idx_skill_runs_one_active_per_user ON skill_runs(user_id) WHERE status IN ('QUEUED', 'RUNNING')",
@@ -860,10 +999,20 @@ async fn create_schema(pool: &SqlitePool) -> Result<(), AppError> {
         "CREATE INDEX IF NOT EXISTS idx_files_parent ON files (parent_id)",
         "CREATE INDEX IF NOT EXISTS idx_files_bundle ON files (bundle_id)",
         "CREATE INDEX IF NOT EXISTS idx_files_path ON files (path)",
-        "CREATE INDEX IF NOT EXISTS idx_logs_bundle_timeline ON log_segments (bundle_id, timeline)",
-        "CREATE INDEX IF NOT EXISTS idx_logs_file_chunk ON log_segments (file_id, chunk_index)",
+        "CREATE INDEX IF NOT EXISTS idx_logs_bundle ON log_segments (bundle_id)",
+        "CREATE INDEX IF NOT EXISTS idx_logs_file_content ON log_segments (file_id, content_id)",
         "CREATE INDEX IF NOT EXISTS idx_logs_file_event_time ON log_segments (file_id, event_time_start_ms, event_time_end_ms)",
+        "CREATE INDEX IF NOT EXISTS idx_logs_content ON log_segments (content_id)",
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_event_time_settings_bundle_scope ON event_time_settings (bundle_id) WHERE file_id IS NULL",
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_event_time_settings_file_scope ON event_time_settings (file_id) WHERE file_id IS NOT NULL",
+        "CREATE INDEX IF NOT EXISTS idx_segment_contents_index ON log_segment_contents (blob_index_id, chunk_index)",
+        "CREATE INDEX IF NOT EXISTS idx_files_blob_index ON files (blob_index_id)",
         "CREATE INDEX IF NOT EXISTS idx_line_offsets_file_line ON log_line_offsets (file_id, line_number)",
+        "CREATE INDEX IF NOT EXISTS idx_event_spans_segment ON log_event_spans (segment_id, content_start)",
+        "CREATE INDEX IF NOT EXISTS idx_event_spans_file_line ON log_event_spans (file_id, line_start)",
+        "CREATE INDEX IF NOT EXISTS idx_log_templates_bundle ON log_templates (bundle_id, line_count DESC)",
+        "CREATE INDEX IF NOT EXISTS idx_log_templates_key ON log_templates (template_key, bundle_id)",
+        "CREATE INDEX IF NOT EXISTS idx_bundles_template_state ON bundles (template_state) WHERE template_state = 'PENDING'",
         "CREATE INDEX IF NOT EXISTS idx_temp_results_expiry ON temp_results (expires_at)",
         "CREATE INDEX IF NOT EXISTS idx_user_sessions_user ON user_sessions (user_id)",
         "CREATE INDEX IF NOT EXISTS idx_user_sessions_expiry ON user_sessions (expires_at)",
@@ -950,6 +1099,10 @@ async fn ensure_log_segment_optional_columns(pool: &SqlitePool) -> Result<(), Ap
             "event_time_indexed",
             "ALTER TABLE log_segments ADD COLUMN event_time_indexed INTEGER NOT NULL DEFAULT 0",
         ),
+        (
+            "event_time_shift_ms",
+            "ALTER TABLE log_segments ADD COLUMN event_time_shift_ms INTEGER NOT NULL DEFAULT 0",
+        ),
     ];
     for (column, statement) in columns {
         if !existing.iter().any(|name| name == column) {
@@ -962,6 +1115,228 @@ async fn ensure_log_segment_optional_columns(pool: &SqlitePool) -> Result<(), Ap
     Ok(())
 }
 
+async fn ensure_event_time_shift_columns(pool: &SqlitePool) -> Result<(), AppError> {
+    let columns = [
+        (
+            "files",
+            "event_time_shift_ms",
+            "ALTER TABLE files ADD COLUMN event_time_shift_ms INTEGER NOT NULL DEFAULT 0",
+        ),
+        (
+            "bundles",
+            "event_time_rekey_pending",
+            "ALTER TABLE bundles ADD COLUMN event_time_rekey_pending INTEGER NOT NULL DEFAULT 0",
+        ),
+    ];
+    for (table, column, statement) in columns {
+        let existing: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?)")
+            .bind(table)
+            .fetch_all(pool)
+            .await
+            .map_err(AppError::Database)?;
+        if !existing.iter().any(|name| name == column) {
+            sqlx::query(statement)
+                .execute(pool)
+                .await
+                .map_err(AppError::Database)?;
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the `scope_file_id` sentinel column (0 for the whole bundle) with a
+/// nullable `file_id` foreign key, dropping settings of files that no longer exist.
+async fn migrate_event_time_setting_scope(pool: &SqlitePool) -> Result<(), AppError> {
+    let existing: Vec<String> =
+        sqlx::query_scalar("SELECT name FROM pragma_table_info('event_time_settings')")
+            .fetch_all(pool)
+            .await
+            .map_err(AppError::Database)?;
+    if !existing.iter().any(|name| name == "scope_file_id") {
+        return Ok(());
+    }
+    let mut tx = pool.begin().await.map_err(AppError::Database)?;
+    let statements = [
+        r#"
+        CREATE TABLE event_time_settings_new (
+            bundle_id TEXT NOT NULL REFERENCES bundles(id) ON DELETE CASCADE,
+            file_id INTEGER REFERENCES files(id) ON DELETE CASCADE,
+            utc_offset_minutes INTEGER NOT NULL CHECK (utc_offset_minutes BETWEEN -840 AND 840),
+            clock_skew_ms INTEGER NOT NULL DEFAULT 0,
+            updated_by_user_id TEXT REFERENCES users(id) ON DELETE SET NULL,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+        "INSERT INTO event_time_settings_new (bundle_id, file_id, utc_offset_minutes, clock_skew_ms, updated_by_user_id, updated_at) SELECT s.bundle_id, NULLIF(s.scope_file_id, 0), s.utc_offset_minutes, s.clock_skew_ms, s.updated_by_user_id, s.updated_at FROM event_time_settings s WHERE s.scope_file_id = 0 OR EXISTS (SELECT 1 FROM files f WHERE f.id = s.scope_file_id AND f.bundle_id = s.bundle_id)",
+        "DROP TABLE event_time_settings",
+        "ALTER TABLE event_time_settings_new RENAME TO event_time_settings",
+    ];
+    for statement in statements {
+        sqlx::query(statement)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::Database)?;
+    }
+    tx.commit().await.map_err(AppError::Database)
+}
+
+async fn ensure_bundle_template_state_column(pool: &SqlitePool) -> Result<(), AppError> {
+    let existing: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info('bundles')")
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::Database)?;
+    if !existing.iter().any(|name| name == "template_state") {
+        sqlx::query("ALTER TABLE bundles ADD COLUMN template_state TEXT NOT NULL DEFAULT 'NONE'")
+            .execute(pool)
+            .await
+            .map_err(AppError::Database)?;
+    }
+    Ok(())
+}
+
+/// Server-side path a drop-folder bundle was ingested from.
+async fn ensure_bundle_source_path_column(pool: &SqlitePool) -> Result<(), AppError> {
+    let existing: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info('bundles')")
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::Database)?;
+    if !existing.iter().any(|name| name == "source_path") {
+        sqlx::query("ALTER TABLE bundles ADD COLUMN source_path TEXT")
+            .execute(pool)
+            .await
+            .map_err(AppError::Database)?;
+    }
+    Ok(())
+}
+
+async fn ensure_file_blob_index_column(pool: &SqlitePool) -> Result<(), AppError> {
+    let existing: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info('files')")
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::Database)?;
+    if !existing.iter().any(|name| name == "blob_index_id") {
+        sqlx::query(
+            "ALTER TABLE files ADD COLUMN blob_index_id INTEGER REFERENCES blob_indexes(id)",
+        )
+        .execute(pool)
+        .await
+        .map_err(AppError::Database)?;
+    }
+    Ok(())
+}
+
+/// Rebuilds `log_segments` of a pre-sharing database around
+/// `log_segment_contents`. Each batch moves the text and the columns that only
+/// depend on it into a content row keeping the segment's id, copies the rest
+/// into `log_segments_rebuild` and deletes the moved rows, so a restart picks
+/// up after the last committed batch. The final swap also rebuilds the
+/// full-text index, in one transaction.
+async fn split_log_segment_content(pool: &SqlitePool) -> Result<(), AppError> {
+    let existing: Vec<String> =
+        sqlx::query_scalar("SELECT name FROM pragma_table_info('log_segments')")
+            .fetch_all(pool)
+            .await
+            .map_err(AppError::Database)?;
+    if !existing.iter().any(|name| name == "content") {
+        return Ok(());
+    }
+    tracing::info!(
+        "moving log segment content into shareable storage; the search index will be rebuilt"
+    );
+    let mut tx = pool.begin().await.map_err(AppError::Database)?;
+    for statement in [
+        "DROP TRIGGER IF EXISTS log_segments_fts_ai",
+        "DROP TRIGGER IF EXISTS log_segments_fts_ad",
+        "DROP TRIGGER IF EXISTS log_segments_fts_au",
+        "DROP TRIGGER IF EXISTS log_segment_contents_fts_ai",
+        "DROP TRIGGER IF EXISTS log_segment_contents_fts_ad",
+        "DROP TRIGGER IF EXISTS log_segment_contents_fts_au",
+        "DROP TRIGGER IF EXISTS log_segments_private_content_ad",
+        "DROP TABLE IF EXISTS log_segments_fts",
+        r#"
+        CREATE TABLE IF NOT EXISTS log_segments_rebuild (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            bundle_id TEXT NOT NULL REFERENCES bundles(id) ON DELETE CASCADE,
+            file_id INTEGER REFERENCES files(id) ON DELETE CASCADE,
+            content_id INTEGER NOT NULL REFERENCES log_segment_contents(id),
+            event_time_start_ms INTEGER,
+            event_time_end_ms INTEGER,
+            event_time_indexed INTEGER NOT NULL DEFAULT 0,
+            event_time_shift_ms INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+    ] {
+        sqlx::query(statement)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::Database)?;
+    }
+    tx.commit().await.map_err(AppError::Database)?;
+
+    // Columns added after the oldest schemas fall back to their defaults.
+    let column = |name: &str, fallback: &str| {
+        if existing.iter().any(|present| present == name) {
+            name.to_string()
+        } else {
+            fallback.to_string()
+        }
+    };
+    // Segments of a deleted bundle or file were unreachable already.
+    let owned = "id <= ? AND bundle_id IN (SELECT id FROM bundles) AND (file_id IS NULL OR file_id IN (SELECT id FROM files))";
+    let move_contents = format!(
+        "INSERT INTO log_segment_contents (id, timeline, line_offset, line_end, chunk_index, level_mask, content) SELECT id, timeline, line_offset, line_end, chunk_index, {}, content FROM log_segments WHERE {owned}",
+        column("level_mask", "NULL"),
+    );
+    let move_segments = format!(
+        "INSERT INTO log_segments_rebuild (id, bundle_id, file_id, content_id, event_time_start_ms, event_time_end_ms, event_time_indexed, event_time_shift_ms, created_at) SELECT id, bundle_id, file_id, id, {}, {}, {}, {}, created_at FROM log_segments WHERE {owned}",
+        column("event_time_start_ms", "NULL"),
+        column("event_time_end_ms", "NULL"),
+        column("event_time_indexed", "0"),
+        column("event_time_shift_ms", "0"),
+    );
+    loop {
+        let mut tx = pool.begin().await.map_err(AppError::Database)?;
+        let batch_end: Option<i64> = sqlx::query_scalar(
+            "SELECT MAX(id) FROM (SELECT id FROM log_segments ORDER BY id LIMIT ?)",
+        )
+        .bind(LOG_SEGMENT_BACKFILL_BATCH_SIZE)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+        let Some(batch_end) = batch_end else {
+            tx.commit().await.map_err(AppError::Database)?;
+            break;
+        };
+        for statement in [
+            move_contents.as_str(),
+            move_segments.as_str(),
+            "DELETE FROM log_segments WHERE id <= ?",
+        ] {
+            sqlx::query(statement)
+                .bind(batch_end)
+                .execute(&mut *tx)
+                .await
+                .map_err(AppError::Database)?;
+        }
+        tx.commit().await.map_err(AppError::Database)?;
+    }
+
+    let mut tx = pool.begin().await.map_err(AppError::Database)?;
+    for statement in [
+        "DROP TABLE log_segments",
+        "ALTER TABLE log_segments_rebuild RENAME TO log_segments",
+        LOG_SEGMENTS_FTS_TABLE,
+        "INSERT INTO log_segments_fts(log_segments_fts) VALUES ('rebuild')",
+    ] {
+        sqlx::query(statement)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::Database)?;
+    }
+    tx.commit().await.map_err(AppError::Database)
+}
+
 async fn ensure_log_segment_event_time_indexes(pool: &SqlitePool) -> Result<(), AppError> {
     sqlx::query(
         "CREATE INDEX IF NOT EXISTS idx_logs_event_time_indexed ON log_segments (event_time_indexed, id)",
@@ -972,6 +1347,17 @@ async fn ensure_log_segment_event_time_indexes(pool: &SqlitePool) -> Result<(),
     Ok(())
 }
 
+async fn ensure_log_segment_level_index(pool: &SqlitePool) -> Result<(), AppError> {
+    // Partial index: only content still waiting for level classification.
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_segment_contents_level_unclassified ON log_segment_contents (id) WHERE level_mask IS NULL",
+    )
+    .execute(pool)
+    .await
+    .map_err(AppError::Database)?;
+    Ok(())
+}
+
 async fn backfill_log_segment_event_times(pool: &SqlitePool) -> Result<(), AppError> {
     // The legacy *_ms columns store deterministic wall-clock comparison keys,
     // not Unix milliseconds or absolute timestamps.
@@ -979,7 +1365,7 @@ async fn backfill_log_segment_event_times(pool: &SqlitePool) -> Result<(), AppEr
     loop {
         let mut tx = pool.begin().await.map_err(AppError::Database)?;
         let segments: Vec<(i64, String)> = sqlx::query_as(
-            "SELECT id, content FROM log_segments WHERE id > ? AND event_time_indexed = 0 ORDER BY id LIMIT ?",
+            "SELECT ls.id, lc.content FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE ls.id > ? AND ls.event_time_indexed = 0 ORDER BY ls.id LIMIT ?",
         )
         .bind(last_id)
         .bind(LOG_SEGMENT_BACKFILL_BATCH_SIZE)
@@ -1022,6 +1408,35 @@ async fn backfill_log_segment_event_times(pool: &SqlitePool) -> Result<(), AppEr
     Ok(())
 }
 
+async fn backfill_log_segment_levels(pool: &SqlitePool) -> Result<(), AppError> {
+    loop {
+        let mut tx = pool.begin().await.map_err(AppError::Database)?;
+        let contents: Vec<(i64, String)> = sqlx::query_as(
+            "SELECT id, content FROM log_segment_contents WHERE level_mask IS NULL ORDER BY id LIMIT ?",
+        )
+        .bind(LOG_SEGMENT_BACKFILL_BATCH_SIZE)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+        if contents.is_empty() {
+            tx.commit().await.map_err(AppError::Database)?;
+            break;
+        }
+        for (id, content) in contents {
+            sqlx::query(
+                "UPDATE log_segment_contents SET level_mask = ? WHERE id = ? AND level_mask IS NULL",
+            )
+            .bind(crate::ingest::level_mask(&content))
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::Database)?;
+        }
+        tx.commit().await.map_err(AppError::Database)?;
+    }
+    Ok(())
+}
+
 fn ensure_sqlite_parent(database_url: &str) -> Result<(), AppError> {
     let Some(path) = database_url.strip_prefix("sqlite://") else {
         return Ok(());
@@ -1279,23 +1694,60 @@ mod tests {
         .execute(&pool)
         .await
         .expect("create legacy log_segments");
+        sqlx::query(
+            r#"
+            CREATE TABLE bundles (
+                id TEXT PRIMARY KEY,
+                issue_code TEXT NOT NULL,
+                hash TEXT NOT NULL UNIQUE,
+                name TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'PENDING',
+                process_stage TEXT NOT NULL DEFAULT 'PENDING',
+                failure_stage TEXT,
+                failure_code TEXT,
+                failure_reason TEXT,
+                retryable INTEGER,
+                deleted_at TEXT,
+                uploader_user_id TEXT,
+                size_bytes INTEGER,
+                content_size_bytes INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .expect("create legacy bundles");
+        sqlx::query(
+            "INSERT INTO bundles (id, issue_code, hash, name) VALUES ('legacy', 'LEGACY', 'legacy-hash', 'legacy.log')",
+        )
+        .execute(&pool)
+        .await
+        .expect("insert legacy bundle");
 
-        sqlx::query("INSERT INTO log_segments (content) VALUES (?)")
+        sqlx::query("INSERT INTO log_segments (bundle_id, content) VALUES ('legacy', ?)")
             .bind("2026-08-14T09:32:15 first\nnoise\n2026-08-14T09:33:15 second")
             .execute(&pool)
             .await
             .expect("insert backfillable segment");
-        sqlx::query("INSERT INTO log_segments (content) VALUES (?)")
+        sqlx::query("INSERT INTO log_segments (bundle_id, content) VALUES ('legacy', ?)")
             .bind("not a dated log line")
             .execute(&pool)
             .await
             .expect("insert unparseable segment");
-        sqlx::query("INSERT INTO log_segments (content, event_time_start_ms) VALUES (?, ?)")
-            .bind("2026-08-14T09:34:15 partial")
-            .bind(111_i64)
+        sqlx::query(
+            "INSERT INTO log_segments (bundle_id, content, event_time_start_ms) VALUES ('legacy', ?, ?)",
+        )
+        .bind("2026-08-14T09:34:15 partial")
+        .bind(111_i64)
+        .execute(&pool)
+        .await
+        .expect("insert partially bounded segment");
+        sqlx::query("INSERT INTO log_segments (bundle_id, content) VALUES ('deleted', ?)")
+            .bind("orphaned text")
             .execute(&pool)
             .await
-            .expect("insert partially bounded segment");
+            .expect("insert orphaned segment");
 
         super::prepare_schema(&pool, false)
             .await
@@ -1327,15 +1779,43 @@ mod tests {
             "event_time_start_ms",
             "event_time_end_ms",
             "event_time_indexed",
+            "event_time_shift_ms",
         ] {
             assert!(
                 log_segment_columns.iter().any(|name| name == column),
                 "log_segments.{column}"
             );
         }
+        for column in ["content", "timeline", "line_offset", "level_mask"] {
+            assert!(
+                !log_segment_columns.iter().any(|name| name == column),
+                "legacy {column} column should move to log_segment_contents"
+            );
+        }
+        let leftovers: (i64, i64) = sqlx::query_as(
+            "SELECT (SELECT COUNT(*) FROM sqlite_master WHERE name = 'log_segments_rebuild'), (SELECT COUNT(*) FROM log_segment_contents WHERE content = 'orphaned text')",
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("inspect migration leftovers");
+        assert_eq!(leftovers, (0, 0));
+        let migrated_matches: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM log_segments_fts WHERE log_segments_fts MATCH 'partial'",
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("search migrated content");
+        assert_eq!(migrated_matches, 1);
+        let unclassified: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM log_segment_contents WHERE level_mask IS NULL",
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("inspect backfilled levels");
+        assert_eq!(unclassified, 0);
 
         let bounds: (Option<i64>, Option<i64>) = sqlx::query_as(
-            "SELECT event_time_start_ms, event_time_end_ms FROM log_segments WHERE content LIKE '2026-%'",
+            "SELECT event_time_start_ms, event_time_end_ms FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE lc.content LIKE '2026-%'",
         )
         .fetch_one(&pool)
             .await
@@ -1350,7 +1830,7 @@ mod tests {
         );
 
         let partial_bounds: (Option<i64>, Option<i64>) = sqlx::query_as(
-            "SELECT event_time_start_ms, event_time_end_ms FROM log_segments WHERE content LIKE '%partial'",
+            "SELECT event_time_start_ms, event_time_end_ms FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE lc.content LIKE '%partial'",
         )
         .fetch_one(&pool)
         .await
@@ -1364,7 +1844,7 @@ mod tests {
         );
 
         sqlx::query(
-            "UPDATE log_segments SET event_time_start_ms = 111, event_time_end_ms = 222 WHERE content LIKE '2026-%'",
+            "UPDATE log_segments SET event_time_start_ms = 111, event_time_end_ms = 222 WHERE content_id IN (SELECT id FROM log_segment_contents WHERE content LIKE '2026-%')",
         )
         .execute(&pool)
         .await
@@ -1373,15 +1853,22 @@ mod tests {
             .await
             .expect("repeat backfill schema");
         let preserved_bounds: (Option<i64>, Option<i64>) = sqlx::query_as(
-            "SELECT event_time_start_ms, event_time_end_ms FROM log_segments WHERE content LIKE '2026-%'",
+            "SELECT event_time_start_ms, event_time_end_ms FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE lc.content LIKE '2026-%'",
         )
         .fetch_one(&pool)
         .await
         .expect("inspect preserved segment");
         assert_eq!(preserved_bounds, (Some(111), Some(222)));
 
-        sqlx::query("INSERT INTO log_segments (content) VALUES (?)")
-            .bind("2026-08-14T09:35:15 added after upgrade")
+        let late_content_id: i64 = sqlx::query_scalar(
+            "INSERT INTO log_segment_contents (content) VALUES (?) RETURNING id",
+        )
+        .bind("2026-08-14T09:35:15 added after upgrade")
+        .fetch_one(&pool)
+        .await
+        .expect("insert content after upgrade");
+        sqlx::query("INSERT INTO log_segments (bundle_id, content_id) VALUES ('legacy', ?)")
+            .bind(late_content_id)
             .execute(&pool)
             .await
             .expect("insert segment after upgrade");
@@ -1389,7 +1876,7 @@ mod tests {
             .await
             .expect("do not repeat completed backfill");
         let late_bounds: (Option<i64>, Option<i64>) = sqlx::query_as(
-            "SELECT event_time_start_ms, event_time_end_ms FROM log_segments WHERE content LIKE '%added after upgrade'",
+            "SELECT event_time_start_ms, event_time_end_ms FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE lc.content LIKE '%added after upgrade'",
         )
         .fetch_one(&pool)
         .await
@@ -1403,7 +1890,7 @@ mod tests {
         );
 
         let null_bounds: (Option<i64>, Option<i64>) = sqlx::query_as(
-            "SELECT event_time_start_ms, event_time_end_ms FROM log_segments WHERE content = 'not a dated log line'",
+            "SELECT event_time_start_ms, event_time_end_ms FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE lc.content = 'not a dated log line'",
         )
         .fetch_one(&pool)
         .await
@@ -1411,7 +1898,7 @@ mod tests {
         assert_eq!(null_bounds, (None, None));
 
         let unparseable_indexed: i64 = sqlx::query_scalar(
-            "SELECT event_time_indexed FROM log_segments WHERE content = 'not a dated log line'",
+            "SELECT event_time_indexed FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE lc.content = 'not a dated log line'",
         )
         .fetch_one(&pool)
         .await
@@ -1419,7 +1906,7 @@ mod tests {
         assert_eq!(unparseable_indexed, 1);
 
         sqlx::query(
-            "UPDATE log_segments SET content = '2026-08-14T09:36:15 now parseable' WHERE content = 'not a dated log line'",
+            "UPDATE log_segment_contents SET content = '2026-08-14T09:36:15 now parseable' WHERE content = 'not a dated log line'",
         )
         .execute(&pool)
         .await
@@ -1428,24 +1915,31 @@ mod tests {
             .await
             .expect("skip completed event time indexing");
         let skipped_bounds: (Option<i64>, Option<i64>, i64) = sqlx::query_as(
-            "SELECT event_time_start_ms, event_time_end_ms, event_time_indexed FROM log_segments WHERE content = '2026-08-14T09:36:15 now parseable'",
+            "SELECT event_time_start_ms, event_time_end_ms, event_time_indexed FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE lc.content = '2026-08-14T09:36:15 now parseable'",
         )
         .fetch_one(&pool)
         .await
         .expect("inspect skipped completed segment");
         assert_eq!(skipped_bounds, (None, None, 1));
 
-        sqlx::query(
-            "INSERT INTO log_segments (content, event_time_start_ms, event_time_end_ms) VALUES (?, ?, ?)",
+        let interrupted_content_id: i64 = sqlx::query_scalar(
+            "INSERT INTO log_segment_contents (content) VALUES (?) RETURNING id",
         )
         .bind("2026-08-14T09:37:15 interrupted")
+        .fetch_one(&pool)
+        .await
+        .expect("insert interrupted content");
+        sqlx::query(
+            "INSERT INTO log_segments (bundle_id, content_id, event_time_start_ms, event_time_end_ms) VALUES ('legacy', ?, ?, ?)",
+        )
+        .bind(interrupted_content_id)
         .bind(111_i64)
         .bind(222_i64)
         .execute(&pool)
         .await
         .expect("insert interrupted segment");
         let pending_indexed: i64 = sqlx::query_scalar(
-            "SELECT event_time_indexed FROM log_segments WHERE content LIKE '%interrupted%'",
+            "SELECT event_time_indexed FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE lc.content LIKE '%interrupted%'",
         )
         .fetch_one(&pool)
         .await
@@ -1455,7 +1949,7 @@ mod tests {
             .await
             .expect("resume interrupted event time indexing");
         let resumed: (Option<i64>, Option<i64>, i64) = sqlx::query_as(
-            "SELECT event_time_start_ms, event_time_end_ms, event_time_indexed FROM log_segments WHERE content LIKE '%interrupted%'",
+            "SELECT event_time_start_ms, event_time_end_ms, event_time_indexed FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE lc.content LIKE '%interrupted%'",
         )
         .fetch_one(&pool)
         .await
@@ -1511,12 +2005,19 @@ mod tests {
         .fetch_one(&pool)
         .await
         .expect("insert file");
+        let content_id: i64 = sqlx::query_scalar(
+            "INSERT INTO log_segment_contents (content) VALUES (?) RETURNING id",
+        )
+        .bind("2026-08-14T09:32:15 requestId=backfill123")
+        .fetch_one(&pool)
+        .await
+        .expect("insert segment content");
         sqlx::query(
-            "INSERT INTO log_segments (bundle_id, file_id, content, event_time_start_ms, event_time_end_ms) VALUES (?, ?, ?, NULL, NULL)",
+            "INSERT INTO log_segments (bundle_id, file_id, content_id, event_time_start_ms, event_time_end_ms) VALUES (?, ?, ?, NULL, NULL)",
         )
         .bind("backfill-fts-bundle")
         .bind(file_id)
-        .bind("2026-08-14T09:32:15 requestId=backfill123")
+        .bind(content_id)
         .execute(&pool)
         .await
         .expect("insert segment");
@@ -1545,7 +2046,13 @@ mod tests {
     async fn event_time_backfill_query_uses_pending_index() {
         let pool = super::init_pool("sqlite::memory:").expect("init pool");
         sqlx::query(
-            "CREATE TABLE log_segments (id INTEGER PRIMARY KEY, content TEXT NOT NULL, event_time_indexed INTEGER NOT NULL DEFAULT 0)",
+            "CREATE TABLE log_segment_contents (id INTEGER PRIMARY KEY, content TEXT NOT NULL)",
+        )
+        .execute(&pool)
+        .await
+        .expect("create log segment contents table");
+        sqlx::query(
+            "CREATE TABLE log_segments (id INTEGER PRIMARY KEY, content_id INTEGER NOT NULL, event_time_indexed INTEGER NOT NULL DEFAULT 0)",
         )
         .execute(&pool)
         .await
@@ -1555,7 +2062,7 @@ mod tests {
             .await
             .expect("create event time indexes before backfill");
         let plan: Vec<(i64, i64, i64, String)> = sqlx::query_as(
-            "EXPLAIN QUERY PLAN SELECT id, content FROM log_segments WHERE id > 0 AND event_time_indexed = 0 ORDER BY id LIMIT 500",
+            "EXPLAIN QUERY PLAN SELECT ls.id, lc.content FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE ls.id > 0 AND ls.event_time_indexed = 0 ORDER BY ls.id LIMIT 500",
         )
         .fetch_all(&pool)
         .await
@@ -1567,6 +2074,80 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn prepare_schema_moves_event_time_settings_to_a_file_foreign_key() {
+        let pool = super::init_pool("sqlite::memory:").expect("init pool");
+        super::prepare_schema(&pool, true)
+            .await
+            .expect("prepare schema");
+        sqlx::query("INSERT INTO issues (code, name) VALUES ('ZONE', 'Zone')")
+            .execute(&pool)
+            .await
+            .expect("insert issue");
+        sqlx::query(
+            "INSERT INTO bundles (id, issue_code, hash, name, status) VALUES ('zone-bundle', 'ZONE', 'zone-hash', 'Zone', 'READY')",
+        )
+        .execute(&pool)
+        .await
+        .expect("insert bundle");
+        let file_id: i64 = sqlx::query_scalar(
+            "INSERT INTO files (bundle_id, name, path, is_dir) VALUES ('zone-bundle', 'app.log', '/app.log', 0) RETURNING id",
+        )
+        .fetch_one(&pool)
+        .await
+        .expect("insert file");
+        for statement in [
+            "DROP TABLE event_time_settings",
+            "CREATE TABLE event_time_settings (bundle_id TEXT NOT NULL, scope_file_id INTEGER NOT NULL DEFAULT 0, utc_offset_minutes INTEGER NOT NULL, clock_skew_ms INTEGER NOT NULL DEFAULT 0, updated_by_user_id TEXT, updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP, PRIMARY KEY (bundle_id, scope_file_id))",
+        ] {
+            sqlx::query(statement)
+                .execute(&pool)
+                .await
+                .expect("create legacy event_time_settings");
+        }
+        sqlx::query(
+            "INSERT INTO event_time_settings (bundle_id, scope_file_id, utc_offset_minutes) VALUES ('zone-bundle', 0, 480), ('zone-bundle', ?, 60), ('zone-bundle', ?, 120)",
+        )
+        .bind(file_id)
+        .bind(file_id + 1_000)
+        .execute(&pool)
+        .await
+        .expect("insert legacy settings");
+
+        super::prepare_schema(&pool, false)
+            .await
+            .expect("upgrade schema");
+        super::prepare_schema(&pool, false)
+            .await
+            .expect("upgrade schema again");
+
+        let settings: Vec<(Option<i64>, i64)> = sqlx::query_as(
+            "SELECT file_id, utc_offset_minutes FROM event_time_settings ORDER BY file_id IS NOT NULL",
+        )
+        .fetch_all(&pool)
+        .await
+        .expect("inspect migrated settings");
+        assert_eq!(settings, vec![(None, 480), (Some(file_id), 60)]);
+        let duplicate_bundle_scope = sqlx::query(
+            "INSERT INTO event_time_settings (bundle_id, utc_offset_minutes) VALUES ('zone-bundle', 0)",
+        )
+        .execute(&pool)
+        .await;
+        assert!(duplicate_bundle_scope.is_err());
+
+        sqlx::query("DELETE FROM files WHERE id = ?")
+            .bind(file_id)
+            .execute(&pool)
+            .await
+            .expect("delete file");
+        let remaining: Vec<Option<i64>> =
+            sqlx::query_scalar("SELECT file_id FROM event_time_settings")
+                .fetch_all(&pool)
+                .await
+                .expect("inspect settings after file deletion");
+        assert_eq!(remaining, vec![None]);
+    }
+
     #[tokio::test]
     async fn schema_creates_authentication_storage() {
         let pool = super::init_pool("sqlite::memory:").expect("init pool");
@@ -1605,7 +2186,10 @@ mod tests {
         .await
         .expect("load fts schema");
         assert!(schema.contains("tokenize='trigram'"), "{schema}");
-        assert!(schema.contains("content='log_segments'"), "{schema}");
+        assert!(
+            schema.contains("content='log_segment_contents'"),
+            "{schema}"
+        );
         assert!(schema.contains("content_rowid='id'"), "{schema}");
 
         sqlx::query("INSERT INTO issues (code, name) VALUES ('SEARCH', 'Search')")
@@ -1620,8 +2204,15 @@ mod tests {
         .fetch_one(&pool)
         .await
         .expect("insert file");
-        sqlx::query("INSERT INTO log_segments (bundle_id, file_id, content) VALUES ('bundle', ?, 'requestId=abcdef123456')")
+        let content_id: i64 = sqlx::query_scalar("INSERT INTO log_segment_contents (content) VALUES ('requestId=abcdef123456') RETURNING id")
+        .fetch_one(&pool)
+        .await
+        .expect("insert content");
+        sqlx::query(
+            "INSERT INTO log_segments (bundle_id, file_id, content_id) VALUES ('bundle', ?, ?)",
+        )
         .bind(file_id)
+        .bind(content_id)
         .execute(&pool)
         .await
         .expect("insert segment content");
@@ -3,7 +3,10 @@ use std::path::Path;
 use serde::{Deserialize, Serialize};
 use tokio::io::AsyncReadExt;
 
-use crate::error::AppError;
+use crate::{
+    error::AppError,
+    text_encoding::{TextEncoding, detect_encoding},
+};
 
 const PROBE_BYTES: u64 = 8 * 1024;
 
@@ -88,9 +91,53 @@ pub fn effective_mime_type(name: &str, supplied: Option<&str>) -> Option<String>
 pub fn is_supported_archive_name(name: &str) -> bool {
     let lower = name.to_ascii_lowercase();
     lower.ends_with(".zip")
-        || lower.ends_with(".tar.gz")
-        || lower.ends_with(".tgz")
-        || (lower.ends_with(".gz") && !lower.ends_with(".tar.gz"))
+        || TarCompression::from_name(&lower).is_some()
+        || [".gz", ".bz2", ".xz", ".zst"]
+            .iter()
+            .any(|suffix| lower.ends_with(suffix))
+}
+
+/// Compression wrapped around a tar archive, recognised from the file name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TarCompression {
+    None,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+const TAR_ARCHIVE_SUFFIXES: &[(&str, TarCompression)] = &[
+    (".tar", TarCompression::None),
+    (".tar.gz", TarCompression::Gzip),
+    (".tgz", TarCompression::Gzip),
+    (".tar.bz2", TarCompression::Bzip2),
+    (".tbz2", TarCompression::Bzip2),
+    (".tbz", TarCompression::Bzip2),
+    (".tar.xz", TarCompression::Xz),
+    (".txz", TarCompression::Xz),
+    (".tar.zst", TarCompression::Zstd),
+    (".tzst", TarCompression::Zstd),
+];
+
+impl TarCompression {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        let lower = name.to_ascii_lowercase();
+        TAR_ARCHIVE_SUFFIXES
+            .iter()
+            .find(|(suffix, _)| lower.ends_with(suffix))
+            .map(|(_, compression)| *compression)
+    }
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::None => "tar",
+            Self::Gzip => "tar.gz",
+            Self::Bzip2 => "tar.bz2",
+            Self::Xz => "tar.xz",
+            Self::Zstd => "tar.zst",
+        }
+    }
 }
 
 fn classify_declared(name: &str, mime_type: Option<&str>) -> Option<PreviewKind> {
@@ -216,6 +263,10 @@ fn is_probably_text(sample: &[u8]) -> bool {
     if sample.is_empty() {
         return true;
     }
+    let encoding = detect_encoding(sample);
+    if encoding.name != TextEncoding::Utf8 {
+        return mostly_printable(&encoding.decode_lossy(sample));
+    }
     if sample.contains(&0) {
         return false;
     }
@@ -227,11 +278,17 @@ fn is_probably_text(sample: &[u8]) -> bool {
         }
         Err(_) => return false,
     };
+    mostly_printable(text)
+}
+
+fn mostly_printable(text: &str) -> bool {
     let mut total = 0usize;
     let mut controls = 0usize;
     for ch in text.chars() {
         total += 1;
-        if ch.is_control() && !matches!(ch, '\n' | '\r' | '\t' | '\u{0008}' | '\u{000c}') {
+        if ch == '\u{FFFD}'
+            || (ch.is_control() && !matches!(ch, '\n' | '\r' | '\t' | '\u{0008}' | '\u{000c}'))
+        {
             controls += 1;
         }
     }
@@ -240,7 +297,9 @@ fn is_probably_text(sample: &[u8]) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use super::{PreviewKind, classify_file, preview_kind_from_metadata};
+    use super::{
+        PreviewKind, classify_file, is_supported_archive_name, preview_kind_from_metadata,
+    };
 
     #[tokio::test]
     async fn probes_unknown_extension_as_text_or_binary() {
@@ -286,4 +345,65 @@ mod tests {
             PreviewKind::Archive
         );
     }
+
+    #[test]
+    fn tar_family_names_are_supported_archives() {
+        for name in [
+            "dump.tar",
+            "dump.tar.gz",
+            "dump.tgz",
+            "dump.tar.bz2",
+            "dump.tbz2",
+            "dump.TAR.XZ",
+            "dump.txz",
+            "dump.tar.zst",
+            "dump.tzst",
+        ] {
+            assert!(is_supported_archive_name(name), "{name}");
+        }
+        assert!(!is_supported_archive_name("guitar"));
+    }
+
+    #[test]
+    fn single_stream_compressed_logs_are_supported_archives() {
+        for name in ["app.log.gz", "app.log.bz2", "app.log.XZ", "app.log.zst"] {
+            assert!(is_supported_archive_name(name), "{name}");
+        }
+        assert!(!is_supported_archive_name("app.log.zstd"));
+    }
+
+    #[tokio::test]
+    async fn legacy_and_utf16_logs_are_classified_as_text() {
+        let root = std::env::temp_dir().join(format!("rain-classify-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&root)
+            .await
+            .expect("create test root");
+        let utf16_path = root.join("service.data");
+        let gbk_path = root.join("firmware.data");
+        let utf16: Vec<u8> = "ERROR 服务已停止\r\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        tokio::fs::write(&utf16_path, utf16)
+            .await
+            .expect("write utf-16 sample");
+        tokio::fs::write(
+            &gbk_path,
+            encoding_rs::GBK.encode("错误：固件升级失败，设备重启\n").0,
+        )
+        .await
+        .expect("write gbk sample");
+
+        for path in [utf16_path, gbk_path] {
+            assert_eq!(
+                classify_file(&path, "sample.data", None)
+                    .await
+                    .expect("classify legacy text"),
+                PreviewKind::Text,
+                "{}",
+                path.display()
+            );
+        }
+        let _ = tokio::fs::remove_dir_all(root).await;
+    }
 }
@@ -1,5 +1,3 @@
-use once_cell::sync::Lazy;
-use regex::Regex;
 use sqlx::{QueryBuilder, Sqlite};
 use std::{
     collections::HashMap,
@@ -8,7 +6,7 @@ use std::{
     pin::Pin,
 };
 use tokio::fs;
-use tokio::io::BufReader;
+use tokio::io::{AsyncSeekExt, BufReader};
 
 use crate::{
     blob_store::{BlobStore, mark_blob_ready, persist_blob},
@@ -20,19 +18,35 @@ use crate::{
 };
 
 mod archive;
+mod blob_index;
 mod indexing;
 pub(crate) mod limits;
 mod quota;
 
+pub use crate::text_encoding::{
+    EncodingSource, FileEncoding, TextEncoding, detect_encoding, detect_file_encoding,
+    read_encoding_sample,
+};
 pub use archive::ArchiveBudget;
 #[cfg(test)]
-use archive::{archive_parent_depth, extract_gzip_file, gzip_output_name, sanitize_archive_path};
+use archive::{
+    StreamCompression, archive_parent_depth, extract_compressed_file, sanitize_archive_path,
+    stream_output_name,
+};
 use archive::{extract_archive, validate_extracted_path};
 use indexing::clean_log_line;
+pub use indexing::encoding::StreamingDecoder;
+use indexing::events::{EventBoundaries, EventSpan, MAX_EVENT_CHUNK_LINES};
+pub use indexing::levels::{LogLevel, level_mask};
 pub use indexing::line_reader::{
-    LimitedLine, TRUNCATED_LINE_MARKER, decode_log_line, read_line_bytes_limited,
+    LimitedLine, TRUNCATED_LINE_MARKER, decode_log_line, read_encoded_line_limited,
+    read_encoded_line_limited_with_budget_and_callback, read_line_bytes_limited,
     read_line_bytes_limited_with_budget, read_line_bytes_limited_with_budget_and_callback,
 };
+use indexing::timestamps::DatedRecognizer;
+pub use indexing::timestamps::{
+    EventTimeParser, PatternRecognizer, RecognizedTime, TimestampRecognizer, TimestampRecognizers,
+};
 use limits::{
     INDEX_CHUNK_MAX_LINES, INDEX_CHUNK_TARGET_BYTES, INDEX_COMMIT_MAX_LINES,
     INDEX_COMMIT_TARGET_BYTES, LINE_OFFSET_INTERVAL,
@@ -40,22 +54,18 @@ use limits::{
 
 const LINE_OFFSET_BATCH_SIZE: usize = 500;
 const SEGMENT_BATCH_SIZE: usize = 100;
-static EVENT_TIMESTAMP_PATTERN: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(
-        r"^(?:\[[^\]\r\n]*\]\s*)*(?:\[(?P<bracket>\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:[.,]\d{1,9})?)\](?:\s|\[|$)|(?P<plain>\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:[.,]\d{1,9})?)(?:\s|$))",
-    )
-    .expect("valid event timestamp pattern")
-});
+const EVENT_SPAN_BATCH_SIZE: usize = 500;
 pub use quota::IssueQuota;
 
+/// Parses a dated `YYYY-MM-DD HH:MM:SS` line prefix into a wall-clock key.
+///
+/// Used where no per-bundle context exists, such as backfilling legacy rows;
+/// fresh indexing goes through [`EventTimeParser`] and its full recogniser set.
 pub(crate) fn parse_event_time_ms(line: &str) -> Option<i64> {
-    let timestamp = EVENT_TIMESTAMP_PATTERN
-        .captures(line)
-        .and_then(|captures| captures.name("bracket").or_else(|| captures.name("plain")))?
-        .as_str()
-        .replace('T', " ")
-        .replace(',', ".");
-    wall_clock::parse(&timestamp).and_then(wall_clock::comparison_key)
+    match DatedRecognizer.recognize(line)? {
+        RecognizedTime::Full(datetime) => wall_clock::comparison_key(datetime),
+        _ => None,
+    }
 }
 
 pub(crate) fn event_time_range(content: &str) -> (Option<i64>, Option<i64>) {
@@ -85,6 +95,15 @@ pub struct ProcessFileOptions<'a> {
     pub archive_budget: ArchiveBudget,
     pub issue_quota: IssueQuota,
     pub indexing: &'a IndexingConfig,
+    /// Directory the file is placed under; `None` puts it at the bundle root.
+    pub parent: Option<&'a BundleDirectory>,
+}
+
+/// A directory node of a bundle's file tree.
+pub struct BundleDirectory {
+    pub id: i64,
+    /// Database path, `/{bundle_hash}/...`.
+    pub path: String,
 }
 
 fn uploaded_file_meta(
@@ -102,6 +121,12 @@ fn uploaded_file_meta(
     })
 }
 
+fn insert_encoding_meta(meta: &mut serde_json::Value, encoding: Option<FileEncoding>) {
+    if let (Some(object), Some(encoding)) = (meta.as_object_mut(), encoding) {
+        object.insert("encoding".into(), serde_json::json!(encoding));
+    }
+}
+
 fn extracted_entry_meta(kind: &str, preview_kind: PreviewKind) -> serde_json::Value {
     serde_json::json!({
         "kind": kind,
@@ -109,6 +134,13 @@ fn extracted_entry_meta(kind: &str, preview_kind: PreviewKind) -> serde_json::Va
     })
 }
 
+fn appended_directory_meta() -> serde_json::Value {
+    serde_json::json!({
+        "kind": "appended_dir",
+        "preview_kind": PreviewKind::Directory.as_str()
+    })
+}
+
 fn extracted_directory_meta(source: &str, storage_name: &str) -> serde_json::Value {
     serde_json::json!({
         "source": source,
@@ -125,6 +157,7 @@ struct PreparedDirectoryEntry {
     size_bytes: Option<i64>,
     mime_type: Option<String>,
     preview_kind: PreviewKind,
+    encoding: Option<FileEncoding>,
     meta: serde_json::Value,
     blob_id: Option<i64>,
 }
@@ -145,6 +178,7 @@ pub async fn process_uploaded_file(options: ProcessFileOptions<'_>) -> Result<()
         archive_budget,
         issue_quota,
         indexing,
+        parent,
     } = options;
 
     let bundle_dir = data_root.join(bundle_hash);
@@ -160,14 +194,20 @@ pub async fn process_uploaded_file(options: ProcessFileOptions<'_>) -> Result<()
         issue_quota.reserve(size_bytes).await?;
     }
 
-    let relative_path = format!("/{bundle_hash}/{storage_name}");
-    let meta = uploaded_file_meta(original_name, display_name, storage_name, preview_kind);
+    let parent_path = parent.map_or_else(|| format!("/{bundle_hash}"), |dir| dir.path.clone());
+    let relative_path = format!("{parent_path}/{storage_name}");
+    let encoding = match preview_kind {
+        PreviewKind::Text => Some(detect_file_encoding(&disk_path).await?),
+        _ => None,
+    };
+    let mut meta = uploaded_file_meta(original_name, display_name, storage_name, preview_kind);
+    insert_encoding_meta(&mut meta, encoding);
     let blob_id = persist_blob(pool, blob_store.as_ref(), &disk_path).await?;
 
     let file_id = insert_file_record(
         pool,
         bundle_id,
-        None,
+        parent.map(|dir| dir.id),
         display_name,
         &relative_path,
         false,
@@ -182,7 +222,15 @@ pub async fn process_uploaded_file(options: ProcessFileOptions<'_>) -> Result<()
     update_process_stage(pool, bundle_id, "EXTRACTING").await?;
     if preview_kind == PreviewKind::Text {
         update_process_stage(pool, bundle_id, "INDEXING").await?;
-        ingest_text_file(pool, bundle_id, file_id, &disk_path, size_bytes, indexing).await?;
+        ingest_text_file(
+            pool,
+            bundle_id,
+            file_id,
+            &disk_path,
+            encoding.unwrap_or_default(),
+            indexing,
+        )
+        .await?;
     }
 
     if preview_kind == PreviewKind::Archive {
@@ -201,7 +249,7 @@ pub async fn process_uploaded_file(options: ProcessFileOptions<'_>) -> Result<()
         )
         .await?;
 
-        let extracted_relative_path = format!("/{bundle_hash}/{extracted_dir_name}");
+        let extracted_relative_path = format!("{parent_path}/{extracted_dir_name}");
         let dir_meta = extracted_directory_meta(original_name, extracted_dir_name.as_str());
 
         let dir_id = insert_file_record(
@@ -224,7 +272,7 @@ pub async fn process_uploaded_file(options: ProcessFileOptions<'_>) -> Result<()
             bundle_id,
             dir_id,
             extracted_dir,
-            format!("{}/{extracted_dir_name}", bundle_hash),
+            extracted_relative_path.trim_start_matches('/').to_string(),
             archive_budget,
             issue_quota,
             indexing,
@@ -238,6 +286,57 @@ pub async fn process_uploaded_file(options: ProcessFileOptions<'_>) -> Result<()
     Ok(())
 }
 
+/// Finds or creates the directory nodes for `segments` below the bundle root
+/// and returns the innermost one. Segments must already be sanitized.
+pub async fn ensure_bundle_directory(
+    pool: &sqlx::SqlitePool,
+    bundle_id: &str,
+    bundle_hash: &str,
+    segments: &[String],
+) -> Result<Option<BundleDirectory>, AppError> {
+    let mut current: Option<BundleDirectory> = None;
+    for segment in segments {
+        let path = format!(
+            "{}/{segment}",
+            current
+                .as_ref()
+                .map_or_else(|| format!("/{bundle_hash}"), |dir| dir.path.clone())
+        );
+        let existing: Option<(i64, bool)> =
+            sqlx::query_as("SELECT id, is_dir FROM files WHERE bundle_id = ? AND path = ?")
+                .bind(bundle_id)
+                .bind(&path)
+                .fetch_optional(pool)
+                .await
+                .map_err(AppError::Database)?;
+        let id = match existing {
+            Some((id, true)) => id,
+            Some((_, false)) => {
+                return Err(AppError::Conflict(format!(
+                    "bundle path {path} is a file, not a directory"
+                )));
+            }
+            None => {
+                insert_file_record(
+                    pool,
+                    bundle_id,
+                    current.as_ref().map(|dir| dir.id),
+                    segment,
+                    &path,
+                    true,
+                    None,
+                    None,
+                    Some(appended_directory_meta()),
+                    None,
+                )
+                .await?
+            }
+        };
+        current = Some(BundleDirectory { id, path });
+    }
+    Ok(current)
+}
+
 async fn update_process_stage(
     pool: &sqlx::SqlitePool,
     bundle_id: &str,
@@ -297,7 +396,11 @@ fn ingest_directory<'a>(
             if !is_dir && preview_kind != PreviewKind::Archive {
                 issue_quota.reserve(metadata.len()).await?;
             }
-            let meta = extracted_entry_meta(
+            let encoding = match preview_kind {
+                PreviewKind::Text => Some(detect_file_encoding(&disk_path).await?),
+                _ => None,
+            };
+            let mut meta = extracted_entry_meta(
                 if is_dir {
                     "extracted_dir"
                 } else {
@@ -305,6 +408,7 @@ fn ingest_directory<'a>(
                 },
                 preview_kind,
             );
+            insert_encoding_meta(&mut meta, encoding);
             let blob_id = if is_dir {
                 None
             } else {
@@ -319,6 +423,7 @@ fn ingest_directory<'a>(
                 size_bytes,
                 mime_type,
                 preview_kind,
+                encoding,
                 meta,
                 blob_id,
             });
@@ -337,6 +442,7 @@ fn ingest_directory<'a>(
                 is_dir,
                 size_bytes,
                 preview_kind,
+                encoding,
                 ..
             } = entry;
 
@@ -357,15 +463,13 @@ fn ingest_directory<'a>(
                 continue;
             }
 
-            if preview_kind == PreviewKind::Text
-                && let Some(size) = size_bytes
-            {
+            if preview_kind == PreviewKind::Text && size_bytes.is_some() {
                 ingest_text_file(
                     pool,
                     bundle_id,
                     record_id,
                     &disk_path,
-                    size as u64,
+                    encoding.unwrap_or_default(),
                     indexing,
                 )
                 .await?;
@@ -539,32 +643,94 @@ async fn move_or_copy_file(source: &Path, destination: &Path) -> Result<(), AppE
     }
 }
 
+/// Rebuilds the search index of one text file after its encoding was overridden.
+pub(crate) async fn reindex_text_file(
+    pool: &sqlx::SqlitePool,
+    bundle_id: &str,
+    file_id: i64,
+    disk_path: &Path,
+    encoding: FileEncoding,
+    indexing: &IndexingConfig,
+) -> Result<(), AppError> {
+    // Unlinking first keeps the file from serving as a half-deleted source
+    // for other files of its blob. Event spans go with their segments through
+    // ON DELETE CASCADE.
+    let mut tx = pool.begin().await.map_err(AppError::Database)?;
+    sqlx::query("UPDATE files SET blob_index_id = NULL WHERE id = ?")
+        .bind(file_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+    sqlx::query("DELETE FROM log_segments WHERE file_id = ?")
+        .bind(file_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+    tx.commit().await.map_err(AppError::Database)?;
+    ingest_text_file(pool, bundle_id, file_id, disk_path, encoding, indexing).await
+}
+
 async fn ingest_text_file(
     pool: &sqlx::SqlitePool,
     bundle_id: &str,
     file_id: i64,
     disk_path: &Path,
-    _size_bytes: u64,
+    encoding: FileEncoding,
     indexing: &IndexingConfig,
 ) -> Result<(), AppError> {
-    let file = fs::File::open(disk_path)
+    let (recognizers, reference) = load_timestamp_context(pool, bundle_id).await?;
+    let shift_ms = crate::services::event_time::resolve_file_setting(pool, bundle_id, file_id)
+        .await?
+        .map_or(0, |setting| setting.shift_ms());
+    sqlx::query("UPDATE files SET event_time_shift_ms = ? WHERE id = ?")
+        .bind(shift_ms)
+        .bind(file_id)
+        .execute(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+    let blob_index_id =
+        match blob_index::index_key_for_file(pool, file_id, encoding, indexing).await? {
+            Some((blob_id, index_key)) => {
+                let context = blob_index::LinkContext {
+                    bundle_id,
+                    file_id,
+                    shift_ms,
+                    recognizers: &recognizers,
+                    reference,
+                };
+                if blob_index::link_existing(pool, blob_id, &index_key, &context).await? {
+                    return Ok(());
+                }
+                blob_index::claim(pool, file_id, blob_id, &index_key).await?
+            }
+            None => None,
+        };
+
+    let mut file = fs::File::open(disk_path)
         .await
         .map_err(|error| io_error_at("open log file for indexing", disk_path, error))?;
+    file.seek(std::io::SeekFrom::Start(encoding.bom_bytes))
+        .await
+        .map_err(|error| io_error_at("skip log file byte order mark", disk_path, error))?;
     let mut reader = BufReader::new(file);
     let mut line_number = 0i64;
-    let mut bytes_scanned = 0u64;
+    let mut bytes_scanned = encoding.bom_bytes;
     let mut chunk_index = 0i64;
     let mut chunk = LogChunk::new(chunk_index, INDEX_CHUNK_TARGET_BYTES);
     let mut pending_chunks = Vec::new();
     let mut line = Vec::new();
     let mut offsets = Vec::new();
     let mut budget = IndexBatchBudget::default();
+    let mut event_times = EventTimeParser::new(&recognizers, reference).with_shift_ms(shift_ms);
+    let mut boundaries = EventBoundaries::default();
     let mut tx = pool.begin().await.map_err(AppError::Database)?;
 
     loop {
         let line_offset = bytes_scanned;
-        let Some((read, _original_length, truncated)) = read_line_bytes_limited(
+        let Some((read, _original_length, truncated)) = read_encoded_line_limited(
             &mut reader,
+            encoding.name,
             &mut line,
             usize::try_from(indexing.max_indexed_line_size).map_err(|_| {
                 AppError::Config(
@@ -584,40 +750,71 @@ async fn ingest_text_file(
         }
         bytes_scanned = bytes_scanned.saturating_add(read as u64);
 
-        let cleaned = clean_log_line(&line, truncated);
+        let cleaned = clean_log_line(&line, truncated, &encoding);
         if !cleaned.is_empty() {
-            chunk.push(line_number, cleaned);
+            let event_time_ms = event_times.parse_ms(&cleaned);
+            let mut continues = boundaries.continues(
+                &cleaned,
+                encoding.starts_indented(&line),
+                event_time_ms.is_some(),
+            );
+            if continues
+                && chunk.reached_event_limit(MAX_EVENT_CHUNK_LINES, INDEX_CHUNK_TARGET_BYTES)
+            {
+                boundaries.reset();
+                continues = false;
+            }
 
-            if chunk.reached_target(INDEX_CHUNK_MAX_LINES, INDEX_CHUNK_TARGET_BYTES) {
-                budget.record_chunk(chunk.byte_len());
-                pending_chunks.push(chunk);
-                chunk_index += 1;
-                chunk = LogChunk::new(chunk_index, INDEX_CHUNK_TARGET_BYTES);
+            // Chunks only close between events so a stack trace stays whole.
+            if !continues {
+                if chunk.reached_target(INDEX_CHUNK_MAX_LINES, INDEX_CHUNK_TARGET_BYTES) {
+                    budget.record_chunk(chunk.byte_len());
+                    pending_chunks.push(chunk.finish());
+                    chunk_index += 1;
+                    chunk = LogChunk::new(chunk_index, INDEX_CHUNK_TARGET_BYTES);
+                }
+                if budget.should_commit() {
+                    if !chunk.is_empty() {
+                        budget.record_chunk(chunk.byte_len());
+                        pending_chunks.push(chunk.finish());
+                        chunk_index += 1;
+                        chunk = LogChunk::new(chunk_index, INDEX_CHUNK_TARGET_BYTES);
+                    }
+                    flush_log_chunks(
+                        &mut tx,
+                        bundle_id,
+                        file_id,
+                        blob_index_id,
+                        shift_ms,
+                        &pending_chunks,
+                    )
+                    .await?;
+                    pending_chunks.clear();
+                    tx.commit().await.map_err(AppError::Database)?;
+                    tx = pool.begin().await.map_err(AppError::Database)?;
+                    budget.reset();
+                }
             }
+            chunk.push(line_number, cleaned, event_time_ms, continues);
         }
 
         line_number += 1;
         budget.record_line();
-        if budget.should_commit() {
-            if !chunk.is_empty() {
-                budget.record_chunk(chunk.byte_len());
-                pending_chunks.push(chunk);
-                chunk_index += 1;
-                chunk = LogChunk::new(chunk_index, INDEX_CHUNK_TARGET_BYTES);
-            }
-            flush_log_chunks(&mut tx, bundle_id, file_id, &pending_chunks).await?;
-            pending_chunks.clear();
-            tx.commit().await.map_err(AppError::Database)?;
-            tx = pool.begin().await.map_err(AppError::Database)?;
-            budget.reset();
-        }
     }
 
     if !chunk.is_empty() {
         budget.record_chunk(chunk.byte_len());
-        pending_chunks.push(chunk);
+        pending_chunks.push(chunk.finish());
     }
-    flush_log_chunks(&mut tx, bundle_id, file_id, &pending_chunks).await?;
+    flush_log_chunks(
+        &mut tx,
+        bundle_id,
+        file_id,
+        blob_index_id,
+        shift_ms,
+        &pending_chunks,
+    )
+    .await?;
 
     sqlx::query("DELETE FROM log_line_offsets WHERE file_id = ?")
         .bind(file_id)
@@ -625,7 +822,7 @@ async fn ingest_text_file(
         .await
         .map_err(AppError::Database)?;
 
-    insert_line_offsets(&mut tx, file_id, &offsets).await?;
+    insert_line_offsets(&mut tx, file_id, blob_index_id, &offsets).await?;
 
     sqlx::query("UPDATE files SET line_count = ? WHERE id = ?")
         .bind(line_number)
@@ -633,22 +830,60 @@ async fn ingest_text_file(
         .execute(&mut *tx)
         .await
         .map_err(AppError::Database)?;
+    if let Some(index_id) = blob_index_id {
+        blob_index::mark_ready(&mut tx, index_id, event_times.used_reference()).await?;
+    }
 
     tx.commit().await.map_err(AppError::Database)?;
     Ok(())
 }
 
+/// Loads admin-defined timestamp formats and the bundle upload time, which
+/// anchors year and date inference for partial timestamps.
+pub(crate) async fn load_timestamp_context(
+    pool: &sqlx::SqlitePool,
+    bundle_id: &str,
+) -> Result<(TimestampRecognizers, chrono::NaiveDateTime), AppError> {
+    let custom = crate::repositories::timestamp_formats::list(pool)
+        .await?
+        .into_iter()
+        .filter_map(|record| PatternRecognizer::new(&record.pattern, &record.format).ok())
+        .collect();
+    let created_at: Option<String> =
+        sqlx::query_scalar("SELECT created_at FROM bundles WHERE id = ?")
+            .bind(bundle_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(AppError::Database)?;
+    let reference = created_at
+        .as_deref()
+        .and_then(wall_clock::parse)
+        .unwrap_or_else(|| chrono::Utc::now().naive_utc());
+    Ok((TimestampRecognizers::with_custom(custom), reference))
+}
+
+/// Stores the offsets with the blob index being built, where every file
+/// linked to it later finds them, or with the file when its content is private.
 async fn insert_line_offsets(
     tx: &mut sqlx::Transaction<'_, Sqlite>,
     file_id: i64,
+    blob_index_id: Option<i64>,
     offsets: &[(i64, i64)],
 ) -> Result<(), AppError> {
-    for batch in offsets.chunks(LINE_OFFSET_BATCH_SIZE) {
-        let mut builder = QueryBuilder::<Sqlite>::new(
+    let (statement, owner_id) = match blob_index_id {
+        Some(index_id) => (
+            "INSERT INTO blob_line_offsets (blob_index_id, line_number, byte_offset) ",
+            index_id,
+        ),
+        None => (
             "INSERT INTO log_line_offsets (file_id, line_number, byte_offset) ",
-        );
+            file_id,
+        ),
+    };
+    for batch in offsets.chunks(LINE_OFFSET_BATCH_SIZE) {
+        let mut builder = QueryBuilder::<Sqlite>::new(statement);
         builder.push_values(batch, |mut row, (line_number, byte_offset)| {
-            row.push_bind(file_id)
+            row.push_bind(owner_id)
                 .push_bind(*line_number)
                 .push_bind(*byte_offset);
         });
@@ -667,9 +902,12 @@ struct LogChunk {
     line_end: Option<i64>,
     event_time_start_ms: Option<i64>,
     event_time_end_ms: Option<i64>,
+    level_mask: i64,
     content: String,
     content_bytes: usize,
     line_count: usize,
+    open_event: Option<EventSpan>,
+    events: Vec<EventSpan>,
 }
 
 #[derive(Default)]
@@ -706,14 +944,25 @@ impl LogChunk {
             line_end: None,
             event_time_start_ms: None,
             event_time_end_ms: None,
+            level_mask: 0,
             content: String::with_capacity(content_capacity),
             content_bytes: 0,
             line_count: 0,
+            open_event: None,
+            events: Vec::new(),
         }
     }
 
-    fn push(&mut self, line_number: i64, content: String) {
-        if let Some(event_time_ms) = parse_event_time_ms(&content) {
+    /// Appends one line; `continues` extends the open event instead of
+    /// starting a new one.
+    fn push(
+        &mut self,
+        line_number: i64,
+        content: String,
+        event_time_ms: Option<i64>,
+        continues: bool,
+    ) {
+        if let Some(event_time_ms) = event_time_ms {
             self.event_time_start_ms = Some(
                 self.event_time_start_ms
                     .map_or(event_time_ms, |current| current.min(event_time_ms)),
@@ -723,6 +972,9 @@ impl LogChunk {
                     .map_or(event_time_ms, |current| current.max(event_time_ms)),
             );
         }
+        if let Some(level) = LogLevel::detect(&content) {
+            self.level_mask |= level.bit();
+        }
         if self.line_start.is_none() {
             self.line_start = Some(line_number);
         }
@@ -731,9 +983,44 @@ impl LogChunk {
             self.content.push('\n');
             self.content_bytes = self.content_bytes.saturating_add(1);
         }
+        let content_start = self.content.len();
         self.content_bytes = self.content_bytes.saturating_add(content.len());
         self.content.push_str(&content);
         self.line_count = self.line_count.saturating_add(1);
+
+        match self.open_event.as_mut() {
+            Some(event) if continues => {
+                event.line_end = line_number;
+                event.content_end = self.content.len();
+                event.line_count += 1;
+            }
+            _ => {
+                self.close_event();
+                self.open_event = Some(EventSpan {
+                    line_start: line_number,
+                    line_end: line_number,
+                    content_start,
+                    content_end: self.content.len(),
+                    event_time_ms,
+                    line_count: 1,
+                });
+            }
+        }
+    }
+
+    /// Keeps the open event when it spans several lines; single-line events
+    /// are already addressable through the segment itself.
+    fn close_event(&mut self) {
+        if let Some(event) = self.open_event.take()
+            && event.line_count > 1
+        {
+            self.events.push(event);
+        }
+    }
+
+    fn finish(mut self) -> Self {
+        self.close_event();
+        self
     }
 
     #[cfg(test)]
@@ -756,41 +1043,77 @@ impl LogChunk {
     fn reached_target(&self, max_lines: usize, target_bytes: usize) -> bool {
         self.line_count >= max_lines || self.content_bytes >= target_bytes
     }
+
+    /// Whether an event still open at the chunk target must be cut anyway.
+    fn reached_event_limit(&self, max_lines: usize, target_bytes: usize) -> bool {
+        self.line_count >= max_lines || self.content_bytes >= target_bytes.saturating_mul(4)
+    }
 }
 
 async fn flush_log_chunks(
     tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
     bundle_id: &str,
     file_id: i64,
+    blob_index_id: Option<i64>,
+    event_time_shift_ms: i64,
     chunks: &[LogChunk],
 ) -> Result<(), AppError> {
     for batch in chunks.chunks(SEGMENT_BATCH_SIZE) {
-        let mut segments = QueryBuilder::<Sqlite>::new(
-            "INSERT INTO log_segments (bundle_id, file_id, timeline, content, line_offset, line_end, chunk_index, event_time_start_ms, event_time_end_ms, event_time_indexed) ",
+        // log_segments_fts is an external-content table over
+        // log_segment_contents, maintained by triggers.
+        let mut contents = QueryBuilder::<Sqlite>::new(
+            "INSERT INTO log_segment_contents (blob_index_id, timeline, line_offset, line_end, chunk_index, level_mask, content) ",
         );
-        segments.push_values(batch, |mut row, chunk| {
-            row.push_bind(bundle_id)
-                .push_bind(file_id)
+        contents.push_values(batch, |mut row, chunk| {
+            row.push_bind(blob_index_id)
                 .push_bind("all")
-                .push_bind(chunk.content())
                 .push_bind(chunk.line_start)
                 .push_bind(chunk.line_end)
                 .push_bind(chunk.chunk_index)
+                .push_bind(chunk.level_mask)
+                .push_bind(chunk.content());
+        });
+        contents.push(" RETURNING id, chunk_index");
+        let content_ids: HashMap<i64, i64> = contents
+            .build_query_as::<(i64, i64)>()
+            .fetch_all(&mut **tx)
+            .await
+            .map_err(AppError::Database)?
+            .into_iter()
+            .map(|(content_id, chunk_index)| (chunk_index, content_id))
+            .collect();
+        if batch
+            .iter()
+            .any(|chunk| !content_ids.contains_key(&chunk.chunk_index))
+        {
+            return Err(AppError::Database(sqlx::Error::Protocol(
+                "log content insert did not return every chunk".into(),
+            )));
+        }
+
+        let mut segments = QueryBuilder::<Sqlite>::new(
+            "INSERT INTO log_segments (bundle_id, file_id, content_id, event_time_start_ms, event_time_end_ms, event_time_indexed, event_time_shift_ms) ",
+        );
+        segments.push_values(batch, |mut row, chunk| {
+            row.push_bind(bundle_id)
+                .push_bind(file_id)
+                .push_bind(content_ids[&chunk.chunk_index])
                 .push_bind(chunk.event_time_start_ms)
                 .push_bind(chunk.event_time_end_ms)
-                .push_bind(1_i64);
+                .push_bind(1_i64)
+                .push_bind(event_time_shift_ms);
         });
-        segments.push(" RETURNING id, chunk_index");
+        segments.push(" RETURNING id, content_id");
         let returned = segments
             .build_query_as::<(i64, i64)>()
             .fetch_all(&mut **tx)
             .await
             .map_err(AppError::Database)?;
         let mut segment_ids = HashMap::with_capacity(returned.len());
-        for (segment_id, chunk_index) in returned {
-            if segment_ids.insert(chunk_index, segment_id).is_some() {
+        for (segment_id, content_id) in returned {
+            if segment_ids.insert(content_id, segment_id).is_some() {
                 return Err(AppError::Database(sqlx::Error::Protocol(format!(
-                    "duplicate returned log chunk index {chunk_index}"
+                    "duplicate returned log content id {content_id}"
                 ))));
             }
         }
@@ -801,14 +1124,40 @@ async fn flush_log_chunks(
         }
         if batch
             .iter()
-            .any(|chunk| !segment_ids.contains_key(&chunk.chunk_index))
+            .any(|chunk| !segment_ids.contains_key(&content_ids[&chunk.chunk_index]))
         {
             return Err(AppError::Database(sqlx::Error::Protocol(
-                "log segment insert returned an unexpected chunk index".into(),
+                "log segment insert returned an unexpected content id".into(),
             )));
         }
 
-        // log_segments_fts is an external-content table maintained by triggers.
+        let spans: Vec<(i64, &EventSpan)> = batch
+            .iter()
+            .flat_map(|chunk| {
+                let segment_id = segment_ids[&content_ids[&chunk.chunk_index]];
+                chunk.events.iter().map(move |event| (segment_id, event))
+            })
+            .collect();
+        for spans in spans.chunks(EVENT_SPAN_BATCH_SIZE) {
+            let mut builder = QueryBuilder::<Sqlite>::new(
+                "INSERT INTO log_event_spans (segment_id, file_id, line_start, line_end, content_start, content_end, event_time_ms, event_time_shift_ms) ",
+            );
+            builder.push_values(spans, |mut row, (segment_id, event)| {
+                row.push_bind(*segment_id)
+                    .push_bind(file_id)
+                    .push_bind(event.line_start)
+                    .push_bind(event.line_end)
+                    .push_bind(event.content_start as i64)
+                    .push_bind(event.content_end as i64)
+                    .push_bind(event.event_time_ms)
+                    .push_bind(event_time_shift_ms);
+            });
+            builder
+                .build()
+                .execute(&mut **tx)
+                .await
+                .map_err(AppError::Database)?;
+        }
     }
 
     Ok(())
@@ -841,10 +1190,10 @@ mod tests {
     };
     use super::{
         ArchiveBudget, IndexBatchBudget, IssueQuota, LogChunk, PreparedDirectoryEntry,
-        archive_parent_depth, event_time_range, extract_gzip_file, extracted_directory_meta,
-        extracted_entry_meta, flush_log_chunks, gzip_output_name, insert_directory_children,
-        insert_line_offsets, parse_event_time_ms, sanitize_archive_path, uploaded_file_meta,
-        validate_extracted_path,
+        StreamCompression, archive_parent_depth, event_time_range, extract_archive,
+        extract_compressed_file, extracted_directory_meta, extracted_entry_meta, flush_log_chunks,
+        insert_directory_children, insert_line_offsets, parse_event_time_ms, sanitize_archive_path,
+        stream_output_name, uploaded_file_meta, validate_extracted_path,
     };
 
     #[test]
@@ -917,8 +1266,8 @@ mod tests {
     #[test]
     fn log_chunk_tracks_utf8_content_bytes_and_reuses_content() {
         let mut chunk = LogChunk::new(0, 2);
-        chunk.push(0, "alpha".into());
-        chunk.push(1, "世界".into());
+        chunk.push(0, "alpha".into(), None, false);
+        chunk.push(1, "世界".into(), None, false);
 
         assert_eq!(chunk.len(), 2);
         assert_eq!(chunk.byte_len(), "alpha\n世界".len());
@@ -928,7 +1277,7 @@ mod tests {
     #[test]
     fn log_chunk_reaches_byte_target_independently() {
         let mut chunk = LogChunk::new(0, 1);
-        chunk.push(0, "x".repeat(INDEX_CHUNK_TARGET_BYTES));
+        chunk.push(0, "x".repeat(INDEX_CHUNK_TARGET_BYTES), None, false);
 
         assert!(chunk.reached_target(INDEX_CHUNK_MAX_LINES, INDEX_CHUNK_TARGET_BYTES));
         assert_eq!(chunk.len(), 1);
@@ -938,7 +1287,7 @@ mod tests {
     fn log_chunk_reaches_line_target_independently() {
         let mut chunk = LogChunk::new(0, INDEX_CHUNK_MAX_LINES);
         for line in 0..INDEX_CHUNK_MAX_LINES {
-            chunk.push(line as i64, "x".into());
+            chunk.push(line as i64, "x".into(), None, false);
         }
 
         assert!(chunk.reached_target(INDEX_CHUNK_MAX_LINES, INDEX_CHUNK_TARGET_BYTES));
@@ -949,7 +1298,7 @@ mod tests {
     fn log_chunk_byte_accounting_saturates() {
         let mut chunk = LogChunk::new(0, 1);
         chunk.content_bytes = usize::MAX;
-        chunk.push(0, "x".into());
+        chunk.push(0, "x".into(), None, false);
 
         assert_eq!(chunk.byte_len(), usize::MAX);
     }
@@ -965,6 +1314,7 @@ mod tests {
             preview_kind: crate::file_classification::PreviewKind::Text,
             meta: serde_json::json!({ "kind": "extracted_file" }),
             blob_id: None,
+            encoding: None,
         }
     }
 
@@ -1021,12 +1371,12 @@ mod tests {
         let mut chunks = Vec::new();
         for index in 0..125_i64 {
             let mut chunk = LogChunk::new(index, 1);
-            chunk.push(index, format!("unique-content-{index}"));
+            chunk.push(index, format!("unique-content-{index}"), None, false);
             chunks.push(chunk);
         }
         let mut tx = pool.begin().await.unwrap();
 
-        flush_log_chunks(&mut tx, "bundle", file_id, &chunks)
+        flush_log_chunks(&mut tx, "bundle", file_id, None, 0, &chunks)
             .await
             .unwrap();
         tx.commit().await.unwrap();
@@ -1035,7 +1385,7 @@ mod tests {
             r#"
             SELECT COUNT(*)
             FROM log_segments ls
-            JOIN log_segments_fts ON log_segments_fts.rowid = ls.id
+            JOIN log_segments_fts ON log_segments_fts.rowid = ls.content_id
             WHERE ls.file_id = ? AND log_segments_fts MATCH 'unique'
             "#,
         )
@@ -1056,11 +1406,21 @@ mod tests {
         .await
         .unwrap();
         let mut chunk = LogChunk::new(0, 128);
-        chunk.push(0, "2026-08-14T09:32:15 first".into());
-        chunk.push(1, "2026-08-14T09:33:15 second".into());
+        chunk.push(
+            0,
+            "2026-08-14T09:32:15 first".into(),
+            parse_event_time_ms("2026-08-14T09:32:15 first"),
+            false,
+        );
+        chunk.push(
+            1,
+            "2026-08-14T09:33:15 second".into(),
+            parse_event_time_ms("2026-08-14T09:33:15 second"),
+            false,
+        );
         let mut tx = pool.begin().await.unwrap();
 
-        flush_log_chunks(&mut tx, "bundle", file_id, &[chunk])
+        flush_log_chunks(&mut tx, "bundle", file_id, None, 0, &[chunk])
             .await
             .unwrap();
         tx.commit().await.unwrap();
@@ -1082,6 +1442,39 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn flush_records_multi_line_event_spans_with_content_ranges() {
+        let pool = quota_fixture("EVENTSPANS", &["bundle"]).await;
+        let file_id: i64 = sqlx::query_scalar(
+            "INSERT INTO files (bundle_id, name, path, is_dir) VALUES ('bundle', 'app.log', '/bundle-hash/app.log', 0) RETURNING id",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        let started = parse_event_time_ms("2026-08-14T09:32:15 failed");
+        let mut chunk = LogChunk::new(0, 128);
+        chunk.push(0, "2026-08-14T09:32:15 failed".into(), started, false);
+        chunk.push(2, "\tat Service.run".into(), None, true);
+        chunk.push(3, "2026-08-14T09:32:16 single".into(), None, false);
+        let chunk = chunk.finish();
+        let mut tx = pool.begin().await.unwrap();
+
+        flush_log_chunks(&mut tx, "bundle", file_id, None, 0, &[chunk])
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let spans: Vec<(i64, i64, i64, i64, Option<i64>)> = sqlx::query_as(
+            "SELECT line_start, line_end, content_start, content_end, event_time_ms FROM log_event_spans WHERE file_id = ?",
+        )
+        .bind(file_id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        let content_end = "2026-08-14T09:32:15 failed\n\tat Service.run".len() as i64;
+        assert_eq!(spans, vec![(0, 2, 0, content_end, started)]);
+    }
+
     #[tokio::test]
     async fn batch_segment_failure_rolls_back_segments_and_fts_together() {
         let pool = quota_fixture("ROLLBACK", &["bundle"]).await;
@@ -1096,10 +1489,10 @@ mod tests {
             .await
             .unwrap();
         let mut chunk = LogChunk::new(0, 1);
-        chunk.push(0, "content".into());
+        chunk.push(0, "content".into(), None, false);
         let mut tx = pool.begin().await.unwrap();
 
-        let result = flush_log_chunks(&mut tx, "bundle", file_id, &[chunk]).await;
+        let result = flush_log_chunks(&mut tx, "bundle", file_id, None, 0, &[chunk]).await;
         assert!(result.is_err());
         tx.rollback().await.unwrap();
 
@@ -1126,7 +1519,7 @@ mod tests {
             .collect::<Vec<_>>();
         let mut tx = pool.begin().await.unwrap();
 
-        insert_line_offsets(&mut tx, file_id, &offsets)
+        insert_line_offsets(&mut tx, file_id, None, &offsets)
             .await
             .unwrap();
         tx.commit().await.unwrap();
@@ -1240,6 +1633,27 @@ mod tests {
         assert_eq!(used, 60);
     }
 
+    #[tokio::test]
+    async fn ready_bundle_quota_charges_appends_against_the_issue_limit() {
+        let pool = quota_fixture("APPEND", &["ready", "processing"]).await;
+        sqlx::query(
+            "UPDATE bundles SET status = 'READY', content_size_bytes = 40 WHERE id = 'ready'",
+        )
+        .execute(&pool)
+        .await
+        .expect("mark bundle ready");
+        let processing = IssueQuota::for_ready_bundle(pool.clone(), "APPEND", "processing", 100);
+        let ready = IssueQuota::for_ready_bundle(pool.clone(), "APPEND", "ready", 100);
+
+        processing
+            .reserve(1)
+            .await
+            .expect_err("only READY bundles accept appends");
+        ready.reserve(60).await.expect("append up to the limit");
+        ready.reserve(1).await.expect_err("reject overflow");
+        assert_eq!(ready.reserved_bytes().await.unwrap(), 100);
+    }
+
     #[test]
     fn archive_depth_counts_only_parent_directories() {
         let path =
@@ -1257,13 +1671,22 @@ mod tests {
 
     #[test]
     fn gzip_output_name_handles_mixed_case_suffix_with_utf8_name() {
-        assert_eq!(gzip_output_name("构建日志.Gz"), "____");
+        assert_eq!(
+            stream_output_name("构建日志.Gz", StreamCompression::Gzip),
+            "____"
+        );
     }
 
     #[test]
     fn gzip_output_name_prefixes_windows_reserved_device_names() {
-        assert_eq!(gzip_output_name("NUL.GZ"), "_NUL");
-        assert_eq!(gzip_output_name("com1.txt.gz"), "_com1.txt");
+        assert_eq!(
+            stream_output_name("NUL.GZ", StreamCompression::Gzip),
+            "_NUL"
+        );
+        assert_eq!(
+            stream_output_name("com1.txt.xz", StreamCompression::Xz),
+            "_com1.txt"
+        );
     }
 
     #[test]
@@ -1319,10 +1742,11 @@ mod tests {
             ..ArchiveConfig::default()
         };
 
-        let error = extract_gzip_file(
+        let error = extract_compressed_file(
             "sample.log.gz",
             &source,
             &destination,
+            StreamCompression::Gzip,
             ArchiveBudget::new(config),
         )
         .await
@@ -1343,11 +1767,283 @@ mod tests {
         let budget = ArchiveBudget::new(config);
         budget.reserve_bytes(4).unwrap();
 
-        let error = extract_gzip_file("sample.log.gz", &source, &destination, budget)
-            .await
-            .unwrap_err();
+        let error = extract_compressed_file(
+            "sample.log.gz",
+            &source,
+            &destination,
+            StreamCompression::Gzip,
+            budget,
+        )
+        .await
+        .unwrap_err();
 
         assert!(error.to_string().contains("max bundle size 8 B"));
         let _ = std::fs::remove_dir_all(source.parent().unwrap());
     }
+
+    fn tar_bytes(path: &str, content: &[u8]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, content).unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    fn tar_fixture(name: &str, archive_name: &str, compressed: &[u8]) -> (PathBuf, PathBuf) {
+        let root = std::env::temp_dir().join(format!("rain-ingest-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        let source = root.join(archive_name);
+        std::fs::write(&source, compressed).unwrap();
+        (source, root.join("out"))
+    }
+
+    #[tokio::test]
+    async fn tar_family_archives_extract_with_every_supported_compression() {
+        let tar = tar_bytes("nested/device.log", b"INFO tar family\n");
+        let xz = {
+            let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+            encoder.write_all(&tar).unwrap();
+            encoder.finish().unwrap()
+        };
+        let bz2 = {
+            let mut encoder =
+                bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+            encoder.write_all(&tar).unwrap();
+            encoder.finish().unwrap()
+        };
+        let zst = zstd::stream::encode_all(tar.as_slice(), 0).unwrap();
+
+        for (case, archive_name, bytes) in [
+            ("plain-tar", "dump.tar", tar.clone()),
+            ("tar-bz2", "dump.tar.bz2", bz2),
+            ("tar-xz", "dump.TAR.XZ", xz),
+            ("tar-zst", "dump.tzst", zst),
+        ] {
+            let (source, destination) = tar_fixture(case, archive_name, &bytes);
+            std::fs::create_dir_all(&destination).unwrap();
+
+            extract_archive(
+                archive_name,
+                &source,
+                &destination,
+                ArchiveBudget::default(),
+            )
+            .await
+            .unwrap_or_else(|error| panic!("{archive_name}: {error}"));
+
+            assert_eq!(
+                std::fs::read(destination.join("nested/device.log")).unwrap(),
+                b"INFO tar family\n",
+                "{archive_name}"
+            );
+            let _ = std::fs::remove_dir_all(source.parent().unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn corrupt_compressed_tars_are_reported_as_bad_archives() {
+        let payload: Vec<u8> = (0..64 * 1024_u32)
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        let tar = tar_bytes("device.log", &payload);
+        let gzip = {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&tar).unwrap();
+            encoder.finish().unwrap()
+        };
+        let xz = {
+            let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+            encoder.write_all(&tar).unwrap();
+            encoder.finish().unwrap()
+        };
+        for (archive_name, mut bytes) in [("dump.tar.gz", gzip), ("dump.tar.xz", xz)] {
+            let middle = bytes.len() / 2;
+            for byte in &mut bytes[middle..middle + 16] {
+                *byte ^= 0xa5;
+            }
+            let (source, destination) = tar_fixture("tar-corrupt", archive_name, &bytes);
+            std::fs::create_dir_all(&destination).unwrap();
+
+            let error = extract_archive(
+                archive_name,
+                &source,
+                &destination,
+                ArchiveBudget::default(),
+            )
+            .await
+            .expect_err(archive_name);
+
+            assert!(
+                matches!(&error, AppError::BadRequest(message) if message.contains("truncated or corrupt")),
+                "{archive_name}: {error:?}"
+            );
+            let _ = std::fs::remove_dir_all(source.parent().unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn tar_xz_applies_the_compression_ratio_guard() {
+        let tar = tar_bytes("flat.log", &vec![b'a'; 256 * 1024]);
+        let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 9);
+        encoder.write_all(&tar).unwrap();
+        let (source, destination) =
+            tar_fixture("tar-xz-ratio", "bomb.tar.xz", &encoder.finish().unwrap());
+        let config = ArchiveConfig {
+            max_compression_ratio: 10,
+            ..ArchiveConfig::default()
+        };
+
+        let error = extract_archive(
+            "bomb.tar.xz",
+            &source,
+            &destination,
+            ArchiveBudget::new(config),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(error.to_string().contains("compression ratio is too high"));
+        let _ = std::fs::remove_dir_all(source.parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn single_stream_logs_decompress_with_every_supported_codec() {
+        let content = b"ERROR rotated fleet log\n";
+        let xz = {
+            let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+            encoder.write_all(content).unwrap();
+            encoder.finish().unwrap()
+        };
+        let bz2 = {
+            let mut encoder =
+                bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+            encoder.write_all(content).unwrap();
+            encoder.finish().unwrap()
+        };
+        let zst = zstd::stream::encode_all(content.as_slice(), 0).unwrap();
+
+        for (case, archive_name, bytes, output) in [
+            ("stream-bz2", "kernel.log.bz2", bz2, "kernel.log"),
+            ("stream-xz", "kernel.log.XZ", xz, "kernel.log"),
+            ("stream-zst", "kernel.log.1.zst", zst, "kernel.log.1"),
+        ] {
+            let (source, destination) = tar_fixture(case, archive_name, &bytes);
+
+            extract_archive(
+                archive_name,
+                &source,
+                &destination,
+                ArchiveBudget::default(),
+            )
+            .await
+            .unwrap_or_else(|error| panic!("{archive_name}: {error}"));
+
+            assert_eq!(
+                std::fs::read(destination.join(output)).unwrap(),
+                content,
+                "{archive_name}"
+            );
+            let _ = std::fs::remove_dir_all(source.parent().unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn single_stream_reports_entry_limit_and_truncation() {
+        let zst = zstd::stream::encode_all(b"hello".as_slice(), 0).unwrap();
+        let (source, destination) = tar_fixture("stream-zst-limit", "sample.log.zst", &zst);
+        let config = ArchiveConfig {
+            max_entry_size: 4,
+            max_extracted_size: 8,
+            ..ArchiveConfig::default()
+        };
+
+        let error = extract_compressed_file(
+            "sample.log.zst",
+            &source,
+            &destination,
+            StreamCompression::Zstd,
+            ArchiveBudget::new(config),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(error.to_string().contains("max entry size 4 B"));
+        let _ = std::fs::remove_dir_all(source.parent().unwrap());
+
+        let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+        encoder.write_all(&[b'x'; 4096]).unwrap();
+        let mut xz = encoder.finish().unwrap();
+        xz.truncate(xz.len() / 2);
+        let (source, destination) = tar_fixture("stream-xz-truncated", "sample.log.xz", &xz);
+
+        let error = extract_compressed_file(
+            "sample.log.xz",
+            &source,
+            &destination,
+            StreamCompression::Xz,
+            ArchiveBudget::default(),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(
+            error
+                .to_string()
+                .contains("xz stream is truncated or corrupt"),
+            "{error}"
+        );
+        let _ = std::fs::remove_dir_all(source.parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn single_stream_reports_corrupt_data_as_a_bad_archive() {
+        let payload: Vec<u8> = (0..64 * 1024_u32)
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        let mut bzip2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bzip2.write_all(&payload).unwrap();
+        let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+        xz.write_all(&payload).unwrap();
+        let mut zstd = zstd::stream::write::Encoder::new(Vec::new(), 0).unwrap();
+        zstd.include_checksum(true).unwrap();
+        zstd.write_all(&payload).unwrap();
+        for (name, compression, mut bytes) in [
+            (
+                "sample.log.bz2",
+                StreamCompression::Bzip2,
+                bzip2.finish().unwrap(),
+            ),
+            ("sample.log.xz", StreamCompression::Xz, xz.finish().unwrap()),
+            (
+                "sample.log.zst",
+                StreamCompression::Zstd,
+                zstd.finish().unwrap(),
+            ),
+        ] {
+            let middle = bytes.len() / 2;
+            for byte in &mut bytes[middle..middle + 16] {
+                *byte ^= 0xa5;
+            }
+            let (source, destination) = tar_fixture("stream-corrupt", name, &bytes);
+
+            let error = extract_compressed_file(
+                name,
+                &source,
+                &destination,
+                compression,
+                ArchiveBudget::default(),
+            )
+            .await
+            .expect_err(name);
+
+            assert!(
+                matches!(&error, AppError::BadRequest(message) if message.contains("truncated or corrupt")),
+                "{name}: {error:?}"
+            );
+            let _ = std::fs::remove_dir_all(source.parent().unwrap());
+        }
+    }
 }
@@ -10,6 +10,7 @@ pub struct ArchiveBudget {
     counters: Arc<Mutex<ArchiveCounters>>,
     pub(crate) config: ArchiveConfig,
     temp_budget: Option<TempBudget>,
+    password: Option<Arc<str>>,
 }
 
 impl Default for ArchiveBudget {
@@ -30,9 +31,19 @@ impl ArchiveBudget {
             counters: Arc::new(Mutex::new(ArchiveCounters::default())),
             config,
             temp_budget: None,
+            password: None,
         }
     }
 
+    pub fn with_password(mut self, password: Option<String>) -> Self {
+        self.password = password.map(Arc::from);
+        self
+    }
+
+    pub(crate) fn password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+
     pub fn with_temp_budget(mut self, temp_budget: TempBudget) -> Self {
         self.temp_budget = Some(temp_budget);
         self
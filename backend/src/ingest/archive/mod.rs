@@ -1,19 +1,20 @@
 use std::{io, path::Path};
 
-use crate::error::AppError;
+use crate::{error::AppError, file_classification::TarCompression};
 
 mod budget;
-mod gzip;
 pub(crate) mod path_policy;
-mod tar_gz;
+mod stream;
+mod tar;
 mod zip;
 
 pub use budget::ArchiveBudget;
-#[cfg(test)]
-pub(crate) use gzip::extract_gzip_file;
 pub(crate) use path_policy::validate_extracted_path;
 #[cfg(test)]
-pub(crate) use path_policy::{archive_parent_depth, gzip_output_name, sanitize_archive_path};
+pub(crate) use path_policy::{archive_parent_depth, sanitize_archive_path};
+pub(crate) use stream::StreamCompression;
+#[cfg(test)]
+pub(crate) use stream::{extract_compressed_file, stream_output_name};
 
 pub(crate) async fn extract_archive(
     name: &str,
@@ -22,11 +23,11 @@ pub(crate) async fn extract_archive(
     archive_budget: ArchiveBudget,
 ) -> Result<(), AppError> {
     if is_zip_file(name) {
-        zip::extract_zip_archive(src, dest, archive_budget).await
-    } else if is_tar_gz_file(name) {
-        tar_gz::extract_tar_gz_archive(src, dest, archive_budget).await
-    } else if is_gzip_file(name) {
-        gzip::extract_gzip_file(name, src, dest, archive_budget).await
+        zip::extract_zip_archive(name, src, dest, archive_budget).await
+    } else if let Some(compression) = TarCompression::from_name(name) {
+        tar::extract_tar_archive(src, dest, compression, archive_budget).await
+    } else if let Some(compression) = StreamCompression::from_name(name) {
+        stream::extract_compressed_file(name, src, dest, compression, archive_budget).await
     } else {
         Err(AppError::BadRequest(format!(
             "unsupported archive type: {name}"
@@ -42,15 +43,6 @@ fn is_zip_file(name: &str) -> bool {
         .unwrap_or(false)
 }
 
-fn is_tar_gz_file(name: &str) -> bool {
-    let lower = name.to_ascii_lowercase();
-    lower.ends_with(".tar.gz") || lower.ends_with(".tgz")
-}
-
-fn is_gzip_file(name: &str) -> bool {
-    name.to_ascii_lowercase().ends_with(".gz") && !is_tar_gz_file(name)
-}
-
 pub(super) fn io_error(err: std::io::Error) -> AppError {
     AppError::Io(err)
 }
@@ -65,3 +57,54 @@ pub(super) fn io_error_at(operation: &str, path: &Path, error: std::io::Error) -
 pub(super) fn join_error(err: tokio::task::JoinError) -> AppError {
     io_error(io::Error::other(err.to_string()))
 }
+
+/// Maps a read error from a decompressing reader: bad input is a bad archive,
+/// anything else stays a retryable I/O error.
+pub(super) fn decode_error(label: &str, error: io::Error) -> AppError {
+    if is_corrupt_stream_error(&error) {
+        AppError::BadRequest(format!("{label} stream is truncated or corrupt"))
+    } else {
+        io_error(error)
+    }
+}
+
+/// Decoders and the tar reader report bad input as `UnexpectedEof`,
+/// `InvalidData` or `InvalidInput` (zstd and tar headers use `Other`) without
+/// an OS error code, unlike a real read failure on the staged file.
+fn is_corrupt_stream_error(error: &io::Error) -> bool {
+    use io::ErrorKind;
+
+    error.raw_os_error().is_none()
+        && matches!(
+            error.kind(),
+            ErrorKind::UnexpectedEof
+                | ErrorKind::InvalidData
+                | ErrorKind::InvalidInput
+                | ErrorKind::Other
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_corrupt_stream_error;
+
+    #[test]
+    fn os_read_failures_are_not_classed_as_corrupt_data() {
+        for kind in [
+            std::io::ErrorKind::UnexpectedEof,
+            std::io::ErrorKind::InvalidData,
+            std::io::ErrorKind::Other,
+        ] {
+            assert!(is_corrupt_stream_error(&std::io::Error::new(
+                kind,
+                "bad block"
+            )));
+        }
+        assert!(!is_corrupt_stream_error(
+            &std::io::Error::from_raw_os_error(5)
+        ));
+        assert!(!is_corrupt_stream_error(&std::io::Error::from(
+            std::io::ErrorKind::PermissionDenied
+        )));
+    }
+}
@@ -126,10 +126,11 @@ pub(crate) fn is_windows_reserved_name(segment: &str) -> bool {
         })
 }
 
-pub(crate) fn gzip_output_name(name: &str) -> String {
+pub(crate) fn decompressed_output_name(name: &str, suffix: &str) -> String {
     let lower = name.to_ascii_lowercase();
-    let stripped = if lower.ends_with(".gz") {
-        name.get(..name.len().saturating_sub(3)).unwrap_or(name)
+    let stripped = if lower.ends_with(suffix) {
+        name.get(..name.len().saturating_sub(suffix.len()))
+            .unwrap_or(name)
     } else {
         name
     };
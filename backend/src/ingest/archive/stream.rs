@@ -0,0 +1,178 @@
+use std::{
+    fs::File as StdFile,
+    io::{Read, Write},
+    path::Path,
+};
+
+use bzip2::read::BzDecoder;
+use flate2::read::GzDecoder;
+use tokio::task;
+use xz2::read::XzDecoder;
+
+use crate::error::AppError;
+
+use super::{
+    ArchiveBudget, decode_error, io_error, io_error_at, join_error,
+    path_policy::{
+        decompressed_output_name, format_binary_size, validate_archive_ratio,
+        validate_extracted_path,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StreamCompression {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl StreamCompression {
+    /// Single-stream compressed files; tar-wrapped names must be matched first.
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        let lower = name.to_ascii_lowercase();
+        [Self::Gzip, Self::Bzip2, Self::Xz, Self::Zstd]
+            .into_iter()
+            .find(|compression| lower.ends_with(compression.suffix()))
+    }
+
+    fn suffix(self) -> &'static str {
+        match self {
+            Self::Gzip => ".gz",
+            Self::Bzip2 => ".bz2",
+            Self::Xz => ".xz",
+            Self::Zstd => ".zst",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Bzip2 => "bzip2",
+            Self::Xz => "xz",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    fn decoder(self, file: StdFile) -> Result<Box<dyn Read>, std::io::Error> {
+        Ok(match self {
+            Self::Gzip => Box::new(GzDecoder::new(file)),
+            Self::Bzip2 => Box::new(BzDecoder::new(file)),
+            Self::Xz => Box::new(XzDecoder::new(file)),
+            Self::Zstd => Box::new(zstd::stream::read::Decoder::new(file)?),
+        })
+    }
+}
+
+pub(crate) fn stream_output_name(name: &str, compression: StreamCompression) -> String {
+    decompressed_output_name(name, compression.suffix())
+}
+
+pub(crate) async fn extract_compressed_file(
+    name: &str,
+    src: &Path,
+    dest: &Path,
+    compression: StreamCompression,
+    archive_budget: ArchiveBudget,
+) -> Result<(), AppError> {
+    let src_path = src.to_path_buf();
+    let dest_path = dest.to_path_buf();
+    let source_name = name.to_string();
+    let output_name = stream_output_name(name, compression);
+    let label = compression.label();
+    task::spawn_blocking(move || -> Result<(), AppError> {
+        archive_budget.reserve_entry()?;
+        let remaining = archive_budget.remaining_bytes()?;
+        let entry_limit = archive_budget.config.max_entry_size;
+        let copy_limit = entry_limit.min(remaining);
+        let compressed_size = std::fs::metadata(&src_path).map_err(io_error)?.len().max(1);
+        let file = StdFile::open(&src_path)
+            .map_err(|error| io_error_at(&format!("open {label} archive"), &src_path, error))?;
+        let mut decoder = compression
+            .decoder(file)
+            .map_err(|error| io_error_at(&format!("decode {label} archive"), &src_path, error))?;
+        std::fs::create_dir_all(&dest_path).map_err(|error| {
+            io_error_at(
+                &format!("create {label} extraction directory"),
+                &dest_path,
+                error,
+            )
+        })?;
+        let out_path = dest_path.join(output_name);
+        validate_extracted_path(
+            &out_path,
+            &source_name,
+            archive_budget.config.max_output_path_chars,
+        )?;
+        if out_path.exists() {
+            return Err(AppError::BadRequest(format!(
+                "{label} output path already exists: {}",
+                out_path.display()
+            )));
+        }
+        let mut outfile = StdFile::create(&out_path)
+            .map_err(|error| io_error_at(&format!("create {label} output"), &out_path, error))?;
+        let temp_budget = archive_budget.clone();
+        let copied = copy_with_limit(label, &mut decoder, &mut outfile, copy_limit, &temp_budget)
+            .map_err(|error| {
+            if matches!(&error, AppError::BadRequest(message) if message.contains("exceeds limit"))
+            {
+                if remaining <= entry_limit {
+                    AppError::BadRequest(format!(
+                        "archive bundle exceeds configured extracted size; max bundle size {}",
+                        format_binary_size(archive_budget.config.max_extracted_size)
+                    ))
+                } else {
+                    AppError::BadRequest(format!(
+                        "archive entry exceeds configured limit; max entry size {}",
+                        format_binary_size(entry_limit)
+                    ))
+                }
+            } else {
+                error
+            }
+        })?;
+        archive_budget.reserve_bytes(copied)?;
+        validate_archive_ratio(
+            &format!("{label} file"),
+            copied,
+            compressed_size,
+            archive_budget.config.max_compression_ratio,
+        )?;
+        Ok(())
+    })
+    .await
+    .map_err(join_error)??;
+
+    Ok(())
+}
+
+fn copy_with_limit<R: Read, W: Write>(
+    label: &str,
+    reader: &mut R,
+    writer: &mut W,
+    limit: u64,
+    archive_budget: &ArchiveBudget,
+) -> Result<u64, AppError> {
+    let mut buffer = [0u8; 16 * 1024];
+    let mut total = 0u64;
+    loop {
+        let read = reader
+            .read(&mut buffer)
+            .map_err(|error| decode_error(label, error))?;
+        if read == 0 {
+            break;
+        }
+        total = total
+            .checked_add(read as u64)
+            .ok_or_else(|| AppError::BadRequest(format!("{label} extracted size overflow")))?;
+        if total > limit {
+            return Err(AppError::BadRequest(format!(
+                "{label} exceeds limit of {limit} bytes"
+            )));
+        }
+        archive_budget.reserve_temp_bytes(read as u64)?;
+        writer.write_all(&buffer[..read]).map_err(io_error)?;
+    }
+    Ok(total)
+}
@@ -1,46 +1,70 @@
-use std::{collections::HashSet, fs::File as StdFile, path::Path};
+use std::{
+    collections::HashSet,
+    fs::File as StdFile,
+    io::{BufReader, Read},
+    path::Path,
+};
 
+use bzip2::read::BzDecoder;
 use flate2::read::GzDecoder;
 use tokio::task;
+use xz2::read::XzDecoder;
 
-use crate::error::AppError;
+use crate::{error::AppError, file_classification::TarCompression};
 
 use super::{
-    ArchiveBudget, io_error, io_error_at, join_error,
+    ArchiveBudget, decode_error, io_error, io_error_at, join_error,
     path_policy::{
         archive_parent_depth, format_binary_size, normalize_extracted_path, sanitize_archive_path,
         validate_archive_ratio, validate_extracted_path,
     },
 };
 
-pub(crate) async fn extract_tar_gz_archive(
+impl TarCompression {
+    fn decoder(self, file: StdFile) -> Result<Box<dyn Read>, std::io::Error> {
+        Ok(match self {
+            Self::None => Box::new(BufReader::new(file)),
+            Self::Gzip => Box::new(GzDecoder::new(file)),
+            Self::Bzip2 => Box::new(BzDecoder::new(file)),
+            Self::Xz => Box::new(XzDecoder::new(file)),
+            Self::Zstd => Box::new(zstd::stream::read::Decoder::new(file)?),
+        })
+    }
+}
+
+pub(crate) async fn extract_tar_archive(
     src: &Path,
     dest: &Path,
+    compression: TarCompression,
     archive_budget: ArchiveBudget,
 ) -> Result<(), AppError> {
     let src_path = src.to_path_buf();
     let dest_path = dest.to_path_buf();
     task::spawn_blocking(move || -> Result<(), AppError> {
+        let label = compression.label();
         let compressed_size = std::fs::metadata(&src_path).map_err(io_error)?.len().max(1);
         let file = StdFile::open(&src_path)
-            .map_err(|error| io_error_at("open tar.gz archive", &src_path, error))?;
-        let decoder = GzDecoder::new(file);
+            .map_err(|error| io_error_at(&format!("open {label} archive"), &src_path, error))?;
+        let decoder = compression
+            .decoder(file)
+            .map_err(|error| io_error_at(&format!("decode {label} archive"), &src_path, error))?;
         let mut archive = tar::Archive::new(decoder);
         let mut total_uncompressed = 0u64;
         let mut entries_count = 0usize;
         let mut seen_paths = HashSet::new();
 
-        for entry_result in archive.entries().map_err(io_error)? {
+        let corrupt = |error| decode_error(label, error);
+        for entry_result in archive.entries().map_err(corrupt)? {
             entries_count += 1;
             if entries_count > archive_budget.config.max_entries {
                 return Err(AppError::BadRequest(format!(
-                    "tar.gz has too many entries; max {}",
+                    "{label} has too many entries; max {}",
                     archive_budget.config.max_entries
                 )));
             }
 
-            let mut entry = entry_result.map_err(io_error)?;
-            let raw_path = entry.path().map_err(io_error)?.into_owned();
+            let mut entry = entry_result.map_err(corrupt)?;
+            let raw_path = entry.path().map_err(corrupt)?.into_owned();
             let entry_path = sanitize_archive_path(&raw_path);
             if entry_path.as_os_str().is_empty() {
                 continue;
@@ -53,12 +77,12 @@ pub(crate) async fn extract_tar_gz_archive(
             };
             if depth > archive_budget.config.max_path_depth {
                 return Err(AppError::BadRequest(format!(
-                    "tar.gz entry is too deep: {}",
+                    "{label} entry is too deep: {}",
                     raw_path.display()
                 )));
             }
 
-            let entry_size = entry.header().size().map_err(io_error)?;
+            let entry_size = entry.header().size().map_err(corrupt)?;
             if entry_size > archive_budget.config.max_entry_size {
                 return Err(AppError::BadRequest(format!(
                     "archive entry exceeds configured limit; max entry size {}: {}",
@@ -69,7 +93,7 @@ pub(crate) async fn extract_tar_gz_archive(
 
             total_uncompressed = total_uncompressed
                 .checked_add(entry_size)
-                .ok_or_else(|| AppError::BadRequest("tar.gz extracted size overflow".into()))?;
+                .ok_or_else(|| AppError::BadRequest(format!("{label} extracted size overflow")))?;
             archive_budget.reserve_entry()?;
             archive_budget.reserve_bytes(entry_size)?;
             archive_budget.reserve_temp_bytes(entry_size)?;
@@ -89,7 +113,7 @@ pub(crate) async fn extract_tar_gz_archive(
             let normalized_out = normalize_extracted_path(&out_path);
             if !seen_paths.insert(normalized_out) {
                 return Err(AppError::BadRequest(format!(
-                    "tar.gz contains duplicate normalized path: {}",
+                    "{label} contains duplicate normalized path: {}",
                     raw_path.display()
                 )));
             }
@@ -103,11 +127,18 @@ pub(crate) async fn extract_tar_gz_archive(
                 }
                 let mut outfile = StdFile::create(&out_path)
                     .map_err(|error| io_error_at("create extracted file", &out_path, error))?;
-                let copied = std::io::copy(&mut entry, &mut outfile)
-                    .map_err(|error| io_error_at("write extracted file", &out_path, error))?;
+                let copied =
+                    std::io::copy(&mut entry, &mut outfile).map_err(|error| {
+                        match corrupt(error) {
+                            AppError::Io(error) => {
+                                io_error_at("write extracted file", &out_path, error)
+                            }
+                            error => error,
+                        }
+                    })?;
                 if copied != entry_size {
                     return Err(AppError::BadRequest(format!(
-                        "tar.gz entry size mismatch: {}",
+                        "{label} entry size mismatch: {}",
                         raw_path.display()
                     )));
                 }
@@ -1,6 +1,12 @@
-use std::{collections::HashSet, path::Path};
+use std::{
+    collections::HashSet,
+    io::{Read, Write},
+    path::Path,
+};
 
+use actix_web::http::StatusCode;
 use tokio::task;
+use zip::result::ZipError;
 
 use crate::error::AppError;
 
@@ -13,12 +19,18 @@ use super::{
 };
 
 pub(crate) async fn extract_zip_archive(
+    name: &str,
     src: &Path,
     dest: &Path,
     archive_budget: ArchiveBudget,
 ) -> Result<(), AppError> {
     let src_path = src.to_path_buf();
     let dest_path = dest.to_path_buf();
+    // `src` is the uuid-prefixed storage name; messages name what the user uploaded.
+    let archive_name = Path::new(name)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| name.to_string());
     task::spawn_blocking(move || -> Result<(), AppError> {
         let file = std::fs::File::open(&src_path)
             .map_err(|error| io_error_at("open zip archive", &src_path, error))?;
@@ -34,9 +46,19 @@ pub(crate) async fn extract_zip_archive(
 
         let mut seen_paths = HashSet::new();
         for i in 0..archive.len() {
-            let mut entry = archive
-                .by_index(i)
-                .map_err(|err| AppError::BadRequest(err.to_string()))?;
+            let encrypted = matches!(
+                archive.by_index(i),
+                Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED))
+            );
+            let mut entry = match archive_budget.password().filter(|_| encrypted) {
+                Some(password) => archive
+                    .by_index_decrypt(i, password.as_bytes())
+                    .map_err(|err| zip_entry_error(err, &archive_name))?
+                    .map_err(|_| invalid_password_error(&archive_name))?,
+                None => archive
+                    .by_index(i)
+                    .map_err(|err| zip_entry_error(err, &archive_name))?,
+            };
             let entry_path = sanitize_archive_path(Path::new(entry.name()));
             if entry_path.as_os_str().is_empty() {
                 continue;
@@ -98,8 +120,15 @@ pub(crate) async fn extract_zip_archive(
                 }
                 let mut outfile = std::fs::File::create(&out_path)
                     .map_err(|error| io_error_at("create extracted file", &out_path, error))?;
-                let copied = std::io::copy(&mut entry, &mut outfile)
-                    .map_err(|error| io_error_at("write extracted file", &out_path, error))?;
+                let copied = copy_entry(&mut entry, &mut outfile).map_err(|error| match error {
+                    EntryCopyError::Read(error) if encrypted && is_decrypt_failure(&error) => {
+                        invalid_password_error(&archive_name)
+                    }
+                    EntryCopyError::Read(error) => io_error_at("read zip entry", &src_path, error),
+                    EntryCopyError::Write(error) => {
+                        io_error_at("write extracted file", &out_path, error)
+                    }
+                })?;
                 if copied != uncompressed_size {
                     return Err(AppError::BadRequest(format!(
                         "zip entry size mismatch: {}",
@@ -115,3 +144,95 @@ pub(crate) async fn extract_zip_archive(
 
     Ok(())
 }
+
+#[derive(Debug)]
+enum EntryCopyError {
+    Read(std::io::Error),
+    Write(std::io::Error),
+}
+
+fn copy_entry<R: Read, W: Write>(reader: &mut R, writer: &mut W) -> Result<u64, EntryCopyError> {
+    let mut buffer = [0u8; 16 * 1024];
+    let mut total = 0u64;
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(total),
+            Ok(read) => read,
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(EntryCopyError::Read(error)),
+        };
+        writer
+            .write_all(&buffer[..read])
+            .map_err(EntryCopyError::Write)?;
+        total += read as u64;
+    }
+}
+
+/// ZipCrypto only checks one header byte, so a wrong password usually
+/// surfaces later as a checksum or inflate failure from the entry reader.
+fn is_decrypt_failure(error: &std::io::Error) -> bool {
+    matches!(
+        error.kind(),
+        std::io::ErrorKind::InvalidData
+            | std::io::ErrorKind::InvalidInput
+            | std::io::ErrorKind::UnexpectedEof
+    )
+}
+
+fn zip_entry_error(error: ZipError, archive_name: &str) -> AppError {
+    match error {
+        ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED) => AppError::public(
+            StatusCode::BAD_REQUEST,
+            "ARCHIVE_PASSWORD_REQUIRED",
+            format!("压缩包 {archive_name} 已加密，请填写解压密码后重新上传"),
+        ),
+        other => AppError::BadRequest(other.to_string()),
+    }
+}
+
+fn invalid_password_error(archive_name: &str) -> AppError {
+    AppError::public(
+        StatusCode::BAD_REQUEST,
+        "ARCHIVE_PASSWORD_INVALID",
+        format!("压缩包 {archive_name} 的解压密码不正确，请确认后重新上传"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EntryCopyError, copy_entry, is_decrypt_failure};
+
+    struct FailingWriter(std::io::ErrorKind);
+
+    impl std::io::Write for FailingWriter {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(self.0.into())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_failures_are_not_mistaken_for_a_wrong_password() {
+        for kind in [
+            std::io::ErrorKind::PermissionDenied,
+            std::io::ErrorKind::QuotaExceeded,
+            std::io::ErrorKind::Other,
+        ] {
+            let error = copy_entry(&mut b"payload".as_slice(), &mut FailingWriter(kind))
+                .expect_err("write fails");
+            assert!(
+                matches!(&error, EntryCopyError::Write(error) if error.kind() == kind),
+                "{error:?}"
+            );
+        }
+
+        let checksum = std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid checksum");
+        assert!(is_decrypt_failure(&checksum));
+        assert!(!is_decrypt_failure(&std::io::Error::from(
+            std::io::ErrorKind::PermissionDenied
+        )));
+    }
+}
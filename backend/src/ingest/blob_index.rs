@@ -0,0 +1,458 @@
+//! Sharing of indexed log content between files backed by the same blob.
+//!
+//! Chunking depends only on the blob bytes and the settings folded into the
+//! index key, so the first file to index a blob under a key builds a
+//! `blob_indexes` row that owns the segment text, its full-text entries, the
+//! columns derived from the text alone (line range, chunk index, levels) and
+//! the line offsets. Later files with the same blob and key only get
+//! `log_segments` rows holding their bundle and shifted event times, copied
+//! from a file already linked to the index, and skip reading the file
+//! altogether. A file links to the index it uses through
+//! `files.blob_index_id`; indexes that lost their last link are collected by
+//! the blob GC.
+
+use std::collections::HashMap;
+
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, QueryBuilder, Sqlite, SqlitePool};
+
+use crate::{config::IndexingConfig, error::AppError, services::wall_clock};
+
+use super::{
+    EVENT_SPAN_BATCH_SIZE, EventTimeParser, FileEncoding, SEGMENT_BATCH_SIZE, TimestampRecognizers,
+    limits::{
+        INDEX_CHUNK_MAX_LINES, INDEX_CHUNK_TARGET_BYTES, INDEX_COMMIT_MAX_LINES,
+        INDEX_COMMIT_TARGET_BYTES, LINE_OFFSET_INTERVAL,
+    },
+};
+
+/// Bumped whenever chunking or cleaning changes, so new files stop linking to
+/// content produced by the old rules.
+const INDEX_FORMAT_VERSION: u32 = 1;
+
+/// Builds unfinished for this long are assumed to have died with their
+/// process and stop blocking new builds of the same index.
+const STALE_BUILD_HOURS: u64 = 6;
+
+/// Timestamp context of the file being linked. Only consulted when the shared
+/// event times were completed from the builder's bundle reference time.
+pub(super) struct LinkContext<'a> {
+    pub bundle_id: &'a str,
+    pub file_id: i64,
+    pub shift_ms: i64,
+    pub recognizers: &'a TimestampRecognizers,
+    pub reference: chrono::NaiveDateTime,
+}
+
+#[derive(FromRow)]
+struct DonorSegment {
+    id: i64,
+    content_id: i64,
+    chunk_index: i64,
+    event_time_start_ms: Option<i64>,
+    event_time_end_ms: Option<i64>,
+    event_time_indexed: i64,
+    event_time_shift_ms: i64,
+    content: Option<String>,
+}
+
+#[derive(FromRow)]
+struct DonorSpan {
+    segment_id: i64,
+    line_start: i64,
+    line_end: i64,
+    content_start: i64,
+    content_end: i64,
+    event_time_ms: Option<i64>,
+    event_time_shift_ms: i64,
+}
+
+/// Blob and index key of a file, or `None` when the file has no blob.
+pub(super) async fn index_key_for_file(
+    pool: &SqlitePool,
+    file_id: i64,
+    encoding: FileEncoding,
+    indexing: &IndexingConfig,
+) -> Result<Option<(i64, String)>, AppError> {
+    let blob_id: Option<i64> = sqlx::query_scalar("SELECT blob_id FROM files WHERE id = ?")
+        .bind(file_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(AppError::Database)?
+        .flatten();
+    let Some(blob_id) = blob_id else {
+        return Ok(None);
+    };
+    let formats = crate::repositories::timestamp_formats::list(pool).await?;
+    let formats: Vec<(&str, &str)> = formats
+        .iter()
+        .map(|record| (record.pattern.as_str(), record.format.as_str()))
+        .collect();
+    Ok(Some((blob_id, index_key(encoding, indexing, &formats))))
+}
+
+/// Fingerprint of every setting that shapes segment text and chunk
+/// boundaries. Admin timestamp formats decide where multi-line events start.
+fn index_key(
+    encoding: FileEncoding,
+    indexing: &IndexingConfig,
+    formats: &[(&str, &str)],
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(
+        format!(
+            "v{INDEX_FORMAT_VERSION}\n{}\n{}\n{}\n{INDEX_CHUNK_MAX_LINES}\n{INDEX_CHUNK_TARGET_BYTES}\n{INDEX_COMMIT_MAX_LINES}\n{INDEX_COMMIT_TARGET_BYTES}\n{LINE_OFFSET_INTERVAL}\n",
+            encoding.name.as_str(),
+            encoding.bom_bytes,
+            indexing.max_indexed_line_size,
+        )
+        .as_bytes(),
+    );
+    for (pattern, format) in formats {
+        hasher.update(format!("{}\0{}\n", pattern.len(), format.len()).as_bytes());
+        hasher.update(pattern.as_bytes());
+        hasher.update(format.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Links the file to a ready index of its blob and copies the per-file rows
+/// from a file already using it. Returns `false`, leaving the file untouched,
+/// when there is nothing to reuse.
+pub(super) async fn link_existing(
+    pool: &SqlitePool,
+    blob_id: i64,
+    index_key: &str,
+    context: &LinkContext<'_>,
+) -> Result<bool, AppError> {
+    let file_id = context.file_id;
+    let mut tx = pool.begin().await.map_err(AppError::Database)?;
+    // Writing first takes the database write lock, so the donor found below
+    // cannot be cleaned up before its rows are copied.
+    let linked: Option<i64> = sqlx::query_scalar(
+        r#"
+        UPDATE files SET blob_index_id = (
+            SELECT bi.id FROM blob_indexes bi
+            WHERE bi.blob_id = ? AND bi.index_key = ? AND bi.state = 'READY'
+              AND EXISTS (SELECT 1 FROM files d WHERE d.blob_index_id = bi.id AND d.id != ?)
+        )
+        WHERE id = ?
+        RETURNING blob_index_id
+        "#,
+    )
+    .bind(blob_id)
+    .bind(index_key)
+    .bind(file_id)
+    .bind(file_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(AppError::Database)?
+    .flatten();
+    let Some(index_id) = linked else {
+        tx.rollback().await.map_err(AppError::Database)?;
+        return Ok(false);
+    };
+    let (donor_id, line_count, reference_sensitive): (i64, Option<i64>, bool) = sqlx::query_as(
+        r#"
+        SELECT d.id, d.line_count, bi.reference_sensitive
+        FROM files d JOIN blob_indexes bi ON bi.id = d.blob_index_id
+        WHERE d.blob_index_id = ? AND d.id != ?
+        ORDER BY d.id
+        LIMIT 1
+        "#,
+    )
+    .bind(index_id)
+    .bind(file_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(AppError::Database)?;
+
+    // Yearless or time-only timestamps resolved against the builder's upload
+    // time are parsed again against this bundle's.
+    let mut parser = reference_sensitive.then(|| {
+        EventTimeParser::new(context.recognizers, context.reference).with_shift_ms(context.shift_ms)
+    });
+    let mut last_chunk = -1_i64;
+    loop {
+        let donors: Vec<DonorSegment> = sqlx::query_as(
+            r#"
+            SELECT ls.id, ls.content_id, lc.chunk_index,
+                   ls.event_time_start_ms, ls.event_time_end_ms, ls.event_time_indexed,
+                   ls.event_time_shift_ms,
+                   CASE WHEN ? THEN lc.content END AS content
+            FROM log_segment_contents lc
+            JOIN log_segments ls ON ls.content_id = lc.id AND ls.file_id = ?
+            WHERE lc.blob_index_id = ? AND lc.chunk_index > ?
+            ORDER BY lc.chunk_index
+            LIMIT ?
+            "#,
+        )
+        .bind(reference_sensitive)
+        .bind(donor_id)
+        .bind(index_id)
+        .bind(last_chunk)
+        .bind(SEGMENT_BATCH_SIZE as i64)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+        let Some(last) = donors.last() else {
+            break;
+        };
+        last_chunk = last.chunk_index;
+        copy_segments(&mut tx, context, &donors, parser.as_mut()).await?;
+    }
+
+    // Line offsets are read from the index from now on.
+    sqlx::query("DELETE FROM log_line_offsets WHERE file_id = ?")
+        .bind(file_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+    sqlx::query("UPDATE files SET line_count = ? WHERE id = ?")
+        .bind(line_count)
+        .bind(file_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+    tx.commit().await.map_err(AppError::Database)?;
+    tracing::debug!(
+        file_id,
+        donor_file_id = donor_id,
+        blob_index_id = index_id,
+        "linked file to an existing blob index"
+    );
+    Ok(true)
+}
+
+async fn copy_segments(
+    tx: &mut sqlx::Transaction<'_, Sqlite>,
+    context: &LinkContext<'_>,
+    donors: &[DonorSegment],
+    mut parser: Option<&mut EventTimeParser<'_>>,
+) -> Result<(), AppError> {
+    // Event times of each line start, keyed by donor segment and byte offset.
+    let mut line_times: HashMap<(i64, i64), Option<i64>> = HashMap::new();
+    let mut segments = QueryBuilder::<Sqlite>::new(
+        "INSERT INTO log_segments (bundle_id, file_id, content_id, event_time_start_ms, event_time_end_ms, event_time_indexed, event_time_shift_ms) ",
+    );
+    let mut rows = Vec::with_capacity(donors.len());
+    for donor in donors {
+        let (start, end) = match (parser.as_deref_mut(), donor.content.as_deref()) {
+            (Some(parser), Some(content)) => {
+                let mut range: (Option<i64>, Option<i64>) = (None, None);
+                let mut position = 0_i64;
+                for line in content.split('\n') {
+                    let time = parser.parse_ms(line);
+                    if let Some(time) = time {
+                        range.0 = Some(range.0.map_or(time, |current| current.min(time)));
+                        range.1 = Some(range.1.map_or(time, |current| current.max(time)));
+                    }
+                    line_times.insert((donor.id, position), time);
+                    position += line.len() as i64 + 1;
+                }
+                range
+            }
+            _ => {
+                let delta = context.shift_ms - donor.event_time_shift_ms;
+                let shift = |key: Option<i64>| {
+                    key.and_then(|key| wall_clock::shift_comparison_key(key, delta))
+                };
+                (
+                    shift(donor.event_time_start_ms),
+                    shift(donor.event_time_end_ms),
+                )
+            }
+        };
+        rows.push((donor, start, end));
+    }
+    segments.push_values(&rows, |mut row, (donor, start, end)| {
+        row.push_bind(context.bundle_id)
+            .push_bind(context.file_id)
+            .push_bind(donor.content_id)
+            .push_bind(*start)
+            .push_bind(*end)
+            .push_bind(donor.event_time_indexed)
+            .push_bind(context.shift_ms);
+    });
+    segments.push(" RETURNING id, content_id");
+    let returned: HashMap<i64, i64> = segments
+        .build_query_as::<(i64, i64)>()
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(AppError::Database)?
+        .into_iter()
+        .map(|(id, content_id)| (content_id, id))
+        .collect();
+    let mut segment_ids = HashMap::with_capacity(donors.len());
+    for donor in donors {
+        let Some(id) = returned.get(&donor.content_id) else {
+            return Err(AppError::Database(sqlx::Error::Protocol(
+                "linked log segment insert did not return every chunk".into(),
+            )));
+        };
+        segment_ids.insert(donor.id, *id);
+    }
+
+    let mut select = QueryBuilder::<Sqlite>::new(
+        "SELECT segment_id, line_start, line_end, content_start, content_end, event_time_ms, event_time_shift_ms FROM log_event_spans WHERE segment_id IN (",
+    );
+    let mut ids = select.separated(", ");
+    for donor in donors {
+        ids.push_bind(donor.id);
+    }
+    select.push(") ORDER BY id");
+    let spans: Vec<DonorSpan> = select
+        .build_query_as()
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(AppError::Database)?;
+    for spans in spans.chunks(EVENT_SPAN_BATCH_SIZE) {
+        let mut builder = QueryBuilder::<Sqlite>::new(
+            "INSERT INTO log_event_spans (segment_id, file_id, line_start, line_end, content_start, content_end, event_time_ms, event_time_shift_ms) ",
+        );
+        builder.push_values(spans, |mut row, span| {
+            // Spans keep the shift they were keyed with; readers adjust.
+            let (event_time_ms, shift_ms) =
+                match line_times.get(&(span.segment_id, span.content_start)) {
+                    Some(time) => (*time, context.shift_ms),
+                    None => (span.event_time_ms, span.event_time_shift_ms),
+                };
+            row.push_bind(segment_ids[&span.segment_id])
+                .push_bind(context.file_id)
+                .push_bind(span.line_start)
+                .push_bind(span.line_end)
+                .push_bind(span.content_start)
+                .push_bind(span.content_end)
+                .push_bind(event_time_ms)
+                .push_bind(shift_ms);
+        });
+        builder
+            .build()
+            .execute(&mut **tx)
+            .await
+            .map_err(AppError::Database)?;
+    }
+    Ok(())
+}
+
+/// Registers a new index of the blob for the file to build. Returns `None`
+/// when another file is building the same index right now; the caller then
+/// indexes into content private to the file. A build left unfinished for
+/// [`STALE_BUILD_HOURS`] is retired first by moving it off the index key, so
+/// files already linked to it keep their content until the blob GC takes it.
+pub(super) async fn claim(
+    pool: &SqlitePool,
+    file_id: i64,
+    blob_id: i64,
+    index_key: &str,
+) -> Result<Option<i64>, AppError> {
+    let mut tx = pool.begin().await.map_err(AppError::Database)?;
+    sqlx::query(
+        "UPDATE blob_indexes SET index_key = index_key || ':stale-' || id WHERE blob_id = ? AND index_key = ? AND state = 'BUILDING' AND datetime(created_at) <= datetime('now', ?)",
+    )
+    .bind(blob_id)
+    .bind(index_key)
+    .bind(format!("-{STALE_BUILD_HOURS} hours"))
+    .execute(&mut *tx)
+    .await
+    .map_err(AppError::Database)?;
+    let index_id: Option<i64> = sqlx::query_scalar(
+        "INSERT INTO blob_indexes (blob_id, index_key) VALUES (?, ?) ON CONFLICT (blob_id, index_key) DO NOTHING RETURNING id",
+    )
+    .bind(blob_id)
+    .bind(index_key)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(AppError::Database)?;
+    sqlx::query("UPDATE files SET blob_index_id = ? WHERE id = ?")
+        .bind(index_id)
+        .bind(file_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+    tx.commit().await.map_err(AppError::Database)?;
+    Ok(index_id)
+}
+
+/// Opens a built index for linking. `reference_sensitive` records whether any
+/// event time depended on the builder's bundle.
+pub(super) async fn mark_ready(
+    tx: &mut sqlx::Transaction<'_, Sqlite>,
+    index_id: i64,
+    reference_sensitive: bool,
+) -> Result<(), AppError> {
+    sqlx::query(
+        "UPDATE blob_indexes SET state = 'READY', reference_sensitive = ? WHERE id = ? AND state = 'BUILDING'",
+    )
+    .bind(reference_sensitive)
+    .bind(index_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(AppError::Database)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{EncodingSource, TextEncoding};
+
+    #[tokio::test]
+    async fn claim_retires_builds_that_never_finished() {
+        let pool = crate::db::init_pool("sqlite::memory:").unwrap();
+        crate::db::prepare_schema(&pool, false).await.unwrap();
+        let blob_id: i64 = sqlx::query_scalar(
+            "INSERT INTO blobs (content_hash, size_bytes, storage_backend, storage_key, state) VALUES ('hash', 1, 'local', 'key', 'READY') RETURNING id",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+        let building = claim(&pool, 1, blob_id, "key-v1").await.unwrap().unwrap();
+        assert_eq!(claim(&pool, 2, blob_id, "key-v1").await.unwrap(), None);
+
+        sqlx::query("UPDATE blob_indexes SET created_at = datetime('now', '-7 hours')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let rebuilt = claim(&pool, 2, blob_id, "key-v1").await.unwrap().unwrap();
+        assert_ne!(rebuilt, building);
+        let (retired_key, retired_state): (String, String) =
+            sqlx::query_as("SELECT index_key, state FROM blob_indexes WHERE id = ?")
+                .bind(building)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_ne!(retired_key, "key-v1");
+        assert_eq!(retired_state, "BUILDING");
+        assert_eq!(claim(&pool, 3, blob_id, "key-v1").await.unwrap(), None);
+    }
+
+    #[test]
+    fn index_key_changes_with_every_input_that_shapes_chunks() {
+        let utf8 = FileEncoding::default();
+        let indexing = IndexingConfig::default();
+        let base = index_key(utf8, &indexing, &[]);
+        assert_eq!(base, index_key(utf8, &indexing, &[]));
+
+        let gbk = FileEncoding {
+            name: TextEncoding::Gb18030,
+            source: EncodingSource::Default,
+            bom_bytes: 0,
+        };
+        let short_lines = IndexingConfig {
+            max_indexed_line_size: 1024,
+        };
+        for other in [
+            index_key(gbk, &indexing, &[]),
+            index_key(utf8, &short_lines, &[]),
+            index_key(utf8, &indexing, &[(r"\d+", "%s")]),
+        ] {
+            assert_ne!(base, other);
+        }
+        assert_ne!(
+            index_key(utf8, &indexing, &[("ab", "c")]),
+            index_key(utf8, &indexing, &[("a", "bc")])
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use encoding_rs::{CoderResult, Decoder};
+
+use crate::text_encoding::{FileEncoding, TextEncoding};
+
+use super::line_reader::TRUNCATED_LINE_MARKER;
+
+impl FileEncoding {
+    /// Whether the raw line starts with a space or tab, before trimming.
+    pub fn starts_indented(&self, line: &[u8]) -> bool {
+        let first = match self.name {
+            TextEncoding::Utf16Le => match line {
+                [unit, 0, ..] => *unit,
+                _ => return false,
+            },
+            TextEncoding::Utf16Be => match line {
+                [0, unit, ..] => *unit,
+                _ => return false,
+            },
+            _ => match line.first() {
+                Some(byte) => *byte,
+                None => return false,
+            },
+        };
+        matches!(first, b' ' | b'\t')
+    }
+
+    pub fn decode_line(&self, line: &[u8], truncated: bool) -> String {
+        if self.name == TextEncoding::Utf8 {
+            return super::line_reader::decode_log_line(line, truncated);
+        }
+        let (decoded, _) = self.name.encoding().decode_without_bom_handling(line);
+        let mut decoded = decoded.trim_end_matches(['\r', '\n']).to_string();
+        if truncated {
+            // The byte budget may split a multi-byte sequence at the end of the kept prefix.
+            while decoded.ends_with('\u{FFFD}') {
+                decoded.pop();
+            }
+            decoded.push_str(TRUNCATED_LINE_MARKER);
+        }
+        decoded
+    }
+
+    pub fn decode_lossy(&self, bytes: &[u8]) -> String {
+        let start = (self.bom_bytes as usize).min(bytes.len());
+        let (decoded, _) = self
+            .name
+            .encoding()
+            .decode_without_bom_handling(&bytes[start..]);
+        decoded.into_owned()
+    }
+}
+
+/// Streams decoded UTF-8 for callers that inspect a line chunk by chunk.
+pub struct StreamingDecoder {
+    decoder: Decoder,
+    output: String,
+}
+
+impl StreamingDecoder {
+    pub fn new(encoding: TextEncoding) -> Self {
+        Self {
+            decoder: encoding.new_decoder(),
+            output: String::new(),
+        }
+    }
+
+    pub fn decode(&mut self, mut chunk: &[u8], last: bool) -> &str {
+        self.output.clear();
+        loop {
+            let needed = self
+                .decoder
+                .max_utf8_buffer_length(chunk.len())
+                .unwrap_or(chunk.len().saturating_mul(3).saturating_add(16));
+            self.output.reserve(needed);
+            let (result, read, _) = self.decoder.decode_to_string(chunk, &mut self.output, last);
+            chunk = &chunk[read..];
+            if result == CoderResult::InputEmpty {
+                return &self.output;
+            }
+        }
+    }
+
+    pub fn reset(&mut self, encoding: TextEncoding) {
+        self.decoder = encoding.new_decoder();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::text_encoding::{EncodingSource, FileEncoding, TextEncoding};
+
+    use super::StreamingDecoder;
+
+    #[test]
+    fn decodes_truncated_legacy_lines_without_replacement_noise() {
+        let encoding = FileEncoding {
+            name: TextEncoding::Gb18030,
+            source: EncodingSource::Detected,
+            bom_bytes: 0,
+        };
+        let bytes = TextEncoding::Gb18030.encode("错误");
+        assert_eq!(encoding.decode_line(&bytes, false), "错误");
+        assert_eq!(
+            encoding.decode_line(&bytes[..3], true),
+            format!("错{}", super::TRUNCATED_LINE_MARKER)
+        );
+    }
+
+    #[test]
+    fn streaming_decoder_joins_characters_split_across_chunks() {
+        let bytes = TextEncoding::ShiftJis.encode("エラー");
+        let mut decoder = StreamingDecoder::new(TextEncoding::ShiftJis);
+        let mut text = decoder.decode(&bytes[..3], false).to_string();
+        text.push_str(decoder.decode(&bytes[3..], true));
+        assert_eq!(text, "エラー");
+    }
+}
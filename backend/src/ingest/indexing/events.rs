@@ -0,0 +1,160 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Lines of one event that a chunk may hold before the event is cut at the
+/// chunk boundary anyway, so a runaway trace cannot grow a segment unbounded.
+pub const MAX_EVENT_CHUNK_LINES: usize = 2_000;
+
+/// Prefixes of unindented lines that continue the previous event, as printed
+/// by Java and Python for chained or nested exceptions.
+const CONTINUATION_PREFIXES: &[&str] = &[
+    "Caused by",
+    "Suppressed:",
+    "Traceback",
+    "...",
+    "During handling of the above exception",
+    "The above exception was the direct cause",
+];
+
+/// Unindented exception header, as Java prints it right after the logged
+/// message and before the `at ...` frames.
+static EXCEPTION_HEADER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[\w$.]+(?:Exception|Error|Throwable)(?::|$)")
+        .expect("valid exception header pattern")
+});
+
+/// Splits an indexed line stream into events: a line that starts an entry,
+/// usually timestamped, followed by its stack-trace style continuations.
+///
+/// Only indented lines, Python tracebacks, well-known continuation prefixes
+/// and exception headers inside a timestamped event are grouped; any other
+/// line without a timestamp starts a new event, even after a timestamped one.
+#[derive(Debug, Default)]
+pub struct EventBoundaries {
+    in_traceback: bool,
+    in_timestamped_event: bool,
+}
+
+impl EventBoundaries {
+    /// Returns whether the trimmed `line` continues the current event rather
+    /// than starting a new one; `indented` reports the untrimmed line start.
+    pub fn continues(&mut self, line: &str, indented: bool, has_timestamp: bool) -> bool {
+        if has_timestamp {
+            self.in_traceback = false;
+            self.in_timestamped_event = true;
+            return false;
+        }
+        if line.starts_with("Traceback") {
+            self.in_traceback = true;
+            return true;
+        }
+        if indented {
+            return true;
+        }
+        if CONTINUATION_PREFIXES
+            .iter()
+            .any(|prefix| line.starts_with(prefix))
+        {
+            return true;
+        }
+        // A Python traceback ends with one unindented `Error: message` line.
+        if self.in_traceback {
+            self.in_traceback = false;
+            return true;
+        }
+        if self.in_timestamped_event && EXCEPTION_HEADER.is_match(line) {
+            return true;
+        }
+        self.in_timestamped_event = false;
+        false
+    }
+
+    /// Forgets the open event, e.g. when it had to be cut at a chunk boundary.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Line and content range of one multi-line event inside a log chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventSpan {
+    pub line_start: i64,
+    pub line_end: i64,
+    pub content_start: usize,
+    pub content_end: usize,
+    pub event_time_ms: Option<i64>,
+    pub line_count: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EventBoundaries;
+
+    fn continuations(lines: &[(&str, bool)]) -> Vec<bool> {
+        let mut boundaries = EventBoundaries::default();
+        lines
+            .iter()
+            .map(|(line, timestamped)| {
+                let indented = line.starts_with([' ', '\t']);
+                boundaries.continues(line.trim(), indented, *timestamped)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn groups_java_stack_traces_with_their_causes() {
+        assert_eq!(
+            continuations(&[
+                ("2026-01-01 10:00:00 ERROR request failed", true),
+                ("java.lang.IllegalStateException: boom", false),
+                ("\tat com.example.Service.run(Service.java:10)", false),
+                ("Caused by: java.io.IOException: closed", false),
+                ("\t... 3 more", false),
+                ("2026-01-01 10:00:01 INFO next", true),
+            ]),
+            vec![false, true, true, true, true, false]
+        );
+    }
+
+    #[test]
+    fn keeps_exception_headers_only_inside_timestamped_events() {
+        assert_eq!(
+            continuations(&[
+                ("2026-01-01 10:00:00 ERROR request failed", true),
+                ("java.lang.IllegalStateException", false),
+                ("\tat com.example.Service.run(Service.java:10)", false),
+                ("plain status line", false),
+                ("java.lang.OutOfMemoryError: heap", false),
+                ("\tat com.example.Cache.grow(Cache.java:3)", false),
+            ]),
+            vec![false, true, true, false, false, true]
+        );
+    }
+
+    #[test]
+    fn unindented_untimed_lines_after_a_timestamped_line_start_new_events() {
+        assert_eq!(
+            continuations(&[
+                ("2026-01-01 10:00:00 INFO started", true),
+                ("plain status line", false),
+                ("  detail of the status line", false),
+                ("another plain line", false),
+            ]),
+            vec![false, false, true, false]
+        );
+    }
+
+    #[test]
+    fn groups_untimed_python_tracebacks_through_the_final_exception_line() {
+        assert_eq!(
+            continuations(&[
+                ("job crashed", false),
+                ("Traceback (most recent call last):", false),
+                ("  File \"job.py\", line 3, in <module>", false),
+                ("ValueError: bad input", false),
+                ("plain line", false),
+            ]),
+            vec![false, true, true, true, false]
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Leading part of a line inspected for a level token; levels printed after
+/// the message are not reliable enough to classify on.
+const LEVEL_SCAN_PREFIX_BYTES: usize = 160;
+
+static LOGCAT_THREADTIME_LEVEL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\d{2}-\d{2}\s+\d{2}:\d{2}:\d{2}(?:\.\d+)?\s+\d+\s+\d+\s+(?P<level>[VDIWEFA])\s")
+        .expect("valid logcat threadtime level pattern")
+});
+static LOGCAT_TAG_LEVEL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?:^|\s)(?P<level>[VDIWEFA])/[^\s/:(]+\s*(?:\(\s*\d+\s*\))?\s*:")
+        .expect("valid logcat tag level pattern")
+});
+static BRACKETED_LETTER_LEVEL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\[(?P<level>[VDIWEF])\]").expect("valid bracketed letter level pattern")
+});
+static DELIMITED_LEVEL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?i)(?:\[\s*(?P<bracket>trace|debug|info|notice|warn|warning|error|err|fatal|critical|crit|severe|panic|verbose)\s*\]|<(?P<angle>trace|debug|info|notice|warn|warning|error|err|fatal|critical|crit|severe|panic|verbose)>|\blevel\s*[=:]\s*"?(?P<field>trace|debug|info|notice|warn|warning|error|err|fatal|critical|crit|severe|panic|verbose)\b)"#,
+    )
+    .expect("valid delimited level pattern")
+});
+static UPPERCASE_LEVEL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?:^|[\s\[(<|:=])(?P<level>TRACE|DEBUG|INFO|NOTICE|WARN|WARNING|ERROR|ERR|FATAL|CRITICAL|CRIT|SEVERE|PANIC|VERBOSE)(?:$|[\s\])>|:,])",
+    )
+    .expect("valid uppercase level pattern")
+});
+
+/// Normalised severity of a log line, ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 6] = [
+        LogLevel::Trace,
+        LogLevel::Debug,
+        LogLevel::Info,
+        LogLevel::Warn,
+        LogLevel::Error,
+        LogLevel::Fatal,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Trace => "TRACE",
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Warn => "WARN",
+            Self::Error => "ERROR",
+            Self::Fatal => "FATAL",
+        }
+    }
+
+    /// Bit of this level in a segment `level_mask`.
+    pub fn bit(self) -> i64 {
+        1 << self as i64
+    }
+
+    /// Mask of this level and every more severe one.
+    pub fn at_least_mask(self) -> i64 {
+        Self::ALL
+            .iter()
+            .filter(|level| **level >= self)
+            .fold(0, |mask, level| mask | level.bit())
+    }
+
+    /// Parses a level name or common alias such as `warning`, `err` or `e`.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "trace" | "verbose" | "v" => Some(Self::Trace),
+            "debug" | "d" => Some(Self::Debug),
+            "info" | "notice" | "i" => Some(Self::Info),
+            "warn" | "warning" | "w" => Some(Self::Warn),
+            "error" | "err" | "severe" | "e" => Some(Self::Error),
+            "fatal" | "critical" | "crit" | "panic" | "f" | "a" => Some(Self::Fatal),
+            _ => None,
+        }
+    }
+
+    /// Detects the level token near the start of a line: logcat `E` and
+    /// `W/Tag:` markers, `[E]`, `[warn]`/`<error>`/`level=info`, or an
+    /// uppercase word.
+    pub fn detect(line: &str) -> Option<Self> {
+        let mut end = line.len().min(LEVEL_SCAN_PREFIX_BYTES);
+        while !line.is_char_boundary(end) {
+            end -= 1;
+        }
+        let prefix = &line[..end];
+        if let Some(captures) = LOGCAT_THREADTIME_LEVEL
+            .captures(prefix)
+            .or_else(|| LOGCAT_TAG_LEVEL.captures(prefix))
+            .or_else(|| BRACKETED_LETTER_LEVEL.captures(prefix))
+        {
+            return Self::parse(&captures["level"]);
+        }
+        if let Some(captures) = DELIMITED_LEVEL.captures(prefix) {
+            let name = captures
+                .name("bracket")
+                .or_else(|| captures.name("angle"))
+                .or_else(|| captures.name("field"))?;
+            return Self::parse(name.as_str());
+        }
+        UPPERCASE_LEVEL
+            .captures(prefix)
+            .and_then(|captures| Self::parse(&captures["level"]))
+    }
+}
+
+/// Bitmask of every level found in the newline-separated `content`.
+pub fn level_mask(content: &str) -> i64 {
+    content
+        .lines()
+        .filter_map(LogLevel::detect)
+        .fold(0, |mask, level| mask | level.bit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LogLevel, level_mask};
+
+    #[test]
+    fn detects_levels_in_common_log_layouts() {
+        let cases = [
+            (
+                "2026-01-01 10:00:00 ERROR request failed",
+                Some(LogLevel::Error),
+            ),
+            (
+                "2026-01-01 10:00:00 [warn] disk almost full",
+                Some(LogLevel::Warn),
+            ),
+            ("[2026-01-01 10:00:00][E][net] reset", Some(LogLevel::Error)),
+            (
+                "10-16 08:01:02.345  1234  5678 E ActivityManager: ANR",
+                Some(LogLevel::Error),
+            ),
+            (
+                "10-16 08:01:02.345 W/Settings( 812): stale",
+                Some(LogLevel::Warn),
+            ),
+            ("ts=1 level=debug msg=\"cache miss\"", Some(LogLevel::Debug)),
+            (
+                "Oct 16 08:01:03 host kernel: <crit> overheated",
+                Some(LogLevel::Fatal),
+            ),
+            ("2026-01-01 10:00:00 user reported an error", None),
+            ("see /var/log/a/b: ok", None),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(LogLevel::detect(line), expected, "{line}");
+        }
+    }
+
+    #[test]
+    fn masks_select_levels_at_or_above_a_threshold() {
+        let mask = level_mask("x INFO a\ny WARN b\nplain");
+        assert_eq!(mask, LogLevel::Info.bit() | LogLevel::Warn.bit());
+        assert_ne!(mask & LogLevel::Warn.at_least_mask(), 0);
+        assert_eq!(mask & LogLevel::Error.at_least_mask(), 0);
+    }
+}
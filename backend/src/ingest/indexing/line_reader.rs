@@ -2,6 +2,8 @@ use std::io;
 
 use tokio::io::{AsyncBufRead, AsyncBufReadExt};
 
+use crate::text_encoding::{FileEncoding, TextEncoding};
+
 pub const TRUNCATED_LINE_MARKER: &str = " ... [line truncated]";
 
 #[derive(Debug, PartialEq, Eq)]
@@ -17,9 +19,12 @@ pub enum LimitedLine {
     },
 }
 
-pub(crate) fn clean_log_line(line: &[u8], truncated: bool) -> String {
+pub(crate) fn clean_log_line(line: &[u8], truncated: bool, encoding: &FileEncoding) -> String {
     // SQLite text values should not contain embedded null bytes in this app.
-    decode_log_line(line, truncated).trim().replace('\0', "")
+    encoding
+        .decode_line(line, truncated)
+        .trim()
+        .replace('\0', "")
 }
 
 pub async fn read_line_bytes_limited<R>(
@@ -169,6 +174,158 @@ where
     }
 }
 
+/// Like [`read_line_bytes_limited`], but splits lines on the encoding's own
+/// line feed so UTF-16 code units such as `0x0A 0x4E` are not mistaken for one.
+pub async fn read_encoded_line_limited<R>(
+    reader: &mut R,
+    encoding: TextEncoding,
+    output: &mut Vec<u8>,
+    max_bytes: usize,
+) -> Result<Option<(usize, usize, bool)>, io::Error>
+where
+    R: AsyncBufRead + Unpin,
+{
+    match read_encoded_line_limited_with_budget_and_callback(
+        reader,
+        encoding,
+        output,
+        max_bytes,
+        usize::MAX,
+        |_| {},
+    )
+    .await?
+    {
+        LimitedLine::EndOfFile => Ok(None),
+        LimitedLine::Line {
+            bytes_read,
+            original_length,
+            truncated,
+        } => Ok(Some((bytes_read, original_length, truncated))),
+        LimitedLine::ScanLimit { .. } => Err(io::Error::other("line scan budget exhausted")),
+    }
+}
+
+pub async fn read_encoded_line_limited_with_budget_and_callback<R, F>(
+    reader: &mut R,
+    encoding: TextEncoding,
+    output: &mut Vec<u8>,
+    max_bytes: usize,
+    scan_budget: usize,
+    on_content: F,
+) -> Result<LimitedLine, io::Error>
+where
+    R: AsyncBufRead + Unpin,
+    F: FnMut(&[u8]),
+{
+    match encoding.utf16_big_endian() {
+        Some(big_endian) => {
+            read_utf16_line(
+                reader,
+                big_endian,
+                output,
+                max_bytes,
+                scan_budget,
+                on_content,
+            )
+            .await
+        }
+        None => {
+            read_line_bytes_limited_with_budget_and_callback(
+                reader,
+                output,
+                max_bytes,
+                scan_budget,
+                on_content,
+            )
+            .await
+        }
+    }
+}
+
+async fn read_utf16_line<R, F>(
+    reader: &mut R,
+    big_endian: bool,
+    output: &mut Vec<u8>,
+    max_bytes: usize,
+    scan_budget: usize,
+    mut on_content: F,
+) -> Result<LimitedLine, io::Error>
+where
+    R: AsyncBufRead + Unpin,
+    F: FnMut(&[u8]),
+{
+    let unit_bytes = |value: u8| {
+        if big_endian { [0, value] } else { [value, 0] }
+    };
+    let (line_feed, carriage_return) = (unit_bytes(b'\n'), unit_bytes(b'\r'));
+    output.clear();
+    let mut total_read = 0usize;
+    let mut content_length = 0usize;
+    let mut pending_carriage_return = false;
+    let mut emit = |unit: &[u8], output: &mut Vec<u8>, content_length: &mut usize| {
+        on_content(unit);
+        if output.len() + unit.len() <= max_bytes {
+            output.extend_from_slice(unit);
+        }
+        *content_length += unit.len();
+    };
+
+    loop {
+        if total_read >= scan_budget {
+            if reader.fill_buf().await?.is_empty() {
+                break;
+            }
+            return Ok(LimitedLine::ScanLimit {
+                bytes_read: total_read,
+            });
+        }
+        let mut unit = [0u8; 2];
+        let mut unit_len = 0usize;
+        while unit_len < 2 {
+            let available = reader.fill_buf().await?;
+            if available.is_empty() {
+                break;
+            }
+            let take = (2 - unit_len).min(available.len());
+            unit[unit_len..unit_len + take].copy_from_slice(&available[..take]);
+            reader.consume(take);
+            unit_len += take;
+        }
+        if unit_len == 0 {
+            break;
+        }
+        total_read += unit_len;
+        if unit_len == 2 && unit == line_feed {
+            return Ok(LimitedLine::Line {
+                bytes_read: total_read,
+                original_length: content_length,
+                truncated: content_length > max_bytes,
+            });
+        }
+        if pending_carriage_return {
+            emit(&carriage_return, output, &mut content_length);
+            pending_carriage_return = false;
+        }
+        if unit_len == 2 && unit == carriage_return {
+            pending_carriage_return = true;
+            continue;
+        }
+        emit(&unit[..unit_len], output, &mut content_length);
+    }
+
+    if total_read == 0 {
+        return Ok(LimitedLine::EndOfFile);
+    }
+    if pending_carriage_return {
+        emit(&carriage_return, output, &mut content_length);
+    }
+    Ok(LimitedLine::Line {
+        bytes_read: total_read,
+        original_length: content_length,
+        truncated: content_length > max_bytes,
+    })
+}
+
 pub fn decode_log_line(line: &[u8], truncated: bool) -> String {
     let line = if truncated {
         match std::str::from_utf8(line) {
@@ -192,9 +349,10 @@ mod tests {
     use tokio::io::BufReader;
 
     use super::{
-        LimitedLine, decode_log_line, read_line_bytes_limited, read_line_bytes_limited_with_budget,
-        read_line_bytes_limited_with_budget_and_callback,
+        LimitedLine, decode_log_line, read_encoded_line_limited, read_line_bytes_limited,
+        read_line_bytes_limited_with_budget, read_line_bytes_limited_with_budget_and_callback,
     };
+    use crate::text_encoding::TextEncoding;
 
     async fn read_with_capacity(
         content: &[u8],
@@ -310,4 +468,33 @@ mod tests {
 
         assert_eq!(result, LimitedLine::ScanLimit { bytes_read: 4 });
     }
+
+    #[tokio::test]
+    async fn utf16_lines_split_on_code_units_not_embedded_line_feed_bytes() {
+        // U+4E0A is 0x0A 0x4E in UTF-16LE and must not end the line.
+        let content: Vec<u8> = "上\r\nb"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let mut reader = BufReader::with_capacity(3, content.as_slice());
+        let mut output = Vec::new();
+
+        let first = read_encoded_line_limited(&mut reader, TextEncoding::Utf16Le, &mut output, 16)
+            .await
+            .unwrap();
+        assert_eq!(first, Some((6, 2, false)));
+        assert_eq!(output, [0x0A, 0x4E]);
+
+        let second = read_encoded_line_limited(&mut reader, TextEncoding::Utf16Le, &mut output, 16)
+            .await
+            .unwrap();
+        assert_eq!(second, Some((2, 2, false)));
+        assert_eq!(output, [b'b', 0]);
+        assert_eq!(
+            read_encoded_line_limited(&mut reader, TextEncoding::Utf16Le, &mut output, 16)
+                .await
+                .unwrap(),
+            None
+        );
+    }
 }
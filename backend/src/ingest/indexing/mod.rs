@@ -1,3 +1,7 @@
+pub(crate) mod encoding;
+pub(crate) mod events;
+pub(crate) mod levels;
 pub(crate) mod line_reader;
+pub(crate) mod timestamps;
 
 pub(crate) use line_reader::clean_log_line;
@@ -0,0 +1,478 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::{error::AppError, services::wall_clock};
+
+/// Capture group an admin-defined pattern may use to select the timestamp text.
+pub const TIMESTAMP_CAPTURE_GROUP: &str = "ts";
+
+static DATED_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(?:\[[^\]\r\n]*\]\s*)*(?:\[(?P<bracket>\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:[.,]\d{1,9})?)\](?:\s|\[|$)|(?P<plain>\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:[.,]\d{1,9})?)(?:\s|$))",
+    )
+    .expect("valid event timestamp pattern")
+});
+static LOGCAT_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?P<month>\d{2})-(?P<day>\d{2})\s+(?P<time>\d{2}:\d{2}:\d{2}(?:\.\d{1,9})?)\s")
+        .expect("valid logcat timestamp pattern")
+});
+static SYSLOG_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(?:<\d{1,3}>)?(?P<month>Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Oct|Nov|Dec)\s+(?P<day>\d{1,2})\s+(?P<time>\d{2}:\d{2}:\d{2})(?:\s|$)",
+    )
+    .expect("valid syslog timestamp pattern")
+});
+static EPOCH_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\[?(?P<seconds>\d{10}|\d{13})(?:\.(?P<fraction>\d{1,9}))?(?:\]|\s|$)")
+        .expect("valid epoch timestamp pattern")
+});
+static CLOCK_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?:\[[^\]\r\n]*\]\s*)*\[?(?P<time>\d{2}:\d{2}:\d{2}(?:[.,]\d{1,9})?)(?:\]|\s|$)")
+        .expect("valid clock timestamp pattern")
+});
+
+/// Earliest and latest epoch values accepted as timestamps, so that ordinary
+/// ten or thirteen digit ids at the start of a line are not read as dates.
+const MIN_EPOCH_SECONDS: i64 = 946_684_800;
+const MAX_EPOCH_SECONDS: i64 = 4_102_444_800;
+
+/// A timestamp found at the start of a log line, possibly missing calendar parts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecognizedTime {
+    Full(NaiveDateTime),
+    WithoutYear {
+        month: u32,
+        day: u32,
+        time: NaiveTime,
+    },
+    TimeOnly(NaiveTime),
+}
+
+/// Recognises one timestamp layout at the start of a log line.
+pub trait TimestampRecognizer: Send + Sync {
+    fn recognize(&self, line: &str) -> Option<RecognizedTime>;
+}
+
+/// `YYYY-MM-DD HH:MM:SS[.fff]`, optionally bracketed and after `[..]` prefixes.
+pub struct DatedRecognizer;
+
+impl TimestampRecognizer for DatedRecognizer {
+    fn recognize(&self, line: &str) -> Option<RecognizedTime> {
+        let timestamp = DATED_PATTERN
+            .captures(line)
+            .and_then(|captures| captures.name("bracket").or_else(|| captures.name("plain")))?
+            .as_str()
+            .replace('T', " ");
+        wall_clock::parse(&timestamp).map(RecognizedTime::Full)
+    }
+}
+
+/// Android logcat `threadtime` lines: `MM-DD HH:MM:SS.mmm`.
+pub struct LogcatRecognizer;
+
+impl TimestampRecognizer for LogcatRecognizer {
+    fn recognize(&self, line: &str) -> Option<RecognizedTime> {
+        let captures = LOGCAT_PATTERN.captures(line)?;
+        Some(RecognizedTime::WithoutYear {
+            month: captures["month"].parse().ok()?,
+            day: captures["day"].parse().ok()?,
+            time: parse_clock(&captures["time"])?,
+        })
+    }
+}
+
+/// BSD syslog lines: `Oct 17 12:00:01`, with an optional `<PRI>` prefix.
+pub struct SyslogRecognizer;
+
+impl TimestampRecognizer for SyslogRecognizer {
+    fn recognize(&self, line: &str) -> Option<RecognizedTime> {
+        let captures = SYSLOG_PATTERN.captures(line)?;
+        let month = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ]
+        .iter()
+        .position(|name| *name == &captures["month"])?;
+        Some(RecognizedTime::WithoutYear {
+            month: u32::try_from(month).ok()? + 1,
+            day: captures["day"].parse().ok()?,
+            time: parse_clock(&captures["time"])?,
+        })
+    }
+}
+
+/// Unix epoch seconds (10 digits) or milliseconds (13 digits), read as UTC.
+pub struct EpochRecognizer;
+
+impl TimestampRecognizer for EpochRecognizer {
+    fn recognize(&self, line: &str) -> Option<RecognizedTime> {
+        let captures = EPOCH_PATTERN.captures(line)?;
+        let digits = &captures["seconds"];
+        let value: i64 = digits.parse().ok()?;
+        let (seconds, mut millis) = if digits.len() == 13 {
+            (value / 1_000, value % 1_000)
+        } else {
+            (value, 0)
+        };
+        if digits.len() == 10
+            && let Some(fraction) = captures.name("fraction")
+        {
+            let padded = format!("{:0<3}", fraction.as_str());
+            millis = padded[..3].parse().ok()?;
+        }
+        if !(MIN_EPOCH_SECONDS..MAX_EPOCH_SECONDS).contains(&seconds) {
+            return None;
+        }
+        chrono::DateTime::from_timestamp(seconds, u32::try_from(millis).ok()? * 1_000_000)
+            .map(|datetime| RecognizedTime::Full(datetime.naive_utc()))
+    }
+}
+
+/// Time-of-day only lines: `HH:MM:SS[.fff]`, optionally bracketed.
+pub struct ClockRecognizer;
+
+impl TimestampRecognizer for ClockRecognizer {
+    fn recognize(&self, line: &str) -> Option<RecognizedTime> {
+        let captures = CLOCK_PATTERN.captures(line)?;
+        parse_clock(&captures["time"]).map(RecognizedTime::TimeOnly)
+    }
+}
+
+/// An admin-defined regex plus a chrono `strftime` format.
+///
+/// The regex must match at the start of the line; the `ts` capture group (or
+/// the whole match) is parsed with the format. Formats without a year, or
+/// without a date, are completed by [`EventTimeParser`].
+pub struct PatternRecognizer {
+    regex: Regex,
+    format: String,
+}
+
+impl PatternRecognizer {
+    pub fn new(pattern: &str, format: &str) -> Result<Self, AppError> {
+        let pattern = pattern.trim();
+        let anchored = if pattern.starts_with('^') {
+            pattern.to_owned()
+        } else {
+            format!("^(?:{pattern})")
+        };
+        let regex = Regex::new(&anchored)
+            .map_err(|error| AppError::BadRequest(format!("invalid timestamp pattern: {error}")))?;
+        let format = format.trim();
+        if format.is_empty()
+            || chrono::format::StrftimeItems::new(format)
+                .any(|item| matches!(item, chrono::format::Item::Error))
+        {
+            return Err(AppError::BadRequest(format!(
+                "invalid timestamp format: {format}"
+            )));
+        }
+        Ok(Self {
+            regex,
+            format: format.to_owned(),
+        })
+    }
+
+    /// Parses `sample` as a whole line, for validating a definition before saving it.
+    pub fn recognizes(&self, sample: &str) -> bool {
+        self.recognize(sample).is_some()
+    }
+}
+
+impl TimestampRecognizer for PatternRecognizer {
+    fn recognize(&self, line: &str) -> Option<RecognizedTime> {
+        let captures = self.regex.captures(line)?;
+        let text = captures
+            .name(TIMESTAMP_CAPTURE_GROUP)
+            .or_else(|| captures.get(0))?
+            .as_str()
+            .trim();
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(text, &self.format) {
+            return Some(RecognizedTime::Full(truncate_to_millis(datetime)));
+        }
+        // 2000 is a leap year, so a yearless Feb 29 still parses here.
+        if let Ok(datetime) =
+            NaiveDateTime::parse_from_str(&format!("2000 {text}"), &format!("%Y {}", self.format))
+        {
+            return Some(RecognizedTime::WithoutYear {
+                month: datetime.month(),
+                day: datetime.day(),
+                time: truncate_time_to_millis(datetime.time()),
+            });
+        }
+        NaiveTime::parse_from_str(text, &self.format)
+            .ok()
+            .map(|time| RecognizedTime::TimeOnly(truncate_time_to_millis(time)))
+    }
+}
+
+/// Ordered recognisers: admin-defined patterns first, then the built-ins.
+pub struct TimestampRecognizers {
+    recognizers: Vec<Box<dyn TimestampRecognizer>>,
+}
+
+impl Default for TimestampRecognizers {
+    fn default() -> Self {
+        Self::with_custom(Vec::new())
+    }
+}
+
+impl TimestampRecognizers {
+    pub fn with_custom(custom: Vec<PatternRecognizer>) -> Self {
+        let mut recognizers: Vec<Box<dyn TimestampRecognizer>> = custom
+            .into_iter()
+            .map(|recognizer| Box::new(recognizer) as Box<dyn TimestampRecognizer>)
+            .collect();
+        recognizers.push(Box::new(DatedRecognizer));
+        recognizers.push(Box::new(LogcatRecognizer));
+        recognizers.push(Box::new(SyslogRecognizer));
+        recognizers.push(Box::new(EpochRecognizer));
+        recognizers.push(Box::new(ClockRecognizer));
+        Self { recognizers }
+    }
+
+    pub fn recognize(&self, line: &str) -> Option<RecognizedTime> {
+        self.recognizers
+            .iter()
+            .find_map(|recognizer| recognizer.recognize(line))
+    }
+}
+
+/// Per-file parser that completes partial timestamps into wall-clock values.
+///
+/// Yearless timestamps take the year of the previous complete timestamp in
+/// the file, or else the bundle's reference time; a value later than the
+/// reference is assumed to belong to the previous year. Time-only values take
+/// the previous date, rolling over to the next day when the clock wraps.
+pub struct EventTimeParser<'a> {
+    recognizers: &'a TimestampRecognizers,
+    reference: NaiveDateTime,
+    last: Option<NaiveDateTime>,
+    shift_ms: i64,
+    used_reference: bool,
+}
+
+impl<'a> EventTimeParser<'a> {
+    pub fn new(recognizers: &'a TimestampRecognizers, reference: NaiveDateTime) -> Self {
+        Self {
+            recognizers,
+            reference,
+            last: None,
+            shift_ms: 0,
+            used_reference: false,
+        }
+    }
+
+    /// Applies a timezone and clock-skew correction to every returned key.
+    pub fn with_shift_ms(mut self, shift_ms: i64) -> Self {
+        self.shift_ms = shift_ms;
+        self
+    }
+
+    pub fn parse(&mut self, line: &str) -> Option<NaiveDateTime> {
+        let recognized = self.recognizers.recognize(line)?;
+        if self.last.is_none() && !matches!(recognized, RecognizedTime::Full(_)) {
+            self.used_reference = true;
+        }
+        let resolved = match recognized {
+            RecognizedTime::Full(datetime) => datetime,
+            RecognizedTime::WithoutYear { month, day, time } => {
+                self.resolve_without_year(month, day, time)?
+            }
+            RecognizedTime::TimeOnly(time) => self.resolve_time_only(time),
+        };
+        self.last = Some(resolved);
+        Some(resolved)
+    }
+
+    /// Parses `line` and returns its shifted wall-clock comparison key.
+    pub fn parse_ms(&mut self, line: &str) -> Option<i64> {
+        let parsed = self.parse(line)?;
+        self.shifted_key(parsed)
+    }
+
+    /// Most recent parsed time, which resolves later year- or date-less
+    /// timestamps.
+    pub fn last(&self) -> Option<NaiveDateTime> {
+        self.last
+    }
+
+    /// Shifted comparison key of [`Self::last`], i.e. the event time carried
+    /// forward to lines without their own timestamp.
+    pub fn last_ms(&self) -> Option<i64> {
+        self.last.and_then(|last| self.shifted_key(last))
+    }
+
+    /// Whether any result so far was completed from the reference time rather
+    /// than from an earlier timestamp, i.e. depends on the bundle it is in.
+    pub fn used_reference(&self) -> bool {
+        self.used_reference
+    }
+
+    /// Resumes parsing after a line whose time was `last`.
+    pub fn with_last(mut self, last: Option<NaiveDateTime>) -> Self {
+        self.last = last;
+        self
+    }
+
+    fn shifted_key(&self, time: NaiveDateTime) -> Option<i64> {
+        time.checked_add_signed(Duration::milliseconds(self.shift_ms))
+            .and_then(wall_clock::comparison_key)
+    }
+
+    fn resolve_without_year(&self, month: u32, day: u32, time: NaiveTime) -> Option<NaiveDateTime> {
+        let at =
+            |year: i32| NaiveDate::from_ymd_opt(year, month, day).map(|date| date.and_time(time));
+        if let Some(last) = self.last {
+            let candidate = at(last.year())?;
+            if candidate + Duration::days(183) < last {
+                return at(last.year() + 1);
+            }
+            return Some(candidate);
+        }
+        let year = self.reference.year();
+        match at(year) {
+            Some(candidate) if candidate <= self.reference + Duration::days(1) => Some(candidate),
+            _ => (1..=4).find_map(|back| at(year - back)),
+        }
+    }
+
+    fn resolve_time_only(&self, time: NaiveTime) -> NaiveDateTime {
+        let Some(last) = self.last else {
+            return self.reference.date().and_time(time);
+        };
+        let candidate = last.date().and_time(time);
+        if candidate + Duration::hours(12) < last {
+            candidate + Duration::days(1)
+        } else {
+            candidate
+        }
+    }
+}
+
+fn parse_clock(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(&value.replace(',', "."), "%H:%M:%S%.f")
+        .ok()
+        .map(truncate_time_to_millis)
+}
+
+fn truncate_time_to_millis(time: NaiveTime) -> NaiveTime {
+    time.with_nanosecond((time.nanosecond() / 1_000_000) * 1_000_000)
+        .unwrap_or(time)
+}
+
+fn truncate_to_millis(datetime: NaiveDateTime) -> NaiveDateTime {
+    datetime
+        .date()
+        .and_time(truncate_time_to_millis(datetime.time()))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::{EventTimeParser, PatternRecognizer, TimestampRecognizers};
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f").unwrap()
+    }
+
+    #[test]
+    fn built_in_recognizers_cover_logcat_syslog_epoch_and_clock_lines() {
+        let recognizers = TimestampRecognizers::default();
+        let mut parser = EventTimeParser::new(&recognizers, at("2026-10-17 12:00:00.0"));
+
+        assert_eq!(
+            parser.parse("10-16 08:01:02.345  1234  5678 E ActivityManager: ANR"),
+            Some(at("2026-10-16 08:01:02.345"))
+        );
+        assert_eq!(
+            parser.parse("Oct 16 08:01:03 host kernel: oops"),
+            Some(at("2026-10-16 08:01:03.0"))
+        );
+        assert_eq!(
+            parser.parse("1792137600 boot"),
+            Some(at("2026-10-16 08:00:00.0"))
+        );
+        assert_eq!(
+            parser.parse("1792137600123 boot"),
+            Some(at("2026-10-16 08:00:00.123"))
+        );
+        assert_eq!(
+            parser.parse("[08:00:05.5] worker up"),
+            Some(at("2026-10-16 08:00:05.5"))
+        );
+        assert_eq!(parser.parse("12345 not a timestamp"), None);
+        assert_eq!(parser.parse("2026-08-14T09:32:15Z message"), None);
+    }
+
+    #[test]
+    fn year_and_date_are_inferred_from_context() {
+        let recognizers = TimestampRecognizers::default();
+        let mut parser = EventTimeParser::new(&recognizers, at("2026-01-02 09:00:00.0"));
+
+        assert_eq!(
+            parser.parse("Dec 31 23:59:58 host cron: tick"),
+            Some(at("2025-12-31 23:59:58.0"))
+        );
+        assert_eq!(
+            parser.parse("Jan  1 00:00:01 host cron: tick"),
+            Some(at("2026-01-01 00:00:01.0"))
+        );
+        assert_eq!(
+            parser.parse("23:59:59 late"),
+            Some(at("2026-01-01 23:59:59.0"))
+        );
+        assert_eq!(
+            parser.parse("00:00:02 early"),
+            Some(at("2026-01-02 00:00:02.0"))
+        );
+
+        let mut fresh = EventTimeParser::new(&recognizers, at("2026-01-02 09:00:00.0"));
+        assert_eq!(
+            fresh.parse("07:30:00 boot"),
+            Some(at("2026-01-02 07:30:00.0"))
+        );
+    }
+
+    #[test]
+    fn admin_patterns_take_precedence_and_may_omit_the_year() {
+        let custom = vec![
+            PatternRecognizer::new(
+                r"I\((?P<ts>\d{2}/\d{2} \d{2}:\d{2}:\d{2})\)",
+                "%m/%d %H:%M:%S",
+            )
+            .unwrap(),
+            PatternRecognizer::new(r"\d{2}:\d{2}:\d{2}", "%H:%M:%S").unwrap(),
+        ];
+        let recognizers = TimestampRecognizers::with_custom(custom);
+        let mut parser = EventTimeParser::new(&recognizers, at("2026-10-17 12:00:00.0"));
+
+        assert_eq!(
+            parser.parse("I(10/15 11:12:13) radio on"),
+            Some(at("2026-10-15 11:12:13.0"))
+        );
+        assert_eq!(
+            parser.parse("11:12:14 next"),
+            Some(at("2026-10-15 11:12:14.0"))
+        );
+        assert!(PatternRecognizer::new("(", "%H").is_err());
+        assert!(PatternRecognizer::new(r"\d+", "%Q").is_err());
+    }
+
+    #[test]
+    fn reference_use_is_tracked_until_a_full_timestamp_anchors_the_file() {
+        let recognizers = TimestampRecognizers::default();
+        let mut anchored = EventTimeParser::new(&recognizers, at("2026-01-02 09:00:00.0"));
+        anchored.parse("2025-06-01 10:00:00 start");
+        anchored.parse("Jun  1 10:00:01 host cron: tick");
+        anchored.parse("10:00:02 later");
+        assert!(!anchored.used_reference());
+
+        let mut partial = EventTimeParser::new(&recognizers, at("2026-01-02 09:00:00.0"));
+        partial.parse("no timestamp here");
+        assert!(!partial.used_reference());
+        partial.parse("07:30:00 boot");
+        assert!(partial.used_reference());
+    }
+}
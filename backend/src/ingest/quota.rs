@@ -8,6 +8,7 @@ pub struct IssueQuota {
     pool: SqlitePool,
     issue_code: String,
     bundle_id: String,
+    bundle_status: &'static str,
     limit: u64,
 }
 
@@ -22,10 +23,24 @@ impl IssueQuota {
             pool,
             issue_code: issue_code.into(),
             bundle_id: bundle_id.into(),
+            bundle_status: "PROCESSING",
             limit,
         }
     }
 
+    /// Charges content appended to a bundle that is already READY.
+    pub fn for_ready_bundle(
+        pool: SqlitePool,
+        issue_code: impl Into<String>,
+        bundle_id: impl Into<String>,
+        limit: u64,
+    ) -> Self {
+        Self {
+            bundle_status: "READY",
+            ..Self::new(pool, issue_code, bundle_id, limit)
+        }
+    }
+
     pub async fn reserve(&self, bytes: u64) -> Result<(), AppError> {
         if bytes == 0 {
             return Ok(());
@@ -42,7 +57,7 @@ impl IssueQuota {
             SET content_size_bytes = content_size_bytes + ?
             WHERE id = ?
               AND issue_code = ?
-              AND status = 'PROCESSING'
+              AND status = ?
               AND (
                 SELECT COALESCE(SUM(content_size_bytes), 0)
                 FROM bundles
@@ -53,6 +68,7 @@ impl IssueQuota {
         .bind(bytes)
         .bind(&self.bundle_id)
         .bind(&self.issue_code)
+        .bind(self.bundle_status)
         .bind(&self.issue_code)
         .bind(limit)
         .bind(bytes)
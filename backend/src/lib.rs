@@ -11,6 +11,7 @@ pub mod repositories;
 pub mod routes;
 pub mod services;
 pub mod skill_schema;
+pub mod text_encoding;
 pub mod upload;
 
 use chrono::{DateTime, Utc};
@@ -104,6 +105,9 @@ pub struct UploadRuntime {
     pub receive_permits: Arc<Semaphore>,
     pub tmp_bytes: Arc<AtomicU64>,
     pub temp_cleanup_queue: crate::upload::job::TempCleanupQueue,
+    pub resumable: crate::upload::resumable::ResumableUploads,
+    /// Bundles with an append in progress.
+    pub appending: Arc<Mutex<HashSet<String>>>,
 }
 
 impl UploadRuntime {
@@ -113,6 +117,8 @@ impl UploadRuntime {
             receive_permits: Arc::new(Semaphore::new(receiving)),
             tmp_bytes: Arc::new(AtomicU64::new(0)),
             temp_cleanup_queue: crate::upload::job::TempCleanupQueue::default(),
+            resumable: crate::upload::resumable::ResumableUploads::default(),
+            appending: Arc::new(Mutex::new(HashSet::new())),
         }
     }
 }
@@ -1,13 +1,116 @@
 use caseless::default_case_fold_str;
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    config::MAX_TEMP_RESULT_LOGICAL_LINE_BYTES,
+    ingest::{EventTimeParser, LogLevel},
+    services::wall_clock,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Expression {
+    /// Case-insensitive substring, stored case folded.
     Term(String),
+    /// Quoted phrase, matched with its exact case.
+    Phrase(String),
+    /// `/pattern/`, matched anywhere in the line.
+    Regex(LineRegex),
+    /// `level:ERROR,WARN` or `level>=WARN` as a mask of [`LogLevel::bit`]s.
+    Level(i64),
+    /// `time>=2026-10-01T12:00` and the other comparisons on event time.
+    Time(TimeBound),
     Not(Box<Expression>),
     And(Box<Expression>, Box<Expression>),
     Or(Box<Expression>, Box<Expression>),
 }
 
+/// Compiled `/pattern/` term; equal when the patterns are.
+#[derive(Debug, Clone)]
+pub struct LineRegex(Regex);
+
+impl PartialEq for LineRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Eq for LineRegex {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeComparison {
+    Before,
+    AtOrBefore,
+    After,
+    AtOrAfter,
+}
+
+/// Bound on event time; `key` is a wall-clock comparison key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeBound {
+    pub comparison: TimeComparison,
+    pub key: i64,
+}
+
+impl TimeBound {
+    fn admits(self, key: i64) -> bool {
+        match self.comparison {
+            TimeComparison::Before => key < self.key,
+            TimeComparison::AtOrBefore => key <= self.key,
+            TimeComparison::After => key > self.key,
+            TimeComparison::AtOrAfter => key >= self.key,
+        }
+    }
+}
+
+/// Level and event time of the log event a line belongs to, which `level`
+/// and `time` predicates test.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineEvent {
+    pub level: Option<LogLevel>,
+    /// Wall-clock comparison key of the event's timestamp.
+    pub time_key: Option<i64>,
+}
+
+impl LineEvent {
+    /// Facts of a line read on its own: its level and no event time.
+    pub fn of_line(line: &str) -> Self {
+        Self {
+            level: LogLevel::detect(line),
+            time_key: None,
+        }
+    }
+}
+
+/// Follows multi-line events through consecutive lines of one file: a line
+/// with a timestamp starts an event, and continuation lines keep its time
+/// and level unless they name a level of their own.
+pub struct LineEventTracker<'a> {
+    parser: EventTimeParser<'a>,
+    current: LineEvent,
+}
+
+impl<'a> LineEventTracker<'a> {
+    pub fn new(parser: EventTimeParser<'a>) -> Self {
+        Self {
+            parser,
+            current: LineEvent::default(),
+        }
+    }
+
+    pub fn observe(&mut self, line: &str) -> LineEvent {
+        let level = LogLevel::detect(line);
+        if let Some(time_key) = self.parser.parse_ms(line) {
+            self.current = LineEvent {
+                level,
+                time_key: Some(time_key),
+            };
+        } else if level.is_some() {
+            self.current.level = level;
+        }
+        self.current
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ParseError {
     pub offset: usize,
@@ -16,7 +119,7 @@ pub struct ParseError {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum TokenKind {
-    Term(String),
+    Operand(Expression),
     And,
     Or,
     Not,
@@ -31,20 +134,86 @@ struct Token {
 }
 
 impl Expression {
+    /// Matches a line on its own; `level` predicates use the line's level
+    /// and `time` predicates never match.
     pub fn matches(&self, line: &str) -> bool {
+        self.matches_event(line, LineEvent::of_line(line))
+    }
+
+    /// Matches a line that belongs to `event`.
+    pub fn matches_event(&self, line: &str, event: LineEvent) -> bool {
         let normalized = default_case_fold_str(line);
-        self.matches_normalized(&normalized)
+        self.matches_normalized(line, &normalized, event)
     }
 
-    fn matches_normalized(&self, line: &str) -> bool {
+    fn matches_normalized(&self, line: &str, normalized: &str, event: LineEvent) -> bool {
         match self {
-            Self::Term(term) => line.contains(term),
-            Self::Not(expression) => !expression.matches_normalized(line),
+            Self::Term(term) => normalized.contains(term),
+            Self::Phrase(phrase) => line.contains(phrase),
+            Self::Regex(regex) => regex.0.is_match(line),
+            Self::Level(mask) => event.level.is_some_and(|level| mask & level.bit() != 0),
+            Self::Time(bound) => event.time_key.is_some_and(|key| bound.admits(key)),
+            Self::Not(expression) => !expression.matches_normalized(line, normalized, event),
             Self::And(left, right) => {
-                left.matches_normalized(line) && right.matches_normalized(line)
+                left.matches_normalized(line, normalized, event)
+                    && right.matches_normalized(line, normalized, event)
+            }
+            Self::Or(left, right) => {
+                left.matches_normalized(line, normalized, event)
+                    || right.matches_normalized(line, normalized, event)
+            }
+        }
+    }
+
+    /// Whether `level` or `time` predicates appear, so callers know to track
+    /// multi-line events with a [`LineEventTracker`].
+    pub fn uses_event_fields(&self) -> bool {
+        match self {
+            Self::Level(_) | Self::Time(_) => true,
+            Self::Term(_) | Self::Phrase(_) | Self::Regex(_) => false,
+            Self::Not(expression) => expression.uses_event_fields(),
+            Self::And(left, right) | Self::Or(left, right) => {
+                left.uses_event_fields() || right.uses_event_fields()
+            }
+        }
+    }
+
+    /// Whether matching needs the whole line instead of streamed substrings.
+    fn needs_line_text(&self) -> bool {
+        match self {
+            Self::Regex(_) | Self::Level(_) => true,
+            Self::Term(_) | Self::Phrase(_) | Self::Time(_) => false,
+            Self::Not(expression) => expression.needs_line_text(),
+            Self::And(left, right) | Self::Or(left, right) => {
+                left.needs_line_text() || right.needs_line_text()
+            }
+        }
+    }
+
+    /// FTS5 query over the trigram index that every chunk holding a
+    /// matching line also matches, or `None` when no such narrowing exists.
+    /// Negations and field predicates are left to line verification, and
+    /// only ASCII substrings of at least three characters are used since the
+    /// index folds ASCII case only.
+    pub(crate) fn fts_prefilter(&self) -> Option<String> {
+        match self {
+            Self::Term(text) | Self::Phrase(text) if text.is_ascii() && text.len() >= 3 => {
+                Some(format!("\"{}\"", text.replace('"', "\"\"")))
             }
+            Self::Term(_)
+            | Self::Phrase(_)
+            | Self::Regex(_)
+            | Self::Level(_)
+            | Self::Time(_)
+            | Self::Not(_) => None,
+            Self::And(left, right) => match (left.fts_prefilter(), right.fts_prefilter()) {
+                (Some(left), Some(right)) => Some(format!("({left} AND {right})")),
+                (Some(one), None) | (None, Some(one)) => Some(one),
+                (None, None) => None,
+            },
             Self::Or(left, right) => {
-                left.matches_normalized(line) || right.matches_normalized(line)
+                let (left, right) = (left.fts_prefilter()?, right.fts_prefilter()?);
+                Some(format!("({left} OR {right})"))
             }
         }
     }
@@ -55,14 +224,16 @@ impl Expression {
         ExpressionChunkMatcher {
             terms: terms
                 .into_iter()
-                .map(|term| ChunkTerm {
+                .map(|(term, exact)| ChunkTerm {
                     keep_chars: term.chars().count().saturating_sub(1),
                     term,
+                    exact,
                     tail: String::new(),
                     found: false,
                 })
                 .collect(),
             pending: Vec::new(),
+            line: self.needs_line_text().then(String::new),
         }
     }
 }
@@ -72,14 +243,39 @@ pub const MAX_EXPRESSION_BYTES: usize = 16 * 1024;
 const MAX_EXPRESSION_TOKENS: usize = 128;
 const MAX_EXPRESSION_NESTING_DEPTH: usize = 32;
 const MAX_EXPRESSION_AST_NODES: usize = 128;
+/// Compiled program size cap for `/regex/` terms.
+const REGEX_SIZE_LIMIT: usize = 4 * 1024 * 1024;
+/// Longest line prefix kept for predicates that need the whole line.
+const MAX_BUFFERED_LINE_BYTES: usize = MAX_TEMP_RESULT_LOGICAL_LINE_BYTES as usize;
+
+/// Field predicate prefixes, longest first so `time>=` wins over `time>`.
+const FIELD_PREFIXES: [(&str, Field); 6] = [
+    ("level>=", Field::MinLevel),
+    ("level:", Field::Levels),
+    ("time>=", Field::Time(TimeComparison::AtOrAfter)),
+    ("time<=", Field::Time(TimeComparison::AtOrBefore)),
+    ("time>", Field::Time(TimeComparison::After)),
+    ("time<", Field::Time(TimeComparison::Before)),
+];
+
+#[derive(Clone, Copy)]
+enum Field {
+    Levels,
+    MinLevel,
+    Time(TimeComparison),
+}
 
 pub(crate) struct ExpressionChunkMatcher {
     terms: Vec<ChunkTerm>,
     pending: Vec<u8>,
+    /// Decoded line prefix, kept only when a predicate needs the whole line.
+    line: Option<String>,
 }
 
 struct ChunkTerm {
     term: String,
+    /// Matched against the text as is rather than case folded.
+    exact: bool,
     keep_chars: usize,
     tail: String,
     found: bool,
@@ -88,6 +284,9 @@ struct ChunkTerm {
 impl ExpressionChunkMatcher {
     pub(crate) fn reset(&mut self) {
         self.pending.clear();
+        if let Some(line) = &mut self.line {
+            line.clear();
+        }
         for term in &mut self.terms {
             term.tail.clear();
             term.found = false;
@@ -103,9 +302,17 @@ impl ExpressionChunkMatcher {
         self.decode_pending(true);
     }
 
+    /// Evaluates the fed line on its own, see [`Expression::matches`].
     pub(crate) fn matches(&self, expression: &Expression) -> bool {
+        let event = LineEvent::of_line(self.line.as_deref().unwrap_or_default());
+        self.matches_event(expression, event)
+    }
+
+    /// Evaluates the fed line as part of `event`.
+    pub(crate) fn matches_event(&self, expression: &Expression, event: LineEvent) -> bool {
         let mut cursor = 0;
-        let result = evaluate_chunk_matches(expression, &self.terms, &mut cursor);
+        let line = self.line.as_deref().unwrap_or_default();
+        let result = evaluate_chunk_matches(expression, &self.terms, &mut cursor, line, event);
         debug_assert_eq!(cursor, self.terms.len());
         result
     }
@@ -154,46 +361,63 @@ impl ExpressionChunkMatcher {
     }
 
     fn feed_text(&mut self, text: &str) {
-        self.feed_normalized(&default_case_fold_str(text));
-    }
-
-    fn feed_normalized(&mut self, normalized: &str) {
+        if let Some(line) = &mut self.line {
+            let (prefix, _) = prefix_by_bytes(text, MAX_BUFFERED_LINE_BYTES - line.len());
+            line.push_str(prefix);
+        }
+        let folded = self
+            .terms
+            .iter()
+            .any(|term| !term.exact && !term.found)
+            .then(|| default_case_fold_str(text));
         for term in &mut self.terms {
             if term.found {
                 continue;
             }
-
-            if normalized.contains(&term.term) {
-                term.found = true;
-                continue;
+            match &folded {
+                Some(folded) if !term.exact => term.feed(folded),
+                _ => term.feed(text),
             }
+        }
+    }
+}
 
-            let (prefix, has_more) = prefix_by_chars(normalized, term.keep_chars);
-            if !term.tail.is_empty() && !prefix.is_empty() {
-                let mut boundary = String::with_capacity(term.tail.len() + prefix.len());
-                boundary.push_str(&term.tail);
-                boundary.push_str(prefix);
-                if boundary.contains(&term.term) {
-                    term.found = true;
-                    continue;
-                }
-            }
+impl ChunkTerm {
+    /// Looks for the term in `text` and in the seam with the previous piece.
+    fn feed(&mut self, text: &str) {
+        if text.contains(&self.term) {
+            self.found = true;
+            return;
+        }
 
-            if has_more {
-                term.tail = keep_suffix(normalized, term.keep_chars);
-            } else {
-                let mut combined = String::with_capacity(term.tail.len() + normalized.len());
-                combined.push_str(&term.tail);
-                combined.push_str(normalized);
-                term.tail = keep_suffix(&combined, term.keep_chars);
+        let (prefix, has_more) = prefix_by_chars(text, self.keep_chars);
+        if !self.tail.is_empty() && !prefix.is_empty() {
+            let mut boundary = String::with_capacity(self.tail.len() + prefix.len());
+            boundary.push_str(&self.tail);
+            boundary.push_str(prefix);
+            if boundary.contains(&self.term) {
+                self.found = true;
+                return;
             }
         }
+
+        if has_more {
+            self.tail = keep_suffix(text, self.keep_chars);
+        } else {
+            let mut combined = String::with_capacity(self.tail.len() + text.len());
+            combined.push_str(&self.tail);
+            combined.push_str(text);
+            self.tail = keep_suffix(&combined, self.keep_chars);
+        }
     }
 }
 
-fn collect_terms(expression: &Expression, terms: &mut Vec<String>) {
+/// Substring terms in evaluation order, flagged when matched exactly.
+fn collect_terms(expression: &Expression, terms: &mut Vec<(String, bool)>) {
     match expression {
-        Expression::Term(term) => terms.push(term.clone()),
+        Expression::Term(term) => terms.push((term.clone(), false)),
+        Expression::Phrase(phrase) => terms.push((phrase.clone(), true)),
+        Expression::Regex(_) | Expression::Level(_) | Expression::Time(_) => {}
         Expression::Not(expression) => collect_terms(expression, terms),
         Expression::And(left, right) | Expression::Or(left, right) => {
             collect_terms(left, terms);
@@ -206,22 +430,29 @@ fn evaluate_chunk_matches(
     expression: &Expression,
     terms: &[ChunkTerm],
     cursor: &mut usize,
+    line: &str,
+    event: LineEvent,
 ) -> bool {
     match expression {
-        Expression::Term(_) => {
+        Expression::Term(_) | Expression::Phrase(_) => {
             let result = terms[*cursor].found;
             *cursor += 1;
             result
         }
-        Expression::Not(expression) => !evaluate_chunk_matches(expression, terms, cursor),
+        Expression::Regex(regex) => regex.0.is_match(line),
+        Expression::Level(mask) => event.level.is_some_and(|level| mask & level.bit() != 0),
+        Expression::Time(bound) => event.time_key.is_some_and(|key| bound.admits(key)),
+        Expression::Not(expression) => {
+            !evaluate_chunk_matches(expression, terms, cursor, line, event)
+        }
         Expression::And(left, right) => {
-            let left = evaluate_chunk_matches(left, terms, cursor);
-            let right = evaluate_chunk_matches(right, terms, cursor);
+            let left = evaluate_chunk_matches(left, terms, cursor, line, event);
+            let right = evaluate_chunk_matches(right, terms, cursor, line, event);
             left && right
         }
         Expression::Or(left, right) => {
-            let left = evaluate_chunk_matches(left, terms, cursor);
-            let right = evaluate_chunk_matches(right, terms, cursor);
+            let left = evaluate_chunk_matches(left, terms, cursor, line, event);
+            let right = evaluate_chunk_matches(right, terms, cursor, line, event);
             left || right
         }
     }
@@ -245,6 +476,17 @@ fn prefix_by_chars(value: &str, chars: usize) -> (&str, bool) {
     (&value[..end], true)
 }
 
+fn prefix_by_bytes(value: &str, bytes: usize) -> (&str, bool) {
+    if value.len() <= bytes {
+        return (value, false);
+    }
+    let mut end = bytes;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    (&value[..end], true)
+}
+
 pub fn parse(input: &str) -> Result<Expression, ParseError> {
     if input.len() > MAX_EXPRESSION_BYTES {
         return Err(ParseError {
@@ -317,43 +559,10 @@ fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
             cursor += 1;
             continue;
         }
+        let offset = cursor;
         if character == '"' {
-            let offset = cursor;
-            cursor += 1;
-            let mut phrase = String::new();
-            let mut closed = false;
-            while cursor < input.len() {
-                let quoted = input[cursor..].chars().next().expect("quoted character");
-                if quoted == '"' {
-                    cursor += quoted.len_utf8();
-                    closed = true;
-                    break;
-                }
-                if quoted == '\\' {
-                    cursor += quoted.len_utf8();
-                    if cursor >= input.len() {
-                        return Err(ParseError {
-                            offset,
-                            message: "unterminated quoted phrase".into(),
-                        });
-                    }
-                    let escaped = input[cursor..].chars().next().expect("escaped character");
-                    if escaped != '"' && escaped != '\\' {
-                        phrase.push('\\');
-                    }
-                    phrase.push(escaped);
-                    cursor += escaped.len_utf8();
-                    continue;
-                }
-                phrase.push(quoted);
-                cursor += quoted.len_utf8();
-            }
-            if !closed {
-                return Err(ParseError {
-                    offset,
-                    message: "unterminated quoted phrase".into(),
-                });
-            }
+            let (phrase, end) = read_quoted(input, offset)?;
+            cursor = end;
             let phrase = phrase.trim();
             if phrase.is_empty() {
                 return Err(ParseError {
@@ -362,32 +571,201 @@ fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
                 });
             }
             tokens.push(Token {
-                kind: TokenKind::Term(default_case_fold_str(phrase)),
+                kind: TokenKind::Operand(Expression::Phrase(phrase.to_string())),
                 offset,
             });
             continue;
         }
-
-        let offset = cursor;
-        while cursor < input.len() {
-            let next = input[cursor..].chars().next().expect("term character");
-            if next.is_whitespace() || next == '(' || next == ')' {
-                break;
-            }
-            cursor += next.len_utf8();
+        if character == '/' {
+            let (regex, end) = read_regex(input, offset)?;
+            cursor = end;
+            tokens.push(Token {
+                kind: TokenKind::Operand(regex),
+                offset,
+            });
+            continue;
         }
+        if let Some((prefix, field)) = FIELD_PREFIXES.iter().find(|(prefix, _)| {
+            input
+                .get(offset..offset + prefix.len())
+                .is_some_and(|candidate| candidate.eq_ignore_ascii_case(prefix))
+        }) {
+            let (predicate, end) = read_field(input, offset + prefix.len(), *field)?;
+            cursor = end;
+            tokens.push(Token {
+                kind: TokenKind::Operand(predicate),
+                offset,
+            });
+            continue;
+        }
+
+        cursor = word_end(input, offset);
         let word = &input[offset..cursor];
         let kind = match word.to_ascii_uppercase().as_str() {
             "AND" => TokenKind::And,
             "OR" => TokenKind::Or,
             "NOT" => TokenKind::Not,
-            _ => TokenKind::Term(default_case_fold_str(word)),
+            _ => TokenKind::Operand(Expression::Term(default_case_fold_str(word))),
         };
         tokens.push(Token { kind, offset });
     }
     Ok(tokens)
 }
 
+fn word_end(input: &str, mut cursor: usize) -> usize {
+    while cursor < input.len() {
+        let next = input[cursor..].chars().next().expect("term character");
+        if next.is_whitespace() || next == '(' || next == ')' {
+            break;
+        }
+        cursor += next.len_utf8();
+    }
+    cursor
+}
+
+/// Reads the quoted string opening at `offset`, returning its unescaped text
+/// and the offset just past the closing quote.
+fn read_quoted(input: &str, offset: usize) -> Result<(String, usize), ParseError> {
+    let mut cursor = offset + 1;
+    let mut text = String::new();
+    while cursor < input.len() {
+        let quoted = input[cursor..].chars().next().expect("quoted character");
+        if quoted == '"' {
+            return Ok((text, cursor + 1));
+        }
+        if quoted == '\\' {
+            cursor += 1;
+            if cursor >= input.len() {
+                break;
+            }
+            let escaped = input[cursor..].chars().next().expect("escaped character");
+            if escaped != '"' && escaped != '\\' {
+                text.push('\\');
+            }
+            text.push(escaped);
+            cursor += escaped.len_utf8();
+            continue;
+        }
+        text.push(quoted);
+        cursor += quoted.len_utf8();
+    }
+    Err(ParseError {
+        offset,
+        message: "unterminated quoted phrase".into(),
+    })
+}
+
+/// Reads the `/pattern/` opening at `offset`. The pattern is passed to the
+/// regex engine verbatim, where `\/` already means a literal slash, so
+/// syntax error spans map straight back onto the input.
+fn read_regex(input: &str, offset: usize) -> Result<(Expression, usize), ParseError> {
+    let start = offset + 1;
+    let mut cursor = start;
+    loop {
+        let Some(next) = input[cursor..].chars().next() else {
+            return Err(ParseError {
+                offset,
+                message: "unterminated regex".into(),
+            });
+        };
+        match next {
+            '/' => break,
+            '\\' => {
+                cursor += 1;
+                if let Some(escaped) = input[cursor..].chars().next() {
+                    cursor += escaped.len_utf8();
+                }
+            }
+            _ => cursor += next.len_utf8(),
+        }
+    }
+    let pattern = &input[start..cursor];
+    if pattern.is_empty() {
+        return Err(ParseError {
+            offset,
+            message: "regex cannot be empty".into(),
+        });
+    }
+    if let Err(error) = regex_syntax::Parser::new().parse(pattern) {
+        let (position, message) = match &error {
+            regex_syntax::Error::Parse(error) => {
+                (error.span().start.offset, error.kind().to_string())
+            }
+            regex_syntax::Error::Translate(error) => {
+                (error.span().start.offset, error.kind().to_string())
+            }
+            _ => (0, error.to_string()),
+        };
+        return Err(ParseError {
+            offset: start + position,
+            message: format!("invalid regex: {message}"),
+        });
+    }
+    let regex = RegexBuilder::new(pattern)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|_| ParseError {
+            offset,
+            message: "regex is too large".into(),
+        })?;
+    Ok((Expression::Regex(LineRegex(regex)), cursor + 1))
+}
+
+/// Reads the value of a field predicate starting at `start`, which may be
+/// quoted to include spaces, e.g. `time>="2026-10-01 12:00"`.
+fn read_field(input: &str, start: usize, field: Field) -> Result<(Expression, usize), ParseError> {
+    let (value, end) = if input[start..].starts_with('"') {
+        read_quoted(input, start)?
+    } else {
+        let end = word_end(input, start);
+        (input[start..end].to_string(), end)
+    };
+    if value.trim().is_empty() {
+        return Err(ParseError {
+            offset: start,
+            message: "field predicate needs a value".into(),
+        });
+    }
+    let value_offset = start + usize::from(input[start..].starts_with('"'));
+    let predicate = match field {
+        Field::Levels => {
+            let mut mask = 0;
+            let mut item_offset = value_offset;
+            for item in value.split(',') {
+                if !item.trim().is_empty() {
+                    mask |= parse_level(item, item_offset)?.bit();
+                }
+                item_offset += item.len() + 1;
+            }
+            if mask == 0 {
+                return Err(ParseError {
+                    offset: value_offset,
+                    message: "field predicate needs a value".into(),
+                });
+            }
+            Expression::Level(mask)
+        }
+        Field::MinLevel => Expression::Level(parse_level(&value, value_offset)?.at_least_mask()),
+        Field::Time(comparison) => {
+            let key = wall_clock::parse(&value)
+                .and_then(wall_clock::comparison_key)
+                .ok_or_else(|| ParseError {
+                    offset: value_offset,
+                    message: "invalid time, expected YYYY-MM-DD HH:MM[:SS[.mmm]]".into(),
+                })?;
+            Expression::Time(TimeBound { comparison, key })
+        }
+    };
+    Ok((predicate, end))
+}
+
+fn parse_level(value: &str, offset: usize) -> Result<LogLevel, ParseError> {
+    LogLevel::parse(value).ok_or_else(|| ParseError {
+        offset: offset + (value.len() - value.trim_start().len()),
+        message: format!("unknown log level {}", value.trim()),
+    })
+}
+
 struct Parser {
     tokens: Vec<Token>,
     cursor: usize,
@@ -442,7 +820,7 @@ impl Parser {
             });
         };
         match token.kind {
-            TokenKind::Term(term) => Ok(Expression::Term(term)),
+            TokenKind::Operand(expression) => Ok(expression),
             TokenKind::LeftParen => {
                 self.enter_nesting(token.offset)?;
                 let expression = self.parse_or();
@@ -483,7 +861,11 @@ impl Parser {
 
 fn ast_node_count(expression: &Expression) -> usize {
     match expression {
-        Expression::Term(_) => 1,
+        Expression::Term(_)
+        | Expression::Phrase(_)
+        | Expression::Regex(_)
+        | Expression::Level(_)
+        | Expression::Time(_) => 1,
         Expression::Not(expression) => 1 + ast_node_count(expression),
         Expression::And(left, right) | Expression::Or(left, right) => {
             1 + ast_node_count(left) + ast_node_count(right)
@@ -493,7 +875,10 @@ fn ast_node_count(expression: &Expression) -> usize {
 
 #[cfg(test)]
 mod tests {
-    use super::parse;
+    use chrono::NaiveDateTime;
+
+    use super::{LineEventTracker, parse};
+    use crate::ingest::{EventTimeParser, TimestampRecognizers};
 
     #[test]
     fn applies_not_then_and_then_or_precedence() {
@@ -505,7 +890,7 @@ mod tests {
 
     #[test]
     fn supports_parentheses_and_quoted_phrases() {
-        let expression = parse("(ERROR OR WARN) AND \"tracking point\"").expect("parse expression");
+        let expression = parse("(ERROR OR WARN) AND \"Tracking Point\"").expect("parse expression");
         assert!(expression.matches("warn Interaction Tracking Point moved"));
         assert!(!expression.matches("warn tracking stopped"));
     }
@@ -520,7 +905,28 @@ mod tests {
     #[test]
     fn treats_operator_words_as_literal_text_inside_quotes() {
         let expression = parse(r#""AND OR NOT (vds): mounted""#).expect("parse expression");
-        assert!(expression.matches("and or not (vds): mounted successfully"));
+        assert!(expression.matches("AND OR NOT (vds): mounted successfully"));
+    }
+
+    #[test]
+    fn narrows_chunks_by_positive_ascii_terms_only() {
+        let prefilter = |input: &str| parse(input).expect("parse expression").fts_prefilter();
+        assert_eq!(
+            prefilter("error AND NOT timeout").as_deref(),
+            Some("\"error\"")
+        );
+        assert_eq!(
+            prefilter("(disk OR \"io \\\"fault\\\"\") AND crash").as_deref(),
+            Some("((\"disk\" OR \"io \"\"fault\"\"\") AND \"crash\")")
+        );
+        assert_eq!(prefilter("disk OR id"), None);
+        assert_eq!(prefilter("NOT timeout"), None);
+        assert_eq!(prefilter("崩溃日志"), None);
+        assert_eq!(
+            prefilter("/time(out|d)/ AND level:ERROR AND disk").as_deref(),
+            Some("\"disk\"")
+        );
+        assert_eq!(prefilter("disk OR /fault/"), None);
     }
 
     #[test]
@@ -687,4 +1093,98 @@ mod tests {
         let error = parse("ERROR WARN").expect_err("missing operator");
         assert_eq!(error.offset, 6);
     }
+
+    #[test]
+    fn matches_quoted_phrases_with_their_exact_case() {
+        let expression = parse("\"Disk Full\"").expect("parse expression");
+        assert!(expression.matches("ERROR Disk Full on /data"));
+        assert!(!expression.matches("ERROR disk full on /data"));
+        assert!(parse("disk").expect("term").matches("DISK FULL"));
+    }
+
+    #[test]
+    fn matches_regex_terms_anywhere_in_the_line() {
+        let expression = parse(r"/timeout after \d+ms/ AND NOT /retry\/\d/").expect("regex");
+        assert!(expression.matches("WARN timeout after 250ms"));
+        assert!(!expression.matches("WARN timeout after 250ms retry/2"));
+        assert!(!expression.matches("WARN Timeout after 250ms"));
+        assert!(parse("/(?i)timeout/").expect("flags").matches("TIMEOUT"));
+    }
+
+    #[test]
+    fn matches_level_predicates_against_the_line_level() {
+        let errors = parse("level:ERROR,fatal").expect("level list");
+        assert!(errors.matches("2026-10-01 12:00:00 ERROR disk"));
+        assert!(errors.matches("FATAL crash"));
+        assert!(!errors.matches("WARN slow"));
+
+        let at_least_warn = parse("LEVEL>=warn AND disk").expect("minimum level");
+        assert!(at_least_warn.matches("WARN disk slow"));
+        assert!(at_least_warn.matches("ERROR disk full"));
+        assert!(!at_least_warn.matches("INFO disk mounted"));
+        assert!(!at_least_warn.matches("disk without a level"));
+    }
+
+    #[test]
+    fn matches_time_predicates_against_the_tracked_event() {
+        let expression =
+            parse("time>=2026-10-01T12:00 AND time<\"2026-10-01 12:30\" AND level:ERROR")
+                .expect("time range");
+        let reference = NaiveDateTime::parse_from_str("2026-10-02 00:00:00", "%Y-%m-%d %H:%M:%S")
+            .expect("reference time");
+        let recognizers = TimestampRecognizers::default();
+        let mut tracker = LineEventTracker::new(EventTimeParser::new(&recognizers, reference));
+        let lines = [
+            ("2026-10-01 11:59:59.000 ERROR too early", false),
+            ("2026-10-01 12:00:00.000 ERROR disk full", true),
+            ("    at Disk.write(Disk.java:12)", true),
+            ("2026-10-01 12:10:00.000 INFO recovered", false),
+            ("2026-10-01 12:30:00.000 ERROR too late", false),
+        ];
+        for (line, expected) in lines {
+            let event = tracker.observe(line);
+            assert_eq!(expression.matches_event(line, event), expected, "{line}");
+        }
+        assert!(!expression.matches("2026-10-01 12:00:00.000 ERROR disk full"));
+    }
+
+    #[test]
+    fn reports_precise_offsets_for_new_syntax_errors() {
+        let offset = |input: &str| parse(input).expect_err(input).offset;
+        assert_eq!(offset("error AND /unterminated"), 10);
+        assert_eq!(offset("error AND //"), 10);
+        assert_eq!(offset("error AND /abc(def/"), 14);
+        assert_eq!(offset("error AND /a{2,1}/"), 12);
+        assert_eq!(offset("level:ERROR,LOUD"), 12);
+        assert_eq!(offset("level>= AND x"), 7);
+        assert_eq!(offset("x OR time>=yesterday"), 11);
+        assert_eq!(offset("time<\"2026-13-01 00:00\""), 6);
+    }
+
+    #[test]
+    fn chunk_matcher_handles_exact_phrases_and_regex_across_all_splits() {
+        let source = "2026-10-01 12:00:00 ERROR Disk Full ΟΣ code=507";
+        let cases = [
+            ("\"Disk Full\"", true),
+            ("\"disk full\"", false),
+            ("\"Disk Full\" AND ος", true),
+            (r"/code=\d{3}$/ AND level:ERROR", true),
+            (r"/code=\d{4}/ OR level:WARN", false),
+        ];
+        for (expression_text, expected) in cases {
+            let expression = parse(expression_text).expect("expression");
+            assert_eq!(expression.matches(source), expected, "{expression_text}");
+            for split in 0..=source.len() {
+                let mut matcher = expression.chunk_matcher();
+                matcher.feed_bytes(&source.as_bytes()[..split]);
+                matcher.feed_bytes(&source.as_bytes()[split..]);
+                matcher.finish();
+                assert_eq!(
+                    matcher.matches(&expression),
+                    expected,
+                    "expression: {expression_text:?}, split: {split}"
+                );
+            }
+        }
+    }
 }
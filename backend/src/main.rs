@@ -200,6 +200,15 @@ async fn main() -> std::io::Result<()> {
     ];
     background_tasks.push(spawn_deleting_bundle_cleanup(shared_state.db.pool.clone()));
     background_tasks.push(spawn_session_cleanup(shared_state.db.pool.clone()));
+    background_tasks.push(backend::services::event_time::spawn_event_time_rekey(
+        shared_state.db.pool.clone(),
+    ));
+    background_tasks.push(backend::services::file_reindex::spawn_file_reindexing(
+        backend::services::file_reindex::FileReindexing::from_state(&shared_state),
+    ));
+    background_tasks.push(backend::services::log_templates::spawn_template_mining(
+        backend::services::log_templates::TemplateMining::from_state(&shared_state),
+    ));
     if !recovery_runtime.invariant_recovery_ready() {
         background_tasks.push(spawn_invariant_recovery_supervisor(
             shared_state.db.pool.clone(),
@@ -210,6 +219,21 @@ async fn main() -> std::io::Result<()> {
     background_tasks.push(backend::upload::job::spawn_temp_cleanup_worker(
         shared_state.upload.temp_cleanup_queue.clone(),
     ));
+    background_tasks.push(backend::upload::resumable::spawn_resumable_upload_cleanup(
+        shared_state.upload.resumable.clone(),
+        shared_state.upload.temp_cleanup_queue.clone(),
+    ));
+    if let Some(drop_folder) = config.drop_folder.clone() {
+        info!(
+            root = %absolute_diagnostic_path(&drop_folder.root).display(),
+            poll_interval_seconds = drop_folder.poll_interval.as_secs(),
+            "drop folder ingestion enabled"
+        );
+        background_tasks.push(backend::upload::drop_folder::spawn_drop_folder_ingestion(
+            shared_state.clone(),
+            drop_folder,
+        ));
+    }
     background_tasks.push(backend::routes::spawn_temp_result_cleanup(
         shared_state.clone(),
     ));
@@ -22,3 +22,88 @@ pub struct FileNodeResponse {
     pub node: FileNode,
     pub children: Vec<FileNode>,
 }
+
+/// A page of the side-by-side line diff of two text files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDiffResponse {
+    pub left: FileDiffSide,
+    pub right: FileDiffSide,
+    pub ignore_timestamps: bool,
+    pub ignore_numbers: bool,
+    /// Whether the files differ too much for an exact diff and the rest was
+    /// reported as one replaced block.
+    pub approximate: bool,
+    pub identical: bool,
+    pub summary: FileDiffSummary,
+    /// Rows listed, after `context` dropped distant unchanged rows.
+    pub total_rows: u64,
+    pub start: u64,
+    pub limit: u64,
+    pub next_start: Option<u64>,
+    pub rows: Vec<FileDiffRow>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDiffSide {
+    pub bundle_hash: String,
+    pub file_id: String,
+    pub path: String,
+    pub line_count: u64,
+}
+
+/// Row counts of the whole diff by kind.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileDiffSummary {
+    pub equal: u64,
+    pub deleted: u64,
+    pub inserted: u64,
+    pub replaced: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDiffRow {
+    /// `equal`, `delete`, `insert` or `replace`.
+    pub kind: String,
+    pub left: Option<FileDiffLine>,
+    pub right: Option<FileDiffLine>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDiffLine {
+    pub line_number: u64,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergedTimelineResponse {
+    /// Merged files in request order; lines refer to them by index.
+    pub sources: Vec<MergedTimelineSource>,
+    pub limit: u64,
+    /// Cursor for the next page, `None` once every file is exhausted.
+    pub next_cursor: Option<String>,
+    pub lines: Vec<MergedTimelineLine>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergedTimelineSource {
+    pub bundle_hash: String,
+    pub file_id: String,
+    pub name: String,
+    pub path: String,
+    pub event_time_shift_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergedTimelineLine {
+    /// Index into `sources`.
+    pub source: usize,
+    pub file_id: String,
+    pub line_number: i64,
+    /// Shifted event time the line is ordered by; untimed lines inherit the
+    /// last timestamp above them.
+    pub event_time: Option<String>,
+    /// Whether `event_time` was parsed from this line rather than inherited.
+    pub timed: bool,
+    pub content: String,
+    pub truncated: bool,
+}
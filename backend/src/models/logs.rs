@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,6 +13,21 @@ pub struct LogSearchHit {
     pub line_end: Option<i64>,
     pub line_number: Option<i64>,
     pub chunk_index: Option<i64>,
+    /// Normalised level of the matched line or event, such as `ERROR`.
+    pub level: Option<String>,
+    /// Start time of the multi-line event the hit belongs to, if any.
+    pub event_time: Option<String>,
+    /// Capture groups of the first match, in regex search mode only.
+    pub captures: Option<Vec<LogSearchCapture>>,
+    /// Index into `LogSearchResponse::contexts` of the block around the hit.
+    pub context_index: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogSearchCapture {
+    pub index: usize,
+    pub name: Option<String>,
+    pub value: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,4 +35,209 @@ pub struct LogSearchResponse {
     pub total: u64,
     pub hits: Vec<LogSearchHit>,
     pub truncated: bool,
+    /// Matching segments per normalised level, ignoring the level filter.
+    #[serde(default)]
+    pub level_counts: BTreeMap<String, u64>,
+    /// Applied time filter in canonical wall-clock form.
+    pub time_range: Option<LogSearchTimeRange>,
+    /// Matching segments left out by the time filter because they carry no
+    /// parseable timestamp; `None` without a time filter.
+    pub untimed_hits: Option<u64>,
+    /// Context blocks requested with `before`/`after`; hits whose windows
+    /// overlap share one block.
+    pub contexts: Option<Vec<LogSearchContext>>,
+    /// Whether some context was cut short or left out by the byte limit.
+    #[serde(default)]
+    pub context_truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogSearchContext {
+    pub file_id: String,
+    pub start: i64,
+    pub lines: Vec<LogContextLine>,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogContextLine {
+    pub line_number: i64,
+    pub content: String,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogSearchTimeRange {
+    pub start: String,
+    pub end: String,
+}
+
+/// Matching segments per event-time bucket; `buckets` holds each bucket's
+/// start and every count vector is aligned with it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogHistogramResponse {
+    pub total: u64,
+    /// Bucket width in milliseconds; `None` when no hit has a timestamp.
+    pub bucket_ms: Option<i64>,
+    pub buckets: Vec<String>,
+    pub totals: Vec<u64>,
+    pub series: Vec<LogHistogramSeries>,
+    /// Whether series beyond the largest ones were left out of `series`.
+    pub series_truncated: bool,
+    /// Matching lines without a parseable event time, not bucketed.
+    pub untimed_hits: u64,
+    /// Whether the scan budget ran out before every candidate was checked.
+    pub truncated: bool,
+}
+
+/// Counts of one bundle, or of one file when split per file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogHistogramSeries {
+    pub bundle_hash: String,
+    pub file_id: Option<String>,
+    pub path: Option<String>,
+    pub counts: Vec<u64>,
+    pub total: u64,
+}
+
+/// One matching line of a search export, written as an NDJSON object or a
+/// CSV row.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogExportRecord {
+    pub bundle_hash: String,
+    pub file_id: String,
+    pub path: String,
+    pub line_number: Option<i64>,
+    /// Event time of the line's event; `None` before the first timestamp.
+    pub event_time: Option<String>,
+    pub level: Option<String>,
+    pub content: String,
+}
+
+/// Matches of a search across every accessible issue, grouped by issue and
+/// bundle. `total` and `untimed_hits` count matching segments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlobalSearchResponse {
+    pub query: String,
+    pub saved_search_id: Option<String>,
+    pub total: u64,
+    /// Issues with at least one match, before `from`/`size` paging.
+    pub issue_count: u64,
+    pub issues: Vec<GlobalSearchIssue>,
+    /// Whether the scan budget ran out before every candidate was checked.
+    pub truncated: bool,
+    pub time_range: Option<LogSearchTimeRange>,
+    pub untimed_hits: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlobalSearchIssue {
+    pub issue_code: String,
+    pub issue_name: String,
+    /// Whether the caller owns the issue.
+    pub owned: bool,
+    pub total: u64,
+    pub bundles: Vec<GlobalSearchBundle>,
+}
+
+/// Matches in one bundle; `hits` holds the first few in index order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlobalSearchBundle {
+    pub bundle_hash: String,
+    pub total: u64,
+    pub hits: Vec<LogSearchHit>,
+}
+
+/// Templates mined from an issue's bundles, most frequent first. Bundles
+/// still queued for mining are not included yet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogTemplateListResponse {
+    pub templates: Vec<LogTemplateSummary>,
+    /// Distinct templates across the mined bundles, before `size` applies.
+    pub total_templates: u64,
+    pub mined_bundles: u64,
+    pub pending_bundles: u64,
+    pub failed_bundles: u64,
+}
+
+/// One template summed over the bundles it was mined from; `<*>` marks the
+/// variable tokens.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogTemplateSummary {
+    pub key: String,
+    pub template: String,
+    pub line_count: i64,
+    pub bundle_count: i64,
+    /// Levels of the events its lines belong to, from `TRACE` to `FATAL`.
+    pub levels: Vec<String>,
+    pub first_event_time: Option<String>,
+    pub last_event_time: Option<String>,
+    pub examples: Vec<LogTemplateExample>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogTemplateExample {
+    pub bundle_hash: String,
+    pub file_id: String,
+    pub line_number: Option<i64>,
+    pub content: String,
+}
+
+/// A page of the lines matching one template, in index order. Pass
+/// `next_cursor` back as `cursor` to continue; it is also set when the scan
+/// budget ran out before the page filled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogTemplateLinesResponse {
+    pub key: String,
+    pub template: String,
+    pub lines: Vec<LogExportRecord>,
+    pub next_cursor: Option<String>,
+}
+
+/// Templates that set two bundles of an issue apart: present in only one of
+/// them, or with a share of the mined lines that moved by at least
+/// `min_ratio`. Each list holds the first `size` entries; the `*_total`
+/// fields count all of them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleTemplateDiffResponse {
+    pub base: BundleTemplateDiffSide,
+    pub target: BundleTemplateDiffSide,
+    pub min_ratio: f64,
+    pub min_count: i64,
+    pub only_in_base: Vec<TemplateDiffEntry>,
+    pub only_in_base_total: u64,
+    pub only_in_target: Vec<TemplateDiffEntry>,
+    pub only_in_target_total: u64,
+    /// Sorted by the size of the change, largest first.
+    pub changed: Vec<TemplateDiffEntry>,
+    pub changed_total: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleTemplateDiffSide {
+    pub bundle_hash: String,
+    pub bundle_name: String,
+    /// Lines covered by the bundle's templates.
+    pub line_count: i64,
+    pub template_count: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateDiffEntry {
+    /// Template keys in each bundle; they differ when the templates were
+    /// paired by overlap rather than by equal text.
+    pub base_key: Option<String>,
+    pub target_key: Option<String>,
+    /// The more general of the two templates.
+    pub template: String,
+    pub levels: Vec<String>,
+    pub base_count: i64,
+    pub target_count: i64,
+    /// Target share of lines over base share; `None` when only one bundle
+    /// has the template.
+    pub ratio: Option<f64>,
+    /// Sample lines from both bundles; the full lists are available from
+    /// `/issues/{issue_code}/templates/{key}/lines?bundle_hash=` with each
+    /// side's key.
+    pub examples: Vec<LogTemplateExample>,
 }
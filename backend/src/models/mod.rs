@@ -6,3 +6,4 @@ pub mod logs;
 pub mod saved_searches;
 pub mod skill_runs;
 pub mod skills;
+pub mod timestamp_formats;
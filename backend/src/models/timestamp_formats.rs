@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct TimestampFormatRecord {
+    pub id: String,
+    pub name: String,
+    pub pattern: String,
+    pub format: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimestampFormatPayload {
+    pub name: String,
+    pub pattern: String,
+    pub format: String,
+    /// Example log line that the definition must recognise before it is saved.
+    pub sample: String,
+}
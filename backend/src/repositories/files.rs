@@ -86,25 +86,48 @@ pub async fn fetch_children(
     .map_err(AppError::Database)
 }
 
+/// Closest indexed line at or before `start`. Files linked to a blob index
+/// read the offsets stored with the index.
 pub async fn nearest_line_offset(
     pool: &sqlx::SqlitePool,
     file_id: i64,
     start: i64,
 ) -> Result<(i64, i64), AppError> {
-    let row = sqlx::query_as::<_, LineOffsetRow>(
-        r#"
-        SELECT line_number, byte_offset
-        FROM log_line_offsets
-        WHERE file_id = ? AND line_number <= ?
-        ORDER BY line_number DESC
-        LIMIT 1
-        "#,
-    )
-    .bind(file_id)
-    .bind(start)
-    .fetch_optional(pool)
-    .await
-    .map_err(AppError::Database)?;
+    let blob_index_id: Option<i64> =
+        sqlx::query_scalar("SELECT blob_index_id FROM files WHERE id = ?")
+            .bind(file_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(AppError::Database)?
+            .flatten();
+    let (statement, owner_id) = match blob_index_id {
+        Some(index_id) => (
+            r#"
+            SELECT line_number, byte_offset
+            FROM blob_line_offsets
+            WHERE blob_index_id = ? AND line_number <= ?
+            ORDER BY line_number DESC
+            LIMIT 1
+            "#,
+            index_id,
+        ),
+        None => (
+            r#"
+            SELECT line_number, byte_offset
+            FROM log_line_offsets
+            WHERE file_id = ? AND line_number <= ?
+            ORDER BY line_number DESC
+            LIMIT 1
+            "#,
+            file_id,
+        ),
+    };
+    let row = sqlx::query_as::<_, LineOffsetRow>(statement)
+        .bind(owner_id)
+        .bind(start)
+        .fetch_optional(pool)
+        .await
+        .map_err(AppError::Database)?;
 
     Ok(row
         .map(|row| (row.line_number, row.byte_offset))
@@ -4,4 +4,5 @@ pub mod saved_searches;
 pub mod sessions;
 pub mod skill_runs;
 pub mod skills;
+pub mod timestamp_formats;
 pub mod users;
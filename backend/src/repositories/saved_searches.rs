@@ -76,7 +76,7 @@ pub async fn mark_used(pool: &SqlitePool, user_id: &str, id: &str) -> Result<boo
         > 0)
 }
 
-async fn find_owned(
+pub async fn find_owned(
     pool: &SqlitePool,
     user_id: &str,
     id: &str,
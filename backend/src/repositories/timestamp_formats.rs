@@ -0,0 +1,50 @@
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    models::timestamp_formats::{TimestampFormatPayload, TimestampFormatRecord},
+};
+
+pub async fn list(pool: &SqlitePool) -> Result<Vec<TimestampFormatRecord>, AppError> {
+    sqlx::query_as(
+        "SELECT id, name, pattern, format, created_at FROM timestamp_formats ORDER BY created_at, id",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(AppError::Database)
+}
+
+pub async fn create(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    user_id: &str,
+    payload: &TimestampFormatPayload,
+) -> Result<Option<TimestampFormatRecord>, AppError> {
+    let id = Uuid::new_v4().to_string();
+    let inserted = sqlx::query("INSERT OR IGNORE INTO timestamp_formats (id, name, pattern, format, created_by_user_id) VALUES (?, ?, ?, ?, ?)")
+        .bind(&id).bind(payload.name.trim()).bind(payload.pattern.trim()).bind(payload.format.trim()).bind(user_id)
+        .execute(&mut **tx).await.map_err(AppError::Database)?;
+    if inserted.rows_affected() == 0 {
+        return Ok(None);
+    }
+    sqlx::query_as(
+        "SELECT id, name, pattern, format, created_at FROM timestamp_formats WHERE id = ?",
+    )
+    .bind(&id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(AppError::Database)
+}
+
+pub async fn delete(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    id: &str,
+) -> Result<Option<TimestampFormatRecord>, AppError> {
+    sqlx::query_as(
+        "DELETE FROM timestamp_formats WHERE id = ? RETURNING id, name, pattern, format, created_at",
+    )
+    .bind(id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(AppError::Database)
+}
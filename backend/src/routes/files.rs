@@ -2,7 +2,7 @@ use actix_files::NamedFile;
 use actix_web::{
     HttpResponse, delete, get,
     http::header::{Charset, ContentDisposition, DispositionParam, DispositionType, ExtendedValue},
-    web,
+    put, web,
 };
 use serde::Deserialize;
 use serde_json::json;
@@ -12,16 +12,37 @@ use crate::{
     auth::extractor::{RequireBusinessUser, RequireUser},
     error::AppError,
     file_classification::PreviewKind,
-    models::files::{FileNode, FileNodeResponse},
-    repositories::files::{fetch_children, fetch_file, resolve_file_path, to_file_node},
+    ingest::{
+        EventTimeParser, FileEncoding, TextEncoding, TimestampRecognizers, load_timestamp_context,
+        read_encoding_sample,
+    },
+    models::files::{
+        FileDiffLine, FileDiffResponse, FileDiffRow, FileDiffSide, FileDiffSummary, FileNode,
+        FileNodeResponse, MergedTimelineLine, MergedTimelineResponse, MergedTimelineSource,
+    },
+    repositories::files::{
+        ensure_text_preview, fetch_children, fetch_file, resolve_file_path, to_file_node,
+    },
     services::{
+        event_time::{self, EventTimeSetting},
         file_deletion::delete_file_tree,
-        file_reader::{read_file_lines, read_file_preview},
+        file_diff::{
+            DiffOptions, DiffRowKind, MAX_DIFF_EDIT_DISTANCE, diff_lines, read_diff_lines,
+        },
+        file_reader::{FileLineSource, find_line_at_time, read_file_lines, read_file_preview},
+        file_reindex::{FileReindexing, trigger_reindexing},
+        json_size::json_string_encoded_len,
+        merged_timeline::{MAX_MERGED_FILES, MergeInput, encode_cursor, merge_page, parse_cursor},
+        wall_clock,
     },
+    upload::append::claim_bundle_append,
 };
 
 use super::helpers::{ensure_bundle_ready, load_bundle};
-use super::issues::{require_issue_owner, touch_issue_activity_best_effort};
+use super::issues::{
+    ensure_issue_active, normalize_issue_code, require_issue_owner,
+    touch_issue_activity_best_effort,
+};
 use super::temp_results::request_client_key;
 
 #[derive(Deserialize)]
@@ -32,10 +53,48 @@ struct FilePath {
 
 #[derive(Deserialize)]
 struct LinesQuery {
+    start: Option<i64>,
+    /// Local wall-clock time; the page starts at the first line at or after it.
+    at: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct DiffQuery {
+    /// Bundle hash of the right-hand file; defaults to the left file's bundle.
+    against_bundle: Option<String>,
+    against_file: i64,
+    #[serde(default)]
+    ignore_timestamps: bool,
+    #[serde(default)]
+    ignore_numbers: bool,
+    /// Unchanged lines kept around each change; all rows when unset.
+    context: Option<usize>,
     start: Option<i64>,
     limit: Option<i64>,
 }
 
+#[derive(Deserialize)]
+struct MergedTimelineQuery {
+    /// Comma-separated file ids, merged in this order on equal times.
+    files: String,
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct MergedFileRow {
+    id: i64,
+    bundle_id: String,
+    bundle_hash: String,
+    event_time_shift_ms: i64,
+}
+
+#[derive(Deserialize)]
+pub struct EncodingOverrideRequest {
+    encoding: String,
+}
+
 // scoped under /api in routes::register
 #[get("/files/v1/{bundle_id}/files/{file_id}")]
 pub async fn get_file_node(
@@ -124,7 +183,31 @@ pub async fn get_file_lines(
         .parse::<i64>()
         .map_err(|_| AppError::BadRequest(format!("invalid file id: {file_id}")))?;
     let record = fetch_file(&state.db.pool, &bundle.id, parsed_id).await?;
-    let start = query.start.unwrap_or(0).max(0);
+    let at = query
+        .at
+        .as_deref()
+        .map(str::trim)
+        .filter(|at| !at.is_empty());
+    let start = match at {
+        Some(_) if query.start.is_some() => {
+            return Err(AppError::BadRequest("start 与 at 不能同时使用".into()));
+        }
+        Some(at) => {
+            let at = wall_clock::parse(at).ok_or_else(|| {
+                AppError::BadRequest("at 必须是本地日志时间，例如 2026-08-14 09:32:15".into())
+            })?;
+            find_line_at_time(
+                &state.db.pool,
+                &bundle.id,
+                &record,
+                state.storage.blob_store.as_ref(),
+                &state.limits.api,
+                at,
+            )
+            .await?
+        }
+        None => query.start.unwrap_or(0).max(0),
+    };
     let limit = query
         .limit
         .unwrap_or(state.limits.api.default_line_page_size)
@@ -143,6 +226,299 @@ pub async fn get_file_lines(
     Ok(HttpResponse::Ok().json(lines))
 }
 
+#[get("/files/v1/{bundle_id}/files/{file_id}/diff")]
+pub async fn diff_files(
+    request: actix_web::HttpRequest,
+    params: web::Path<FilePath>,
+    query: web::Query<DiffQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let _line_read = state.acquire_line_read(&request_client_key(&request))?;
+    let FilePath { bundle_id, file_id } = params.into_inner();
+    let query = query.into_inner();
+    let left_bundle = load_bundle(&state.db.pool, &bundle_id).await?;
+    ensure_bundle_ready(&left_bundle)?;
+    let other_bundle = match query.against_bundle.as_deref().map(str::trim) {
+        Some(hash) if !hash.is_empty() && hash != left_bundle.hash => {
+            let bundle = load_bundle(&state.db.pool, hash).await?;
+            ensure_bundle_ready(&bundle)?;
+            if bundle.issue_code != left_bundle.issue_code {
+                return Err(AppError::BadRequest("只能对比同一 Issue 内的文件".into()));
+            }
+            Some(bundle)
+        }
+        _ => None,
+    };
+    let right_bundle = other_bundle.as_ref().unwrap_or(&left_bundle);
+    let left_id = file_id
+        .parse::<i64>()
+        .map_err(|_| AppError::BadRequest(format!("invalid file id: {file_id}")))?;
+    let left_record = fetch_file(&state.db.pool, &left_bundle.id, left_id).await?;
+    let right_record = fetch_file(&state.db.pool, &right_bundle.id, query.against_file).await?;
+    let left_lines = read_diff_lines(
+        &left_record,
+        state.storage.blob_store.as_ref(),
+        &state.limits.api,
+    )
+    .await?;
+    let right_lines = read_diff_lines(
+        &right_record,
+        state.storage.blob_store.as_ref(),
+        &state.limits.api,
+    )
+    .await?;
+    let options = DiffOptions {
+        ignore_timestamps: query.ignore_timestamps,
+        ignore_numbers: query.ignore_numbers,
+    };
+    let (diff, left_lines, right_lines) = tokio::task::spawn_blocking(move || {
+        let left: Vec<&str> = left_lines.iter().map(String::as_str).collect();
+        let right: Vec<&str> = right_lines.iter().map(String::as_str).collect();
+        let diff = diff_lines(&left, &right, options, MAX_DIFF_EDIT_DISTANCE);
+        (diff, left_lines, right_lines)
+    })
+    .await
+    .map_err(|error| AppError::Io(std::io::Error::other(error.to_string())))?;
+
+    let mut summary = FileDiffSummary::default();
+    for row in &diff.rows {
+        match row.kind {
+            DiffRowKind::Equal => summary.equal += 1,
+            DiffRowKind::Delete => summary.deleted += 1,
+            DiffRowKind::Insert => summary.inserted += 1,
+            DiffRowKind::Replace => summary.replaced += 1,
+        }
+    }
+    let identical = summary.equal as usize == diff.rows.len();
+    let listed: Vec<_> = match query.context {
+        Some(context) => {
+            // Keeps unchanged rows within `context` rows of any change.
+            let mut keep = vec![false; diff.rows.len()];
+            for (index, row) in diff.rows.iter().enumerate() {
+                if row.kind != DiffRowKind::Equal {
+                    let end = index.saturating_add(context).min(diff.rows.len() - 1);
+                    keep[index.saturating_sub(context)..=end].fill(true);
+                }
+            }
+            diff.rows
+                .iter()
+                .zip(keep)
+                .filter(|(_, keep)| *keep)
+                .map(|(row, _)| *row)
+                .collect()
+        }
+        None => diff.rows,
+    };
+
+    let start = query.start.unwrap_or(0).max(0) as usize;
+    let limit = query
+        .limit
+        .unwrap_or(state.limits.api.default_line_page_size)
+        .clamp(1, state.limits.api.max_line_page_size) as usize;
+    let line = |lines: &[String], index: Option<usize>| {
+        index.map(|index| FileDiffLine {
+            line_number: index as u64,
+            content: lines[index].clone(),
+        })
+    };
+    let mut page_bytes = 0_u64;
+    let mut rows = Vec::new();
+    for row in listed.iter().skip(start).take(limit) {
+        let row_bytes = [
+            row.left.map(|index| &left_lines[index]),
+            row.right.map(|index| &right_lines[index]),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|content| json_string_encoded_len(content).saturating_add(64))
+        .sum::<u64>();
+        if !rows.is_empty()
+            && page_bytes.saturating_add(row_bytes) > state.limits.api.max_line_page_bytes
+        {
+            break;
+        }
+        page_bytes = page_bytes.saturating_add(row_bytes);
+        rows.push(FileDiffRow {
+            kind: row.kind.as_str().to_string(),
+            left: line(&left_lines, row.left),
+            right: line(&right_lines, row.right),
+        });
+    }
+    let next_start = Some(start + rows.len()).filter(|next| *next < listed.len());
+    touch_issue_activity_best_effort(&state.db.pool, &left_bundle.issue_code, "file diff read")
+        .await;
+
+    Ok(HttpResponse::Ok().json(FileDiffResponse {
+        left: FileDiffSide {
+            bundle_hash: left_bundle.hash.clone(),
+            file_id: left_record.id.to_string(),
+            path: left_record.path,
+            line_count: left_lines.len() as u64,
+        },
+        right: FileDiffSide {
+            bundle_hash: right_bundle.hash.clone(),
+            file_id: right_record.id.to_string(),
+            path: right_record.path,
+            line_count: right_lines.len() as u64,
+        },
+        ignore_timestamps: options.ignore_timestamps,
+        ignore_numbers: options.ignore_numbers,
+        approximate: diff.approximate,
+        identical,
+        summary,
+        total_rows: listed.len() as u64,
+        start: start as u64,
+        limit: limit as u64,
+        next_start: next_start.map(|next| next as u64),
+        rows,
+    }))
+}
+
+/// Merges the lines of several files of one issue in event-time order.
+#[get("/issues/{issue_code}/timeline")]
+pub async fn get_merged_timeline(
+    request: actix_web::HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<MergedTimelineQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let _line_read = state.acquire_line_read(&request_client_key(&request))?;
+    let issue_code = normalize_issue_code(&path.into_inner())?;
+    ensure_issue_active(&state.db.pool, &issue_code).await?;
+    let file_ids = query
+        .files
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| {
+            id.parse::<i64>()
+                .map_err(|_| AppError::BadRequest(format!("invalid file id: {id}")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if file_ids.is_empty() {
+        return Err(AppError::BadRequest("files 参数不能为空".into()));
+    }
+    if file_ids.len() > MAX_MERGED_FILES {
+        return Err(AppError::BadRequest(format!(
+            "最多合并 {MAX_MERGED_FILES} 个文件"
+        )));
+    }
+    if file_ids
+        .iter()
+        .enumerate()
+        .any(|(index, id)| file_ids[..index].contains(id))
+    {
+        return Err(AppError::BadRequest("files 参数包含重复文件".into()));
+    }
+    let positions = parse_cursor(query.cursor.as_deref(), &file_ids)?;
+    let limit = query
+        .limit
+        .unwrap_or(state.limits.api.default_line_page_size)
+        .clamp(1, state.limits.api.max_line_page_size);
+
+    let mut builder = sqlx::QueryBuilder::new(
+        "SELECT f.id, f.bundle_id, b.hash AS bundle_hash, f.event_time_shift_ms FROM files f JOIN bundles b ON b.id = f.bundle_id WHERE b.status = 'READY' AND b.deleted_at IS NULL AND b.issue_code = ",
+    );
+    builder.push_bind(&issue_code).push(" AND f.id IN (");
+    let mut ids = builder.separated(", ");
+    for id in &file_ids {
+        ids.push_bind(id);
+    }
+    builder.push(")");
+    let rows: Vec<MergedFileRow> = builder.build_query_as().fetch_all(&state.db.pool).await?;
+
+    let mut files = Vec::with_capacity(file_ids.len());
+    let mut contexts: Vec<(String, TimestampRecognizers, chrono::NaiveDateTime)> = Vec::new();
+    for id in &file_ids {
+        let row = rows
+            .iter()
+            .find(|row| row.id == *id)
+            .ok_or_else(|| AppError::NotFound(format!("file not found: {id}")))?;
+        let record = fetch_file(&state.db.pool, &row.bundle_id, row.id).await?;
+        if !contexts
+            .iter()
+            .any(|(bundle_id, ..)| *bundle_id == row.bundle_id)
+        {
+            let (recognizers, reference) =
+                load_timestamp_context(&state.db.pool, &row.bundle_id).await?;
+            contexts.push((row.bundle_id.clone(), recognizers, reference));
+        }
+        files.push((row, record));
+    }
+
+    let mut inputs = Vec::with_capacity(files.len());
+    for ((row, record), position) in files.iter().zip(&positions) {
+        let (_, recognizers, reference) = contexts
+            .iter()
+            .find(|(bundle_id, ..)| *bundle_id == row.bundle_id)
+            .expect("context loaded for every bundle");
+        let lines = FileLineSource::open(
+            &state.db.pool,
+            record,
+            state.storage.blob_store.as_ref(),
+            &state.limits.api,
+            position.line,
+        )
+        .await?;
+        inputs.push(MergeInput::new(
+            lines,
+            EventTimeParser::new(recognizers, *reference).with_shift_ms(row.event_time_shift_ms),
+            *position,
+        ));
+    }
+
+    let sources: Vec<MergedTimelineSource> = files
+        .iter()
+        .map(|(row, record)| MergedTimelineSource {
+            bundle_hash: row.bundle_hash.clone(),
+            file_id: row.id.to_string(),
+            name: record.name.clone(),
+            path: record.path.clone(),
+            event_time_shift_ms: row.event_time_shift_ms,
+        })
+        .collect();
+    let page_base_bytes = sources
+        .iter()
+        .map(|source| {
+            json_string_encoded_len(&source.name)
+                .saturating_add(json_string_encoded_len(&source.path))
+                .saturating_add(160)
+        })
+        .sum::<u64>()
+        .saturating_add(256);
+    let page = merge_page(
+        &mut inputs,
+        limit as usize,
+        page_base_bytes,
+        state.limits.api.max_line_page_bytes,
+    )
+    .await?;
+    let lines = page
+        .lines
+        .into_iter()
+        .map(|line| MergedTimelineLine {
+            source: line.source,
+            file_id: sources[line.source].file_id.clone(),
+            line_number: line.line_number,
+            event_time: line
+                .event_time_ms
+                .and_then(wall_clock::from_comparison_key)
+                .map(wall_clock::format),
+            timed: line.timed,
+            content: line.content,
+            truncated: line.truncated,
+        })
+        .collect();
+    touch_issue_activity_best_effort(&state.db.pool, &issue_code, "merged timeline read").await;
+
+    Ok(HttpResponse::Ok().json(MergedTimelineResponse {
+        sources,
+        limit: limit as u64,
+        next_cursor: page.next_cursor.as_deref().map(encode_cursor),
+        lines,
+    }))
+}
+
 #[get("/files/v1/{bundle_id}/files/{file_id}/download")]
 pub async fn download_file(
     _user: RequireUser,
@@ -212,8 +588,155 @@ pub async fn delete_file_node(
         .parse::<i64>()
         .map_err(|_| AppError::BadRequest(format!("invalid file id: {file_id}")))?;
     let _record = fetch_file(&state.db.pool, &bundle.id, parsed_id).await?;
+    // An append rolls back by file id, so the two must not interleave.
+    let _append_lease = claim_bundle_append(&state.upload.appending, &bundle.id)?;
     delete_file_tree(&state.db.pool, &bundle.id, parsed_id).await?;
     touch_issue_activity_best_effort(&state.db.pool, &bundle.issue_code, "file deletion").await;
 
     Ok(HttpResponse::NoContent().finish())
 }
+
+#[put("/files/v1/{bundle_id}/files/{file_id}/encoding")]
+pub async fn override_file_encoding(
+    user: RequireBusinessUser,
+    params: web::Path<FilePath>,
+    payload: web::Json<EncodingOverrideRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let FilePath { bundle_id, file_id } = params.into_inner();
+    let bundle = load_bundle(&state.db.pool, &bundle_id).await?;
+    require_issue_owner(&state.db.pool, &bundle.issue_code, &user.0.id).await?;
+    ensure_bundle_ready(&bundle)?;
+    let parsed_id = file_id
+        .parse::<i64>()
+        .map_err(|_| AppError::BadRequest(format!("invalid file id: {file_id}")))?;
+    let record = fetch_file(&state.db.pool, &bundle.id, parsed_id).await?;
+    ensure_text_preview(&record)?;
+    let encoding = TextEncoding::from_label(&payload.encoding).ok_or_else(|| {
+        AppError::BadRequest(format!("unsupported encoding: {}", payload.encoding.trim()))
+    })?;
+
+    let disk_path = resolve_file_path(&record, state.storage.blob_store.as_ref()).await?;
+    let sample = read_encoding_sample(&disk_path).await?;
+    let file_encoding = FileEncoding::overridden(encoding, &sample);
+    let mut meta = record
+        .meta
+        .as_deref()
+        .and_then(|raw| serde_json::from_str::<serde_json::Value>(raw).ok())
+        .filter(serde_json::Value::is_object)
+        .unwrap_or_else(|| json!({}));
+    meta["encoding"] = json!(file_encoding);
+    // Re-indexing a large file takes too long for the request; the background
+    // pass picks the file up from its status and reads the new encoding from meta.
+    sqlx::query("UPDATE files SET meta = ?, status = 'REINDEXING' WHERE id = ? AND bundle_id = ?")
+        .bind(meta.to_string())
+        .bind(parsed_id)
+        .bind(&bundle.id)
+        .execute(&state.db.pool)
+        .await
+        .map_err(AppError::Database)?;
+    trigger_reindexing(FileReindexing::from_state(&state));
+    touch_issue_activity_best_effort(&state.db.pool, &bundle.issue_code, "file encoding override")
+        .await;
+
+    let record = fetch_file(&state.db.pool, &bundle.id, parsed_id).await?;
+    Ok(HttpResponse::Accepted().json(to_file_node(record)))
+}
+
+/// `None` when the settings address the whole bundle (`root`).
+async fn time_settings_scope(
+    state: &AppState,
+    bundle_id: &str,
+    file_id: &str,
+) -> Result<Option<i64>, AppError> {
+    if file_id.eq_ignore_ascii_case("root") {
+        return Ok(None);
+    }
+    let parsed_id = file_id
+        .parse::<i64>()
+        .map_err(|_| AppError::BadRequest(format!("invalid file id: {file_id}")))?;
+    fetch_file(&state.db.pool, bundle_id, parsed_id).await?;
+    Ok(Some(parsed_id))
+}
+
+async fn time_settings_response(
+    state: &AppState,
+    bundle_id: &str,
+    scope_file_id: Option<i64>,
+) -> Result<HttpResponse, AppError> {
+    let setting = event_time::load_setting(&state.db.pool, bundle_id, scope_file_id).await?;
+    let effective = match scope_file_id {
+        Some(file_id) => {
+            event_time::resolve_file_setting(&state.db.pool, bundle_id, file_id).await?
+        }
+        None => setting,
+    };
+    let rekey_pending: bool =
+        sqlx::query_scalar("SELECT event_time_rekey_pending != 0 FROM bundles WHERE id = ?")
+            .bind(bundle_id)
+            .fetch_one(&state.db.pool)
+            .await
+            .map_err(AppError::Database)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "setting": setting,
+        "effective": effective,
+        "rekey_pending": rekey_pending,
+    })))
+}
+
+#[get("/files/v1/{bundle_id}/files/{file_id}/time-settings")]
+pub async fn get_time_settings(
+    params: web::Path<FilePath>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let FilePath { bundle_id, file_id } = params.into_inner();
+    let bundle = load_bundle(&state.db.pool, &bundle_id).await?;
+    ensure_bundle_ready(&bundle)?;
+    let scope_file_id = time_settings_scope(&state, &bundle.id, &file_id).await?;
+    time_settings_response(&state, &bundle.id, scope_file_id).await
+}
+
+#[put("/files/v1/{bundle_id}/files/{file_id}/time-settings")]
+pub async fn update_time_settings(
+    user: RequireBusinessUser,
+    params: web::Path<FilePath>,
+    payload: web::Json<EventTimeSetting>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let FilePath { bundle_id, file_id } = params.into_inner();
+    let bundle = load_bundle(&state.db.pool, &bundle_id).await?;
+    require_issue_owner(&state.db.pool, &bundle.issue_code, &user.0.id).await?;
+    ensure_bundle_ready(&bundle)?;
+    let scope_file_id = time_settings_scope(&state, &bundle.id, &file_id).await?;
+    payload.validate()?;
+    event_time::save_setting(
+        &state.db.pool,
+        &bundle.id,
+        scope_file_id,
+        Some(payload.into_inner()),
+        &user.0.id,
+    )
+    .await?;
+    event_time::trigger_rekey(state.db.pool.clone());
+    touch_issue_activity_best_effort(&state.db.pool, &bundle.issue_code, "time settings update")
+        .await;
+    time_settings_response(&state, &bundle.id, scope_file_id).await
+}
+
+#[delete("/files/v1/{bundle_id}/files/{file_id}/time-settings")]
+pub async fn delete_time_settings(
+    user: RequireBusinessUser,
+    params: web::Path<FilePath>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let FilePath { bundle_id, file_id } = params.into_inner();
+    let bundle = load_bundle(&state.db.pool, &bundle_id).await?;
+    require_issue_owner(&state.db.pool, &bundle.issue_code, &user.0.id).await?;
+    ensure_bundle_ready(&bundle)?;
+    let scope_file_id = time_settings_scope(&state, &bundle.id, &file_id).await?;
+    event_time::save_setting(&state.db.pool, &bundle.id, scope_file_id, None, &user.0.id).await?;
+    event_time::trigger_rekey(state.db.pool.clone());
+    touch_issue_activity_best_effort(&state.db.pool, &bundle.issue_code, "time settings reset")
+        .await;
+    time_settings_response(&state, &bundle.id, scope_file_id).await
+}
@@ -0,0 +1,401 @@
+use std::collections::{HashMap, hash_map::Entry};
+
+use actix_web::{HttpResponse, get, http::StatusCode, web};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use sqlx::FromRow;
+
+use crate::{
+    AppState,
+    auth::extractor::RequireBusinessUser,
+    error::AppError,
+    ingest::{EventTimeParser, TimestampRecognizers, load_timestamp_context},
+    log_expression::{self, Expression, LineEvent, LineEventTracker},
+    models::logs::{GlobalSearchBundle, GlobalSearchIssue, GlobalSearchResponse, LogSearchHit},
+    repositories::saved_searches,
+    services::skill_time_scope::{TimeScopeInput, parse_search_time_range},
+};
+
+use super::logs::{
+    ContentFilter, EventFilter, SegmentFilter, SegmentScope, TimeFilter, build_fts_query,
+    find_ignore_ascii_case, invalid_time_range, level_filter_mask, line_level_at, literal_snippet,
+    log_search_time_range,
+};
+use super::temp_results::invalid_expression;
+
+const GLOBAL_SCAN_BATCH_SIZE: i64 = 200;
+const DEFAULT_GLOBAL_SEARCH_ISSUES: i64 = 20;
+const MAX_GLOBAL_SEARCH_ISSUES: i64 = 100;
+const DEFAULT_HITS_PER_BUNDLE: i64 = 3;
+const MAX_HITS_PER_BUNDLE: i64 = 20;
+
+#[derive(Deserialize)]
+struct GlobalSearchQuery {
+    q: Option<String>,
+    saved_search_id: Option<String>,
+    #[serde(default)]
+    mode: GlobalSearchMode,
+    #[serde(default)]
+    owned: bool,
+    path_like: Option<String>,
+    level: Option<String>,
+    min_level: Option<String>,
+    start: Option<String>,
+    end: Option<String>,
+    incident_time: Option<String>,
+    before_minutes: Option<i64>,
+    after_minutes: Option<i64>,
+    hits_per_bundle: Option<i64>,
+    from: Option<i64>,
+    size: Option<i64>,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum GlobalSearchMode {
+    #[default]
+    Content,
+    /// The AND/OR/NOT syntax of detailed (`DETAIL`) saved searches.
+    Expression,
+}
+
+enum GlobalMatcher {
+    Literal(String),
+    Expression(Expression),
+}
+
+impl GlobalMatcher {
+    fn fts_query(&self) -> Option<String> {
+        match self {
+            Self::Literal(term) => Some(build_fts_query(term)),
+            Self::Expression(expression) => expression.fts_prefilter(),
+        }
+    }
+
+    /// Whether lines must be read as part of their multi-line events.
+    fn uses_event_fields(&self) -> bool {
+        match self {
+            Self::Literal(_) => false,
+            Self::Expression(expression) => expression.uses_event_fields(),
+        }
+    }
+
+    /// First matching line whose event passes `filter`, with the byte
+    /// position of the match and the text to centre a snippet on. `events`
+    /// follows the chunk's events for `level` and `time` predicates and for
+    /// `filter`; an event continuing from the previous chunk is not known here.
+    fn first_match<'c>(
+        &'c self,
+        content: &'c str,
+        mut events: Option<LineEventTracker<'_>>,
+        filter: EventFilter<'_>,
+    ) -> ChunkMatch<'c> {
+        let mut untimed = false;
+        let mut line_start = 0;
+        for line in content.split('\n') {
+            let event = match events.as_mut() {
+                Some(events) => events.observe(line),
+                None => LineEvent::of_line(line),
+            };
+            let found = match self {
+                Self::Literal(term) => find_ignore_ascii_case(line, term)
+                    .map(|position| (line_start + position, term.as_str())),
+                Self::Expression(expression) => expression
+                    .matches_event(line, event)
+                    .then_some((line_start, line)),
+            };
+            if let Some((position, needle)) = found {
+                if !filter.in_time_range(event) {
+                    untimed |= event.time_key.is_none();
+                } else if filter.has_level(event) {
+                    return ChunkMatch::Hit(position, needle);
+                }
+            }
+            line_start += line.len() + 1;
+        }
+        if untimed {
+            ChunkMatch::Untimed
+        } else {
+            ChunkMatch::None
+        }
+    }
+}
+
+/// Outcome of checking one chunk against a [`GlobalMatcher`].
+enum ChunkMatch<'c> {
+    Hit(usize, &'c str),
+    /// Only lines outside the time range matched, at least one without an
+    /// event time.
+    Untimed,
+    None,
+}
+
+#[derive(FromRow)]
+struct GlobalCandidateRow {
+    segment_id: i64,
+    issue_code: String,
+    issue_name: String,
+    owner_user_id: Option<String>,
+    bundle_id: String,
+    bundle_hash: String,
+    file_id: i64,
+    path: String,
+    offset: Option<i64>,
+    line_end: Option<i64>,
+    chunk_index: Option<i64>,
+    content: String,
+    event_time_shift_ms: i64,
+}
+
+struct IssueGroup {
+    name: String,
+    owned: bool,
+    total: u64,
+    bundles: HashMap<String, GlobalSearchBundle>,
+}
+
+// scoped under /api in routes::register
+#[get("/search")]
+pub async fn search_all_issues(
+    user: RequireBusinessUser,
+    query: web::Query<GlobalSearchQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let term = query.into_inner();
+    let pool = &state.db.pool;
+    let q = term
+        .q
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+    let (search_text, matcher, saved_search_id) = match (q, term.saved_search_id.as_deref()) {
+        (Some(_), Some(_)) => {
+            return Err(AppError::BadRequest(
+                "q 与 saved_search_id 不能同时指定".into(),
+            ));
+        }
+        (None, None) => {
+            return Err(AppError::BadRequest(
+                "query parameter q or saved_search_id is required".into(),
+            ));
+        }
+        (None, Some(id)) => {
+            let saved = saved_searches::find_owned(pool, &user.0.id, id)
+                .await?
+                .ok_or_else(|| {
+                    AppError::api(
+                        StatusCode::NOT_FOUND,
+                        "SAVED_SEARCH_NOT_FOUND",
+                        "搜索条件不存在",
+                    )
+                })?;
+            if saved.scope_type != "GLOBAL" || saved.search_type != "DETAIL" {
+                return Err(AppError::api(
+                    StatusCode::BAD_REQUEST,
+                    "SAVED_SEARCH_NOT_GLOBAL",
+                    "只有全局范围的详细搜索条件可以用于全局搜索",
+                ));
+            }
+            let expression =
+                log_expression::parse(&saved.query_text).map_err(invalid_expression)?;
+            (
+                saved.query_text,
+                GlobalMatcher::Expression(expression),
+                Some(saved.id),
+            )
+        }
+        (Some(q), None) => match term.mode {
+            GlobalSearchMode::Expression => (
+                q.to_string(),
+                GlobalMatcher::Expression(log_expression::parse(q).map_err(invalid_expression)?),
+                None,
+            ),
+            GlobalSearchMode::Content if q.chars().count() < 3 => {
+                return Err(AppError::BadRequest("搜索关键词至少需要 3 个字符".into()));
+            }
+            GlobalSearchMode::Content => {
+                (q.to_string(), GlobalMatcher::Literal(q.to_string()), None)
+            }
+        },
+    };
+
+    let path_pattern = term
+        .path_like
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| format!("%{value}%"));
+    let level_mask = level_filter_mask(term.level.as_deref(), term.min_level.as_deref())?;
+    let time_range = parse_search_time_range(TimeScopeInput {
+        start: term.start,
+        end: term.end,
+        incident_time: term.incident_time,
+        before_minutes: term.before_minutes,
+        after_minutes: term.after_minutes,
+    })
+    .map_err(invalid_time_range)?;
+    let hits_per_bundle = term
+        .hits_per_bundle
+        .unwrap_or(DEFAULT_HITS_PER_BUNDLE)
+        .clamp(0, MAX_HITS_PER_BUNDLE) as usize;
+    let from = term.from.unwrap_or(0).max(0) as usize;
+    let size = term
+        .size
+        .unwrap_or(DEFAULT_GLOBAL_SEARCH_ISSUES)
+        .clamp(1, MAX_GLOBAL_SEARCH_ISSUES) as usize;
+
+    let filter = SegmentFilter {
+        scope: SegmentScope::Global {
+            owner_user_id: term.owned.then_some(user.0.id.as_str()),
+            path_pattern: path_pattern.as_deref(),
+        },
+        content: ContentFilter::prefilter(matcher.fts_query()),
+        // Levels are checked per line.
+        level_mask: None,
+        time: time_range
+            .as_ref()
+            .map_or(TimeFilter::Any, TimeFilter::WithinOrUntimed),
+    };
+    let columns = "ls.id AS segment_id, b.issue_code, i.name AS issue_name, i.owner_user_id, \
+         b.id AS bundle_id, b.hash AS bundle_hash, ls.file_id, f.path, \
+         lc.line_offset AS offset, lc.line_end, lc.chunk_index, lc.content, \
+         ls.event_time_shift_ms";
+    let event_filter = EventFilter {
+        level_mask,
+        time_range: time_range.as_ref(),
+    };
+    let mut after_id = 0_i64;
+    let mut scanned_bytes = 0_u64;
+    let mut truncated = false;
+    let mut total = 0_u64;
+    let mut untimed_hits = 0_u64;
+    let mut groups: HashMap<String, IssueGroup> = HashMap::new();
+    let mut timestamps: HashMap<String, (TimestampRecognizers, NaiveDateTime)> = HashMap::new();
+
+    'scan: loop {
+        let rows: Vec<GlobalCandidateRow> = filter
+            .scan_query(columns, after_id, GLOBAL_SCAN_BATCH_SIZE)
+            .build_query_as()
+            .fetch_all(pool)
+            .await
+            .map_err(AppError::Database)?;
+        if rows.is_empty() {
+            break;
+        }
+
+        for row in rows {
+            after_id = row.segment_id;
+            scanned_bytes = scanned_bytes.saturating_add(row.content.len() as u64);
+            if scanned_bytes > state.limits.api.max_global_search_scan_bytes {
+                truncated = true;
+                break 'scan;
+            }
+            let events = if matcher.uses_event_fields() || !event_filter.is_empty() {
+                let (recognizers, reference) = match timestamps.entry(row.bundle_id.clone()) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        entry.insert(load_timestamp_context(pool, &row.bundle_id).await?)
+                    }
+                };
+                Some(LineEventTracker::new(
+                    EventTimeParser::new(recognizers, *reference)
+                        .with_shift_ms(row.event_time_shift_ms),
+                ))
+            } else {
+                None
+            };
+            let (position, needle) = match matcher.first_match(&row.content, events, event_filter) {
+                ChunkMatch::Hit(position, needle) => (position, needle),
+                ChunkMatch::Untimed => {
+                    untimed_hits += 1;
+                    continue;
+                }
+                ChunkMatch::None => continue,
+            };
+            total += 1;
+            let issue = groups.entry(row.issue_code).or_insert_with(|| IssueGroup {
+                name: row.issue_name,
+                owned: row.owner_user_id.as_deref() == Some(user.0.id.as_str()),
+                total: 0,
+                bundles: HashMap::new(),
+            });
+            issue.total += 1;
+            let bundle = issue
+                .bundles
+                .entry(row.bundle_hash.clone())
+                .or_insert_with(|| GlobalSearchBundle {
+                    bundle_hash: row.bundle_hash.clone(),
+                    total: 0,
+                    hits: Vec::new(),
+                });
+            bundle.total += 1;
+            if bundle.hits.len() < hits_per_bundle {
+                bundle.hits.push(LogSearchHit {
+                    file_id: row.file_id.to_string(),
+                    path: row.path,
+                    bundle_hash: Some(row.bundle_hash),
+                    snippet: literal_snippet(&row.content, needle),
+                    timeline: None,
+                    offset: row.offset,
+                    line_end: row.line_end,
+                    line_number: row.offset,
+                    chunk_index: row.chunk_index,
+                    level: line_level_at(&row.content, position),
+                    event_time: None,
+                    captures: None,
+                    context_index: None,
+                });
+            }
+        }
+    }
+
+    let issue_count = groups.len() as u64;
+    let issues = ranked_issues(groups)
+        .into_iter()
+        .skip(from)
+        .take(size)
+        .collect();
+    if let Some(id) = &saved_search_id {
+        saved_searches::mark_used(pool, &user.0.id, id).await?;
+    }
+    Ok(HttpResponse::Ok().json(GlobalSearchResponse {
+        query: search_text,
+        saved_search_id,
+        total,
+        issue_count,
+        issues,
+        truncated,
+        time_range: time_range.as_ref().map(log_search_time_range),
+        untimed_hits: time_range.map(|_| untimed_hits),
+    }))
+}
+
+/// Issues and their bundles, most matches first.
+fn ranked_issues(groups: HashMap<String, IssueGroup>) -> Vec<GlobalSearchIssue> {
+    let mut issues: Vec<GlobalSearchIssue> = groups
+        .into_iter()
+        .map(|(issue_code, group)| {
+            let mut bundles: Vec<GlobalSearchBundle> = group.bundles.into_values().collect();
+            bundles.sort_by(|left, right| {
+                right
+                    .total
+                    .cmp(&left.total)
+                    .then_with(|| left.bundle_hash.cmp(&right.bundle_hash))
+            });
+            GlobalSearchIssue {
+                issue_code,
+                issue_name: group.name,
+                owned: group.owned,
+                total: group.total,
+                bundles,
+            }
+        })
+        .collect();
+    issues.sort_by(|left, right| {
+        right
+            .total
+            .cmp(&left.total)
+            .then_with(|| left.issue_code.cmp(&right.issue_code))
+    });
+    issues
+}
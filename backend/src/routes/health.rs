@@ -1,3 +1,8 @@
+use std::{
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
 use actix_web::{HttpResponse, get, http::StatusCode, web};
 use serde_json::json;
 use tokio::io::AsyncWriteExt;
@@ -153,7 +158,3 @@ mod tests {
         let _ = tokio::fs::remove_dir_all(data_root).await;
     }
 }
-use std::{
-    sync::{Mutex, OnceLock},
-    time::{Duration, Instant},
-};
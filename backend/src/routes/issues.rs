@@ -12,6 +12,7 @@ use crate::{
         IssueBundlesResponse, IssueInactivityExpiry, IssueSummary, UploadStage, UploadStatus,
         UploadStatusWrapper,
     },
+    upload::append::claim_bundle_append,
 };
 
 const ISSUE_CODE_MAX_LEN: usize = 64;
@@ -663,6 +664,7 @@ pub async fn delete_issue_bundle(
     .map_err(AppError::Database)?
     .ok_or_else(|| AppError::NotFound(format!("bundle {bundle_hash}")))?;
     reject_processing_bundle(&bundle)?;
+    let append_lease = claim_bundle_append(&state.upload.appending, &bundle.id)?;
     sqlx::query(
         "UPDATE bundles SET status = 'DELETING', deleted_at = CURRENT_TIMESTAMP WHERE id = ?",
     )
@@ -670,6 +672,7 @@ pub async fn delete_issue_bundle(
     .execute(&state.db.pool)
     .await
     .map_err(AppError::Database)?;
+    drop(append_lease);
 
     // Finish request-scoped writes before the heavyweight cleanup can compete for
     // SQLite's single writer lock.
@@ -1006,8 +1009,20 @@ mod tests {
                 .fetch_one(&pool)
                 .await
                 .unwrap();
-        sqlx::query("INSERT INTO log_segments(bundle_id,file_id,content) VALUES('old-bundle',?,'old content')")
-            .bind(old_file_id).execute(&pool).await.unwrap();
+        let old_content_id: i64 = sqlx::query_scalar(
+            "INSERT INTO log_segment_contents(content) VALUES('old content') RETURNING id",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO log_segments(bundle_id,file_id,content_id) VALUES('old-bundle',?,?)",
+        )
+        .bind(old_file_id)
+        .bind(old_content_id)
+        .execute(&pool)
+        .await
+        .unwrap();
         sqlx::query("INSERT INTO log_line_offsets(file_id,line_number,byte_offset) VALUES(?,1,0)")
             .bind(old_file_id)
             .execute(&pool)
@@ -0,0 +1,568 @@
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    path::{Path, PathBuf},
+};
+
+use actix_web::{HttpRequest, HttpResponse, get, web};
+use async_stream::stream;
+use chrono::NaiveDateTime;
+use futures_util::{Stream, StreamExt};
+use serde::Deserialize;
+use sqlx::FromRow;
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+use crate::{
+    AppState, LineReadLease,
+    error::AppError,
+    ingest::{EventTimeParser, TimestampRecognizers, load_timestamp_context},
+    log_expression::LineEventTracker,
+    models::logs::LogExportRecord,
+    repositories::files::FileRow,
+    services::{
+        log_context::{IndexedSegment, segment_line_numbers},
+        skill_time_scope::{SkillTimeScope, TimeScopeInput, parse_search_time_range},
+        wall_clock,
+    },
+};
+
+use super::helpers::{ensure_bundle_ready, load_bundle};
+use super::issues::{ensure_issue_active, normalize_issue_code, touch_issue_activity_best_effort};
+use super::logs::{
+    EventFilter, LineMatcher, SegmentFilter, SegmentScope, TimeFilter, invalid_time_range,
+    level_filter_mask,
+};
+use super::temp_results::request_client_key;
+
+const EXPORT_SCAN_BATCH_SIZE: i64 = 200;
+const CSV_HEADER: &str = "bundle_hash,path,line_number,event_time,level,content\n";
+
+#[derive(Deserialize)]
+struct BundleExportQuery {
+    q: String,
+    #[serde(default)]
+    mode: ExportMode,
+    #[serde(default)]
+    format: ExportFormat,
+    timeline: Option<String>,
+    path_like: Option<String>,
+    file_id: Option<i64>,
+    level: Option<String>,
+    min_level: Option<String>,
+    start: Option<String>,
+    end: Option<String>,
+    incident_time: Option<String>,
+    before_minutes: Option<i64>,
+    after_minutes: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct IssueExportQuery {
+    q: String,
+    #[serde(default)]
+    mode: ExportMode,
+    #[serde(default)]
+    format: ExportFormat,
+    path_like: Option<String>,
+    level: Option<String>,
+    min_level: Option<String>,
+    start: Option<String>,
+    end: Option<String>,
+    incident_time: Option<String>,
+    before_minutes: Option<i64>,
+    after_minutes: Option<i64>,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ExportMode {
+    #[default]
+    Content,
+    Regex,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    #[default]
+    Ndjson,
+    Csv,
+}
+
+/// Owned form of [`SegmentScope`] that can move into the response stream.
+enum ExportScope {
+    Bundle {
+        bundle_id: String,
+        timeline: Option<String>,
+        path_pattern: Option<String>,
+        file_id: Option<i64>,
+    },
+    Issue {
+        issue_code: String,
+        path_pattern: Option<String>,
+    },
+}
+
+impl ExportScope {
+    fn borrow(&self) -> SegmentScope<'_> {
+        match self {
+            Self::Bundle {
+                bundle_id,
+                timeline,
+                path_pattern,
+                file_id,
+            } => SegmentScope::Bundle {
+                bundle_id,
+                timeline: timeline.as_deref(),
+                path_pattern: path_pattern.as_deref(),
+                file_id: *file_id,
+            },
+            Self::Issue {
+                issue_code,
+                path_pattern,
+            } => SegmentScope::Issue {
+                issue_code,
+                path_pattern: path_pattern.as_deref(),
+                file_id: None,
+            },
+        }
+    }
+}
+
+struct ExportRequest {
+    scope: ExportScope,
+    matcher: LineMatcher,
+    level_mask: Option<i64>,
+    time_range: Option<SkillTimeScope>,
+    format: ExportFormat,
+}
+
+#[derive(FromRow)]
+struct ExportSegmentRow {
+    segment_id: i64,
+    bundle_id: String,
+    bundle_hash: String,
+    file_id: i64,
+    path: String,
+    offset: Option<i64>,
+    line_end: Option<i64>,
+    content: String,
+    event_time_shift_ms: i64,
+}
+
+// scoped under /api in routes::register
+#[get("/log/v2/{bundle_id}/search/export")]
+pub async fn export_bundle_search(
+    request: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<BundleExportQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let term = query.into_inner();
+    let search_term = term.q.trim();
+    if search_term.is_empty() {
+        return Err(AppError::BadRequest("query parameter q is required".into()));
+    }
+    let bundle = load_bundle(&state.db.pool, &path.into_inner()).await?;
+    ensure_bundle_ready(&bundle)?;
+    let export = ExportRequest {
+        scope: ExportScope::Bundle {
+            bundle_id: bundle.id.clone(),
+            timeline: non_empty(term.timeline),
+            path_pattern: non_empty(term.path_like).map(|value| format!("%{value}%")),
+            file_id: term.file_id,
+        },
+        matcher: LineMatcher::parse(search_term, matches!(term.mode, ExportMode::Regex))?,
+        level_mask: level_filter_mask(term.level.as_deref(), term.min_level.as_deref())?,
+        time_range: parse_search_time_range(TimeScopeInput {
+            start: term.start,
+            end: term.end,
+            incident_time: term.incident_time,
+            before_minutes: term.before_minutes,
+            after_minutes: term.after_minutes,
+        })
+        .map_err(invalid_time_range)?,
+        format: term.format,
+    };
+    let lease = state.acquire_line_read(&request_client_key(&request))?;
+    touch_issue_activity_best_effort(&state.db.pool, &bundle.issue_code, "bundle log export").await;
+    export_response(state, export, lease, &bundle.hash).await
+}
+
+#[get("/issues/{issue_code}/search/export")]
+pub async fn export_issue_search(
+    request: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<IssueExportQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let issue_code = normalize_issue_code(&path.into_inner())?;
+    ensure_issue_active(&state.db.pool, &issue_code).await?;
+    let term = query.into_inner();
+    let search_term = term.q.trim();
+    if search_term.is_empty() {
+        return Err(AppError::BadRequest("query parameter q is required".into()));
+    }
+    let export = ExportRequest {
+        scope: ExportScope::Issue {
+            issue_code: issue_code.clone(),
+            path_pattern: non_empty(term.path_like).map(|value| format!("%{value}%")),
+        },
+        matcher: LineMatcher::parse(search_term, matches!(term.mode, ExportMode::Regex))?,
+        level_mask: level_filter_mask(term.level.as_deref(), term.min_level.as_deref())?,
+        time_range: parse_search_time_range(TimeScopeInput {
+            start: term.start,
+            end: term.end,
+            incident_time: term.incident_time,
+            before_minutes: term.before_minutes,
+            after_minutes: term.after_minutes,
+        })
+        .map_err(invalid_time_range)?,
+        format: term.format,
+    };
+    let lease = state.acquire_line_read(&request_client_key(&request))?;
+    touch_issue_activity_best_effort(&state.db.pool, &issue_code, "issue log export").await;
+    export_response(state, export, lease, &issue_code).await
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+async fn export_response(
+    state: web::Data<AppState>,
+    export: ExportRequest,
+    lease: LineReadLease,
+    name: &str,
+) -> Result<HttpResponse, AppError> {
+    let (content_type, extension) = match export.format {
+        ExportFormat::Ndjson => ("application/x-ndjson", "ndjson"),
+        ExportFormat::Csv => ("text/csv; charset=utf-8", "csv"),
+    };
+    let file_name: String = name
+        .chars()
+        .filter(|character| character.is_ascii_alphanumeric() || matches!(character, '-' | '_'))
+        .collect();
+    let mut response = HttpResponse::Ok();
+    response
+        .insert_header(("content-type", content_type))
+        .insert_header(("cache-control", "no-store"))
+        .insert_header((
+            "content-disposition",
+            format!("attachment; filename=\"{file_name}-search.{extension}\""),
+        ));
+    match export.format {
+        ExportFormat::Ndjson => {
+            let records = export_stream(state, export, lease).map(|chunk| match chunk {
+                Ok(ExportChunk::Records(records)) => Ok(web::Bytes::from(records)),
+                Ok(ExportChunk::Done {
+                    exported,
+                    truncated,
+                }) => {
+                    let summary = serde_json::json!({
+                        "summary": { "exported": exported, "truncated": truncated }
+                    });
+                    Ok(web::Bytes::from(format!("{summary}\n")))
+                }
+                Err(error) => Err(export_failed(error)),
+            });
+            Ok(response.streaming(records))
+        }
+        ExportFormat::Csv => {
+            let spool = CsvSpool::create(&state.storage.data_root).await?;
+            let (exported, truncated) = spool
+                .write(export_stream(state.clone(), export, lease))
+                .await?;
+            let file = tokio::fs::File::open(&spool.path).await?;
+            response
+                .insert_header(("x-export-rows", exported.to_string()))
+                .insert_header(("x-export-truncated", truncated.to_string()));
+            Ok(response.streaming(stream! {
+                let _spool = spool;
+                let mut file = ReaderStream::new(file);
+                while let Some(chunk) = file.next().await {
+                    yield chunk;
+                }
+            }))
+        }
+    }
+}
+
+/// CSV has no room for a summary, so the rows are written to a temporary
+/// file first and the row count and truncation go out as response headers.
+struct CsvSpool {
+    path: PathBuf,
+}
+
+impl CsvSpool {
+    async fn create(data_root: &Path) -> Result<Self, AppError> {
+        let temp_root = data_root.join(".tmp");
+        tokio::fs::create_dir_all(&temp_root).await?;
+        Ok(Self {
+            path: temp_root.join(format!("export-{}.csv", Uuid::new_v4())),
+        })
+    }
+
+    /// Writes the header and every record; returns `(exported, truncated)`.
+    async fn write(
+        &self,
+        chunks: impl Stream<Item = Result<ExportChunk, AppError>>,
+    ) -> Result<(u64, bool), AppError> {
+        let mut file = tokio::io::BufWriter::new(tokio::fs::File::create(&self.path).await?);
+        file.write_all(CSV_HEADER.as_bytes()).await?;
+        let mut chunks = std::pin::pin!(chunks);
+        let mut summary = (0, false);
+        while let Some(chunk) = chunks.next().await {
+            match chunk? {
+                ExportChunk::Records(records) => file.write_all(records.as_bytes()).await?,
+                ExportChunk::Done {
+                    exported,
+                    truncated,
+                } => summary = (exported, truncated),
+            }
+        }
+        file.flush().await?;
+        Ok(summary)
+    }
+}
+
+impl Drop for CsvSpool {
+    fn drop(&mut self) {
+        if let Err(error) = std::fs::remove_file(&self.path)
+            && error.kind() != std::io::ErrorKind::NotFound
+        {
+            tracing::warn!(path = %self.path.display(), %error, "failed to remove csv export");
+        }
+    }
+}
+
+enum ExportChunk {
+    /// Formatted records of one segment.
+    Records(String),
+    Done {
+        exported: u64,
+        truncated: bool,
+    },
+}
+
+/// Walks every matching segment in id order and yields one record per
+/// matching line. Dropping the stream, e.g. when the client disconnects,
+/// stops the scan and releases the line read lease.
+fn export_stream(
+    state: web::Data<AppState>,
+    export: ExportRequest,
+    lease: LineReadLease,
+) -> impl Stream<Item = Result<ExportChunk, AppError>> {
+    stream! {
+        let _lease = lease;
+        let mut after_id = 0_i64;
+        let mut scanned_bytes = 0_u64;
+        let mut exported = 0_u64;
+        let mut truncated = false;
+        let mut timestamps: HashMap<String, (TimestampRecognizers, NaiveDateTime)> = HashMap::new();
+        let mut files: HashMap<i64, Option<FileRow>> = HashMap::new();
+
+        let filter = SegmentFilter {
+            scope: export.scope.borrow(),
+            content: export.matcher.content_filter(),
+            level_mask: export.level_mask,
+            time: TimeFilter::within(export.time_range.as_ref()),
+        };
+        let event_filter = EventFilter {
+            level_mask: export.level_mask,
+            time_range: export.time_range.as_ref(),
+        };
+
+        'scan: loop {
+            let rows: Vec<ExportSegmentRow> = match filter
+                .scan_query(
+                    r#"ls.id AS segment_id, ls.bundle_id, b.hash AS bundle_hash, ls.file_id, f.path,
+                   lc.line_offset AS offset, lc.line_end, lc.content, ls.event_time_shift_ms"#,
+                    after_id,
+                    EXPORT_SCAN_BATCH_SIZE,
+                )
+                .build_query_as()
+                .fetch_all(&state.db.pool)
+                .await
+            {
+                Ok(rows) => rows,
+                Err(error) => {
+                    yield Err(AppError::Database(error));
+                    break;
+                }
+            };
+            if rows.is_empty() {
+                break;
+            }
+
+            for row in rows {
+                after_id = row.segment_id;
+                scanned_bytes = scanned_bytes.saturating_add(row.content.len() as u64);
+                if scanned_bytes > state.limits.api.max_export_scan_bytes {
+                    truncated = true;
+                    break 'scan;
+                }
+                let matches = export.matcher.matching_lines(&row.content);
+                if matches.is_empty() {
+                    continue;
+                }
+                let (recognizers, reference) = match timestamps.entry(row.bundle_id.clone()) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        match load_timestamp_context(&state.db.pool, &row.bundle_id).await {
+                            Ok(context) => entry.insert(context),
+                            Err(error) => {
+                                yield Err(error);
+                                break 'scan;
+                            }
+                        }
+                    }
+                };
+                let line_numbers = match row_line_numbers(&state, &mut files, &row).await {
+                    Ok(line_numbers) => line_numbers,
+                    Err(error) => {
+                        yield Err(error);
+                        break 'scan;
+                    }
+                };
+                let mut events = LineEventTracker::new(
+                    EventTimeParser::new(recognizers, *reference)
+                        .with_shift_ms(row.event_time_shift_ms),
+                );
+                let mut matches = matches.into_iter().peekable();
+                let mut chunk = String::new();
+                for (index, line) in row.content.split('\n').enumerate() {
+                    let Some(&next) = matches.peek() else {
+                        break;
+                    };
+                    let event = events.observe(line);
+                    if index != next {
+                        continue;
+                    }
+                    matches.next();
+                    if !event_filter.has_level(event) || !event_filter.in_time_range(event) {
+                        continue;
+                    }
+                    let record = LogExportRecord {
+                        bundle_hash: row.bundle_hash.clone(),
+                        file_id: row.file_id.to_string(),
+                        path: row.path.clone(),
+                        line_number: line_numbers
+                            .as_ref()
+                            .and_then(|numbers| numbers.get(index).copied())
+                            .or_else(|| row.offset.map(|offset| offset + index as i64)),
+                        event_time: event
+                            .time_key
+                            .and_then(wall_clock::from_comparison_key)
+                            .map(wall_clock::format),
+                        level: event.level.map(|level| level.as_str().to_string()),
+                        content: line.to_string(),
+                    };
+                    write_record(&mut chunk, &record, export.format);
+                    exported += 1;
+                }
+                if !chunk.is_empty() {
+                    yield Ok(ExportChunk::Records(chunk));
+                }
+            }
+        }
+
+        yield Ok(ExportChunk::Done { exported, truncated });
+    }
+}
+
+/// File line numbers of each indexed line of `row` when blank lines were
+/// skipped at index time; `None` when line `offset + index` is exact.
+async fn row_line_numbers(
+    state: &AppState,
+    files: &mut HashMap<i64, Option<FileRow>>,
+    row: &ExportSegmentRow,
+) -> Result<Option<Vec<i64>>, AppError> {
+    segment_line_numbers(
+        &state.db.pool,
+        state.storage.blob_store.as_ref(),
+        &state.limits.api,
+        files,
+        IndexedSegment {
+            bundle_id: &row.bundle_id,
+            file_id: row.file_id,
+            offset: row.offset,
+            line_end: row.line_end,
+            content: &row.content,
+        },
+    )
+    .await
+}
+
+fn export_failed(error: AppError) -> actix_web::Error {
+    tracing::warn!(error = %error, "search export aborted");
+    actix_web::error::ErrorInternalServerError("search export failed")
+}
+
+fn write_record(out: &mut String, record: &LogExportRecord, format: ExportFormat) {
+    match format {
+        ExportFormat::Ndjson => {
+            if let Ok(line) = serde_json::to_string(record) {
+                out.push_str(&line);
+                out.push('\n');
+            }
+        }
+        ExportFormat::Csv => {
+            let line_number = record.line_number.map(|value| value.to_string());
+            let fields = [
+                record.bundle_hash.as_str(),
+                record.path.as_str(),
+                line_number.as_deref().unwrap_or(""),
+                record.event_time.as_deref().unwrap_or(""),
+                record.level.as_deref().unwrap_or(""),
+                record.content.as_str(),
+            ];
+            for (index, field) in fields.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                push_csv_field(out, field);
+            }
+            out.push('\n');
+        }
+    }
+}
+
+fn push_csv_field(out: &mut String, field: &str) {
+    if field.contains([',', '"', '\n', '\r']) {
+        out.push('"');
+        out.push_str(&field.replace('"', "\"\""));
+        out.push('"');
+    } else {
+        out.push_str(field);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LineMatcher, push_csv_field};
+    use crate::services::log_regex::LogRegex;
+
+    #[test]
+    fn matches_each_line_once_in_content_and_regex_mode() {
+        let content = "Disk failure a\nok\ndisk FAILURE b disk failure c\nfine";
+        let literal = LineMatcher::Literal("disk failure".into());
+        assert_eq!(literal.matching_lines(content), [0, 2]);
+        let regex = LineMatcher::Regex(LogRegex::compile(r"(?i)failure \w").unwrap());
+        assert_eq!(regex.matching_lines(content), [0, 2]);
+        let spanning = LineMatcher::Regex(LogRegex::compile(r"ok\n\w+").unwrap());
+        assert_eq!(spanning.matching_lines(content), [1]);
+    }
+
+    #[test]
+    fn quotes_csv_fields_only_when_needed() {
+        let mut out = String::new();
+        push_csv_field(&mut out, "plain");
+        out.push(',');
+        push_csv_field(&mut out, "say \"hi\", then\nleave");
+        assert_eq!(out, "plain,\"say \"\"hi\"\", then\nleave\"");
+    }
+}
@@ -0,0 +1,658 @@
+use std::collections::HashMap;
+
+use actix_web::{HttpRequest, HttpResponse, get, web};
+use serde::Deserialize;
+use sqlx::{FromRow, QueryBuilder, Sqlite};
+
+use crate::{
+    AppState,
+    auth::extractor::RequireBusinessUser,
+    error::AppError,
+    ingest::{EventTimeParser, LogLevel, load_timestamp_context},
+    log_expression::{LineEvent, LineEventTracker},
+    models::logs::{
+        BundleTemplateDiffResponse, BundleTemplateDiffSide, LogExportRecord, LogTemplateExample,
+        LogTemplateLinesResponse, LogTemplateListResponse, LogTemplateSummary, TemplateDiffEntry,
+    },
+    repositories::files::FileRow,
+    services::{
+        log_context::{IndexedSegment, segment_line_numbers},
+        log_templates::{
+            StoredTemplate, TemplateExample, TemplateMining, WILDCARD, diff_templates,
+            load_bundle_templates, matches_template, queue_unmined_bundles, template_tokens,
+            trigger_mining,
+        },
+        wall_clock,
+    },
+};
+
+use super::helpers::load_bundle;
+use super::issues::{
+    ensure_issue_active, normalize_issue_code, require_issue_owner,
+    touch_issue_activity_best_effort,
+};
+use super::logs::{
+    ContentFilter, SegmentFilter, SegmentScope, TimeFilter, build_fts_query, level_filter_mask,
+};
+use super::temp_results::request_client_key;
+
+const DEFAULT_TEMPLATE_PAGE_SIZE: i64 = 50;
+const MAX_TEMPLATE_PAGE_SIZE: i64 = 500;
+const DEFAULT_LINE_PAGE_SIZE: usize = 100;
+const MAX_LINE_PAGE_SIZE: usize = 1_000;
+const MAX_SUMMARY_EXAMPLES: usize = 3;
+const TEMPLATE_SCAN_BATCH_SIZE: i64 = 200;
+const DEFAULT_DIFF_MIN_RATIO: f64 = 2.0;
+const MAX_DIFF_MIN_RATIO: f64 = 1_000.0;
+const DIFF_EXAMPLES_PER_SIDE: usize = 2;
+
+#[derive(Deserialize)]
+struct TemplateListQuery {
+    bundle_hash: Option<String>,
+    size: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct TemplateLinesQuery {
+    bundle_hash: Option<String>,
+    cursor: Option<String>,
+    size: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct TemplateDiffQuery {
+    base: String,
+    target: String,
+    min_ratio: Option<f64>,
+    min_count: Option<i64>,
+    level: Option<String>,
+    min_level: Option<String>,
+    size: Option<i64>,
+}
+
+#[derive(FromRow)]
+struct DiffBundleRow {
+    id: String,
+    hash: String,
+    name: String,
+    status: String,
+    template_state: String,
+}
+
+#[derive(FromRow)]
+struct TemplateAggregateRow {
+    template_key: String,
+    template: String,
+    line_count: i64,
+    bundle_count: i64,
+    level_mask: i64,
+    first_event_time_ms: Option<i64>,
+    last_event_time_ms: Option<i64>,
+}
+
+#[derive(FromRow)]
+struct TemplateExampleRow {
+    template_key: String,
+    bundle_hash: String,
+    examples: String,
+}
+
+#[derive(FromRow)]
+struct TemplateSegmentRow {
+    segment_id: i64,
+    bundle_id: String,
+    bundle_hash: String,
+    file_id: i64,
+    path: String,
+    offset: Option<i64>,
+    line_end: Option<i64>,
+    content: String,
+    event_time_shift_ms: i64,
+}
+
+/// Bundle restriction of the template endpoints; `None` covers every ready
+/// bundle of the issue.
+async fn bundle_filter(
+    state: &AppState,
+    issue_code: &str,
+    bundle_hash: Option<&str>,
+) -> Result<Option<String>, AppError> {
+    let Some(hash) = bundle_hash.map(str::trim).filter(|hash| !hash.is_empty()) else {
+        return Ok(None);
+    };
+    let bundle = load_bundle(&state.db.pool, hash).await?;
+    if bundle.issue_code != issue_code {
+        return Err(AppError::NotFound(format!("bundle {hash}")));
+    }
+    Ok(Some(bundle.id))
+}
+
+fn push_mined_bundles(
+    builder: &mut QueryBuilder<'_, Sqlite>,
+    issue_code: &str,
+    bundle_id: Option<&str>,
+) {
+    builder.push(
+        " FROM log_templates t JOIN bundles b ON b.id = t.bundle_id WHERE b.status = 'READY' AND b.deleted_at IS NULL AND b.template_state = 'READY' AND b.issue_code = ",
+    );
+    builder.push_bind(issue_code.to_string());
+    if let Some(bundle_id) = bundle_id {
+        builder.push(" AND b.id = ");
+        builder.push_bind(bundle_id.to_string());
+    }
+}
+
+fn event_time(key: Option<i64>) -> Option<String> {
+    key.and_then(wall_clock::from_comparison_key)
+        .map(wall_clock::format)
+}
+
+// scoped under /api in routes::register
+#[get("/issues/{issue_code}/templates")]
+pub async fn list_issue_templates(
+    path: web::Path<String>,
+    query: web::Query<TemplateListQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let issue_code = normalize_issue_code(&path.into_inner())?;
+    ensure_issue_active(&state.db.pool, &issue_code).await?;
+    let query = query.into_inner();
+    let bundle_id = bundle_filter(&state, &issue_code, query.bundle_hash.as_deref()).await?;
+    let size = query
+        .size
+        .unwrap_or(DEFAULT_TEMPLATE_PAGE_SIZE)
+        .clamp(1, MAX_TEMPLATE_PAGE_SIZE);
+
+    if queue_unmined_bundles(&state.db.pool, &issue_code).await? > 0 {
+        trigger_mining(TemplateMining::from_state(&state));
+    }
+    let mut states = QueryBuilder::<Sqlite>::new(
+        "SELECT COALESCE(SUM(template_state = 'READY'), 0), COALESCE(SUM(template_state IN ('PENDING', 'NONE')), 0), COALESCE(SUM(template_state = 'FAILED'), 0) FROM bundles WHERE status = 'READY' AND deleted_at IS NULL AND issue_code = ",
+    );
+    states.push_bind(&issue_code);
+    if let Some(bundle_id) = &bundle_id {
+        states.push(" AND id = ");
+        states.push_bind(bundle_id);
+    }
+    let (mined_bundles, pending_bundles, failed_bundles): (i64, i64, i64) = states
+        .build_query_as()
+        .fetch_one(&state.db.pool)
+        .await
+        .map_err(AppError::Database)?;
+
+    let mut total = QueryBuilder::<Sqlite>::new("SELECT COUNT(DISTINCT t.template_key)");
+    push_mined_bundles(&mut total, &issue_code, bundle_id.as_deref());
+    let total_templates: i64 = total
+        .build_query_scalar()
+        .fetch_one(&state.db.pool)
+        .await
+        .map_err(AppError::Database)?;
+
+    // SQLite has no bitwise OR aggregate; level bits are disjoint, so OR-ing
+    // the per-bit maxima gives the union of the bundles' level masks.
+    let level_mask = LogLevel::ALL
+        .iter()
+        .map(|level| format!("MAX(t.level_mask & {})", level.bit()))
+        .collect::<Vec<_>>()
+        .join(" | ");
+    let mut aggregate = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT t.template_key, MIN(t.template) AS template, SUM(t.line_count) AS line_count, COUNT(DISTINCT t.bundle_id) AS bundle_count, ({level_mask}) AS level_mask, MIN(t.first_event_time_ms) AS first_event_time_ms, MAX(t.last_event_time_ms) AS last_event_time_ms"
+    ));
+    push_mined_bundles(&mut aggregate, &issue_code, bundle_id.as_deref());
+    aggregate.push(" GROUP BY t.template_key ORDER BY line_count DESC, t.template_key LIMIT ");
+    aggregate.push_bind(size);
+    let rows: Vec<TemplateAggregateRow> = aggregate
+        .build_query_as()
+        .fetch_all(&state.db.pool)
+        .await
+        .map_err(AppError::Database)?;
+
+    let mut examples: HashMap<String, Vec<LogTemplateExample>> = HashMap::new();
+    if !rows.is_empty() {
+        let mut builder =
+            QueryBuilder::<Sqlite>::new("SELECT t.template_key, b.hash AS bundle_hash, t.examples");
+        push_mined_bundles(&mut builder, &issue_code, bundle_id.as_deref());
+        builder.push(" AND t.template_key IN (");
+        let mut keys = builder.separated(", ");
+        for row in &rows {
+            keys.push_bind(row.template_key.clone());
+        }
+        builder.push(") ORDER BY b.created_at, b.id");
+        let example_rows: Vec<TemplateExampleRow> = builder
+            .build_query_as()
+            .fetch_all(&state.db.pool)
+            .await
+            .map_err(AppError::Database)?;
+        for row in example_rows {
+            let entry = examples.entry(row.template_key).or_default();
+            let stored: Vec<TemplateExample> =
+                serde_json::from_str(&row.examples).unwrap_or_default();
+            for example in stored {
+                if entry.len() >= MAX_SUMMARY_EXAMPLES {
+                    break;
+                }
+                entry.push(LogTemplateExample {
+                    bundle_hash: row.bundle_hash.clone(),
+                    file_id: example.file_id.to_string(),
+                    line_number: example.line_number,
+                    content: example.content,
+                });
+            }
+        }
+    }
+
+    let templates = rows
+        .into_iter()
+        .map(|row| LogTemplateSummary {
+            examples: examples.remove(&row.template_key).unwrap_or_default(),
+            key: row.template_key,
+            template: row.template,
+            line_count: row.line_count,
+            bundle_count: row.bundle_count,
+            levels: LogLevel::ALL
+                .iter()
+                .filter(|level| row.level_mask & level.bit() != 0)
+                .map(|level| level.as_str().to_string())
+                .collect(),
+            first_event_time: event_time(row.first_event_time_ms),
+            last_event_time: event_time(row.last_event_time_ms),
+        })
+        .collect();
+    touch_issue_activity_best_effort(&state.db.pool, &issue_code, "issue log templates").await;
+    Ok(HttpResponse::Ok().json(LogTemplateListResponse {
+        templates,
+        total_templates: total_templates.max(0) as u64,
+        mined_bundles: mined_bundles.max(0) as u64,
+        pending_bundles: pending_bundles.max(0) as u64,
+        failed_bundles: failed_bundles.max(0) as u64,
+    }))
+}
+
+/// Parses a `segment_id:line_index` cursor of the template lines endpoint.
+fn parse_cursor(cursor: Option<&str>) -> Result<(i64, usize), AppError> {
+    let Some(cursor) = cursor.map(str::trim).filter(|cursor| !cursor.is_empty()) else {
+        return Ok((0, 0));
+    };
+    cursor
+        .split_once(':')
+        .and_then(|(segment, line)| Some((segment.parse().ok()?, line.parse().ok()?)))
+        .filter(|(segment, _): &(i64, usize)| *segment > 0)
+        .ok_or_else(|| AppError::BadRequest("cursor 参数无效".into()))
+}
+
+/// Trigram prefilter for a template: its longest literal fragment of at
+/// least 3 characters, if any.
+fn template_fts_query(template: &[&str]) -> Option<String> {
+    template
+        .iter()
+        .map(|token| token.strip_suffix(WILDCARD).unwrap_or(token))
+        .filter(|fragment| !fragment.contains(WILDCARD) && fragment.chars().count() >= 3)
+        .max_by_key(|fragment| fragment.chars().count())
+        .map(build_fts_query)
+}
+
+#[get("/issues/{issue_code}/templates/{key}/lines")]
+pub async fn get_template_lines(
+    request: HttpRequest,
+    path: web::Path<(String, String)>,
+    query: web::Query<TemplateLinesQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let (issue_code, key) = path.into_inner();
+    let issue_code = normalize_issue_code(&issue_code)?;
+    ensure_issue_active(&state.db.pool, &issue_code).await?;
+    let query = query.into_inner();
+    let bundle_id = bundle_filter(&state, &issue_code, query.bundle_hash.as_deref()).await?;
+    let (start_segment, start_line) = parse_cursor(query.cursor.as_deref())?;
+    let size = query
+        .size
+        .unwrap_or(DEFAULT_LINE_PAGE_SIZE)
+        .clamp(1, MAX_LINE_PAGE_SIZE);
+
+    let mut lookup = QueryBuilder::<Sqlite>::new("SELECT t.template");
+    push_mined_bundles(&mut lookup, &issue_code, bundle_id.as_deref());
+    lookup.push(" AND t.template_key = ");
+    lookup.push_bind(&key);
+    lookup.push(" LIMIT 1");
+    let template: String = lookup
+        .build_query_scalar()
+        .fetch_optional(&state.db.pool)
+        .await
+        .map_err(AppError::Database)?
+        .ok_or_else(|| AppError::NotFound(format!("log template {key}")))?;
+    let template_tokens_expected: Vec<&str> = template.split(' ').collect();
+    let fts_query = template_fts_query(&template_tokens_expected);
+    let scope = match &bundle_id {
+        Some(bundle_id) => SegmentScope::Bundle {
+            bundle_id,
+            timeline: None,
+            path_pattern: None,
+            file_id: None,
+        },
+        None => SegmentScope::Issue {
+            issue_code: &issue_code,
+            path_pattern: None,
+            file_id: None,
+        },
+    };
+
+    let filter = SegmentFilter {
+        scope,
+        content: ContentFilter::prefilter(fts_query),
+        level_mask: None,
+        time: TimeFilter::Any,
+    };
+
+    let _lease = state.acquire_line_read(&request_client_key(&request))?;
+    let mut lines = Vec::new();
+    let mut next_cursor = None;
+    let mut after_id = start_segment.saturating_sub(1);
+    let mut scanned_bytes = 0_u64;
+    let mut timestamps = HashMap::new();
+    let mut files: HashMap<i64, Option<FileRow>> = HashMap::new();
+    'scan: loop {
+        let rows: Vec<TemplateSegmentRow> = filter
+            .scan_query(
+                r#"ls.id AS segment_id, ls.bundle_id, b.hash AS bundle_hash, ls.file_id, f.path,
+               lc.line_offset AS offset, lc.line_end, lc.content, ls.event_time_shift_ms"#,
+                after_id,
+                TEMPLATE_SCAN_BATCH_SIZE,
+            )
+            .build_query_as()
+            .fetch_all(&state.db.pool)
+            .await
+            .map_err(AppError::Database)?;
+        if rows.is_empty() {
+            break;
+        }
+        for row in rows {
+            if scanned_bytes >= state.limits.api.max_template_scan_bytes {
+                next_cursor = Some(format!("{}:0", row.segment_id));
+                break 'scan;
+            }
+            after_id = row.segment_id;
+            scanned_bytes = scanned_bytes.saturating_add(row.content.len() as u64);
+            let skip = if row.segment_id == start_segment {
+                start_line
+            } else {
+                0
+            };
+            let matching: Vec<usize> = row
+                .content
+                .split('\n')
+                .enumerate()
+                .filter(|(index, line)| {
+                    *index >= skip
+                        && matches_template(&template_tokens_expected, &template_tokens(line))
+                })
+                .map(|(index, _)| index)
+                .collect();
+            if matching.is_empty() {
+                continue;
+            }
+            if !timestamps.contains_key(&row.bundle_id) {
+                let context = load_timestamp_context(&state.db.pool, &row.bundle_id).await?;
+                timestamps.insert(row.bundle_id.clone(), context);
+            }
+            let (recognizers, reference) = &timestamps[&row.bundle_id];
+            let line_numbers = segment_line_numbers(
+                &state.db.pool,
+                state.storage.blob_store.as_ref(),
+                &state.limits.api,
+                &mut files,
+                IndexedSegment {
+                    bundle_id: &row.bundle_id,
+                    file_id: row.file_id,
+                    offset: row.offset,
+                    line_end: row.line_end,
+                    content: &row.content,
+                },
+            )
+            .await?;
+            let mut events = LineEventTracker::new(
+                EventTimeParser::new(recognizers, *reference)
+                    .with_shift_ms(row.event_time_shift_ms),
+            );
+            let mut matching = matching.into_iter().peekable();
+            for (index, line) in row.content.split('\n').enumerate() {
+                let Some(&next) = matching.peek() else {
+                    break;
+                };
+                let LineEvent { level, time_key } = events.observe(line);
+                if index != next {
+                    continue;
+                }
+                matching.next();
+                if lines.len() == size {
+                    next_cursor = Some(format!("{}:{index}", row.segment_id));
+                    break 'scan;
+                }
+                lines.push(LogExportRecord {
+                    bundle_hash: row.bundle_hash.clone(),
+                    file_id: row.file_id.to_string(),
+                    path: row.path.clone(),
+                    line_number: line_numbers
+                        .as_ref()
+                        .and_then(|numbers| numbers.get(index).copied())
+                        .or_else(|| row.offset.map(|offset| offset + index as i64)),
+                    event_time: event_time(time_key),
+                    level: level.map(|level| level.as_str().to_string()),
+                    content: line.to_string(),
+                });
+            }
+        }
+    }
+
+    touch_issue_activity_best_effort(&state.db.pool, &issue_code, "issue log template lines").await;
+    Ok(HttpResponse::Ok().json(LogTemplateLinesResponse {
+        key,
+        template,
+        lines,
+        next_cursor,
+    }))
+}
+
+async fn load_diff_bundle(
+    state: &AppState,
+    issue_code: &str,
+    hash: &str,
+) -> Result<DiffBundleRow, AppError> {
+    let bundle: DiffBundleRow = sqlx::query_as(
+        "SELECT id, hash, name, status, template_state FROM bundles WHERE issue_code = ? AND hash = ? AND deleted_at IS NULL LIMIT 1",
+    )
+    .bind(issue_code)
+    .bind(hash.trim())
+    .fetch_optional(&state.db.pool)
+    .await
+    .map_err(AppError::Database)?
+    .ok_or_else(|| AppError::NotFound(format!("bundle {}", hash.trim())))?;
+    if bundle.status != "READY" {
+        return Err(AppError::Conflict("bundle is still processing".into()));
+    }
+    Ok(bundle)
+}
+
+fn diff_entry(
+    base: Option<(&StoredTemplate, &str)>,
+    target: Option<(&StoredTemplate, &str)>,
+    ratio: Option<f64>,
+) -> TemplateDiffEntry {
+    let wildcards = |template: &StoredTemplate| template.template.matches(WILDCARD).count();
+    let (template, _) = match (base, target) {
+        (Some(base), Some(target)) if wildcards(base.0) > wildcards(target.0) => base,
+        (_, Some(target)) => target,
+        (Some(base), None) => base,
+        (None, None) => unreachable!("diff entry has a side"),
+    };
+    let level_mask = base.map_or(0, |(template, _)| template.level_mask)
+        | target.map_or(0, |(template, _)| template.level_mask);
+    let examples = [base, target]
+        .into_iter()
+        .flatten()
+        .flat_map(|(template, bundle_hash)| {
+            serde_json::from_str::<Vec<TemplateExample>>(&template.examples)
+                .unwrap_or_default()
+                .into_iter()
+                .take(DIFF_EXAMPLES_PER_SIDE)
+                .map(move |example| LogTemplateExample {
+                    bundle_hash: bundle_hash.to_string(),
+                    file_id: example.file_id.to_string(),
+                    line_number: example.line_number,
+                    content: example.content,
+                })
+        })
+        .collect();
+    TemplateDiffEntry {
+        base_key: base.map(|(template, _)| template.template_key.clone()),
+        target_key: target.map(|(template, _)| template.template_key.clone()),
+        template: template.template.clone(),
+        levels: LogLevel::ALL
+            .iter()
+            .filter(|level| level_mask & level.bit() != 0)
+            .map(|level| level.as_str().to_string())
+            .collect(),
+        base_count: base.map_or(0, |(template, _)| template.line_count),
+        target_count: target.map_or(0, |(template, _)| template.line_count),
+        ratio,
+        examples,
+    }
+}
+
+#[get("/issues/{issue_code}/bundles/diff")]
+pub async fn diff_bundle_templates(
+    user: RequireBusinessUser,
+    path: web::Path<String>,
+    query: web::Query<TemplateDiffQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let issue_code = require_issue_owner(&state.db.pool, &path.into_inner(), &user.0.id).await?;
+    let query = query.into_inner();
+    let min_ratio = query.min_ratio.unwrap_or(DEFAULT_DIFF_MIN_RATIO);
+    if !(min_ratio > 1.0 && min_ratio <= MAX_DIFF_MIN_RATIO) {
+        return Err(AppError::BadRequest(
+            "min_ratio 必须大于 1 且不超过 1000".into(),
+        ));
+    }
+    let min_count = query.min_count.unwrap_or(1).max(1);
+    let size = query
+        .size
+        .unwrap_or(DEFAULT_TEMPLATE_PAGE_SIZE)
+        .clamp(1, MAX_TEMPLATE_PAGE_SIZE) as usize;
+    let level_mask = level_filter_mask(query.level.as_deref(), query.min_level.as_deref())?;
+    let base = load_diff_bundle(&state, &issue_code, &query.base).await?;
+    let target = load_diff_bundle(&state, &issue_code, &query.target).await?;
+    if base.id == target.id {
+        return Err(AppError::BadRequest(
+            "请选择两个不同的 bundle 进行对比".into(),
+        ));
+    }
+    for bundle in [&base, &target] {
+        match bundle.template_state.as_str() {
+            "READY" => {}
+            "FAILED" => {
+                return Err(AppError::Conflict(format!(
+                    "bundle {} 的日志模板归并失败",
+                    bundle.hash
+                )));
+            }
+            _ => {
+                if queue_unmined_bundles(&state.db.pool, &issue_code).await? > 0 {
+                    trigger_mining(TemplateMining::from_state(&state));
+                }
+                return Err(AppError::Conflict(format!(
+                    "bundle {} 的日志模板仍在归并中，请稍后重试",
+                    bundle.hash
+                )));
+            }
+        }
+    }
+
+    let base_templates = load_bundle_templates(&state.db.pool, &base.id).await?;
+    let target_templates = load_bundle_templates(&state.db.pool, &target.id).await?;
+    let side = |bundle: &DiffBundleRow, templates: &[StoredTemplate]| BundleTemplateDiffSide {
+        bundle_hash: bundle.hash.clone(),
+        bundle_name: bundle.name.clone(),
+        line_count: templates.iter().map(|template| template.line_count).sum(),
+        template_count: templates.len() as u64,
+    };
+    let base_side = side(&base, &base_templates);
+    let target_side = side(&target, &target_templates);
+    // Shares are computed over every mined line; the level filter only
+    // narrows which templates are reported.
+    let diff = diff_templates(&base_templates, &target_templates, min_ratio, min_count);
+    let kept =
+        |template: &StoredTemplate| level_mask.is_none_or(|mask| template.level_mask & mask != 0);
+    let only_in_base: Vec<_> = diff
+        .only_in_base
+        .iter()
+        .filter(|template| kept(template))
+        .collect();
+    let only_in_target: Vec<_> = diff
+        .only_in_target
+        .iter()
+        .filter(|template| kept(template))
+        .collect();
+    let changed: Vec<_> = diff
+        .changed
+        .iter()
+        .filter(|change| kept(change.base) || kept(change.target))
+        .collect();
+
+    touch_issue_activity_best_effort(&state.db.pool, &issue_code, "bundle template diff").await;
+    Ok(HttpResponse::Ok().json(BundleTemplateDiffResponse {
+        only_in_base_total: only_in_base.len() as u64,
+        only_in_base: only_in_base
+            .iter()
+            .take(size)
+            .map(|template| diff_entry(Some((template, &base.hash)), None, None))
+            .collect(),
+        only_in_target_total: only_in_target.len() as u64,
+        only_in_target: only_in_target
+            .iter()
+            .take(size)
+            .map(|template| diff_entry(None, Some((template, &target.hash)), None))
+            .collect(),
+        changed_total: changed.len() as u64,
+        changed: changed
+            .iter()
+            .take(size)
+            .map(|change| {
+                diff_entry(
+                    Some((change.base, &base.hash)),
+                    Some((change.target, &target.hash)),
+                    Some(change.ratio),
+                )
+            })
+            .collect(),
+        base: base_side,
+        target: target_side,
+        min_ratio,
+        min_count,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_cursor, template_fts_query};
+
+    #[test]
+    fn cursor_round_trips_segment_and_line() {
+        assert_eq!(parse_cursor(None).unwrap(), (0, 0));
+        assert_eq!(parse_cursor(Some("42:7")).unwrap(), (42, 7));
+        assert!(parse_cursor(Some("0:1")).is_err());
+        assert!(parse_cursor(Some("42")).is_err());
+        assert!(parse_cursor(Some("x:1")).is_err());
+    }
+
+    #[test]
+    fn prefilter_uses_longest_literal_fragment() {
+        let template: Vec<&str> = "<*> INFO connected to <*> as session=<*>"
+            .split(' ')
+            .collect();
+        assert_eq!(
+            template_fts_query(&template).as_deref(),
+            Some("\"connected\"")
+        );
+        assert_eq!(template_fts_query(&["<*>", "ok", "<*>"]), None);
+    }
+}
@@ -1,25 +1,54 @@
-use actix_web::{HttpResponse, get, web};
+use std::collections::{BTreeMap, HashMap, hash_map::Entry};
+
+use actix_web::{HttpResponse, get, http::StatusCode, web};
+use chrono::NaiveDateTime;
 use serde::Deserialize;
-use sqlx::FromRow;
+use sqlx::{FromRow, QueryBuilder, Sqlite};
 
 use crate::{
     AppState,
     error::AppError,
-    models::logs::{LogSearchHit, LogSearchResponse},
+    ingest::{EventTimeParser, LogLevel, TimestampRecognizers, load_timestamp_context},
+    log_expression::{LineEvent, LineEventTracker},
+    models::logs::{
+        LogHistogramResponse, LogSearchCapture, LogSearchHit, LogSearchResponse, LogSearchTimeRange,
+    },
+    services::{
+        log_context::{HitAnchor, MAX_SEARCH_CONTEXT_LINES, attach_context},
+        log_histogram::{DEFAULT_HISTOGRAM_BUCKETS, SeriesKey, TimeHistogram},
+        log_regex::LogRegex,
+        skill_time_scope::{
+            SkillTimeScope, TimeScopeError, TimeScopeInput, parse_search_time_range,
+        },
+        wall_clock,
+    },
 };
 
 use super::issues::{ensure_issue_active, normalize_issue_code, touch_issue_activity_best_effort};
 
 use super::helpers::{ensure_bundle_ready, load_bundle};
+use super::temp_results::request_client_key;
 
-const SHORT_SEARCH_SCAN_LIMIT: i64 = 10_001;
+const MAX_EVENT_SNIPPET_CHARS: usize = 8_000;
+const SEARCH_SCAN_BATCH_SIZE: i64 = 200;
 
 #[derive(Deserialize)]
 struct LogQuery {
     q: String,
+    #[serde(default)]
+    mode: LogSearchMode,
     timeline: Option<String>,
     path_like: Option<String>,
     file_id: Option<i64>,
+    level: Option<String>,
+    min_level: Option<String>,
+    start: Option<String>,
+    end: Option<String>,
+    incident_time: Option<String>,
+    before_minutes: Option<i64>,
+    after_minutes: Option<i64>,
+    before: Option<i64>,
+    after: Option<i64>,
     from: Option<i64>,
     size: Option<i64>,
 }
@@ -27,6 +56,7 @@ struct LogQuery {
 // scoped under /api in routes::register
 #[get("/log/v2/{bundle_id}/search")]
 pub async fn search_logs(
+    request: actix_web::HttpRequest,
     path: web::Path<String>,
     query: web::Query<LogQuery>,
     state: web::Data<AppState>,
@@ -40,7 +70,6 @@ pub async fn search_logs(
 
     let bundle = load_bundle(&state.db.pool, &bundle_hash).await?;
     ensure_bundle_ready(&bundle)?;
-    let fts_query = build_fts_query(search_term);
     let timeline = term.timeline.and_then(|value| {
         let trimmed = value.trim().to_string();
         if trimmed.is_empty() {
@@ -58,201 +87,145 @@ pub async fn search_logs(
         }
     });
     let file_id = term.file_id;
+    let level_mask = level_filter_mask(term.level.as_deref(), term.min_level.as_deref())?;
+    let context = context_lines(term.before, term.after)?;
+    let _line_read = context
+        .map(|_| state.acquire_line_read(&request_client_key(&request)))
+        .transpose()?;
+    let time_range = parse_search_time_range(TimeScopeInput {
+        start: term.start,
+        end: term.end,
+        incident_time: term.incident_time,
+        before_minutes: term.before_minutes,
+        after_minutes: term.after_minutes,
+    })
+    .map_err(invalid_time_range)?;
     let from = term.from.unwrap_or(0).max(0);
     let size = term
         .size
         .unwrap_or(state.limits.api.default_search_results)
         .clamp(1, state.limits.api.max_search_results);
     let path_pattern = path_like.as_ref().map(|value| format!("%{}%", value));
-    let short_pattern = format!("%{}%", escape_like_pattern(search_term));
-    if search_term.chars().count() < 3 {
-        return Err(AppError::BadRequest("搜索关键词至少需要 3 个字符".into()));
+    let scope = SegmentScope::Bundle {
+        bundle_id: &bundle.id,
+        timeline: timeline.as_deref(),
+        path_pattern: path_pattern.as_deref(),
+        file_id,
+    };
+    let matcher = LineMatcher::parse(search_term, matches!(term.mode, LogSearchMode::Regex))?;
+    let events = EventFilter {
+        level_mask,
+        time_range: time_range.as_ref(),
+    };
+    if matches!(matcher, LineMatcher::Regex(_)) || !events.is_empty() {
+        let (mut response, anchors) = search_lines(
+            &state.db.pool,
+            scope,
+            &matcher,
+            events,
+            (from, size),
+            state.limits.api.max_regex_scan_bytes,
+        )
+        .await?;
+        if let Some(context) = context {
+            add_context(&state, &mut response, &anchors, context).await?;
+        }
+        touch_issue_activity_best_effort(&state.db.pool, &bundle.issue_code, "bundle log search")
+            .await;
+        return Ok(HttpResponse::Ok().json(response));
     }
 
-    let truncated = if search_term.chars().count() < 3 {
-        let candidate_count: i64 = sqlx::query_scalar(
-            r#"
-            SELECT COUNT(*) FROM (
-                SELECT ls.id
-                FROM log_segments ls
-                JOIN files f ON f.id = ls.file_id
-                WHERE ls.bundle_id = ?
-                  AND (? IS NULL OR ls.timeline = ?)
-                  AND (? IS NULL OR f.path LIKE ?)
-                  AND (? IS NULL OR ls.file_id = ?)
-                ORDER BY ls.id
-                LIMIT ?
-            )
-            "#,
-        )
-        .bind(&bundle.id)
-        .bind(&timeline)
-        .bind(&timeline)
-        .bind(&path_pattern)
-        .bind(&path_pattern)
-        .bind(file_id)
-        .bind(file_id)
-        .bind(SHORT_SEARCH_SCAN_LIMIT + 1)
-        .fetch_one(&state.db.pool)
-        .await
-        .map_err(AppError::Database)?;
-        candidate_count > SHORT_SEARCH_SCAN_LIMIT
-    } else {
-        false
-    };
+    let page = literal_page(
+        &state.db.pool,
+        &SegmentFilter {
+            scope,
+            content: matcher.content_filter(),
+            level_mask: None,
+            time: TimeFilter::Any,
+        },
+        (from, size),
+    )
+    .await?;
+    let rows = page.rows;
 
-    let total: i64 = if search_term.chars().count() < 3 {
-        sqlx::query_scalar(
-            r#"
-        WITH candidates AS MATERIALIZED (
-        SELECT ls.content, ls.file_id, ls.timeline, ls.line_offset AS offset,
-               ls.line_end, ls.chunk_index, f.path
-        FROM log_segments ls
-        JOIN files f ON f.id = ls.file_id
-        WHERE ls.bundle_id = ?
-          AND (? IS NULL OR ls.timeline = ?)
-          AND (? IS NULL OR f.path LIKE ?)
-          AND (? IS NULL OR ls.file_id = ?)
-        ORDER BY ls.id
-        LIMIT ?
-        )
-        SELECT COUNT(*) FROM candidates
-        WHERE content LIKE ? ESCAPE '\' COLLATE NOCASE
-        "#,
-        )
-        .bind(&bundle.id)
-        .bind(&timeline)
-        .bind(&timeline)
-        .bind(&path_pattern)
-        .bind(&path_pattern)
-        .bind(file_id)
-        .bind(file_id)
-        .bind(SHORT_SEARCH_SCAN_LIMIT)
-        .bind(&short_pattern)
-        .fetch_one(&state.db.pool)
-        .await
-        .map_err(AppError::Database)?
-    } else {
-        sqlx::query_scalar(
-            r#"
-        SELECT COUNT(*) FROM log_segments ls
-        JOIN log_segments_fts ON log_segments_fts.rowid = ls.id
-        JOIN files f ON f.id = ls.file_id
-        WHERE log_segments_fts MATCH ?
-          AND ls.bundle_id = ?
-          AND (? IS NULL OR ls.timeline = ?)
-          AND (? IS NULL OR f.path LIKE ?)
-          AND (? IS NULL OR ls.file_id = ?)
-        "#,
+    let mut hits = Vec::with_capacity(rows.len());
+    let mut anchors = Vec::with_capacity(rows.len());
+    for row in rows {
+        let event = match_event(
+            &state.db.pool,
+            row.segment_id,
+            row.event_time_shift_ms,
+            &row.content,
+            search_term,
         )
-        .bind(&fts_query)
-        .bind(&bundle.id)
-        .bind(&timeline)
-        .bind(&timeline)
-        .bind(&path_pattern)
-        .bind(&path_pattern)
-        .bind(file_id)
-        .bind(file_id)
-        .fetch_one(&state.db.pool)
-        .await
-        .map_err(AppError::Database)?
-    };
+        .await?;
+        anchors.push(match &event {
+            Some(event) => Some(HitAnchor::event(
+                row.file_id,
+                event.line_start,
+                event.line_end,
+            )),
+            None => find_ignore_ascii_case(&row.content, search_term).and_then(|position| {
+                HitAnchor::at_position(
+                    row.file_id,
+                    &row.content,
+                    position,
+                    row.offset,
+                    row.line_end,
+                )
+            }),
+        });
+        hits.push(match event {
+            Some(event) => LogSearchHit {
+                file_id: row.file_id.to_string(),
+                path: row.path,
+                bundle_hash: Some(bundle.hash.clone()),
+                snippet: event.snippet,
+                timeline: row.timeline,
+                offset: Some(event.line_start),
+                line_end: Some(event.line_end),
+                line_number: Some(event.line_start),
+                chunk_index: row.chunk_index,
+                level: event.level.map(|level| level.as_str().to_string()),
+                event_time: event.event_time,
+                captures: None,
+                context_index: None,
+            },
+            None => LogSearchHit {
+                file_id: row.file_id.to_string(),
+                path: row.path,
+                bundle_hash: Some(bundle.hash.clone()),
+                snippet: literal_snippet(&row.content, search_term),
+                timeline: row.timeline,
+                offset: row.offset,
+                line_end: row.line_end,
+                line_number: row.offset,
+                chunk_index: row.chunk_index,
+                level: matched_line_level(&row.content, search_term),
+                event_time: None,
+                captures: None,
+                context_index: None,
+            },
+        });
+    }
 
-    let rows = if search_term.chars().count() < 3 {
-        sqlx::query_as::<_, LogRow>(
-            r#"
-        WITH candidates AS MATERIALIZED (
-        SELECT ls.id, ls.file_id, f.path, ls.timeline, ls.line_offset AS offset,
-               ls.line_end, ls.chunk_index, ls.content
-        FROM log_segments ls
-        JOIN files f ON f.id = ls.file_id
-        WHERE ls.bundle_id = ?
-          AND (? IS NULL OR ls.timeline = ?)
-          AND (? IS NULL OR f.path LIKE ?)
-          AND (? IS NULL OR ls.file_id = ?)
-        ORDER BY ls.id
-        LIMIT ?
-        )
-        SELECT file_id, path, timeline, offset, line_end, chunk_index, content
-        FROM candidates
-        WHERE content LIKE ? ESCAPE '\' COLLATE NOCASE
-        ORDER BY offset NULLS FIRST, id
-        LIMIT ? OFFSET ?
-        "#,
-        )
-        .bind(&bundle.id)
-        .bind(&timeline)
-        .bind(&timeline)
-        .bind(&path_pattern)
-        .bind(&path_pattern)
-        .bind(file_id)
-        .bind(file_id)
-        .bind(SHORT_SEARCH_SCAN_LIMIT)
-        .bind(&short_pattern)
-        .bind(size)
-        .bind(from)
-        .fetch_all(&state.db.pool)
-        .await
-        .map_err(AppError::Database)?
-    } else {
-        sqlx::query_as::<_, LogRow>(
-            r#"
-        SELECT ls.file_id,
-               f.path,
-               ls.timeline,
-               ls.line_offset AS offset,
-               ls.line_end,
-               ls.chunk_index,
-               ls.content AS content
-        FROM log_segments ls
-        JOIN log_segments_fts ON log_segments_fts.rowid = ls.id
-        JOIN files f ON f.id = ls.file_id
-        WHERE log_segments_fts MATCH ?
-          AND ls.bundle_id = ?
-          AND (? IS NULL OR ls.timeline = ?)
-          AND (? IS NULL OR f.path LIKE ?)
-          AND (? IS NULL OR ls.file_id = ?)
-        ORDER BY ls.line_offset NULLS FIRST, ls.id
-        LIMIT ? OFFSET ?
-        "#,
-        )
-        .bind(&fts_query)
-        .bind(&bundle.id)
-        .bind(&timeline)
-        .bind(&timeline)
-        .bind(&path_pattern)
-        .bind(&path_pattern)
-        .bind(file_id)
-        .bind(file_id)
-        .bind(size)
-        .bind(from)
-        .fetch_all(&state.db.pool)
-        .await
-        .map_err(AppError::Database)?
+    let mut response = LogSearchResponse {
+        total: page.total.max(0) as u64,
+        hits,
+        truncated: false,
+        level_counts: page.level_counts.into_map(),
+        time_range: None,
+        untimed_hits: None,
+        contexts: None,
+        context_truncated: false,
     };
-
-    let hits = rows
-        .into_iter()
-        .map(|row| LogSearchHit {
-            file_id: row.file_id.to_string(),
-            path: row.path,
-            bundle_hash: Some(bundle.hash.clone()),
-            snippet: literal_snippet(&row.content, search_term),
-            timeline: row.timeline,
-            offset: row.offset,
-            line_end: row.line_end,
-            line_number: row.offset,
-            chunk_index: row.chunk_index,
-        })
-        .collect();
+    if let Some(context) = context {
+        add_context(&state, &mut response, &anchors, context).await?;
+    }
 
     touch_issue_activity_best_effort(&state.db.pool, &bundle.issue_code, "bundle log search").await;
 
-    Ok(HttpResponse::Ok().json(LogSearchResponse {
-        total: total.max(0) as u64,
-        hits,
-        truncated,
-    }))
+    Ok(HttpResponse::Ok().json(response))
 }
 
 #[derive(Deserialize)]
@@ -261,6 +234,15 @@ struct IssueLogQuery {
     #[serde(default)]
     mode: IssueSearchMode,
     path_like: Option<String>,
+    level: Option<String>,
+    min_level: Option<String>,
+    start: Option<String>,
+    end: Option<String>,
+    incident_time: Option<String>,
+    before_minutes: Option<i64>,
+    after_minutes: Option<i64>,
+    before: Option<i64>,
+    after: Option<i64>,
     from: Option<i64>,
     size: Option<i64>,
 }
@@ -271,10 +253,20 @@ enum IssueSearchMode {
     Filename,
     #[default]
     Content,
+    Regex,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum LogSearchMode {
+    #[default]
+    Content,
+    Regex,
 }
 
 #[get("/issues/{issue_code}/search")]
 pub async fn search_issue_logs(
+    request: actix_web::HttpRequest,
     path: web::Path<String>,
     query: web::Query<IssueLogQuery>,
     state: web::Data<AppState>,
@@ -302,7 +294,6 @@ pub async fn search_issue_logs(
         return Ok(response);
     }
 
-    let fts_query = build_fts_query(search_term);
     let path_like = term.path_like.and_then(|value| {
         let trimmed = value.trim().to_string();
         if trimmed.is_empty() {
@@ -311,168 +302,79 @@ pub async fn search_issue_logs(
             Some(trimmed)
         }
     });
+    let level_mask = level_filter_mask(term.level.as_deref(), term.min_level.as_deref())?;
+    let context = context_lines(term.before, term.after)?;
+    let _line_read = context
+        .map(|_| state.acquire_line_read(&request_client_key(&request)))
+        .transpose()?;
+    let time_range = parse_search_time_range(TimeScopeInput {
+        start: term.start,
+        end: term.end,
+        incident_time: term.incident_time,
+        before_minutes: term.before_minutes,
+        after_minutes: term.after_minutes,
+    })
+    .map_err(invalid_time_range)?;
     let from = term.from.unwrap_or(0).max(0);
     let size = term
         .size
         .unwrap_or(state.limits.api.default_search_results)
         .clamp(1, state.limits.api.max_search_results);
     let path_pattern = path_like.as_ref().map(|value| format!("%{}%", value));
-    let short_pattern = format!("%{}%", escape_like_pattern(search_term));
-    if search_term.chars().count() < 3 {
-        return Err(AppError::BadRequest("搜索关键词至少需要 3 个字符".into()));
-    }
-
-    let truncated = if search_term.chars().count() < 3 {
-        let candidate_count: i64 = sqlx::query_scalar(
-            r#"
-            SELECT COUNT(*) FROM (
-                SELECT ls.id
-                FROM log_segments ls
-                JOIN bundles b ON b.id = ls.bundle_id
-                JOIN issues i ON i.code = b.issue_code
-                JOIN files f ON f.id = ls.file_id
-                WHERE b.issue_code = ?
-                  AND i.status = 'ACTIVE'
-                  AND b.status = 'READY'
-                  AND (? IS NULL OR f.path LIKE ?)
-                ORDER BY ls.id
-                LIMIT ?
-            )
-            "#,
-        )
-        .bind(&issue_code)
-        .bind(&path_pattern)
-        .bind(&path_pattern)
-        .bind(SHORT_SEARCH_SCAN_LIMIT + 1)
-        .fetch_one(&state.db.pool)
-        .await
-        .map_err(AppError::Database)?;
-        candidate_count > SHORT_SEARCH_SCAN_LIMIT
-    } else {
-        false
+    let scope = SegmentScope::Issue {
+        issue_code: &issue_code,
+        path_pattern: path_pattern.as_deref(),
+        file_id: None,
     };
-
-    let total: i64 = if search_term.chars().count() < 3 {
-        sqlx::query_scalar(
-            r#"
-        WITH candidates AS MATERIALIZED (
-        SELECT ls.content, f.path
-        FROM log_segments ls
-        JOIN bundles b ON b.id = ls.bundle_id
-        JOIN issues i ON i.code = b.issue_code
-        JOIN files f ON f.id = ls.file_id
-        WHERE b.issue_code = ?
-          AND i.status = 'ACTIVE'
-          AND b.status = 'READY'
-          AND (? IS NULL OR f.path LIKE ?)
-        ORDER BY ls.id
-        LIMIT ?
-        )
-        SELECT COUNT(*) FROM candidates
-        WHERE content LIKE ? ESCAPE '\' COLLATE NOCASE
-        "#,
-        )
-        .bind(&issue_code)
-        .bind(&path_pattern)
-        .bind(&path_pattern)
-        .bind(SHORT_SEARCH_SCAN_LIMIT)
-        .bind(&short_pattern)
-        .fetch_one(&state.db.pool)
-        .await
-        .map_err(AppError::Database)?
-    } else {
-        sqlx::query_scalar(
-            r#"
-        SELECT COUNT(*) FROM log_segments ls
-        JOIN log_segments_fts ON log_segments_fts.rowid = ls.id
-        JOIN bundles b ON b.id = ls.bundle_id
-        JOIN issues i ON i.code = b.issue_code
-        JOIN files f ON f.id = ls.file_id
-        WHERE log_segments_fts MATCH ?
-          AND b.issue_code = ?
-          AND i.status = 'ACTIVE'
-          AND b.status = 'READY'
-          AND (? IS NULL OR f.path LIKE ?)
-        "#,
-        )
-        .bind(&fts_query)
-        .bind(&issue_code)
-        .bind(&path_pattern)
-        .bind(&path_pattern)
-        .fetch_one(&state.db.pool)
-        .await
-        .map_err(AppError::Database)?
+    let matcher = LineMatcher::parse(search_term, matches!(term.mode, IssueSearchMode::Regex))?;
+    let events = EventFilter {
+        level_mask,
+        time_range: time_range.as_ref(),
     };
-
-    let rows = if search_term.chars().count() < 3 {
-        sqlx::query_as::<_, IssueLogRow>(
-            r#"
-        WITH candidates AS MATERIALIZED (
-        SELECT ls.id, ls.file_id, f.path, ls.line_offset AS offset, ls.line_end,
-               ls.chunk_index, ls.content, b.hash AS bundle_hash
-        FROM log_segments ls
-        JOIN bundles b ON b.id = ls.bundle_id
-        JOIN issues i ON i.code = b.issue_code
-        JOIN files f ON f.id = ls.file_id
-        WHERE b.issue_code = ?
-          AND i.status = 'ACTIVE'
-          AND b.status = 'READY'
-          AND (? IS NULL OR f.path LIKE ?)
-        ORDER BY ls.id
-        LIMIT ?
-        )
-        SELECT file_id, path, offset, line_end, chunk_index, content, bundle_hash
-        FROM candidates
-        WHERE content LIKE ? ESCAPE '\' COLLATE NOCASE
-        ORDER BY offset NULLS FIRST, id
-        LIMIT ? OFFSET ?
-        "#,
-        )
-        .bind(&issue_code)
-        .bind(&path_pattern)
-        .bind(&path_pattern)
-        .bind(SHORT_SEARCH_SCAN_LIMIT)
-        .bind(&short_pattern)
-        .bind(size)
-        .bind(from)
-        .fetch_all(&state.db.pool)
-        .await
-        .map_err(AppError::Database)?
-    } else {
-        sqlx::query_as::<_, IssueLogRow>(
-            r#"
-        SELECT ls.file_id,
-               f.path,
-               ls.line_offset AS offset,
-               ls.line_end,
-               ls.chunk_index,
-               ls.content AS content,
-               b.hash as bundle_hash
-        FROM log_segments ls
-        JOIN log_segments_fts ON log_segments_fts.rowid = ls.id
-        JOIN bundles b ON b.id = ls.bundle_id
-        JOIN issues i ON i.code = b.issue_code
-        JOIN files f ON f.id = ls.file_id
-        WHERE log_segments_fts MATCH ?
-          AND b.issue_code = ?
-          AND i.status = 'ACTIVE'
-          AND b.status = 'READY'
-          AND (? IS NULL OR f.path LIKE ?)
-        ORDER BY ls.line_offset NULLS FIRST, ls.id
-        LIMIT ? OFFSET ?
-        "#,
+    if matches!(matcher, LineMatcher::Regex(_)) || !events.is_empty() {
+        let (mut response, anchors) = search_lines(
+            &state.db.pool,
+            scope,
+            &matcher,
+            events,
+            (from, size),
+            state.limits.api.max_regex_scan_bytes,
         )
-        .bind(&fts_query)
-        .bind(&issue_code)
-        .bind(&path_pattern)
-        .bind(&path_pattern)
-        .bind(size)
-        .bind(from)
-        .fetch_all(&state.db.pool)
-        .await
-        .map_err(AppError::Database)?
-    };
+        .await?;
+        if let Some(context) = context {
+            add_context(&state, &mut response, &anchors, context).await?;
+        }
+        touch_issue_activity_best_effort(&state.db.pool, &issue_code, "issue log search").await;
+        return Ok(HttpResponse::Ok().json(response));
+    }
 
+    let page = literal_page(
+        &state.db.pool,
+        &SegmentFilter {
+            scope,
+            content: matcher.content_filter(),
+            level_mask: None,
+            time: TimeFilter::Any,
+        },
+        (from, size),
+    )
+    .await?;
+    let rows = page.rows;
+
+    let anchors: Vec<Option<HitAnchor>> = rows
+        .iter()
+        .map(|row| {
+            find_ignore_ascii_case(&row.content, search_term).and_then(|position| {
+                HitAnchor::at_position(
+                    row.file_id,
+                    &row.content,
+                    position,
+                    row.offset,
+                    row.line_end,
+                )
+            })
+        })
+        .collect();
     let hits = rows
         .into_iter()
         .map(|row| LogSearchHit {
@@ -485,16 +387,253 @@ pub async fn search_issue_logs(
             line_end: row.line_end,
             line_number: row.offset,
             chunk_index: row.chunk_index,
+            level: matched_line_level(&row.content, search_term),
+            event_time: None,
+            captures: None,
+            context_index: None,
         })
         .collect();
 
+    let mut response = LogSearchResponse {
+        total: page.total.max(0) as u64,
+        hits,
+        truncated: false,
+        level_counts: page.level_counts.into_map(),
+        time_range: None,
+        untimed_hits: None,
+        contexts: None,
+        context_truncated: false,
+    };
+    if let Some(context) = context {
+        add_context(&state, &mut response, &anchors, context).await?;
+    }
+
     touch_issue_activity_best_effort(&state.db.pool, &issue_code, "issue log search").await;
 
-    Ok(HttpResponse::Ok().json(LogSearchResponse {
-        total: total.max(0) as u64,
-        hits,
-        truncated,
-    }))
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[derive(Deserialize)]
+struct IssueHistogramQuery {
+    q: String,
+    path_like: Option<String>,
+    file_id: Option<i64>,
+    level: Option<String>,
+    min_level: Option<String>,
+    start: Option<String>,
+    end: Option<String>,
+    incident_time: Option<String>,
+    before_minutes: Option<i64>,
+    after_minutes: Option<i64>,
+    #[serde(default)]
+    series: HistogramSeries,
+    buckets: Option<usize>,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum HistogramSeries {
+    #[default]
+    Bundle,
+    File,
+}
+
+#[derive(FromRow)]
+struct HistogramRow {
+    segment_id: i64,
+    bundle_id: String,
+    event_time_shift_ms: i64,
+    bundle_hash: String,
+    file_id: i64,
+    path: String,
+    offset: Option<i64>,
+    content: String,
+}
+
+/// Counts matching lines of an issue per event-time bucket, split per bundle
+/// or per file, so spikes are visible without paging through hits. Lines are
+/// checked like search hits, within the histogram scan budget.
+#[get("/issues/{issue_code}/search/histogram")]
+pub async fn search_issue_histogram(
+    path: web::Path<String>,
+    query: web::Query<IssueHistogramQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let issue_code = normalize_issue_code(&path.into_inner())?;
+    ensure_issue_active(&state.db.pool, &issue_code).await?;
+    let term = query.into_inner();
+    let search_term = term.q.trim();
+    if search_term.is_empty() {
+        return Err(AppError::BadRequest("query parameter q is required".into()));
+    }
+    if search_term.chars().count() < 3 {
+        return Err(AppError::BadRequest("搜索关键词至少需要 3 个字符".into()));
+    }
+    let level_mask = level_filter_mask(term.level.as_deref(), term.min_level.as_deref())?;
+    let time_range = parse_search_time_range(TimeScopeInput {
+        start: term.start,
+        end: term.end,
+        incident_time: term.incident_time,
+        before_minutes: term.before_minutes,
+        after_minutes: term.after_minutes,
+    })
+    .map_err(invalid_time_range)?;
+    let path_pattern = term
+        .path_like
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| format!("%{}%", value));
+    let matcher = LineMatcher::Literal(search_term.to_string());
+    let events = EventFilter {
+        level_mask,
+        time_range: time_range.as_ref(),
+    };
+    let filter = SegmentFilter {
+        scope: SegmentScope::Issue {
+            issue_code: &issue_code,
+            path_pattern: path_pattern.as_deref(),
+            file_id: term.file_id,
+        },
+        content: matcher.content_filter(),
+        // Levels are checked per line.
+        level_mask: None,
+        time: time_range
+            .as_ref()
+            .map_or(TimeFilter::Any, TimeFilter::WithinOrUntimed),
+    };
+
+    // Line times fall inside their segment's bounds, which size the buckets.
+    let bounds = match &time_range {
+        Some(range) => Some((range.start_ms, range.end_ms)),
+        None => {
+            let mut bounds = QueryBuilder::<Sqlite>::new(
+                "SELECT MIN(ls.event_time_start_ms), MAX(ls.event_time_end_ms)",
+            );
+            SegmentFilter {
+                time: TimeFilter::Timed,
+                ..filter.clone()
+            }
+            .push_from(&mut bounds);
+            let (min, max): (Option<i64>, Option<i64>) = bounds
+                .build_query_as()
+                .fetch_one(&state.db.pool)
+                .await
+                .map_err(AppError::Database)?;
+            min.zip(max)
+        }
+    };
+    let mut histogram = bounds
+        .and_then(|(start, end)| {
+            Some((
+                wall_clock::from_comparison_key(start)?,
+                wall_clock::from_comparison_key(end)?,
+            ))
+        })
+        .map(|(start, end)| {
+            TimeHistogram::new(
+                start,
+                end,
+                term.buckets.unwrap_or(DEFAULT_HISTOGRAM_BUCKETS),
+            )
+        });
+
+    let mut after_id = 0_i64;
+    let mut scanned_bytes = 0_u64;
+    let mut truncated = false;
+    let mut untimed_hits = 0_u64;
+    let mut timestamps: HashMap<String, (TimestampRecognizers, NaiveDateTime)> = HashMap::new();
+    'scan: loop {
+        let rows: Vec<HistogramRow> = filter
+            .scan_query(
+                "ls.id AS segment_id, ls.bundle_id, ls.event_time_shift_ms, \
+                 b.hash AS bundle_hash, ls.file_id, f.path, lc.line_offset AS offset, \
+                 lc.content",
+                after_id,
+                SEARCH_SCAN_BATCH_SIZE,
+            )
+            .build_query_as()
+            .fetch_all(&state.db.pool)
+            .await
+            .map_err(AppError::Database)?;
+        if rows.is_empty() {
+            break;
+        }
+
+        for row in rows {
+            after_id = row.segment_id;
+            scanned_bytes = scanned_bytes.saturating_add(row.content.len() as u64);
+            if scanned_bytes > state.limits.api.max_histogram_scan_bytes {
+                truncated = true;
+                break 'scan;
+            }
+            let matches = matcher.matching_lines(&row.content);
+            if matches.is_empty() {
+                continue;
+            }
+            let (recognizers, reference) = match timestamps.entry(row.bundle_id.clone()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    entry.insert(load_timestamp_context(&state.db.pool, &row.bundle_id).await?)
+                }
+            };
+            // Seeded like search, so buckets agree with the time-range filter.
+            let carried = carried_event_time(&state.db.pool, row.file_id, row.offset).await?;
+            let mut tracker = LineEventTracker::new(
+                EventTimeParser::new(recognizers, *reference)
+                    .with_shift_ms(row.event_time_shift_ms)
+                    .with_last(carried),
+            );
+            let mut matches = matches.into_iter().peekable();
+            for (index, line) in row.content.split('\n').enumerate() {
+                let Some(&next) = matches.peek() else {
+                    break;
+                };
+                let event = tracker.observe(line);
+                if index != next {
+                    continue;
+                }
+                matches.next();
+                if !events.has_level(event) {
+                    continue;
+                }
+                let Some(time_key) = event.time_key else {
+                    untimed_hits += 1;
+                    continue;
+                };
+                if !events.in_time_range(event) {
+                    continue;
+                }
+                if let Some(histogram) = histogram.as_mut() {
+                    let key = SeriesKey {
+                        bundle_hash: &row.bundle_hash,
+                        file: match term.series {
+                            HistogramSeries::Bundle => None,
+                            HistogramSeries::File => Some((row.file_id, row.path.as_str())),
+                        },
+                    };
+                    histogram.record(key, time_key);
+                }
+            }
+        }
+    }
+
+    touch_issue_activity_best_effort(&state.db.pool, &issue_code, "issue log histogram").await;
+    let mut response = match histogram {
+        Some(histogram) => histogram.into_response(untimed_hits),
+        None => LogHistogramResponse {
+            total: 0,
+            bucket_ms: None,
+            buckets: Vec::new(),
+            totals: Vec::new(),
+            series: Vec::new(),
+            series_truncated: false,
+            untimed_hits,
+            truncated: false,
+        },
+    };
+    response.truncated = truncated;
+    Ok(HttpResponse::Ok().json(response))
 }
 
 async fn search_issue_files(
@@ -579,6 +718,10 @@ async fn search_issue_files(
             line_end: None,
             line_number: None,
             chunk_index: None,
+            level: None,
+            event_time: None,
+            captures: None,
+            context_index: None,
         })
         .collect();
 
@@ -586,11 +729,324 @@ async fn search_issue_files(
         total: total.max(0) as u64,
         hits,
         truncated: false,
+        level_counts: BTreeMap::new(),
+        time_range: None,
+        untimed_hits: None,
+        contexts: None,
+        context_truncated: false,
     }))
 }
 
+/// Segment filters of the endpoint a search runs for.
+#[derive(Clone, Copy)]
+pub(super) enum SegmentScope<'a> {
+    Bundle {
+        bundle_id: &'a str,
+        timeline: Option<&'a str>,
+        path_pattern: Option<&'a str>,
+        file_id: Option<i64>,
+    },
+    Issue {
+        issue_code: &'a str,
+        path_pattern: Option<&'a str>,
+        file_id: Option<i64>,
+    },
+    /// Every active issue, or only those owned by `owner_user_id`.
+    Global {
+        owner_user_id: Option<&'a str>,
+        path_pattern: Option<&'a str>,
+    },
+}
+
+/// Content condition a candidate segment has to meet.
+#[derive(Clone)]
+pub(super) enum ContentFilter {
+    /// Every segment, e.g. for a regex without indexable fragments.
+    Any,
+    /// Trigram index query.
+    Fts(String),
+    /// Escaped `LIKE` pattern for terms too short for the trigram index.
+    Like(String),
+}
+
+impl ContentFilter {
+    /// Index query for a literal search term, or a `LIKE` scan when the term
+    /// is shorter than a trigram.
+    pub(super) fn literal(search_term: &str) -> Self {
+        if search_term.chars().count() < 3 {
+            Self::Like(format!("%{}%", escape_like_pattern(search_term)))
+        } else {
+            Self::Fts(build_fts_query(search_term))
+        }
+    }
+
+    /// Prefilter of a matcher that may have no indexable fragment.
+    pub(super) fn prefilter(fts_query: Option<String>) -> Self {
+        fts_query.map_or(Self::Any, Self::Fts)
+    }
+}
+
+/// Selects matching lines of a segment's indexed content.
+pub(super) enum LineMatcher {
+    Literal(String),
+    Regex(LogRegex),
+}
+
+/// First match of a [`LineMatcher`] inside a segment.
+struct LineMatch {
+    position: usize,
+    text: String,
+    captures: Option<Vec<LogSearchCapture>>,
+}
+
+impl LineMatcher {
+    /// Matcher for a `mode=regex` pattern, or for a literal term of at least
+    /// 3 characters.
+    pub(super) fn parse(search_term: &str, regex: bool) -> Result<Self, AppError> {
+        if regex {
+            LogRegex::compile(search_term).map(Self::Regex)
+        } else if search_term.chars().count() < 3 {
+            Err(AppError::BadRequest("搜索关键词至少需要 3 个字符".into()))
+        } else {
+            Ok(Self::Literal(search_term.to_string()))
+        }
+    }
+
+    pub(super) fn content_filter(&self) -> ContentFilter {
+        match self {
+            Self::Literal(term) => ContentFilter::literal(term),
+            Self::Regex(regex) => ContentFilter::prefilter(regex.prefilter().map(str::to_string)),
+        }
+    }
+
+    /// Indexes of the content lines holding a match, ascending.
+    pub(super) fn matching_lines(&self, content: &str) -> Vec<usize> {
+        match self {
+            Self::Literal(term) => content
+                .split('\n')
+                .enumerate()
+                .filter(|(_, line)| find_ignore_ascii_case(line, term).is_some())
+                .map(|(index, _)| index)
+                .collect(),
+            Self::Regex(regex) => {
+                let mut lines = Vec::new();
+                let mut line = 0;
+                let mut cursor = 0;
+                for start in regex.match_starts(content) {
+                    line += content[cursor..start].matches('\n').count();
+                    cursor = start;
+                    if lines.last() != Some(&line) {
+                        lines.push(line);
+                    }
+                }
+                lines
+            }
+        }
+    }
+
+    /// First match starting at or after byte `from` of `content`.
+    fn first_match_from(&self, content: &str, from: usize) -> Option<LineMatch> {
+        let rest = content.get(from..)?;
+        match self {
+            Self::Literal(term) => find_ignore_ascii_case(rest, term).map(|position| LineMatch {
+                position: from + position,
+                text: term.clone(),
+                captures: None,
+            }),
+            Self::Regex(regex) => regex.first_match(rest).map(|found| LineMatch {
+                position: from + found.position,
+                text: found.text,
+                captures: Some(found.captures),
+            }),
+        }
+    }
+}
+
+/// Level and time conditions the event of a matching line has to meet.
+#[derive(Clone, Copy)]
+pub(super) struct EventFilter<'a> {
+    pub(super) level_mask: Option<i64>,
+    pub(super) time_range: Option<&'a SkillTimeScope>,
+}
+
+impl EventFilter<'_> {
+    pub(super) fn is_empty(&self) -> bool {
+        self.level_mask.is_none() && self.time_range.is_none()
+    }
+
+    /// Whether the event falls in the time range; events without a time
+    /// never do when a range is set.
+    pub(super) fn in_time_range(&self, event: LineEvent) -> bool {
+        self.time_range.is_none_or(|range| {
+            event
+                .time_key
+                .is_some_and(|time| range.start_ms <= time && time <= range.end_ms)
+        })
+    }
+
+    pub(super) fn has_level(&self, event: LineEvent) -> bool {
+        self.level_mask.is_none_or(|level_mask| {
+            event
+                .level
+                .is_some_and(|level| level_mask & level.bit() != 0)
+        })
+    }
+}
+
+/// Condition on the indexed event-time bounds of a segment.
+#[derive(Clone, Copy)]
+pub(super) enum TimeFilter<'a> {
+    Any,
+    /// Bounds known and overlapping the range.
+    Within(&'a SkillTimeScope),
+    /// Like `Within`, but segments without known bounds stay candidates.
+    WithinOrUntimed(&'a SkillTimeScope),
+    /// Bounds known.
+    Timed,
+}
+
+impl<'a> TimeFilter<'a> {
+    pub(super) fn within(time_range: Option<&'a SkillTimeScope>) -> Self {
+        time_range.map_or(Self::Any, Self::Within)
+    }
+}
+
+/// Whether a segment has no known event-time bounds.
+const UNTIMED_SEGMENT: &str = "(ls.event_time_indexed != 1 OR ls.event_time_start_ms IS NULL OR ls.event_time_end_ms IS NULL)";
+
+/// The segment filters every log search query is built from.
+#[derive(Clone)]
+pub(super) struct SegmentFilter<'a> {
+    pub(super) scope: SegmentScope<'a>,
+    pub(super) content: ContentFilter,
+    pub(super) level_mask: Option<i64>,
+    pub(super) time: TimeFilter<'a>,
+}
+
+impl SegmentFilter<'_> {
+    /// Appends the joins and the `WHERE` clause. Selected columns may use the
+    /// `ls`, `lc` (content), `f` (file), `b` (bundle) and `i` (issue) aliases.
+    pub(super) fn push_from(&self, builder: &mut QueryBuilder<'_, Sqlite>) {
+        builder.push(" FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id");
+        if let ContentFilter::Fts(_) = self.content {
+            builder.push(" JOIN log_segments_fts ON log_segments_fts.rowid = ls.content_id");
+        }
+        builder.push(
+            " JOIN bundles b ON b.id = ls.bundle_id JOIN issues i ON i.code = b.issue_code JOIN files f ON f.id = ls.file_id WHERE ",
+        );
+        let (path_pattern, file_id) = match self.scope {
+            SegmentScope::Bundle {
+                bundle_id,
+                timeline,
+                path_pattern,
+                file_id,
+            } => {
+                builder.push("ls.bundle_id = ");
+                builder.push_bind(bundle_id.to_string());
+                if let Some(timeline) = timeline {
+                    builder.push(" AND lc.timeline = ");
+                    builder.push_bind(timeline.to_string());
+                }
+                (path_pattern, file_id)
+            }
+            SegmentScope::Issue {
+                issue_code,
+                path_pattern,
+                file_id,
+            } => {
+                builder.push("b.issue_code = ");
+                builder.push_bind(issue_code.to_string());
+                builder.push(" AND i.status = 'ACTIVE' AND b.status = 'READY'");
+                (path_pattern, file_id)
+            }
+            SegmentScope::Global {
+                owner_user_id,
+                path_pattern,
+            } => {
+                builder.push("i.status = 'ACTIVE' AND b.status = 'READY'");
+                if let Some(owner_user_id) = owner_user_id {
+                    builder.push(" AND i.owner_user_id = ");
+                    builder.push_bind(owner_user_id.to_string());
+                }
+                (path_pattern, None)
+            }
+        };
+        if let Some(path_pattern) = path_pattern {
+            builder.push(" AND f.path LIKE ");
+            builder.push_bind(path_pattern.to_string());
+        }
+        if let Some(file_id) = file_id {
+            builder.push(" AND ls.file_id = ");
+            builder.push_bind(file_id);
+        }
+        match &self.content {
+            ContentFilter::Any => {}
+            ContentFilter::Fts(fts_query) => {
+                builder.push(" AND log_segments_fts MATCH ");
+                builder.push_bind(fts_query.clone());
+            }
+            ContentFilter::Like(pattern) => {
+                builder.push(" AND lc.content LIKE ");
+                builder.push_bind(pattern.clone());
+                builder.push(" ESCAPE '\\' COLLATE NOCASE");
+            }
+        }
+        if let Some(level_mask) = self.level_mask {
+            builder.push(" AND (lc.level_mask & ");
+            builder.push_bind(level_mask);
+            builder.push(") != 0");
+        }
+        let push_overlap = |builder: &mut QueryBuilder<'_, Sqlite>, range: &SkillTimeScope| {
+            builder.push("(ls.event_time_indexed = 1 AND ls.event_time_end_ms >= ");
+            builder.push_bind(range.start_ms);
+            builder.push(" AND ls.event_time_start_ms <= ");
+            builder.push_bind(range.end_ms);
+            builder.push(")");
+        };
+        match self.time {
+            TimeFilter::Any => {}
+            TimeFilter::Within(range) => {
+                builder.push(" AND ");
+                push_overlap(builder, range);
+            }
+            TimeFilter::WithinOrUntimed(range) => {
+                builder.push(" AND (");
+                push_overlap(builder, range);
+                builder.push(" OR ");
+                builder.push(UNTIMED_SEGMENT);
+                builder.push(")");
+            }
+            TimeFilter::Timed => {
+                builder.push(" AND NOT ");
+                builder.push(UNTIMED_SEGMENT);
+            }
+        }
+    }
+
+    /// Query selecting `columns` of the next `batch` segments after
+    /// `after_id` in id order.
+    pub(super) fn scan_query(
+        &self,
+        columns: &str,
+        after_id: i64,
+        batch: i64,
+    ) -> QueryBuilder<'static, Sqlite> {
+        let mut builder = QueryBuilder::<Sqlite>::new("SELECT ");
+        builder.push(columns);
+        self.push_from(&mut builder);
+        builder.push(" AND ls.id > ");
+        builder.push_bind(after_id);
+        builder.push(" ORDER BY ls.id LIMIT ");
+        builder.push_bind(batch);
+        builder
+    }
+}
+
 #[derive(FromRow)]
-struct LogRow {
+struct ScanCandidateRow {
+    segment_id: i64,
+    bundle_id: String,
+    event_time_shift_ms: i64,
     file_id: i64,
     path: String,
     timeline: Option<String>,
@@ -598,12 +1054,205 @@ struct LogRow {
     line_end: Option<i64>,
     chunk_index: Option<i64>,
     content: String,
+    bundle_hash: String,
+}
+
+/// Scans candidate segments in id order, prefiltered through the trigram
+/// index when the matcher has literal fragments, and verifies each line until
+/// the scan budget runs out. A segment is a hit when one of its matching lines
+/// belongs to an event that passes `events`; the hit is anchored on that line.
+/// Segments whose only matches lie outside a time range and include a line
+/// without event time count as untimed. `page` is `(from, size)`.
+async fn search_lines(
+    pool: &sqlx::SqlitePool,
+    scope: SegmentScope<'_>,
+    matcher: &LineMatcher,
+    events: EventFilter<'_>,
+    page: (i64, i64),
+    max_scan_bytes: u64,
+) -> Result<(LogSearchResponse, Vec<Option<HitAnchor>>), AppError> {
+    let (from, size) = page;
+    let mut after_id = 0_i64;
+    let mut scanned_bytes = 0_u64;
+    let mut truncated = false;
+    let mut total = 0_i64;
+    let mut level_counts = LevelCounts::default();
+    let mut untimed_hits = 0_u64;
+    let mut hits = Vec::new();
+    let mut anchors = Vec::new();
+    let mut timestamps: HashMap<String, (TimestampRecognizers, NaiveDateTime)> = HashMap::new();
+
+    let filter = SegmentFilter {
+        scope,
+        content: matcher.content_filter(),
+        // Levels are checked per line; facets need every level.
+        level_mask: None,
+        // Untimed segments stay candidates so their lines can be counted.
+        time: events
+            .time_range
+            .map_or(TimeFilter::Any, TimeFilter::WithinOrUntimed),
+    };
+    let columns = "ls.id AS segment_id, ls.bundle_id, ls.event_time_shift_ms, ls.file_id, f.path, \
+         lc.timeline, lc.line_offset AS offset, lc.line_end, lc.chunk_index, lc.content, \
+         b.hash AS bundle_hash";
+
+    'scan: loop {
+        let rows: Vec<ScanCandidateRow> = filter
+            .scan_query(columns, after_id, SEARCH_SCAN_BATCH_SIZE)
+            .build_query_as()
+            .fetch_all(pool)
+            .await
+            .map_err(AppError::Database)?;
+        if rows.is_empty() {
+            break;
+        }
+
+        for row in rows {
+            after_id = row.segment_id;
+            scanned_bytes = scanned_bytes.saturating_add(row.content.len() as u64);
+            if scanned_bytes > max_scan_bytes {
+                truncated = true;
+                break 'scan;
+            }
+            let matches = matcher.matching_lines(&row.content);
+            if matches.is_empty() {
+                continue;
+            }
+            let (recognizers, reference) = match timestamps.entry(row.bundle_id.clone()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    entry.insert(load_timestamp_context(pool, &row.bundle_id).await?)
+                }
+            };
+            let carried = carried_event_time(pool, row.file_id, row.offset).await?;
+            let mut tracker = LineEventTracker::new(
+                EventTimeParser::new(recognizers, *reference)
+                    .with_shift_ms(row.event_time_shift_ms)
+                    .with_last(carried),
+            );
+            let mut matches = matches.into_iter().peekable();
+            let mut levels = 0_i64;
+            let mut in_range = false;
+            let mut untimed = false;
+            let mut hit_line = None;
+            let mut line_start = 0;
+            for (index, line) in row.content.split('\n').enumerate() {
+                let Some(&next) = matches.peek() else {
+                    break;
+                };
+                let event = tracker.observe(line);
+                if index == next {
+                    matches.next();
+                    if events.in_time_range(event) {
+                        in_range = true;
+                        levels |= event.level.map_or(0, LogLevel::bit);
+                        if hit_line.is_none() && events.has_level(event) {
+                            hit_line = Some(line_start);
+                        }
+                    } else {
+                        untimed |= event.time_key.is_none();
+                    }
+                }
+                line_start += line.len() + 1;
+            }
+            level_counts.record(levels);
+            let Some(line_start) = hit_line else {
+                if untimed && !in_range {
+                    untimed_hits += 1;
+                }
+                continue;
+            };
+            total += 1;
+            if total <= from || hits.len() as i64 >= size {
+                continue;
+            }
+            let Some(found) = matcher.first_match_from(&row.content, line_start) else {
+                continue;
+            };
+
+            let event = event_at(
+                pool,
+                row.segment_id,
+                row.event_time_shift_ms,
+                &row.content,
+                found.position,
+            )
+            .await?;
+            let timeline = match scope {
+                SegmentScope::Bundle { .. } => row.timeline,
+                SegmentScope::Issue { .. } | SegmentScope::Global { .. } => None,
+            };
+            anchors.push(match &event {
+                Some(event) => Some(HitAnchor::event(
+                    row.file_id,
+                    event.line_start,
+                    event.line_end,
+                )),
+                None => HitAnchor::at_position(
+                    row.file_id,
+                    &row.content,
+                    found.position,
+                    row.offset,
+                    row.line_end,
+                ),
+            });
+            hits.push(match event {
+                Some(event) => LogSearchHit {
+                    file_id: row.file_id.to_string(),
+                    path: row.path,
+                    bundle_hash: Some(row.bundle_hash),
+                    snippet: event.snippet,
+                    timeline,
+                    offset: Some(event.line_start),
+                    line_end: Some(event.line_end),
+                    line_number: Some(event.line_start),
+                    chunk_index: row.chunk_index,
+                    level: event.level.map(|level| level.as_str().to_string()),
+                    event_time: event.event_time,
+                    captures: found.captures,
+                    context_index: None,
+                },
+                None => LogSearchHit {
+                    file_id: row.file_id.to_string(),
+                    path: row.path,
+                    bundle_hash: Some(row.bundle_hash),
+                    snippet: literal_snippet(&row.content, &found.text),
+                    timeline,
+                    offset: row.offset,
+                    line_end: row.line_end,
+                    line_number: row.offset,
+                    chunk_index: row.chunk_index,
+                    level: line_level_at(&row.content, found.position),
+                    event_time: None,
+                    captures: found.captures,
+                    context_index: None,
+                },
+            });
+        }
+    }
+
+    Ok((
+        LogSearchResponse {
+            total: total.max(0) as u64,
+            hits,
+            truncated,
+            level_counts: level_counts.into_map(),
+            time_range: events.time_range.map(log_search_time_range),
+            untimed_hits: events.time_range.map(|_| untimed_hits),
+            contexts: None,
+            context_truncated: false,
+        },
+        anchors,
+    ))
 }
 
 #[derive(FromRow)]
-struct IssueLogRow {
+struct LogRow {
+    segment_id: i64,
+    event_time_shift_ms: i64,
     file_id: i64,
     path: String,
+    timeline: Option<String>,
     offset: Option<i64>,
     line_end: Option<i64>,
     chunk_index: Option<i64>,
@@ -611,6 +1260,63 @@ struct IssueLogRow {
     bundle_hash: String,
 }
 
+/// One page of a literal search without level or time filters.
+struct LiteralPage {
+    total: i64,
+    level_counts: LevelCounts,
+    rows: Vec<LogRow>,
+}
+
+/// Counts the segments matching `filter` and loads page `(from, size)` of
+/// them in line order.
+async fn literal_page(
+    pool: &sqlx::SqlitePool,
+    filter: &SegmentFilter<'_>,
+    page: (i64, i64),
+) -> Result<LiteralPage, AppError> {
+    let (from, size) = page;
+    let mut count = QueryBuilder::<Sqlite>::new("SELECT COUNT(*)");
+    filter.push_from(&mut count);
+    let total: i64 = count
+        .build_query_scalar()
+        .fetch_one(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+    // Facet counts ignore the level filter so every level stays selectable.
+    let mut levels = QueryBuilder::<Sqlite>::new(format!("SELECT {LEVEL_COUNT_COLUMNS}"));
+    SegmentFilter {
+        level_mask: None,
+        ..filter.clone()
+    }
+    .push_from(&mut levels);
+    let level_counts: LevelCounts = levels
+        .build_query_as()
+        .fetch_one(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+    let mut rows = QueryBuilder::<Sqlite>::new(
+        "SELECT ls.id AS segment_id, ls.event_time_shift_ms, ls.file_id, f.path, lc.timeline, \
+         lc.line_offset AS offset, lc.line_end, lc.chunk_index, lc.content, b.hash AS bundle_hash",
+    );
+    filter.push_from(&mut rows);
+    rows.push(" ORDER BY lc.line_offset NULLS FIRST, ls.id LIMIT ");
+    rows.push_bind(size);
+    rows.push(" OFFSET ");
+    rows.push_bind(from);
+    let rows = rows
+        .build_query_as()
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::Database)?;
+    Ok(LiteralPage {
+        total,
+        level_counts,
+        rows,
+    })
+}
+
 #[derive(FromRow)]
 struct IssueFileSearchRow {
     file_id: i64,
@@ -619,6 +1325,283 @@ struct IssueFileSearchRow {
     bundle_hash: String,
 }
 
+#[derive(FromRow)]
+struct EventSpanRow {
+    line_start: i64,
+    line_end: i64,
+    content_start: i64,
+    content_end: i64,
+    event_time_ms: Option<i64>,
+    event_time_shift_ms: i64,
+}
+
+struct EventMatch {
+    snippet: String,
+    line_start: i64,
+    line_end: i64,
+    level: Option<LogLevel>,
+    event_time: Option<String>,
+}
+
+const LEVEL_COUNT_COLUMNS: &str = "COALESCE(SUM((lc.level_mask & 1) != 0), 0) AS trace, \
+     COALESCE(SUM((lc.level_mask & 2) != 0), 0) AS debug, \
+     COALESCE(SUM((lc.level_mask & 4) != 0), 0) AS info, \
+     COALESCE(SUM((lc.level_mask & 8) != 0), 0) AS warn, \
+     COALESCE(SUM((lc.level_mask & 16) != 0), 0) AS error, \
+     COALESCE(SUM((lc.level_mask & 32) != 0), 0) AS fatal";
+
+/// Matching segments per level; a segment counts once for each level it holds.
+#[derive(Default, FromRow)]
+struct LevelCounts {
+    trace: i64,
+    debug: i64,
+    info: i64,
+    warn: i64,
+    error: i64,
+    fatal: i64,
+}
+
+impl LevelCounts {
+    fn record(&mut self, level_mask: i64) {
+        let counts = [
+            &mut self.trace,
+            &mut self.debug,
+            &mut self.info,
+            &mut self.warn,
+            &mut self.error,
+            &mut self.fatal,
+        ];
+        for (level, count) in LogLevel::ALL.iter().zip(counts) {
+            if level_mask & level.bit() != 0 {
+                *count += 1;
+            }
+        }
+    }
+
+    fn into_map(self) -> BTreeMap<String, u64> {
+        let counts = [
+            self.trace, self.debug, self.info, self.warn, self.error, self.fatal,
+        ];
+        LogLevel::ALL
+            .iter()
+            .zip(counts)
+            .map(|(level, count)| (level.as_str().to_string(), count.max(0) as u64))
+            .collect()
+    }
+}
+
+pub(super) fn log_search_time_range(range: &SkillTimeScope) -> LogSearchTimeRange {
+    LogSearchTimeRange {
+        start: range.start.clone(),
+        end: range.end.clone(),
+    }
+}
+
+pub(super) fn invalid_time_range(error: TimeScopeError) -> AppError {
+    let message = match error {
+        TimeScopeError::InvalidTimestamp => {
+            "时间过滤支持 start+end 或 incident_time+before_minutes+after_minutes；时间必须是本地日志时间，例如 2026-08-14 09:32:15"
+        }
+        TimeScopeError::InvalidRange => "时间过滤的 start 必须早于 end",
+        TimeScopeError::TooLarge => "时间过滤的范围过大",
+        TimeScopeError::InvalidExpansion => {
+            "incident_time 的 before_minutes 和 after_minutes 必须为非负分钟数"
+        }
+        TimeScopeError::ArithmeticOverflow => "时间过滤的范围超出支持的时间边界",
+    };
+    AppError::public(StatusCode::BAD_REQUEST, "INVALID_TIME_RANGE", message)
+}
+
+/// Validates the `before`/`after` context line counts; `None` when no
+/// context was asked for.
+fn context_lines(before: Option<i64>, after: Option<i64>) -> Result<Option<(i64, i64)>, AppError> {
+    let before = before.unwrap_or(0);
+    let after = after.unwrap_or(0);
+    let range = 0..=MAX_SEARCH_CONTEXT_LINES;
+    if !range.contains(&before) || !range.contains(&after) {
+        return Err(AppError::BadRequest(format!(
+            "before 和 after 必须在 0 到 {MAX_SEARCH_CONTEXT_LINES} 之间"
+        )));
+    }
+    Ok((before > 0 || after > 0).then_some((before, after)))
+}
+
+async fn add_context(
+    state: &AppState,
+    response: &mut LogSearchResponse,
+    anchors: &[Option<HitAnchor>],
+    context: (i64, i64),
+) -> Result<(), AppError> {
+    let context = attach_context(
+        &state.db.pool,
+        state.storage.blob_store.as_ref(),
+        &state.limits.api,
+        &mut response.hits,
+        anchors,
+        context,
+    )
+    .await?;
+    response.contexts = Some(context.contexts);
+    response.context_truncated = context.truncated;
+    Ok(())
+}
+
+/// Builds the segment `level_mask` filter from `level=` (comma separated
+/// levels) and `min_level=`; both together select their intersection.
+pub(super) fn level_filter_mask(
+    level: Option<&str>,
+    min_level: Option<&str>,
+) -> Result<Option<i64>, AppError> {
+    let parse = |value: &str| {
+        LogLevel::parse(value)
+            .ok_or_else(|| AppError::BadRequest(format!("未知的日志级别：{}", value.trim())))
+    };
+    let mut mask = None;
+    if let Some(levels) = level.filter(|value| !value.trim().is_empty()) {
+        let mut selected = 0;
+        for value in levels.split(',').filter(|value| !value.trim().is_empty()) {
+            selected |= parse(value)?.bit();
+        }
+        mask = Some(selected);
+    }
+    if let Some(min_level) = min_level.filter(|value| !value.trim().is_empty()) {
+        let at_least = parse(min_level)?.at_least_mask();
+        mask = Some(mask.map_or(at_least, |selected| selected & at_least));
+    }
+    Ok(mask)
+}
+
+/// Level of the first line in `content` that contains `search_term`.
+fn matched_line_level(content: &str, search_term: &str) -> Option<String> {
+    find_ignore_ascii_case(content, search_term)
+        .and_then(|position| line_level_at(content, position))
+}
+
+/// Level of the content line containing byte `position`.
+pub(super) fn line_level_at(content: &str, position: usize) -> Option<String> {
+    let line_start = content[..position].rfind('\n').map_or(0, |index| index + 1);
+    let line_end = content[position..]
+        .find('\n')
+        .map_or(content.len(), |index| position + index);
+    LogLevel::detect(&content[line_start..line_end]).map(|level| level.as_str().to_string())
+}
+
+/// Resolves the first match inside a segment to the multi-line event that
+/// contains it, so stack traces come back whole with their start time.
+async fn match_event(
+    pool: &sqlx::SqlitePool,
+    segment_id: i64,
+    segment_shift_ms: i64,
+    content: &str,
+    search_term: &str,
+) -> Result<Option<EventMatch>, AppError> {
+    match find_ignore_ascii_case(content, search_term) {
+        Some(position) => event_at(pool, segment_id, segment_shift_ms, content, position).await,
+        None => Ok(None),
+    }
+}
+
+/// Multi-line event of a segment that covers byte `position` of its content.
+async fn event_at(
+    pool: &sqlx::SqlitePool,
+    segment_id: i64,
+    segment_shift_ms: i64,
+    content: &str,
+    position: usize,
+) -> Result<Option<EventMatch>, AppError> {
+    let span = sqlx::query_as::<_, EventSpanRow>(
+        r#"
+        SELECT line_start, line_end, content_start, content_end, event_time_ms,
+               event_time_shift_ms
+        FROM log_event_spans
+        WHERE segment_id = ? AND content_start <= ? AND content_end > ?
+        ORDER BY content_start DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(segment_id)
+    .bind(position as i64)
+    .bind(position as i64)
+    .fetch_optional(pool)
+    .await
+    .map_err(AppError::Database)?;
+    let Some(span) = span else {
+        return Ok(None);
+    };
+    let Some(text) = usize::try_from(span.content_start)
+        .ok()
+        .zip(usize::try_from(span.content_end).ok())
+        .and_then(|(start, end)| content.get(start..end))
+    else {
+        return Ok(None);
+    };
+    // Spans keep the shift applied at indexing; later re-keys move segments only.
+    let event_time = span
+        .event_time_ms
+        .and_then(|key| {
+            wall_clock::shift_comparison_key(key, segment_shift_ms - span.event_time_shift_ms)
+        })
+        .and_then(wall_clock::from_comparison_key)
+        .map(wall_clock::format);
+    Ok(Some(EventMatch {
+        level: text.lines().next().and_then(LogLevel::detect),
+        snippet: truncate_chars(text, MAX_EVENT_SNIPPET_CHARS),
+        line_start: span.line_start,
+        line_end: span.line_end,
+        event_time,
+    }))
+}
+
+/// Latest event time indexed in the file before line `line_offset`, before
+/// any shift, so a scan starting mid-file completes year- and date-less
+/// timestamps from the same earlier time as indexing did.
+async fn carried_event_time(
+    pool: &sqlx::SqlitePool,
+    file_id: i64,
+    line_offset: Option<i64>,
+) -> Result<Option<NaiveDateTime>, AppError> {
+    let Some(line_offset) = line_offset else {
+        return Ok(None);
+    };
+    let earlier_ends: Vec<(i64, Option<i64>)> = sqlx::query_as(
+        "SELECT ls.event_time_shift_ms, MAX(ls.event_time_end_ms) FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE ls.file_id = ? AND lc.line_offset < ? AND ls.event_time_end_ms IS NOT NULL GROUP BY ls.event_time_shift_ms",
+    )
+    .bind(file_id)
+    .bind(line_offset)
+    .fetch_all(pool)
+    .await
+    .map_err(AppError::Database)?;
+    Ok(earlier_ends
+        .into_iter()
+        .filter_map(|(shift_ms, end)| wall_clock::shift_comparison_key(end?, -shift_ms))
+        .max()
+        .and_then(wall_clock::from_comparison_key))
+}
+
+pub(super) fn find_ignore_ascii_case(content: &str, search_term: &str) -> Option<usize> {
+    if search_term.is_empty() {
+        return None;
+    }
+    content
+        .char_indices()
+        .map(|(index, _)| index)
+        .find(|&index| {
+            let mut haystack = content[index..].chars();
+            search_term.chars().all(|expected| {
+                haystack
+                    .next()
+                    .is_some_and(|actual| actual.eq_ignore_ascii_case(&expected))
+            })
+        })
+}
+
+fn truncate_chars(value: &str, max_chars: usize) -> String {
+    match value.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{} ...", &value[..end]),
+        None => value.to_string(),
+    }
+}
+
 fn escape_like_pattern(value: &str) -> String {
     value
         .replace('\\', "\\\\")
@@ -626,11 +1609,11 @@ fn escape_like_pattern(value: &str) -> String {
         .replace('_', "\\_")
 }
 
-fn build_fts_query(search_term: &str) -> String {
+pub(super) fn build_fts_query(search_term: &str) -> String {
     format!("\"{}\"", search_term.replace('"', "\"\""))
 }
 
-fn literal_snippet(content: &str, search_term: &str) -> String {
+pub(super) fn literal_snippet(content: &str, search_term: &str) -> String {
     const MAX_CHARS: usize = 400;
     const CONTEXT_BEFORE: usize = 120;
     let content_chars: Vec<char> = content.chars().collect();
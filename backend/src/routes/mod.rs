@@ -11,17 +11,22 @@ use serde_json::json;
 mod ai_provider;
 mod auth;
 mod files;
+mod global_search;
 mod health;
 mod helpers;
 mod issues;
 #[cfg(test)]
 pub(crate) use issues::cleanup_inactive_issues;
 pub use issues::resume_manual_issue_deletions;
+pub(crate) use issues::{normalize_issue_code, touch_issue_activity_best_effort};
+mod log_export;
+mod log_templates;
 mod logs;
 mod saved_searches;
 mod skill_runs;
 mod skills;
 mod temp_results;
+mod timestamp_formats;
 mod uploads;
 
 pub fn spawn_temp_result_cleanup(state: web::Data<crate::AppState>) -> tokio::task::JoinHandle<()> {
@@ -155,6 +160,9 @@ pub fn register(cfg: &mut web::ServiceConfig) {
                 .service(admin::change_status)
                 .service(admin::revoke_sessions)
                 .service(admin::list_audit)
+                .service(timestamp_formats::list)
+                .service(timestamp_formats::create)
+                .service(timestamp_formats::delete_format)
                 .service(auth::login)
                 .service(auth::me)
                 .service(auth::logout)
@@ -184,10 +192,23 @@ pub fn register(cfg: &mut web::ServiceConfig) {
                 .service(files::get_file_node)
                 .service(files::get_file_content)
                 .service(files::get_file_lines)
+                .service(files::diff_files)
+                .service(files::get_merged_timeline)
                 .service(files::download_file)
                 .service(files::delete_file_node)
+                .service(files::override_file_encoding)
+                .service(files::get_time_settings)
+                .service(files::update_time_settings)
+                .service(files::delete_time_settings)
+                .service(global_search::search_all_issues)
                 .service(logs::search_issue_logs)
+                .service(logs::search_issue_histogram)
                 .service(logs::search_logs)
+                .service(log_export::export_issue_search)
+                .service(log_export::export_bundle_search)
+                .service(log_templates::list_issue_templates)
+                .service(log_templates::get_template_lines)
+                .service(log_templates::diff_bundle_templates)
                 .service(temp_results::create_temp_result)
                 .service(temp_results::preview_temp_result)
                 .service(temp_results::get_temp_result)
@@ -195,6 +216,11 @@ pub fn register(cfg: &mut web::ServiceConfig) {
                 .service(temp_results::download_temp_result)
                 .service(temp_results::delete_temp_result)
                 .service(uploads::upload_logs)
+                .service(uploads::create_resumable_upload)
+                .service(uploads::get_resumable_upload_offset)
+                .service(uploads::append_resumable_upload)
+                .service(uploads::delete_resumable_upload)
+                .service(uploads::append_bundle_files)
                 .service(uploads::get_upload_task),
         );
 }
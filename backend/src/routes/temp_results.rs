@@ -22,6 +22,7 @@ use crate::{
     AppState, AuthRateLimitBucket,
     auth::extractor::{RequireBusinessUser, RequireUser},
     error::AppError,
+    ingest::FileEncoding,
     log_expression,
     repositories::files::{FileRow, ensure_text_preview, fetch_file, resolve_file_path},
     services::temp_results::{
@@ -107,14 +108,11 @@ pub(crate) fn register_read_lease(
     })
 }
 
-fn invalid_expression(error: log_expression::ParseError) -> AppError {
+pub(super) fn invalid_expression(error: log_expression::ParseError) -> AppError {
     AppError::public(
         StatusCode::BAD_REQUEST,
         "SEARCH_EXPRESSION_INVALID",
-        format!(
-            "搜索条件无效，请检查 AND/OR/NOT 前后是否都有关键词（位置 {}：{}）",
-            error.offset, error.message
-        ),
+        format!("搜索条件无效（位置 {}：{}）", error.offset, error.message),
     )
 }
 
@@ -15,7 +15,10 @@ use super::storage::{
     temp_result_too_large,
 };
 use super::*;
-use crate::services::temp_results::scan_timeout;
+use crate::ingest::{TimestampRecognizers, load_timestamp_context};
+use crate::services::temp_results::{SourceEventTimes, scan_timeout};
+use chrono::NaiveDateTime;
+use std::sync::Arc;
 
 enum MaterializeMode {
     Full,
@@ -175,7 +178,36 @@ struct IssueSourceRow {
     storage_backend: Option<String>,
     storage_key: Option<String>,
     blob_state: Option<String>,
+    bundle_id: String,
     bundle_hash: String,
+    event_time_shift_ms: i64,
+}
+
+/// Timestamp contexts of the bundles already resolved, keyed by bundle id.
+type TimestampContexts = HashMap<String, (Arc<TimestampRecognizers>, NaiveDateTime)>;
+
+/// Event time settings of a file, loading its bundle's timestamp context
+/// once per bundle.
+async fn source_event_times(
+    pool: &sqlx::SqlitePool,
+    contexts: &mut TimestampContexts,
+    bundle_id: &str,
+    shift_ms: i64,
+) -> Result<SourceEventTimes, AppError> {
+    let (recognizers, reference) = match contexts.get(bundle_id) {
+        Some(context) => context.clone(),
+        None => {
+            let (recognizers, reference) = load_timestamp_context(pool, bundle_id).await?;
+            let context = (Arc::new(recognizers), reference);
+            contexts.insert(bundle_id.to_string(), context.clone());
+            context
+        }
+    };
+    Ok(SourceEventTimes {
+        recognizers,
+        reference,
+        shift_ms,
+    })
 }
 
 pub(crate) async fn resolve_sources(
@@ -205,6 +237,8 @@ pub(crate) async fn resolve_sources(
                 label: source.name,
                 bundle_hash: None,
                 file_id: None,
+                encoding: FileEncoding::default(),
+                event_times: None,
             }],
             _source_lease: Some(source_lease),
         });
@@ -224,7 +258,7 @@ pub(crate) async fn resolve_sources(
             SELECT f.id, f.name, f.path, f.size_bytes, f.line_count, f.mime_type,
                    f.status, f.meta, f.blob_id, bl.storage_backend, bl.storage_key,
                    bl.state AS blob_state,
-                   b.hash AS bundle_hash
+                   b.id AS bundle_id, b.hash AS bundle_hash, f.event_time_shift_ms
             FROM files f
             JOIN bundles b ON b.id = f.bundle_id
             JOIN issues i ON i.code = b.issue_code
@@ -248,6 +282,7 @@ pub(crate) async fn resolve_sources(
             ));
         }
         let mut sources = Vec::new();
+        let mut contexts = TimestampContexts::new();
         for row in rows {
             let file = FileRow {
                 id: row.id,
@@ -271,6 +306,16 @@ pub(crate) async fn resolve_sources(
                 label: file.name.clone(),
                 bundle_hash: Some(row.bundle_hash),
                 file_id: Some(file.id.to_string()),
+                encoding: FileEncoding::from_meta(file.meta.as_deref()),
+                event_times: Some(
+                    source_event_times(
+                        &state.db.pool,
+                        &mut contexts,
+                        &row.bundle_id,
+                        row.event_time_shift_ms,
+                    )
+                    .await?,
+                ),
             });
         }
         if sources.is_empty() {
@@ -298,13 +343,27 @@ pub(crate) async fn resolve_sources(
     let file = fetch_file(&state.db.pool, &bundle.id, file_id).await?;
     ensure_text_preview(&file)?;
     let path = resolve_file_path(&file, state.storage.blob_store.as_ref()).await?;
+    let shift_ms: i64 = sqlx::query_scalar("SELECT event_time_shift_ms FROM files WHERE id = ?")
+        .bind(file.id)
+        .fetch_one(&state.db.pool)
+        .await
+        .map_err(AppError::Database)?;
+    let event_times = source_event_times(
+        &state.db.pool,
+        &mut TimestampContexts::new(),
+        &bundle.id,
+        shift_ms,
+    )
+    .await?;
     Ok(ResolvedSources {
         sources: vec![TempSource {
             path,
             metadata_path: None,
+            encoding: FileEncoding::from_meta(file.meta.as_deref()),
             label: file.name,
             bundle_hash: Some(bundle.hash),
             file_id: Some(file.id.to_string()),
+            event_times: Some(event_times),
         }],
         _source_lease: None,
     })
@@ -578,7 +637,7 @@ mod tests {
         MaterializeMode, materialize_result, materialize_result_with_timeout, resolve_sources,
     };
     use crate::{
-        AppState, config::AppLimits, db, error::AppError, log_expression,
+        AppState, config::AppLimits, db, error::AppError, ingest::FileEncoding, log_expression,
         routes::temp_results::CreateTempResultRequest, services::temp_results::TempSource,
     };
 
@@ -669,6 +728,8 @@ mod tests {
                 label: "app.log".into(),
                 bundle_hash: None,
                 file_id: None,
+                encoding: FileEncoding::default(),
+                event_times: None,
             },
             "TEMP_RESULT_SCAN_LIMIT",
             None,
@@ -695,6 +756,8 @@ mod tests {
                 label: "app.log".into(),
                 bundle_hash: None,
                 file_id: None,
+                encoding: FileEncoding::default(),
+                event_times: None,
             },
             "TEMP_RESULT_SCAN_TIMEOUT",
             Some(Duration::from_millis(1)),
@@ -732,14 +795,19 @@ mod tests {
             .fetch_one(&state.db.pool)
             .await
             .unwrap();
-            sqlx::query(
-                "INSERT INTO log_segments(bundle_id, file_id, content) VALUES(?, ?, 'ERROR')",
+            let content_id: i64 = sqlx::query_scalar(
+                "INSERT INTO log_segment_contents(content) VALUES('ERROR') RETURNING id",
             )
-            .bind(bundle_id)
-            .bind(file_id)
-            .execute(&state.db.pool)
+            .fetch_one(&state.db.pool)
             .await
             .unwrap();
+            sqlx::query("INSERT INTO log_segments(bundle_id, file_id, content_id) VALUES(?, ?, ?)")
+                .bind(bundle_id)
+                .bind(file_id)
+                .bind(content_id)
+                .execute(&state.db.pool)
+                .await
+                .unwrap();
         }
 
         let payload = CreateTempResultRequest {
@@ -354,7 +354,7 @@ mod tests {
     use super::{read_indexed_lines, read_indexed_lines_bounded, staging_path};
     use crate::{
         config::MAX_TEMP_RESULT_LOGICAL_LINE_BYTES,
-        ingest::TRUNCATED_LINE_MARKER,
+        ingest::{FileEncoding, TRUNCATED_LINE_MARKER},
         log_expression,
         services::temp_results::{MatchMetadata, SparseCheckpoint, TempResultExecutor, TempSource},
     };
@@ -385,6 +385,8 @@ mod tests {
             label: "app.log".into(),
             bundle_hash: None,
             file_id: None,
+            encoding: FileEncoding::default(),
+            event_times: None,
         }];
         let expression = log_expression::parse("a").unwrap();
         let mut result = File::create(&result_path).await.unwrap();
@@ -454,6 +456,8 @@ mod tests {
                     path: "app.log".into(),
                     line_number,
                     truncated: false,
+                    level: None,
+                    event_time: None,
                 })
                 .unwrap()
             })
@@ -526,6 +530,8 @@ mod tests {
                     path: "app.log".into(),
                     line_number,
                     truncated: false,
+                    level: None,
+                    event_time: None,
                 })
                 .unwrap()
             })
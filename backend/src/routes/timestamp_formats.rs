@@ -0,0 +1,135 @@
+use actix_web::{HttpRequest, HttpResponse, delete, get, http::StatusCode, post, web};
+use uuid::Uuid;
+
+use crate::{
+    AppState,
+    auth::extractor::RequireAdmin,
+    error::AppError,
+    ingest::PatternRecognizer,
+    models::timestamp_formats::{TimestampFormatPayload, TimestampFormatRecord},
+    repositories::timestamp_formats,
+};
+
+const MAX_TIMESTAMP_FORMATS: usize = 50;
+const MAX_NAME_CHARS: usize = 100;
+const MAX_PATTERN_CHARS: usize = 1_000;
+
+fn invalid_format() -> AppError {
+    AppError::api(
+        StatusCode::BAD_REQUEST,
+        "INVALID_TIMESTAMP_FORMAT",
+        "时间戳正则或 strftime 格式无效",
+    )
+}
+
+fn validate(payload: &TimestampFormatPayload) -> Result<(), AppError> {
+    let name = payload.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+        return Err(AppError::api(
+            StatusCode::BAD_REQUEST,
+            "INVALID_TIMESTAMP_FORMAT_NAME",
+            "时间戳格式名称不能为空且不能超过 100 个字符",
+        ));
+    }
+    if payload.pattern.chars().count() > MAX_PATTERN_CHARS
+        || payload.format.chars().count() > MAX_NAME_CHARS
+    {
+        return Err(invalid_format());
+    }
+    let recognizer =
+        PatternRecognizer::new(&payload.pattern, &payload.format).map_err(|_| invalid_format())?;
+    if !recognizer.recognizes(&payload.sample) {
+        return Err(AppError::api(
+            StatusCode::BAD_REQUEST,
+            "TIMESTAMP_SAMPLE_MISMATCH",
+            "示例日志行无法用该时间戳格式解析",
+        ));
+    }
+    Ok(())
+}
+
+async fn audit(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    req: &HttpRequest,
+    admin: &RequireAdmin,
+    action: &str,
+    record: &TimestampFormatRecord,
+) -> Result<(), AppError> {
+    let value = format!("{}: {} => {}", record.name, record.pattern, record.format);
+    let (old_value, new_value) = if action == "TIMESTAMP_FORMAT_DELETED" {
+        (Some(value), None)
+    } else {
+        (None, Some(value))
+    };
+    sqlx::query("INSERT INTO admin_audit_logs(id,actor_type,actor_user_id,action,old_value,new_value,client_ip,user_agent) VALUES(?,'USER',?,?,?,?,?,?)")
+        .bind(Uuid::new_v4().to_string())
+        .bind(&admin.0.id)
+        .bind(action)
+        .bind(old_value)
+        .bind(new_value)
+        .bind(req.peer_addr().map(|address| address.ip().to_string()))
+        .bind(req.headers().get("user-agent").and_then(|value| value.to_str().ok()))
+        .execute(&mut **tx)
+        .await
+        .map_err(AppError::Database)?;
+    Ok(())
+}
+
+#[get("/admin/timestamp-formats")]
+pub async fn list(
+    _admin: RequireAdmin,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let items = timestamp_formats::list(&state.db.pool).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "items": items })))
+}
+
+#[post("/admin/timestamp-formats")]
+pub async fn create(
+    req: HttpRequest,
+    admin: RequireAdmin,
+    state: web::Data<AppState>,
+    body: web::Json<TimestampFormatPayload>,
+) -> Result<HttpResponse, AppError> {
+    validate(&body)?;
+    let mut tx = state.db.pool.begin().await.map_err(AppError::Database)?;
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM timestamp_formats")
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+    if count as usize >= MAX_TIMESTAMP_FORMATS {
+        return Err(AppError::api(
+            StatusCode::CONFLICT,
+            "TIMESTAMP_FORMAT_LIMIT",
+            "自定义时间戳格式最多 50 个",
+        ));
+    }
+    let record = timestamp_formats::create(&mut tx, &admin.0.id, &body)
+        .await?
+        .ok_or_else(|| {
+            AppError::api(
+                StatusCode::CONFLICT,
+                "TIMESTAMP_FORMAT_EXISTS",
+                "时间戳格式名称已存在",
+            )
+        })?;
+    audit(&mut tx, &req, &admin, "TIMESTAMP_FORMAT_CREATED", &record).await?;
+    tx.commit().await.map_err(AppError::Database)?;
+    Ok(HttpResponse::Created().json(record))
+}
+
+#[delete("/admin/timestamp-formats/{id}")]
+pub async fn delete_format(
+    req: HttpRequest,
+    admin: RequireAdmin,
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let mut tx = state.db.pool.begin().await.map_err(AppError::Database)?;
+    let record = timestamp_formats::delete(&mut tx, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("timestamp format {id}")))?;
+    audit(&mut tx, &req, &admin, "TIMESTAMP_FORMAT_DELETED", &record).await?;
+    tx.commit().await.map_err(AppError::Database)?;
+    Ok(HttpResponse::NoContent().finish())
+}
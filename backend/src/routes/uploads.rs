@@ -1,10 +1,15 @@
+use std::path::PathBuf;
+
 use actix_web::{
-    HttpMessage, HttpRequest, HttpResponse, get,
-    http::{StatusCode, header::CONTENT_LENGTH},
-    post, web,
+    HttpMessage, HttpRequest, HttpResponse, delete, get,
+    http::{
+        StatusCode,
+        header::{CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, LOCATION},
+    },
+    patch, post, route, web,
 };
-use serde::Serialize;
-use tokio::fs;
+use serde::{Deserialize, Serialize};
+use tokio::{fs, sync::OwnedSemaphorePermit};
 use tracing::info;
 use uuid::Uuid;
 
@@ -13,14 +18,18 @@ use crate::{
     auth::extractor::RequireBusinessUser,
     error::AppError,
     models::issues::{UploadStage, UploadStatus},
+    services::log_templates::{TemplateMining, trigger_mining},
     upload::{
+        append::{AppendFiles, append_files, claim_bundle_append, parse_subdirectory},
         job::{UploadJob, spawn_upload_job},
         lifecycle::{
             finalize_upload_reservation, remove_upload_reservation, reserve_upload_bundle,
         },
         multipart::{
-            ReceiveReservation, collect_multipart_upload, limited_multipart, raw_payload_limit,
+            MultipartUpload, ReceiveReservation, collect_multipart_upload, limited_multipart,
+            raw_payload_limit,
         },
+        resumable::{NewResumableUpload, ResumableUpload, discard_temp_dir, discard_upload},
     },
 };
 
@@ -43,31 +52,8 @@ pub async fn upload_logs(
     let issue_code = normalize_issue_code(&path.into_inner())?;
     require_issue_owner(&state.db.pool, &issue_code, &user.0.id).await?;
     let request_limit = raw_payload_limit(state.limits.issue_max_content_size.saturating_mul(2));
-    if let Some(length) = req.headers().get(CONTENT_LENGTH) {
-        let length = length
-            .to_str()
-            .ok()
-            .and_then(|value| value.parse::<u64>().ok())
-            .ok_or_else(|| AppError::BadRequest("invalid Content-Length header".into()))?;
-        if length > request_limit {
-            return Err(AppError::BadRequest(format!(
-                "upload request exceeds the maximum size of {}",
-                crate::upload::filename::format_bytes(request_limit)
-            )));
-        }
-    }
-    let receive_permit = state
-        .upload
-        .receive_permits
-        .clone()
-        .try_acquire_owned()
-        .map_err(|_| {
-            AppError::api(
-                StatusCode::TOO_MANY_REQUESTS,
-                "UPLOAD_RECEIVE_BUSY",
-                "上传接收任务过多，请稍后重试",
-            )
-        })?;
+    check_content_length(&req, request_limit)?;
+    let receive_permit = acquire_receive_permit(&state)?;
 
     let upload_id = Uuid::new_v4().simple().to_string();
     let bundle_id = Uuid::new_v4().simple().to_string();
@@ -118,6 +104,233 @@ pub async fn upload_logs(
         }
     };
 
+    let response = queue_received_upload(
+        &state,
+        request_id.as_deref(),
+        issue_code,
+        bundle_id,
+        bundle_hash,
+        temp_dir,
+        upload,
+        receive_started,
+    )
+    .await?;
+    drop(receive_permit);
+    touch_issue_activity_best_effort(&state.db.pool, &response.issue_code, "upload accepted").await;
+
+    Ok(HttpResponse::build(StatusCode::ACCEPTED).json(response))
+}
+
+fn check_content_length(req: &HttpRequest, request_limit: u64) -> Result<(), AppError> {
+    if let Some(length) = req.headers().get(CONTENT_LENGTH) {
+        let length = length
+            .to_str()
+            .ok()
+            .and_then(|value| value.parse::<u64>().ok())
+            .ok_or_else(|| AppError::BadRequest("invalid Content-Length header".into()))?;
+        if length > request_limit {
+            return Err(AppError::BadRequest(format!(
+                "upload request exceeds the maximum size of {}",
+                crate::upload::filename::format_bytes(request_limit)
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn acquire_receive_permit(state: &AppState) -> Result<OwnedSemaphorePermit, AppError> {
+    state
+        .upload
+        .receive_permits
+        .clone()
+        .try_acquire_owned()
+        .map_err(|_| {
+            AppError::api(
+                StatusCode::TOO_MANY_REQUESTS,
+                "UPLOAD_RECEIVE_BUSY",
+                "上传接收任务过多，请稍后重试",
+            )
+        })
+}
+
+/// Removes a temporary upload directory, queueing a retry that keeps the
+/// budget reservation when removal fails.
+async fn remove_temp_dir(
+    state: &AppState,
+    request_id: Option<&str>,
+    temp_dir: PathBuf,
+    reservation: ReceiveReservation,
+) {
+    if let Err(cleanup_error) = fs::remove_dir_all(&temp_dir).await {
+        tracing::error!(
+            request_id = request_id.unwrap_or("unavailable"),
+            path = %temp_dir.display(),
+            error = %cleanup_error,
+            "failed to remove temporary upload directory; queueing retry"
+        );
+        state
+            .upload
+            .temp_cleanup_queue
+            .enqueue(temp_dir, reservation);
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AppendBundleFilesQuery {
+    /// `/`-separated directory below the bundle root; created when missing.
+    subdirectory: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AppendBundleFilesResponse {
+    issue_code: String,
+    bundle_hash: String,
+    /// Directory node the files were placed under; `null` for the bundle root.
+    directory_id: Option<i64>,
+    file_count: u64,
+    total_bytes: u64,
+    /// Indexed content added to the bundle and charged to the Issue quota.
+    added_content_bytes: u64,
+}
+
+/// Adds files or archives to a READY bundle. Unlike a new upload this runs
+/// to completion within the request, so the response reports the outcome.
+#[post("/issues/{issue_code}/bundles/{bundle_hash}/files")]
+pub async fn append_bundle_files(
+    user: RequireBusinessUser,
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    query: web::Query<AppendBundleFilesQuery>,
+    req: HttpRequest,
+    payload: web::Payload,
+) -> Result<HttpResponse, AppError> {
+    let started = std::time::Instant::now();
+    let request_id = req
+        .extensions()
+        .get::<RequestLogId>()
+        .map(|value| value.0.clone());
+    let (issue_code, bundle_hash) = path.into_inner();
+    let issue_code = require_issue_owner(&state.db.pool, &issue_code, &user.0.id).await?;
+    let subdirectory = parse_subdirectory(query.subdirectory.as_deref())?;
+    let bundle_id: String = sqlx::query_scalar(
+        "SELECT id FROM bundles WHERE issue_code = ? AND hash = ? AND deleted_at IS NULL",
+    )
+    .bind(&issue_code)
+    .bind(&bundle_hash)
+    .fetch_optional(&state.db.pool)
+    .await
+    .map_err(AppError::Database)?
+    .ok_or_else(|| AppError::NotFound(format!("bundle {bundle_hash}")))?;
+
+    let request_limit = raw_payload_limit(state.limits.issue_max_content_size.saturating_mul(2));
+    check_content_length(&req, request_limit)?;
+    let _append_lease = claim_bundle_append(&state.upload.appending, &bundle_id)?;
+    let receive_permit = acquire_receive_permit(&state)?;
+    let temp_dir = state
+        .storage
+        .data_root
+        .join(".tmp")
+        .join(Uuid::new_v4().simple().to_string());
+    fs::create_dir_all(&temp_dir).await.map_err(AppError::Io)?;
+    let upload = match collect_multipart_upload(
+        limited_multipart(&req, payload, request_limit),
+        &temp_dir,
+        state.limits.issue_max_content_size.saturating_mul(2),
+        ReceiveReservation::new(
+            state.upload.tmp_bytes.clone(),
+            state.limits.upload.max_tmp_bytes,
+        ),
+    )
+    .await
+    {
+        Ok(upload) => upload,
+        Err(error) => {
+            remove_temp_dir(
+                &state,
+                request_id.as_deref(),
+                temp_dir,
+                error.receive_reservation,
+            )
+            .await;
+            return Err(error.error);
+        }
+    };
+    drop(receive_permit);
+
+    let appended = match state
+        .upload
+        .processing_permits
+        .clone()
+        .acquire_owned()
+        .await
+    {
+        Ok(_permit) => {
+            append_files(AppendFiles {
+                pool: &state.db.pool,
+                blob_store: state.storage.blob_store.clone(),
+                staging_root: &temp_dir.join("staging"),
+                issue_code: &issue_code,
+                issue_max_content_size: state.limits.issue_max_content_size,
+                bundle_id: &bundle_id,
+                bundle_hash: &bundle_hash,
+                subdirectory: &subdirectory,
+                files: &upload.files,
+                archive_password: upload.archive_password.clone(),
+                archive_config: crate::config::ArchiveConfig::for_content_limit(
+                    state.limits.issue_max_content_size,
+                ),
+                indexing: &state.limits.indexing,
+                receive_reservation: &upload.receive_reservation,
+            })
+            .await
+        }
+        Err(_) => Err(AppError::Conflict("上传处理任务已停止".into())),
+    };
+    let file_count = upload.files.len() as u64;
+    remove_temp_dir(
+        &state,
+        request_id.as_deref(),
+        temp_dir,
+        upload.receive_reservation,
+    )
+    .await;
+    let appended = appended?;
+    trigger_mining(TemplateMining::from_state(&state));
+    touch_issue_activity_best_effort(&state.db.pool, &issue_code, "bundle files appended").await;
+    info!(
+        request_id = request_id.as_deref().unwrap_or("unavailable"),
+        bundle_id = %bundle_id,
+        bundle_hash = %bundle_hash,
+        file_count,
+        total_bytes = upload.total_bytes,
+        added_content_bytes = appended.added_content_bytes,
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "files appended to bundle"
+    );
+
+    Ok(HttpResponse::Ok().json(AppendBundleFilesResponse {
+        issue_code,
+        bundle_hash,
+        directory_id: appended.directory_id,
+        file_count,
+        total_bytes: upload.total_bytes,
+        added_content_bytes: appended.added_content_bytes,
+    }))
+}
+
+/// Turns the PENDING reservation of a fully received upload into a
+/// PROCESSING bundle and hands the staged files to the upload job.
+#[allow(clippy::too_many_arguments)]
+async fn queue_received_upload(
+    state: &AppState,
+    request_id: Option<&str>,
+    issue_code: String,
+    bundle_id: String,
+    bundle_hash: String,
+    temp_dir: PathBuf,
+    upload: MultipartUpload,
+    receive_started: std::time::Instant,
+) -> Result<UploadResponse, AppError> {
     let bundle_name = if upload.files.len() == 1 {
         upload.files[0].display_name.clone()
     } else {
@@ -135,7 +348,7 @@ pub async fn upload_logs(
         remove_upload_reservation(&state.db.pool, &bundle_id).await;
         if let Err(cleanup_error) = fs::remove_dir_all(&temp_dir).await {
             tracing::error!(
-                request_id = request_id.as_deref().unwrap_or("unavailable"),
+                request_id = request_id.unwrap_or("unavailable"),
                 bundle_id = %bundle_id,
                 path = %temp_dir.display(),
                 error = %cleanup_error,
@@ -162,18 +375,20 @@ pub async fn upload_logs(
             state.limits.issue_max_content_size,
         ),
         indexing_config: state.limits.indexing.clone(),
-        request_id: request_id.clone(),
+        request_id: request_id.map(str::to_string),
         issue_code: issue_code.clone(),
         issue_max_content_size: state.limits.issue_max_content_size,
         bundle_id: bundle_id.clone(),
         bundle_hash: bundle_hash.clone(),
         files: upload.files,
+        archive_password: upload.archive_password,
+        template_mining: TemplateMining::from_state(state),
         receive_reservation: upload.receive_reservation,
         temp_cleanup_queue: state.upload.temp_cleanup_queue.clone(),
     });
 
     info!(
-        request_id = request_id.as_deref().unwrap_or("unavailable"),
+        request_id = request_id.unwrap_or("unavailable"),
         bundle_id = %bundle_id,
         bundle_hash = %bundle_hash,
         file_count,
@@ -182,20 +397,15 @@ pub async fn upload_logs(
         "upload received and queued for processing"
     );
 
-    drop(receive_permit);
-    touch_issue_activity_best_effort(&state.db.pool, &issue_code, "upload accepted").await;
-
-    Ok(
-        HttpResponse::build(StatusCode::ACCEPTED).json(UploadResponse {
-            task_id: bundle_hash.clone(),
-            issue_code,
-            bundle_hash,
-            status: UploadStatus::Processing,
-            stage: UploadStage::Receiving,
-            file_count,
-            total_bytes: upload.total_bytes,
-        }),
-    )
+    Ok(UploadResponse {
+        task_id: bundle_hash.clone(),
+        issue_code,
+        bundle_hash,
+        status: UploadStatus::Processing,
+        stage: UploadStage::Receiving,
+        file_count,
+        total_bytes: upload.total_bytes,
+    })
 }
 
 #[derive(Serialize)]
@@ -284,3 +494,238 @@ struct UploadTaskResponse {
     progress_percent: u8,
     total_bytes: u64,
 }
+
+const UPLOAD_OFFSET: &str = "Upload-Offset";
+const UPLOAD_LENGTH: &str = "Upload-Length";
+const CHUNK_CONTENT_TYPE: &str = "application/offset+octet-stream";
+
+#[derive(Deserialize)]
+pub struct CreateResumableUploadRequest {
+    filename: String,
+    size: u64,
+    /// Hex SHA-256 of the whole file, checked once the last chunk arrives.
+    sha256: String,
+    content_type: Option<String>,
+    archive_password: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ResumableUploadResponse {
+    upload_id: String,
+    issue_code: String,
+    size: u64,
+    offset: u64,
+}
+
+#[post("/issues/{issue_code}/uploads/resumable")]
+pub async fn create_resumable_upload(
+    user: RequireBusinessUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    payload: web::Json<CreateResumableUploadRequest>,
+) -> Result<HttpResponse, AppError> {
+    let issue_code = normalize_issue_code(&path.into_inner())?;
+    require_issue_owner(&state.db.pool, &issue_code, &user.0.id).await?;
+    let payload = payload.into_inner();
+    let filename = payload.filename.trim().to_string();
+    if filename.is_empty() {
+        return Err(AppError::BadRequest("filename 不能为空".into()));
+    }
+    let max_size = state.limits.issue_max_content_size.saturating_mul(2);
+    if payload.size == 0 || payload.size > max_size {
+        return Err(AppError::BadRequest(format!(
+            "upload size must be between 1 byte and {}",
+            crate::upload::filename::format_bytes(max_size)
+        )));
+    }
+    if payload.sha256.len() != 64 || !payload.sha256.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(AppError::BadRequest(
+            "sha256 必须是 64 位十六进制字符串".into(),
+        ));
+    }
+
+    let upload_id = Uuid::new_v4().simple().to_string();
+    let temp_dir = state.storage.data_root.join(".tmp").join(&upload_id);
+    let upload = ResumableUpload::new(NewResumableUpload {
+        issue_code: issue_code.clone(),
+        filename,
+        content_type: payload
+            .content_type
+            .filter(|value| !value.trim().is_empty()),
+        size: payload.size,
+        sha256: payload.sha256,
+        archive_password: payload.archive_password.filter(|value| !value.is_empty()),
+        temp_dir: temp_dir.clone(),
+        receive_reservation: ReceiveReservation::new(
+            state.upload.tmp_bytes.clone(),
+            state.limits.upload.max_tmp_bytes,
+        ),
+    })?;
+    fs::create_dir_all(&temp_dir).await.map_err(AppError::Io)?;
+    if let Err(error) = state
+        .upload
+        .resumable
+        .insert(&upload_id, &user.0.id, upload)
+    {
+        let _ = fs::remove_dir_all(&temp_dir).await;
+        return Err(error);
+    }
+    touch_issue_activity_best_effort(&state.db.pool, &issue_code, "resumable upload created").await;
+
+    Ok(HttpResponse::Created()
+        .insert_header((LOCATION, format!("/api/uploads/resumable/{upload_id}")))
+        .insert_header((UPLOAD_OFFSET, "0"))
+        .json(ResumableUploadResponse {
+            upload_id,
+            issue_code,
+            size: payload.size,
+            offset: 0,
+        }))
+}
+
+#[route("/uploads/resumable/{upload_id}", method = "HEAD")]
+pub async fn get_resumable_upload_offset(
+    user: RequireBusinessUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let handle = state.upload.resumable.get(&path.into_inner(), &user.0.id)?;
+    Ok(HttpResponse::Ok()
+        .insert_header((CACHE_CONTROL, "no-store"))
+        .insert_header((UPLOAD_OFFSET, handle.offset().to_string()))
+        .insert_header((UPLOAD_LENGTH, handle.size.to_string()))
+        .finish())
+}
+
+#[patch("/uploads/resumable/{upload_id}")]
+pub async fn append_resumable_upload(
+    user: RequireBusinessUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    req: HttpRequest,
+    payload: web::Payload,
+) -> Result<HttpResponse, AppError> {
+    let receive_started = std::time::Instant::now();
+    let request_id = req
+        .extensions()
+        .get::<RequestLogId>()
+        .map(|value| value.0.clone());
+    let upload_id = path.into_inner();
+    let handle = state.upload.resumable.get(&upload_id, &user.0.id)?;
+    let content_type = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if !content_type.eq_ignore_ascii_case(CHUNK_CONTENT_TYPE) {
+        return Err(AppError::api(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "UPLOAD_CHUNK_CONTENT_TYPE",
+            "上传分片的 Content-Type 必须是 application/offset+octet-stream",
+        ));
+    }
+    let offset = req
+        .headers()
+        .get(UPLOAD_OFFSET)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .ok_or_else(|| AppError::BadRequest("missing or invalid Upload-Offset header".into()))?;
+    let Ok(mut guard) = handle.upload.try_lock() else {
+        return Err(AppError::api(
+            StatusCode::CONFLICT,
+            "UPLOAD_CHUNK_IN_PROGRESS",
+            "该上传正在接收另一个分片，请稍后查询进度后重试",
+        ));
+    };
+    let Some(upload) = guard.as_mut() else {
+        return Err(AppError::NotFound(format!("resumable upload {upload_id}")));
+    };
+    let receive_permit = acquire_receive_permit(&state)?;
+    let appended = upload.append(offset, payload).await;
+    let offset = upload.offset();
+    touch_issue_activity_best_effort(&state.db.pool, &upload.issue_code, "resumable upload chunk")
+        .await;
+    appended?;
+    if !upload.is_complete() {
+        return Ok(HttpResponse::NoContent()
+            .insert_header((UPLOAD_OFFSET, offset.to_string()))
+            .finish());
+    }
+
+    let Some(upload) = guard.take() else {
+        return Err(AppError::NotFound(format!("resumable upload {upload_id}")));
+    };
+    state.upload.resumable.remove(&upload_id);
+    drop(guard);
+    let issue_code = upload.issue_code.clone();
+    let temp_dir = upload.temp_dir.clone();
+    let received = match upload.finish() {
+        Ok(received) => received,
+        Err(error) => {
+            discard_temp_dir(
+                temp_dir,
+                error.receive_reservation,
+                &state.upload.temp_cleanup_queue,
+            )
+            .await;
+            return Err(error.error);
+        }
+    };
+    let bundle_id = Uuid::new_v4().simple().to_string();
+    let bundle_hash = Uuid::new_v4().simple().to_string();
+    if let Err(error) = reserve_upload_bundle(
+        &state.db.pool,
+        &bundle_id,
+        &issue_code,
+        &bundle_hash,
+        &user.0.id,
+    )
+    .await
+    {
+        discard_temp_dir(
+            temp_dir,
+            received.receive_reservation,
+            &state.upload.temp_cleanup_queue,
+        )
+        .await;
+        return Err(error);
+    }
+    let response = queue_received_upload(
+        &state,
+        request_id.as_deref(),
+        issue_code,
+        bundle_id,
+        bundle_hash,
+        temp_dir,
+        received,
+        receive_started,
+    )
+    .await?;
+    drop(receive_permit);
+
+    Ok(HttpResponse::build(StatusCode::ACCEPTED)
+        .insert_header((UPLOAD_OFFSET, offset.to_string()))
+        .json(response))
+}
+
+#[delete("/uploads/resumable/{upload_id}")]
+pub async fn delete_resumable_upload(
+    user: RequireBusinessUser,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let upload_id = path.into_inner();
+    let handle = state.upload.resumable.get(&upload_id, &user.0.id)?;
+    let Ok(mut guard) = handle.upload.try_lock() else {
+        return Err(AppError::api(
+            StatusCode::CONFLICT,
+            "UPLOAD_CHUNK_IN_PROGRESS",
+            "该上传正在接收另一个分片，请稍后查询进度后重试",
+        ));
+    };
+    state.upload.resumable.remove(&upload_id);
+    if let Some(upload) = guard.take() {
+        discard_upload(upload, &state.upload.temp_cleanup_queue).await;
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqlitePool};
+
+use crate::{error::AppError, services::wall_clock};
+
+pub const MAX_UTC_OFFSET_MINUTES: i64 = 14 * 60;
+pub const MAX_CLOCK_SKEW_MS: i64 = 7 * 24 * 60 * 60 * 1_000;
+const REKEY_BATCH_SIZE: i64 = 500;
+
+/// Declared timezone and clock correction for a bundle or file subtree.
+///
+/// Event times are stored as comparison keys of the corrected UTC wall clock:
+/// `local - utc_offset + clock_skew`. Files without any setting keep their
+/// naive wall clock, as before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct EventTimeSetting {
+    pub utc_offset_minutes: i64,
+    #[serde(default)]
+    pub clock_skew_ms: i64,
+}
+
+impl EventTimeSetting {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(-MAX_UTC_OFFSET_MINUTES..=MAX_UTC_OFFSET_MINUTES).contains(&self.utc_offset_minutes) {
+            return Err(AppError::api(
+                actix_web::http::StatusCode::BAD_REQUEST,
+                "INVALID_UTC_OFFSET",
+                "UTC 偏移必须在 -840 到 840 分钟之间",
+            ));
+        }
+        if !(-MAX_CLOCK_SKEW_MS..=MAX_CLOCK_SKEW_MS).contains(&self.clock_skew_ms) {
+            return Err(AppError::api(
+                actix_web::http::StatusCode::BAD_REQUEST,
+                "INVALID_CLOCK_SKEW",
+                "时钟偏差修正不能超过 7 天",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Milliseconds added to a parsed wall clock before it is keyed.
+    pub fn shift_ms(&self) -> i64 {
+        self.clock_skew_ms - self.utc_offset_minutes * 60_000
+    }
+}
+
+#[derive(FromRow)]
+struct ScopedSetting {
+    file_id: Option<i64>,
+    utc_offset_minutes: i64,
+    clock_skew_ms: i64,
+}
+
+#[derive(FromRow)]
+struct StaleSegment {
+    id: i64,
+    event_time_start_ms: Option<i64>,
+    event_time_end_ms: Option<i64>,
+    applied_shift_ms: i64,
+    target_shift_ms: i64,
+}
+
+/// Returns the setting declared exactly on `file_id`, or on the whole bundle
+/// when `file_id` is `None`.
+pub async fn load_setting(
+    pool: &SqlitePool,
+    bundle_id: &str,
+    file_id: Option<i64>,
+) -> Result<Option<EventTimeSetting>, AppError> {
+    sqlx::query_as(
+        "SELECT utc_offset_minutes, clock_skew_ms FROM event_time_settings WHERE bundle_id = ? AND file_id IS ?",
+    )
+    .bind(bundle_id)
+    .bind(file_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(AppError::Database)
+}
+
+/// Resolves the setting that applies to a file: the nearest ancestor-or-self
+/// scope wins, then the bundle-wide setting.
+pub async fn resolve_file_setting(
+    pool: &SqlitePool,
+    bundle_id: &str,
+    file_id: i64,
+) -> Result<Option<EventTimeSetting>, AppError> {
+    sqlx::query_as(
+        "WITH RECURSIVE chain(id, parent_id, depth) AS (SELECT id, parent_id, 0 FROM files WHERE id = ? AND bundle_id = ? UNION ALL SELECT f.id, f.parent_id, c.depth + 1 FROM files f JOIN chain c ON f.id = c.parent_id) SELECT s.utc_offset_minutes, s.clock_skew_ms FROM event_time_settings s LEFT JOIN chain c ON c.id = s.file_id WHERE s.bundle_id = ? AND (s.file_id IS NULL OR c.id IS NOT NULL) ORDER BY s.file_id IS NULL, c.depth LIMIT 1",
+    )
+    .bind(file_id)
+    .bind(bundle_id)
+    .bind(bundle_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(AppError::Database)
+}
+
+/// Replaces or removes the setting declared on `file_id` (`None` for the
+/// whole bundle) and recomputes the target shift of every file.
+pub async fn save_setting(
+    pool: &SqlitePool,
+    bundle_id: &str,
+    file_id: Option<i64>,
+    setting: Option<EventTimeSetting>,
+    user_id: &str,
+) -> Result<(), AppError> {
+    let mut tx = pool.begin().await.map_err(AppError::Database)?;
+    sqlx::query("DELETE FROM event_time_settings WHERE bundle_id = ? AND file_id IS ?")
+        .bind(bundle_id)
+        .bind(file_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+    if let Some(setting) = setting {
+        sqlx::query("INSERT INTO event_time_settings (bundle_id, file_id, utc_offset_minutes, clock_skew_ms, updated_by_user_id) VALUES (?, ?, ?, ?, ?)")
+            .bind(bundle_id).bind(file_id).bind(setting.utc_offset_minutes).bind(setting.clock_skew_ms).bind(user_id)
+            .execute(&mut *tx).await.map_err(AppError::Database)?;
+    }
+    refresh_file_shifts(&mut tx, bundle_id).await?;
+    tx.commit().await.map_err(AppError::Database)
+}
+
+/// Recomputes every file's target shift and flags the bundle for re-keying
+/// when any of them changed.
+async fn refresh_file_shifts(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    bundle_id: &str,
+) -> Result<(), AppError> {
+    let settings: HashMap<Option<i64>, EventTimeSetting> = sqlx::query_as::<_, ScopedSetting>(
+        "SELECT file_id, utc_offset_minutes, clock_skew_ms FROM event_time_settings WHERE bundle_id = ?",
+    )
+    .bind(bundle_id)
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(AppError::Database)?
+    .into_iter()
+    .map(|row| {
+        (
+            row.file_id,
+            EventTimeSetting {
+                utc_offset_minutes: row.utc_offset_minutes,
+                clock_skew_ms: row.clock_skew_ms,
+            },
+        )
+    })
+    .collect();
+    let files: Vec<(i64, Option<i64>, i64)> =
+        sqlx::query_as("SELECT id, parent_id, event_time_shift_ms FROM files WHERE bundle_id = ?")
+            .bind(bundle_id)
+            .fetch_all(&mut **tx)
+            .await
+            .map_err(AppError::Database)?;
+    let parents: HashMap<i64, Option<i64>> = files
+        .iter()
+        .map(|(id, parent_id, _)| (*id, *parent_id))
+        .collect();
+
+    let mut changed = false;
+    for (file_id, _, current_shift) in &files {
+        let mut cursor = Some(*file_id);
+        let mut resolved = None;
+        while let Some(id) = cursor {
+            if let Some(setting) = settings.get(&Some(id)) {
+                resolved = Some(*setting);
+                break;
+            }
+            cursor = parents.get(&id).copied().flatten();
+        }
+        let shift = resolved
+            .or_else(|| settings.get(&None).copied())
+            .map_or(0, |setting| setting.shift_ms());
+        if shift != *current_shift {
+            sqlx::query("UPDATE files SET event_time_shift_ms = ? WHERE id = ?")
+                .bind(shift)
+                .bind(file_id)
+                .execute(&mut **tx)
+                .await
+                .map_err(AppError::Database)?;
+            changed = true;
+        }
+    }
+    if changed {
+        sqlx::query("UPDATE bundles SET event_time_rekey_pending = 1 WHERE id = ?")
+            .bind(bundle_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(AppError::Database)?;
+    }
+    Ok(())
+}
+
+/// Rewrites stored event-time keys whose shift no longer matches their
+/// file's target shift. Returns the number of segments updated.
+pub async fn rekey_pending_segments(pool: &SqlitePool) -> Result<u64, AppError> {
+    let bundles: Vec<String> =
+        sqlx::query_scalar("SELECT id FROM bundles WHERE event_time_rekey_pending = 1")
+            .fetch_all(pool)
+            .await
+            .map_err(AppError::Database)?;
+    let mut updated = 0_u64;
+    for bundle_id in bundles {
+        loop {
+            let mut tx = pool.begin().await.map_err(AppError::Database)?;
+            let segments: Vec<StaleSegment> = sqlx::query_as(
+                "SELECT ls.id, ls.event_time_start_ms, ls.event_time_end_ms, ls.event_time_shift_ms AS applied_shift_ms, f.event_time_shift_ms AS target_shift_ms FROM log_segments ls JOIN files f ON f.id = ls.file_id WHERE ls.bundle_id = ? AND ls.event_time_shift_ms != f.event_time_shift_ms ORDER BY ls.id LIMIT ?",
+            )
+            .bind(&bundle_id)
+            .bind(REKEY_BATCH_SIZE)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(AppError::Database)?;
+            if segments.is_empty() {
+                // Mined template times were keyed with the old shift.
+                sqlx::query("UPDATE bundles SET event_time_rekey_pending = 0, template_state = CASE template_state WHEN 'NONE' THEN 'NONE' ELSE 'PENDING' END WHERE id = ? AND NOT EXISTS (SELECT 1 FROM log_segments ls JOIN files f ON f.id = ls.file_id WHERE ls.bundle_id = bundles.id AND ls.event_time_shift_ms != f.event_time_shift_ms)")
+                    .bind(&bundle_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(AppError::Database)?;
+                tx.commit().await.map_err(AppError::Database)?;
+                break;
+            }
+            for segment in segments {
+                let delta = segment.target_shift_ms - segment.applied_shift_ms;
+                let shift = |key: Option<i64>| {
+                    key.and_then(|key| wall_clock::shift_comparison_key(key, delta))
+                };
+                sqlx::query("UPDATE log_segments SET event_time_start_ms = ?, event_time_end_ms = ?, event_time_shift_ms = ? WHERE id = ? AND event_time_shift_ms = ?")
+                    .bind(shift(segment.event_time_start_ms))
+                    .bind(shift(segment.event_time_end_ms))
+                    .bind(segment.target_shift_ms)
+                    .bind(segment.id)
+                    .bind(segment.applied_shift_ms)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(AppError::Database)?;
+                updated += 1;
+            }
+            tx.commit().await.map_err(AppError::Database)?;
+        }
+    }
+    Ok(updated)
+}
+
+pub fn spawn_event_time_rekey(pool: SqlitePool) -> tokio::task::JoinHandle<()> {
+    crate::spawn_periodic_job(
+        "event-time-rekey",
+        std::time::Duration::ZERO,
+        std::time::Duration::from_secs(60),
+        move || {
+            let pool = pool.clone();
+            async move {
+                rekey_pending_segments(&pool)
+                    .await
+                    .map(|_| ())
+                    .map_err(|error| error.to_string())
+            }
+        },
+    )
+}
+
+/// Starts a re-key pass right away instead of waiting for the periodic job.
+pub fn trigger_rekey(pool: SqlitePool) {
+    tokio::spawn(async move {
+        if let Err(error) = rekey_pending_segments(&pool).await {
+            tracing::warn!(%error, "event time re-key failed; periodic job will retry");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EventTimeSetting;
+    use crate::services::wall_clock;
+
+    #[test]
+    fn shift_converts_local_wall_clock_to_corrected_utc() {
+        let setting = EventTimeSetting {
+            utc_offset_minutes: 480,
+            clock_skew_ms: 1_500,
+        };
+        let local = wall_clock::parse("2026-01-01 07:00:00.000").unwrap();
+        let key = wall_clock::comparison_key(local).unwrap();
+        let shifted = wall_clock::shift_comparison_key(key, setting.shift_ms()).unwrap();
+
+        assert_eq!(
+            wall_clock::from_comparison_key(shifted),
+            wall_clock::parse("2025-12-31 23:00:01.500")
+        );
+        assert_eq!(
+            wall_clock::shift_comparison_key(shifted, -setting.shift_ms()),
+            Some(key)
+        );
+        assert!(
+            EventTimeSetting {
+                utc_offset_minutes: 900,
+                clock_skew_ms: 0
+            }
+            .validate()
+            .is_err()
+        );
+    }
+}
@@ -68,10 +68,17 @@ mod tests {
         .fetch_one(&mut *tx)
         .await
         .expect("insert retained file");
+        let retained_content_id: i64 = sqlx::query_scalar(
+            "INSERT INTO log_segment_contents (content) VALUES ('retained content') RETURNING id",
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .expect("insert retained content");
         sqlx::query(
-            "INSERT INTO log_segments (bundle_id, file_id, content) VALUES ('delete-bundle', ?, 'retained content')",
+            "INSERT INTO log_segments (bundle_id, file_id, content_id) VALUES ('delete-bundle', ?, ?)",
         )
         .bind(retained_id)
+        .bind(retained_content_id)
         .execute(&mut *tx)
         .await
         .expect("insert retained segment");
@@ -93,11 +100,18 @@ mod tests {
             .execute(&mut *tx)
             .await
             .expect("insert line offset");
+            let content_id: i64 = sqlx::query_scalar(
+                "INSERT INTO log_segment_contents (content) VALUES (?) RETURNING id",
+            )
+            .bind(format!("cascade-child-{index}"))
+            .fetch_one(&mut *tx)
+            .await
+            .expect("insert child content");
             sqlx::query(
-                "INSERT INTO log_segments (bundle_id, file_id, content) VALUES ('delete-bundle', ?, ?)",
+                "INSERT INTO log_segments (bundle_id, file_id, content_id) VALUES ('delete-bundle', ?, ?)",
             )
             .bind(file_id)
-            .bind(format!("cascade-child-{index}"))
+            .bind(content_id)
             .execute(&mut *tx)
             .await
             .expect("insert child segment");
@@ -113,12 +127,13 @@ mod tests {
             .await
             .expect("delete file tree");
 
-        let counts: (i64, i64, i64, i64, i64) = sqlx::query_as(
+        let counts: (i64, i64, i64, i64, i64, i64) = sqlx::query_as(
             r#"
             SELECT
                 (SELECT COUNT(*) FROM files WHERE bundle_id = 'delete-bundle'),
                 (SELECT COUNT(*) FROM log_line_offsets),
                 (SELECT COUNT(*) FROM log_segments),
+                (SELECT COUNT(*) FROM log_segment_contents),
                 (SELECT COUNT(*) FROM log_segments_fts),
                 (SELECT content_size_bytes FROM bundles WHERE id = 'delete-bundle')
             "#,
@@ -126,6 +141,6 @@ mod tests {
         .fetch_one(&pool)
         .await
         .expect("count cascaded rows");
-        assert_eq!(counts, (1, 0, 1, 1, 7));
+        assert_eq!(counts, (1, 0, 1, 1, 1, 7));
     }
 }
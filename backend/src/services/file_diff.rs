@@ -0,0 +1,404 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tokio::io::AsyncReadExt;
+
+use crate::{
+    blob_store::BlobStore,
+    config::ApiConfig,
+    error::AppError,
+    ingest::FileEncoding,
+    repositories::files::{FileRow, ensure_text_preview, resolve_file_path},
+};
+
+/// Edit distance beyond which the rest of the files is reported as one
+/// replaced block, bounding the diff's time and memory.
+pub const MAX_DIFF_EDIT_DISTANCE: usize = 2_000;
+
+static TIMESTAMP: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?x)
+        \d{4}[-/.]\d{1,2}[-/.]\d{1,2}
+            (?:[T\s]+\d{1,2}:\d{2}(?::\d{2})?(?:[.,]\d+)?)?
+            (?:\s?(?:Z|[+-]\d{2}:?\d{2}))?
+        | \b\d{1,2}:\d{2}:\d{2}(?:[.,]\d+)?
+        | \b(?:Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Oct|Nov|Dec)\s+\d{1,2}\b
+        | ^\[\s*\d+\.\d+\]",
+    )
+    .expect("valid timestamp regex")
+});
+static NUMBER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"0[xX][0-9a-fA-F]+|\d+(?:\.\d+)?").expect("valid number regex"));
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiffOptions {
+    /// Compares lines with dates, times and dmesg uptimes masked.
+    pub ignore_timestamps: bool,
+    /// Compares lines with decimal and hex numbers masked.
+    pub ignore_numbers: bool,
+}
+
+impl DiffOptions {
+    /// The form of `line` used for comparison; the original is displayed.
+    pub fn normalize<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        let mut line = Cow::Borrowed(line);
+        if self.ignore_timestamps
+            && let Cow::Owned(masked) = TIMESTAMP.replace_all(&line, "<time>")
+        {
+            line = Cow::Owned(masked);
+        }
+        if self.ignore_numbers
+            && let Cow::Owned(masked) = NUMBER.replace_all(&line, "<num>")
+        {
+            line = Cow::Owned(masked);
+        }
+        line
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffRowKind {
+    Equal,
+    Delete,
+    Insert,
+    Replace,
+}
+
+impl DiffRowKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Equal => "equal",
+            Self::Delete => "delete",
+            Self::Insert => "insert",
+            Self::Replace => "replace",
+        }
+    }
+}
+
+/// One side-by-side row: 0-based line indexes of the left and right files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffRow {
+    pub kind: DiffRowKind,
+    pub left: Option<usize>,
+    pub right: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+pub struct LineDiff {
+    pub rows: Vec<DiffRow>,
+    /// Whether the edit distance passed [`MAX_DIFF_EDIT_DISTANCE`] and part
+    /// of the files was reported as a single replaced block.
+    pub approximate: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Diffs two files line by line with Myers' algorithm, pairing deleted and
+/// inserted lines between equal runs into `replace` rows.
+pub fn diff_lines(
+    left: &[&str],
+    right: &[&str],
+    options: DiffOptions,
+    max_edit_distance: usize,
+) -> LineDiff {
+    let mut ids: HashMap<String, u32> = HashMap::new();
+    let mut intern = |line: &str| -> u32 {
+        let normalized = options.normalize(line);
+        if let Some(id) = ids.get(normalized.as_ref()) {
+            return *id;
+        }
+        let id = ids.len() as u32;
+        ids.insert(normalized.into_owned(), id);
+        id
+    };
+    let left_ids: Vec<u32> = left.iter().map(|line| intern(line)).collect();
+    let right_ids: Vec<u32> = right.iter().map(|line| intern(line)).collect();
+
+    let prefix = left_ids
+        .iter()
+        .zip(&right_ids)
+        .take_while(|(left, right)| left == right)
+        .count();
+    let suffix = left_ids[prefix..]
+        .iter()
+        .rev()
+        .zip(right_ids[prefix..].iter().rev())
+        .take_while(|(left, right)| left == right)
+        .count();
+    let middle_left = &left_ids[prefix..left_ids.len() - suffix];
+    let middle_right = &right_ids[prefix..right_ids.len() - suffix];
+
+    let mut edits = vec![Edit::Equal; prefix];
+    let approximate = match myers(middle_left, middle_right, max_edit_distance) {
+        Some(middle) => {
+            edits.extend(middle);
+            false
+        }
+        None => {
+            edits.extend(std::iter::repeat_n(Edit::Delete, middle_left.len()));
+            edits.extend(std::iter::repeat_n(Edit::Insert, middle_right.len()));
+            true
+        }
+    };
+    edits.extend(std::iter::repeat_n(Edit::Equal, suffix));
+    LineDiff {
+        rows: pair_rows(&edits),
+        approximate,
+    }
+}
+
+/// Shortest edit script from `left` to `right`, or `None` when it needs
+/// more than `max_distance` insertions and deletions.
+fn myers(left: &[u32], right: &[u32], max_distance: usize) -> Option<Vec<Edit>> {
+    let (n, m) = (left.len() as isize, right.len() as isize);
+    let max = (n + m) as usize;
+    if max == 0 {
+        return Some(Vec::new());
+    }
+    let offset = max as isize + 1;
+    let mut v = vec![0_isize; 2 * max + 3];
+    // trace[d] holds the furthest x of diagonals -d..=d after step d.
+    let mut trace: Vec<Vec<u32>> = Vec::new();
+    let mut found = None;
+    for d in 0..=max.min(max_distance) as isize {
+        for k in (-d..=d).step_by(2) {
+            let index = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && left[x as usize] == right[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+            if x >= n && y >= m {
+                found = Some(d);
+            }
+        }
+        trace.push((-d..=d).map(|k| v[(offset + k) as usize] as u32).collect());
+        if found.is_some() {
+            break;
+        }
+    }
+    let distance = found?;
+
+    let mut edits = Vec::with_capacity((n + m) as usize);
+    let (mut x, mut y) = (n, m);
+    for d in (1..=distance).rev() {
+        let previous = &trace[(d - 1) as usize];
+        let furthest = |k: isize| previous[(k + d - 1) as usize] as isize;
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && furthest(k - 1) < furthest(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = furthest(previous_k);
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            edits.push(Edit::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        edits.push(if x == previous_x {
+            Edit::Insert
+        } else {
+            Edit::Delete
+        });
+        (x, y) = (previous_x, previous_y);
+    }
+    edits.extend(std::iter::repeat_n(Edit::Equal, x as usize));
+    edits.reverse();
+    Some(edits)
+}
+
+fn pair_rows(edits: &[Edit]) -> Vec<DiffRow> {
+    let mut rows = Vec::with_capacity(edits.len());
+    let (mut left, mut right) = (0, 0);
+    let mut index = 0;
+    while index < edits.len() {
+        if edits[index] == Edit::Equal {
+            rows.push(DiffRow {
+                kind: DiffRowKind::Equal,
+                left: Some(left),
+                right: Some(right),
+            });
+            left += 1;
+            right += 1;
+            index += 1;
+            continue;
+        }
+        let end = edits[index..]
+            .iter()
+            .position(|edit| *edit == Edit::Equal)
+            .map_or(edits.len(), |length| index + length);
+        let deleted = edits[index..end]
+            .iter()
+            .filter(|edit| **edit == Edit::Delete)
+            .count();
+        let inserted = end - index - deleted;
+        for offset in 0..deleted.max(inserted) {
+            let (kind, left_line, right_line) = match (offset < deleted, offset < inserted) {
+                (true, true) => (
+                    DiffRowKind::Replace,
+                    Some(left + offset),
+                    Some(right + offset),
+                ),
+                (true, false) => (DiffRowKind::Delete, Some(left + offset), None),
+                _ => (DiffRowKind::Insert, None, Some(right + offset)),
+            };
+            rows.push(DiffRow {
+                kind,
+                left: left_line,
+                right: right_line,
+            });
+        }
+        left += deleted;
+        right += inserted;
+        index = end;
+    }
+    rows
+}
+
+/// Reads a whole text file for diffing, refusing files over
+/// `api.max_diff_file_size`. Lines split like the line reader's, so the
+/// indexes match `get_file_lines` line numbers.
+pub async fn read_diff_lines(
+    record: &FileRow,
+    blob_store: &dyn BlobStore,
+    api: &ApiConfig,
+) -> Result<Vec<String>, AppError> {
+    if record.is_dir {
+        return Err(AppError::BadRequest("cannot read directory content".into()));
+    }
+    ensure_text_preview(record)?;
+    let disk_path = resolve_file_path(record, blob_store).await?;
+    let size = tokio::fs::metadata(&disk_path)
+        .await
+        .map_err(AppError::Io)?
+        .len();
+    if size > api.max_diff_file_size {
+        return Err(AppError::public(
+            actix_web::http::StatusCode::PAYLOAD_TOO_LARGE,
+            "DIFF_FILE_TOO_LARGE",
+            "文件超过对比大小限制",
+        ));
+    }
+    let mut buffer = Vec::with_capacity(size as usize);
+    tokio::fs::File::open(&disk_path)
+        .await
+        .map_err(AppError::Io)?
+        .read_to_end(&mut buffer)
+        .await
+        .map_err(AppError::Io)?;
+    let content = FileEncoding::from_meta(record.meta.as_deref()).decode_lossy(&buffer);
+    Ok(content.lines().map(str::to_string).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DiffOptions, DiffRow, DiffRowKind, diff_lines};
+
+    fn kinds(
+        left: &str,
+        right: &str,
+        options: DiffOptions,
+    ) -> Vec<(DiffRowKind, Option<usize>, Option<usize>)> {
+        let left: Vec<&str> = left.lines().collect();
+        let right: Vec<&str> = right.lines().collect();
+        diff_lines(&left, &right, options, 100)
+            .rows
+            .into_iter()
+            .map(|DiffRow { kind, left, right }| (kind, left, right))
+            .collect()
+    }
+
+    #[test]
+    fn pairs_changed_lines_and_keeps_unchanged_context() {
+        use DiffRowKind::{Delete, Equal, Insert, Replace};
+        assert_eq!(
+            kinds("a\nb\nc\nd\n", "a\nx\nc\nd\ne\n", DiffOptions::default()),
+            vec![
+                (Equal, Some(0), Some(0)),
+                (Replace, Some(1), Some(1)),
+                (Equal, Some(2), Some(2)),
+                (Equal, Some(3), Some(3)),
+                (Insert, None, Some(4)),
+            ]
+        );
+        assert_eq!(
+            kinds("a\nb\nc\n", "c\n", DiffOptions::default()),
+            vec![
+                (Delete, Some(0), None),
+                (Delete, Some(1), None),
+                (Equal, Some(2), Some(0)),
+            ]
+        );
+        assert!(kinds("", "", DiffOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn finds_a_shortest_script_in_the_middle() {
+        let left = ["p", "a", "b", "c", "a", "b", "b", "a", "s"];
+        let right = ["p", "c", "b", "a", "b", "a", "c", "s"];
+        let diff = diff_lines(&left, &right, DiffOptions::default(), 100);
+        let equal = diff
+            .rows
+            .iter()
+            .filter(|row| row.kind == DiffRowKind::Equal)
+            .inspect(|row| assert_eq!(left[row.left.unwrap()], right[row.right.unwrap()]))
+            .count();
+        // The classic example's LCS has 4 lines, plus the shared ends.
+        assert_eq!(equal, 6);
+        assert!(!diff.approximate);
+    }
+
+    #[test]
+    fn masks_timestamps_and_numbers_when_asked() {
+        let left = "2026-03-01 10:00:00.123 boot took 35 ms\n[   12.5] usb 1-1: new device\n";
+        let right = "2026-03-02T11:22:33Z boot took 35 ms\n[  901.0] usb 1-1: new device\n";
+        let timestamps = DiffOptions {
+            ignore_timestamps: true,
+            ..DiffOptions::default()
+        };
+        assert!(
+            kinds(left, right, timestamps)
+                .iter()
+                .all(|(kind, _, _)| *kind == DiffRowKind::Equal)
+        );
+        assert!(
+            kinds(left, right, DiffOptions::default())
+                .iter()
+                .all(|(kind, _, _)| *kind == DiffRowKind::Replace)
+        );
+        let numbers = DiffOptions {
+            ignore_numbers: true,
+            ..DiffOptions::default()
+        };
+        assert_eq!(
+            kinds("pid 41 at 0x7f00\n", "pid 97 at 0xbeef\n", numbers)[0].0,
+            DiffRowKind::Equal
+        );
+    }
+
+    #[test]
+    fn falls_back_to_one_replaced_block_past_the_distance_cap() {
+        let left: Vec<String> = (0..50).map(|index| format!("left {index}")).collect();
+        let right: Vec<String> = (0..50).map(|index| format!("right {index}")).collect();
+        let left: Vec<&str> = left.iter().map(String::as_str).collect();
+        let right: Vec<&str> = right.iter().map(String::as_str).collect();
+        let diff = diff_lines(&left, &right, DiffOptions::default(), 10);
+        assert!(diff.approximate);
+        assert_eq!(diff.rows.len(), 50);
+        assert!(diff.rows.iter().all(|row| row.kind == DiffRowKind::Replace));
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::NaiveDateTime;
 use serde::Serialize;
 use serde_json::json;
 use tokio::{
@@ -9,11 +10,14 @@ use crate::{
     blob_store::BlobStore,
     config::ApiConfig,
     error::AppError,
-    ingest::{decode_log_line, read_line_bytes_limited},
+    ingest::{EventTimeParser, FileEncoding, load_timestamp_context, read_encoded_line_limited},
     repositories::files::{FileRow, ensure_text_preview, nearest_line_offset, resolve_file_path},
-    services::json_size::{
-        JsonLinePageDecision, RESPONSE_TRUNCATED_LINE_MARKER, fit_json_line_to_page,
-        json_string_encoded_len,
+    services::{
+        json_size::{
+            JsonLinePageDecision, RESPONSE_TRUNCATED_LINE_MARKER, fit_json_line_to_page,
+            json_string_encoded_len,
+        },
+        wall_clock,
     },
 };
 
@@ -24,15 +28,15 @@ pub struct FileLinesResponse {
     line_count: Option<i64>,
     start: i64,
     limit: i64,
-    next_start: Option<i64>,
-    lines: Vec<FileLine>,
+    pub(crate) next_start: Option<i64>,
+    pub(crate) lines: Vec<FileLine>,
 }
 
 #[derive(Serialize)]
-struct FileLine {
-    line_number: i64,
-    content: String,
-    truncated: bool,
+pub(crate) struct FileLine {
+    pub(crate) line_number: i64,
+    pub(crate) content: String,
+    pub(crate) truncated: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     original_length: Option<usize>,
 }
@@ -60,7 +64,7 @@ pub async fn read_file_preview(
         .await
         .map_err(AppError::Io)?;
 
-    let preview = String::from_utf8_lossy(&buffer).to_string();
+    let preview = FileEncoding::from_meta(record.meta.as_deref()).decode_lossy(&buffer);
     let truncated = size_bytes > api.file_preview_size;
 
     Ok(json!({
@@ -97,13 +101,16 @@ pub async fn read_file_lines(
         });
     }
 
+    let encoding = FileEncoding::from_meta(record.meta.as_deref());
     let (base_line, byte_offset) = nearest_line_offset(pool, record.id, start).await?;
     let disk_path = resolve_file_path(record, blob_store).await?;
 
     let mut file = File::open(&disk_path).await.map_err(AppError::Io)?;
-    file.seek(std::io::SeekFrom::Start(byte_offset as u64))
-        .await
-        .map_err(AppError::Io)?;
+    file.seek(std::io::SeekFrom::Start(
+        (byte_offset as u64).max(encoding.bom_bytes),
+    ))
+    .await
+    .map_err(AppError::Io)?;
     let mut reader = BufReader::new(file);
     let mut current_line = base_line;
     let end_line = start.saturating_add(limit);
@@ -114,8 +121,9 @@ pub async fn read_file_lines(
     let mut stopped_by_page_bytes = false;
 
     while current_line < end_line {
-        let Some((_read, original_length, truncated)) = read_line_bytes_limited(
+        let Some((_read, original_length, truncated)) = read_encoded_line_limited(
             &mut reader,
+            encoding.name,
             &mut buffer,
             usize::try_from(api.max_preview_line_size).map_err(|_| {
                 AppError::Config(
@@ -130,7 +138,7 @@ pub async fn read_file_lines(
         };
 
         if current_line >= start {
-            let decoded_content = decode_log_line(&buffer, truncated);
+            let decoded_content = encoding.decode_line(&buffer, truncated);
             let fixed_line_bytes = 256_u64;
             let decision = fit_json_line_to_page(
                 &decoded_content,
@@ -197,6 +205,206 @@ pub async fn read_file_lines(
     })
 }
 
+pub(crate) struct SourceLine {
+    pub(crate) content: String,
+    pub(crate) truncated: bool,
+}
+
+/// Sequential line reader behind one merge input.
+pub(crate) trait LineSource {
+    async fn next_line(&mut self) -> Result<Option<SourceLine>, AppError>;
+}
+
+/// Reads a stored text file from a given line onwards.
+pub(crate) struct FileLineSource {
+    reader: BufReader<File>,
+    encoding: FileEncoding,
+    buffer: Vec<u8>,
+    max_line_size: usize,
+}
+
+impl FileLineSource {
+    pub(crate) async fn open(
+        pool: &sqlx::SqlitePool,
+        record: &FileRow,
+        blob_store: &dyn BlobStore,
+        api: &ApiConfig,
+        start: i64,
+    ) -> Result<Self, AppError> {
+        if record.is_dir {
+            return Err(AppError::BadRequest("cannot read directory content".into()));
+        }
+        ensure_text_preview(record)?;
+        let encoding = FileEncoding::from_meta(record.meta.as_deref());
+        let (base_line, byte_offset) = nearest_line_offset(pool, record.id, start).await?;
+        let disk_path = resolve_file_path(record, blob_store).await?;
+        let mut file = File::open(&disk_path).await.map_err(AppError::Io)?;
+        file.seek(std::io::SeekFrom::Start(
+            (byte_offset as u64).max(encoding.bom_bytes),
+        ))
+        .await
+        .map_err(AppError::Io)?;
+        let mut source = Self {
+            reader: BufReader::new(file),
+            encoding,
+            buffer: Vec::new(),
+            max_line_size: usize::try_from(api.max_preview_line_size).map_err(|_| {
+                AppError::Config(
+                    "RAIN_API_MAX_PREVIEW_LINE_SIZE cannot be represented on this platform".into(),
+                )
+            })?,
+        };
+        for _ in base_line..start {
+            if source.read_raw().await?.is_none() {
+                break;
+            }
+        }
+        Ok(source)
+    }
+
+    async fn read_raw(&mut self) -> Result<Option<bool>, AppError> {
+        Ok(read_encoded_line_limited(
+            &mut self.reader,
+            self.encoding.name,
+            &mut self.buffer,
+            self.max_line_size,
+        )
+        .await
+        .map_err(AppError::Io)?
+        .map(|(_, _, truncated)| truncated))
+    }
+}
+
+impl LineSource for FileLineSource {
+    async fn next_line(&mut self) -> Result<Option<SourceLine>, AppError> {
+        Ok(self.read_raw().await?.map(|truncated| SourceLine {
+            content: self.encoding.decode_line(&self.buffer, truncated),
+            truncated,
+        }))
+    }
+}
+
+/// Finds the first line whose own event time is at or after `at`, or the line
+/// count when the file ends earlier. `at` is read in the file's current
+/// shifted time, the event times searches and line pages show, so it is
+/// compared with shifted keys as is.
+///
+/// The first segment whose end time reaches `at` is looked up in SQL, which
+/// finds it even when the log jumps back in time; only that segment is then
+/// read line by line.
+pub async fn find_line_at_time(
+    pool: &sqlx::SqlitePool,
+    bundle_id: &str,
+    record: &FileRow,
+    blob_store: &dyn BlobStore,
+    api: &ApiConfig,
+    at: NaiveDateTime,
+) -> Result<i64, AppError> {
+    if record.is_dir {
+        return Err(AppError::BadRequest("cannot read directory content".into()));
+    }
+    ensure_text_preview(record)?;
+    let at_key = wall_clock::comparison_key(at)
+        .ok_or_else(|| AppError::BadRequest("at 超出支持的时间范围".into()))?;
+    let file_shift_ms: i64 =
+        sqlx::query_scalar("SELECT event_time_shift_ms FROM files WHERE id = ?")
+            .bind(record.id)
+            .fetch_one(pool)
+            .await
+            .map_err(AppError::Database)?;
+    // Segments not yet re-keyed still carry the shift they were indexed with.
+    let segment_shifts: Vec<i64> = sqlx::query_scalar(
+        "SELECT DISTINCT ls.event_time_shift_ms FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE ls.file_id = ? AND lc.line_offset IS NOT NULL AND lc.line_end IS NOT NULL AND ls.event_time_end_ms IS NOT NULL",
+    )
+    .bind(record.id)
+    .fetch_all(pool)
+    .await
+    .map_err(AppError::Database)?;
+    if segment_shifts.is_empty() {
+        return Err(AppError::public(
+            actix_web::http::StatusCode::BAD_REQUEST,
+            "FILE_NOT_TIMED",
+            "该文件没有可识别的事件时间，无法按时间定位",
+        ));
+    }
+
+    let mut first_offset: Option<i64> = None;
+    for segment_shift_ms in &segment_shifts {
+        let Some(threshold) =
+            wall_clock::shift_comparison_key(at_key, segment_shift_ms - file_shift_ms)
+        else {
+            continue;
+        };
+        let offset: Option<i64> = sqlx::query_scalar(
+            "SELECT MIN(lc.line_offset) FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE ls.file_id = ? AND ls.event_time_shift_ms = ? AND lc.line_offset IS NOT NULL AND lc.line_end IS NOT NULL AND ls.event_time_end_ms >= ?",
+        )
+        .bind(record.id)
+        .bind(segment_shift_ms)
+        .bind(threshold)
+        .fetch_one(pool)
+        .await
+        .map_err(AppError::Database)?;
+        first_offset = match (first_offset, offset) {
+            (Some(first), Some(offset)) => Some(first.min(offset)),
+            (first, offset) => first.or(offset),
+        };
+    }
+    let Some(first_offset) = first_offset else {
+        let last_line: Option<i64> = sqlx::query_scalar(
+            "SELECT MAX(lc.line_end) + 1 FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE ls.file_id = ? AND lc.line_end IS NOT NULL",
+        )
+        .bind(record.id)
+        .fetch_one(pool)
+        .await
+        .map_err(AppError::Database)?;
+        let last_line = last_line.unwrap_or(0);
+        return Ok(record.line_count.unwrap_or(last_line).max(last_line));
+    };
+    let segment_end: i64 = sqlx::query_scalar(
+        "SELECT lc.line_end FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE ls.file_id = ? AND lc.line_offset = ? AND lc.line_end IS NOT NULL ORDER BY ls.id LIMIT 1",
+    )
+    .bind(record.id)
+    .bind(first_offset)
+    .fetch_one(pool)
+    .await
+    .map_err(AppError::Database)?;
+    // The latest time before the segment, for lines that only carry a time of day.
+    let earlier_ends: Vec<(i64, Option<i64>)> = sqlx::query_as(
+        "SELECT ls.event_time_shift_ms, MAX(ls.event_time_end_ms) FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE ls.file_id = ? AND lc.line_offset < ? AND ls.event_time_end_ms IS NOT NULL GROUP BY ls.event_time_shift_ms",
+    )
+    .bind(record.id)
+    .bind(first_offset)
+    .fetch_all(pool)
+    .await
+    .map_err(AppError::Database)?;
+    let carried = earlier_ends
+        .into_iter()
+        .filter_map(|(segment_shift_ms, end)| {
+            wall_clock::shift_comparison_key(end?, file_shift_ms - segment_shift_ms)
+        })
+        .max()
+        .and_then(|key| wall_clock::shift_comparison_key(key, -file_shift_ms))
+        .and_then(wall_clock::from_comparison_key);
+
+    let (recognizers, reference) = load_timestamp_context(pool, bundle_id).await?;
+    let mut parser = EventTimeParser::new(&recognizers, reference)
+        .with_shift_ms(file_shift_ms)
+        .with_last(carried);
+    let mut lines = FileLineSource::open(pool, record, blob_store, api, first_offset).await?;
+    for line_number in first_offset..=segment_end {
+        let Some(line) = lines.next_line().await? else {
+            break;
+        };
+        if parser
+            .parse_ms(&line.content)
+            .is_some_and(|key| key >= at_key)
+        {
+            return Ok(line_number);
+        }
+    }
+    Ok(first_offset)
+}
+
 #[cfg(test)]
 mod tests {
     use sqlx::sqlite::SqlitePoolOptions;
@@ -245,15 +453,17 @@ mod tests {
             .connect("sqlite::memory:")
             .await
             .unwrap();
-        sqlx::query(
+        for statement in [
+            "CREATE TABLE files (id INTEGER PRIMARY KEY, blob_index_id INTEGER)",
             "CREATE TABLE log_line_offsets (file_id INTEGER, line_number INTEGER, byte_offset INTEGER)",
-        )
-        .execute(&pool)
-        .await
-        .unwrap();
-        let mut api = ApiConfig::default();
-        api.max_line_page_bytes = max_page_bytes;
-        api.max_preview_line_size = 1024;
+        ] {
+            sqlx::query(statement).execute(&pool).await.unwrap();
+        }
+        let api = ApiConfig {
+            max_line_page_bytes: max_page_bytes,
+            max_preview_line_size: 1024,
+            ..ApiConfig::default()
+        };
         let response = read_file_lines(&pool, &record, &store, &api, 0, 3)
             .await
             .unwrap();
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use sqlx::FromRow;
+
+use crate::{
+    AppState,
+    blob_store::BlobStore,
+    config::IndexingConfig,
+    error::AppError,
+    ingest::{FileEncoding, reindex_text_file},
+    repositories::files::{fetch_file, resolve_file_path},
+};
+
+static REINDEXING: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// What re-indexing needs from [`AppState`], so routes can start a pass.
+#[derive(Clone)]
+pub struct FileReindexing {
+    pub pool: sqlx::SqlitePool,
+    pub blob_store: Arc<dyn BlobStore>,
+    pub indexing: IndexingConfig,
+}
+
+impl FileReindexing {
+    pub fn from_state(state: &AppState) -> Self {
+        Self {
+            pool: state.db.pool.clone(),
+            blob_store: state.storage.blob_store.clone(),
+            indexing: state.limits.indexing.clone(),
+        }
+    }
+}
+
+#[derive(FromRow)]
+struct PendingFile {
+    id: i64,
+    bundle_id: String,
+    meta: Option<String>,
+}
+
+/// Re-indexes every file queued as `REINDEXING`, one at a time, with the
+/// encoding recorded in its metadata. Returns the number of files re-indexed.
+pub async fn reindex_pending_files(reindexing: &FileReindexing) -> Result<u64, AppError> {
+    let _reindexing = REINDEXING.lock().await;
+    let pool = &reindexing.pool;
+    let mut reindexed = 0_u64;
+    loop {
+        let pending: Option<PendingFile> = sqlx::query_as(
+            "SELECT f.id, f.bundle_id, f.meta FROM files f JOIN bundles b ON b.id = f.bundle_id WHERE f.status = 'REINDEXING' AND b.status = 'READY' AND b.deleted_at IS NULL ORDER BY f.id LIMIT 1",
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(AppError::Database)?;
+        let Some(file) = pending else {
+            return Ok(reindexed);
+        };
+        match reindex_file(reindexing, &file).await {
+            Ok(()) => {
+                // A newer override changes the metadata and keeps the file queued.
+                sqlx::query(
+                    "UPDATE files SET status = 'READY' WHERE id = ? AND status = 'REINDEXING' AND meta IS ?",
+                )
+                .bind(file.id)
+                .bind(&file.meta)
+                .execute(pool)
+                .await
+                .map_err(AppError::Database)?;
+                reindexed += 1;
+            }
+            Err(error) => {
+                tracing::warn!(file_id = file.id, %error, "file re-indexing failed");
+                sqlx::query(
+                    "UPDATE files SET status = 'REINDEX_FAILED' WHERE id = ? AND status = 'REINDEXING'",
+                )
+                .bind(file.id)
+                .execute(pool)
+                .await
+                .map_err(AppError::Database)?;
+            }
+        }
+    }
+}
+
+async fn reindex_file(reindexing: &FileReindexing, file: &PendingFile) -> Result<(), AppError> {
+    let record = fetch_file(&reindexing.pool, &file.bundle_id, file.id).await?;
+    let disk_path = resolve_file_path(&record, reindexing.blob_store.as_ref()).await?;
+    reindex_text_file(
+        &reindexing.pool,
+        &file.bundle_id,
+        file.id,
+        &disk_path,
+        FileEncoding::from_meta(file.meta.as_deref()),
+        &reindexing.indexing,
+    )
+    .await
+}
+
+pub fn spawn_file_reindexing(reindexing: FileReindexing) -> tokio::task::JoinHandle<()> {
+    crate::spawn_periodic_job(
+        "file-reindexing",
+        std::time::Duration::ZERO,
+        std::time::Duration::from_secs(60),
+        move || {
+            let reindexing = reindexing.clone();
+            async move {
+                reindex_pending_files(&reindexing)
+                    .await
+                    .map(|_| ())
+                    .map_err(|error| error.to_string())
+            }
+        },
+    )
+}
+
+/// Starts a re-indexing pass right away instead of waiting for the periodic job.
+pub fn trigger_reindexing(reindexing: FileReindexing) {
+    tokio::spawn(async move {
+        if let Err(error) = reindex_pending_files(&reindexing).await {
+            tracing::warn!(%error, "file re-indexing failed; periodic job will retry");
+        }
+    });
+}
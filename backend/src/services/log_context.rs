@@ -0,0 +1,357 @@
+use std::collections::{HashMap, hash_map::Entry};
+
+use crate::{
+    blob_store::BlobStore,
+    config::ApiConfig,
+    error::AppError,
+    models::logs::{LogContextLine, LogSearchContext, LogSearchHit},
+    repositories::files::{FileRow, fetch_file},
+    services::{file_reader::read_file_lines, json_size::json_string_encoded_len},
+};
+
+/// Most context lines accepted on either side of a search hit.
+pub const MAX_SEARCH_CONTEXT_LINES: i64 = 100;
+/// Per-line overhead charged against the page byte budget, matching the
+/// fixed cost `read_file_lines` reserves for each line object.
+const CONTEXT_LINE_OVERHEAD_BYTES: u64 = 256;
+
+/// File lines a search hit covers, used to centre its context window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HitAnchor {
+    file_id: i64,
+    first_line: i64,
+    last_line: i64,
+    /// Blank lines are not indexed, so when a chunk dropped some the match
+    /// line is only known as the n-th non-blank line after `first_line`;
+    /// holds the chunk's last line and that index.
+    unresolved: Option<(i64, usize)>,
+}
+
+impl HitAnchor {
+    /// A multi-line event with exact file line bounds.
+    pub fn event(file_id: i64, line_start: i64, line_end: i64) -> Self {
+        Self {
+            file_id,
+            first_line: line_start,
+            last_line: line_end,
+            unresolved: None,
+        }
+    }
+
+    /// The line holding byte `position` of a segment starting at file line
+    /// `offset` and ending at `line_end`.
+    pub fn at_position(
+        file_id: i64,
+        content: &str,
+        position: usize,
+        offset: Option<i64>,
+        line_end: Option<i64>,
+    ) -> Option<Self> {
+        let offset = offset?;
+        let position = position.min(content.len());
+        let index = content.as_bytes()[..position]
+            .iter()
+            .filter(|byte| **byte == b'\n')
+            .count();
+        let indexed_lines = content.bytes().filter(|byte| *byte == b'\n').count() as i64 + 1;
+        match line_end {
+            Some(line_end) if line_end - offset + 1 != indexed_lines => Some(Self {
+                file_id,
+                first_line: offset,
+                last_line: offset,
+                unresolved: Some((line_end, index)),
+            }),
+            _ => Some(Self::event(
+                file_id,
+                offset + index as i64,
+                offset + index as i64,
+            )),
+        }
+    }
+}
+
+/// Context blocks shared by the hits whose windows overlap.
+pub struct SearchContext {
+    pub contexts: Vec<LogSearchContext>,
+    pub truncated: bool,
+}
+
+/// Reads `before`/`after` lines around every anchored hit, merging windows
+/// that overlap or touch within a file into one block, and records each
+/// hit's block in `context_index`. Blocks are read in hit order until the
+/// `max_line_page_bytes` budget is spent; later hits then get no context.
+pub async fn attach_context(
+    pool: &sqlx::SqlitePool,
+    blob_store: &dyn BlobStore,
+    api: &ApiConfig,
+    hits: &mut [LogSearchHit],
+    anchors: &[Option<HitAnchor>],
+    (before, after): (i64, i64),
+) -> Result<SearchContext, AppError> {
+    let mut records: HashMap<i64, FileRow> = HashMap::new();
+    let mut windows: Vec<ContextWindow> = Vec::new();
+    for (hit_index, anchor) in anchors.iter().enumerate() {
+        let Some(anchor) = anchor else {
+            continue;
+        };
+        let record = match records.entry(anchor.file_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => match fetch_context_file(pool, anchor.file_id).await? {
+                Some(record) => entry.insert(record),
+                None => continue,
+            },
+        };
+        let (first, last) = resolve_anchor(pool, blob_store, api, record, anchor).await?;
+        windows.push(ContextWindow {
+            file_id: anchor.file_id,
+            start: first.saturating_sub(before).max(0),
+            end: last.saturating_add(after),
+            hits: vec![hit_index],
+        });
+    }
+    let mut windows = merge_windows(windows);
+    windows.sort_by_key(|window| window.hits[0]);
+
+    let mut remaining = api.max_line_page_bytes;
+    let mut truncated = false;
+    let mut contexts = Vec::new();
+    for window in windows {
+        let record = &records[&window.file_id];
+        let limit = (window.end - window.start + 1).min(api.max_line_page_size);
+        let budget = ApiConfig {
+            max_line_page_bytes: remaining,
+            ..api.clone()
+        };
+        let page =
+            match read_file_lines(pool, record, blob_store, &budget, window.start, limit).await {
+                Ok(page) => page,
+                Err(AppError::PublicApi {
+                    code: "LINE_PAGE_TOO_LARGE",
+                    ..
+                }) => {
+                    truncated = true;
+                    break;
+                }
+                Err(error) => return Err(error),
+            };
+        let lines: Vec<LogContextLine> = page
+            .lines
+            .into_iter()
+            .map(|line| LogContextLine {
+                line_number: line.line_number,
+                content: line.content,
+                truncated: line.truncated,
+            })
+            .collect();
+        let spent = lines.iter().fold(0_u64, |total, line| {
+            total
+                .saturating_add(json_string_encoded_len(&line.content))
+                .saturating_add(CONTEXT_LINE_OVERHEAD_BYTES)
+        });
+        remaining = remaining.saturating_sub(spent);
+        let window_truncated = ((lines.len() as i64) < limit && page.next_start.is_some())
+            || limit < window.end - window.start + 1;
+        truncated |= window_truncated;
+        let context_index = contexts.len();
+        for hit_index in &window.hits {
+            hits[*hit_index].context_index = Some(context_index);
+        }
+        contexts.push(LogSearchContext {
+            file_id: window.file_id.to_string(),
+            start: window.start,
+            lines,
+            truncated: window_truncated,
+        });
+        if remaining == 0 {
+            truncated = true;
+            break;
+        }
+    }
+
+    Ok(SearchContext {
+        contexts,
+        truncated,
+    })
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct ContextWindow {
+    file_id: i64,
+    start: i64,
+    end: i64,
+    hits: Vec<usize>,
+}
+
+/// Joins windows of the same file that overlap or are adjacent.
+fn merge_windows(mut windows: Vec<ContextWindow>) -> Vec<ContextWindow> {
+    windows.sort_by_key(|window| (window.file_id, window.start));
+    let mut merged: Vec<ContextWindow> = Vec::with_capacity(windows.len());
+    for window in windows {
+        match merged.last_mut() {
+            Some(last)
+                if last.file_id == window.file_id && window.start <= last.end.saturating_add(1) =>
+            {
+                last.end = last.end.max(window.end);
+                last.hits.extend(window.hits);
+                last.hits.sort_unstable();
+            }
+            _ => merged.push(window),
+        }
+    }
+    merged
+}
+
+async fn fetch_context_file(
+    pool: &sqlx::SqlitePool,
+    file_id: i64,
+) -> Result<Option<FileRow>, AppError> {
+    let bundle_id: Option<String> = sqlx::query_scalar("SELECT bundle_id FROM files WHERE id = ?")
+        .bind(file_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(AppError::Database)?;
+    match bundle_id {
+        Some(bundle_id) => fetch_file(pool, &bundle_id, file_id).await.map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Exact first and last line of an anchor, scanning the chunk's lines for
+/// the n-th non-blank one when blank lines were skipped at index time.
+async fn resolve_anchor(
+    pool: &sqlx::SqlitePool,
+    blob_store: &dyn BlobStore,
+    api: &ApiConfig,
+    record: &FileRow,
+    anchor: &HitAnchor,
+) -> Result<(i64, i64), AppError> {
+    let Some((line_end, index)) = anchor.unresolved else {
+        return Ok((anchor.first_line, anchor.last_line));
+    };
+    let line = indexed_line_numbers(pool, blob_store, api, record, anchor.first_line, line_end)
+        .await?
+        .and_then(|lines| lines.get(index).copied())
+        .unwrap_or(anchor.first_line + index as i64);
+    Ok((line, line))
+}
+
+/// File line numbers of the non-blank lines of a segment spanning
+/// `offset..=line_end`, i.e. of each line in its indexed content. `None`
+/// when the lines do not fit one line page.
+pub async fn indexed_line_numbers(
+    pool: &sqlx::SqlitePool,
+    blob_store: &dyn BlobStore,
+    api: &ApiConfig,
+    record: &FileRow,
+    offset: i64,
+    line_end: i64,
+) -> Result<Option<Vec<i64>>, AppError> {
+    let limit = (line_end - offset + 1).clamp(1, api.max_line_page_size);
+    let page = match read_file_lines(pool, record, blob_store, api, offset, limit).await {
+        Ok(page) => page,
+        Err(AppError::PublicApi {
+            code: "LINE_PAGE_TOO_LARGE",
+            ..
+        }) => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    Ok(Some(
+        page.lines
+            .iter()
+            .filter(|line| !line.content.trim().is_empty())
+            .map(|line| line.line_number)
+            .collect(),
+    ))
+}
+
+/// Location of an indexed segment within its file.
+pub struct IndexedSegment<'a> {
+    pub bundle_id: &'a str,
+    pub file_id: i64,
+    pub offset: Option<i64>,
+    pub line_end: Option<i64>,
+    pub content: &'a str,
+}
+
+/// File line numbers of each indexed line of `segment` when blank lines were
+/// skipped at index time; `None` when line `offset + index` is exact. File
+/// records are cached in `files` across calls.
+pub async fn segment_line_numbers(
+    pool: &sqlx::SqlitePool,
+    blob_store: &dyn BlobStore,
+    api: &ApiConfig,
+    files: &mut HashMap<i64, Option<FileRow>>,
+    segment: IndexedSegment<'_>,
+) -> Result<Option<Vec<i64>>, AppError> {
+    let (Some(offset), Some(line_end)) = (segment.offset, segment.line_end) else {
+        return Ok(None);
+    };
+    let indexed_lines = segment.content.matches('\n').count() as i64 + 1;
+    if line_end - offset + 1 == indexed_lines {
+        return Ok(None);
+    }
+    let record = match files.entry(segment.file_id) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => entry.insert(Some(
+            fetch_file(pool, segment.bundle_id, segment.file_id).await?,
+        )),
+    };
+    let Some(record) = record else {
+        return Ok(None);
+    };
+    indexed_line_numbers(pool, blob_store, api, record, offset, line_end).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ContextWindow, HitAnchor, merge_windows};
+
+    fn window(file_id: i64, start: i64, end: i64, hit: usize) -> ContextWindow {
+        ContextWindow {
+            file_id,
+            start,
+            end,
+            hits: vec![hit],
+        }
+    }
+
+    #[test]
+    fn merges_overlapping_and_adjacent_windows_per_file() {
+        let merged = merge_windows(vec![
+            window(1, 20, 24, 2),
+            window(1, 0, 10, 0),
+            window(2, 5, 9, 3),
+            window(1, 8, 19, 1),
+            window(1, 26, 30, 4),
+        ]);
+        assert_eq!(
+            merged,
+            vec![
+                ContextWindow {
+                    file_id: 1,
+                    start: 0,
+                    end: 24,
+                    hits: vec![0, 1, 2],
+                },
+                window(1, 26, 30, 4),
+                window(2, 5, 9, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn anchors_the_matched_line_unless_blank_lines_were_skipped() {
+        let content = "first\nsecond match\nthird";
+        let position = content.find("match").unwrap();
+        assert_eq!(
+            HitAnchor::at_position(7, content, position, Some(10), Some(12)),
+            Some(HitAnchor::event(7, 11, 11))
+        );
+        let gapped = HitAnchor::at_position(7, content, position, Some(10), Some(14)).unwrap();
+        assert_eq!(gapped.unresolved, Some((14, 1)));
+        assert_eq!(
+            HitAnchor::at_position(7, content, position, None, None),
+            None
+        );
+    }
+}
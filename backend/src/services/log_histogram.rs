@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+
+use crate::{
+    models::logs::{LogHistogramResponse, LogHistogramSeries},
+    services::wall_clock,
+};
+
+/// Bucket count aimed for when the caller does not ask for one.
+pub const DEFAULT_HISTOGRAM_BUCKETS: usize = 60;
+/// Upper bound on requested buckets, keeping responses chart sized.
+pub const MAX_HISTOGRAM_BUCKETS: usize = 500;
+/// Series beyond this many, by hit count, are folded into the totals only.
+pub const MAX_HISTOGRAM_SERIES: usize = 50;
+
+const SECOND_MS: i64 = 1_000;
+const MINUTE_MS: i64 = 60 * SECOND_MS;
+const HOUR_MS: i64 = 60 * MINUTE_MS;
+const DAY_MS: i64 = 24 * HOUR_MS;
+/// Round bucket widths to pick from, smallest first.
+const BUCKET_WIDTHS_MS: &[i64] = &[
+    SECOND_MS,
+    2 * SECOND_MS,
+    5 * SECOND_MS,
+    10 * SECOND_MS,
+    15 * SECOND_MS,
+    30 * SECOND_MS,
+    MINUTE_MS,
+    2 * MINUTE_MS,
+    5 * MINUTE_MS,
+    10 * MINUTE_MS,
+    15 * MINUTE_MS,
+    30 * MINUTE_MS,
+    HOUR_MS,
+    2 * HOUR_MS,
+    3 * HOUR_MS,
+    6 * HOUR_MS,
+    12 * HOUR_MS,
+    DAY_MS,
+    2 * DAY_MS,
+    7 * DAY_MS,
+    14 * DAY_MS,
+    30 * DAY_MS,
+];
+
+/// Hit counts per fixed-width wall-clock bucket, split into series.
+///
+/// Segment times are wall-clock comparison keys, which are not linear, so
+/// every key is decoded and bucketed on calendar arithmetic instead.
+#[derive(Debug)]
+pub struct TimeHistogram {
+    origin_ms: i64,
+    bucket_ms: i64,
+    bucket_count: usize,
+    totals: Vec<u64>,
+    series: HashMap<String, SeriesCounts>,
+}
+
+#[derive(Debug)]
+struct SeriesCounts {
+    bundle_hash: String,
+    file_id: Option<String>,
+    path: Option<String>,
+    counts: Vec<u64>,
+    total: u64,
+}
+
+/// Identity of the series a hit is counted in.
+#[derive(Debug, Clone, Copy)]
+pub struct SeriesKey<'a> {
+    pub bundle_hash: &'a str,
+    pub file: Option<(i64, &'a str)>,
+}
+
+impl TimeHistogram {
+    /// Buckets covering `start..=end`, sized to the roundest width that needs
+    /// at most `target_buckets` of them and aligned to that width.
+    pub fn new(start: NaiveDateTime, end: NaiveDateTime, target_buckets: usize) -> Self {
+        let start_ms = linear_ms(start);
+        let end_ms = linear_ms(end).max(start_ms);
+        let target = target_buckets.clamp(1, MAX_HISTOGRAM_BUCKETS) as i64;
+        let bucket_ms = bucket_width((end_ms - start_ms) / target + 1);
+        let origin_ms = start_ms.div_euclid(bucket_ms) * bucket_ms;
+        let bucket_count = ((end_ms - origin_ms) / bucket_ms + 1) as usize;
+        Self {
+            origin_ms,
+            bucket_ms,
+            bucket_count,
+            totals: vec![0; bucket_count],
+            series: HashMap::new(),
+        }
+    }
+
+    /// Counts a hit at the comparison key `time_key`. Times outside the
+    /// buckets land in the nearest edge bucket; undecodable keys are dropped.
+    pub fn record(&mut self, key: SeriesKey<'_>, time_key: i64) {
+        let Some(time) = wall_clock::from_comparison_key(time_key) else {
+            return;
+        };
+        let offset = (linear_ms(time) - self.origin_ms).div_euclid(self.bucket_ms);
+        let index = offset.clamp(0, self.bucket_count as i64 - 1) as usize;
+        self.totals[index] += 1;
+        let series_id = match key.file {
+            Some((file_id, _)) => format!("{}/{file_id}", key.bundle_hash),
+            None => key.bundle_hash.to_string(),
+        };
+        let bucket_count = self.bucket_count;
+        let series = self
+            .series
+            .entry(series_id)
+            .or_insert_with(|| SeriesCounts {
+                bundle_hash: key.bundle_hash.to_string(),
+                file_id: key.file.map(|(file_id, _)| file_id.to_string()),
+                path: key.file.map(|(_, path)| path.to_string()),
+                counts: vec![0; bucket_count],
+                total: 0,
+            });
+        series.counts[index] += 1;
+        series.total += 1;
+    }
+
+    pub fn into_response(self, untimed_hits: u64) -> LogHistogramResponse {
+        let buckets = (0..self.bucket_count)
+            .filter_map(|index| {
+                let start = self.origin_ms + index as i64 * self.bucket_ms;
+                chrono::DateTime::from_timestamp_millis(start)
+                    .map(|time| wall_clock::format(time.naive_utc()))
+            })
+            .collect();
+        let mut series: Vec<LogHistogramSeries> = self
+            .series
+            .into_values()
+            .map(|series| LogHistogramSeries {
+                bundle_hash: series.bundle_hash,
+                file_id: series.file_id,
+                path: series.path,
+                counts: series.counts,
+                total: series.total,
+            })
+            .collect();
+        series.sort_by(|left, right| {
+            right
+                .total
+                .cmp(&left.total)
+                .then_with(|| left.bundle_hash.cmp(&right.bundle_hash))
+                .then_with(|| left.path.cmp(&right.path))
+        });
+        let series_truncated = series.len() > MAX_HISTOGRAM_SERIES;
+        series.truncate(MAX_HISTOGRAM_SERIES);
+        LogHistogramResponse {
+            total: self.totals.iter().sum(),
+            bucket_ms: Some(self.bucket_ms),
+            buckets,
+            totals: self.totals,
+            series,
+            series_truncated,
+            untimed_hits,
+            truncated: false,
+        }
+    }
+}
+
+/// Milliseconds on a linear scale; the value is only used for bucket
+/// arithmetic and carries no timezone meaning.
+fn linear_ms(time: NaiveDateTime) -> i64 {
+    time.and_utc().timestamp_millis()
+}
+
+fn bucket_width(min_width_ms: i64) -> i64 {
+    if let Some(width) = BUCKET_WIDTHS_MS
+        .iter()
+        .copied()
+        .find(|width| *width >= min_width_ms)
+    {
+        return width;
+    }
+    let month = 30 * DAY_MS;
+    (min_width_ms + month - 1) / month * month
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SeriesKey, TimeHistogram, bucket_width};
+    use crate::services::wall_clock;
+
+    fn key(value: &str) -> i64 {
+        wall_clock::comparison_key(wall_clock::parse(value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn picks_round_widths_that_fit_the_target_bucket_count() {
+        assert_eq!(bucket_width(1), 1_000);
+        assert_eq!(bucket_width(61_000), 120_000);
+        assert_eq!(bucket_width(25 * 3_600_000), 2 * 86_400_000);
+        assert_eq!(bucket_width(31 * 86_400_000), 60 * 86_400_000);
+
+        let day = TimeHistogram::new(
+            wall_clock::parse("2026-03-01 00:00:00").unwrap(),
+            wall_clock::parse("2026-03-01 23:59:59").unwrap(),
+            24,
+        );
+        assert_eq!(day.bucket_ms, 3_600_000);
+        assert_eq!(day.bucket_count, 24);
+    }
+
+    #[test]
+    fn buckets_hits_across_calendar_boundaries_per_series() {
+        let mut histogram = TimeHistogram::new(
+            wall_clock::parse("2026-02-28 23:58:30").unwrap(),
+            wall_clock::parse("2026-03-01 00:01:10").unwrap(),
+            4,
+        );
+        let bundle = SeriesKey {
+            bundle_hash: "a",
+            file: None,
+        };
+        let other = SeriesKey {
+            bundle_hash: "b",
+            file: None,
+        };
+        histogram.record(bundle, key("2026-02-28 23:58:30"));
+        histogram.record(bundle, key("2026-02-28 23:59:59.999"));
+        histogram.record(other, key("2026-03-01 00:00:00"));
+        histogram.record(other, key("2026-03-01 00:01:10"));
+        histogram.record(other, key("2026-03-02 00:00:00"));
+
+        let response = histogram.into_response(2);
+        assert_eq!(response.bucket_ms, Some(60_000));
+        assert_eq!(
+            response.buckets,
+            [
+                "2026-02-28 23:58:00",
+                "2026-02-28 23:59:00",
+                "2026-03-01 00:00:00",
+                "2026-03-01 00:01:00"
+            ]
+        );
+        assert_eq!(response.totals, [1, 1, 1, 2]);
+        assert_eq!(response.total, 5);
+        assert_eq!(response.series[0].bundle_hash, "b");
+        assert_eq!(response.series[0].counts, [0, 0, 1, 2]);
+        assert_eq!(response.series[1].counts, [1, 1, 0, 0]);
+        assert_eq!(response.untimed_hits, 2);
+    }
+}
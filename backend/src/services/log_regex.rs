@@ -0,0 +1,237 @@
+use actix_web::http::StatusCode;
+use regex::{Regex, RegexBuilder};
+use regex_syntax::hir::{Hir, HirKind};
+
+use crate::{error::AppError, models::logs::LogSearchCapture};
+
+/// Longest pattern accepted by the regex search mode.
+pub const MAX_REGEX_PATTERN_CHARS: usize = 1_024;
+/// Compiled program size cap, so a pathological pattern fails fast instead
+/// of consuming the scan budget building its automaton.
+const REGEX_SIZE_LIMIT: usize = 4 * 1024 * 1024;
+/// The trigram index can only look up fragments of at least three characters.
+const MIN_INDEXED_FRAGMENT_CHARS: usize = 3;
+
+/// A user supplied search regex plus the FTS5 query that prefilters candidate
+/// segments by the literal fragments every match must contain.
+#[derive(Debug)]
+pub struct LogRegex {
+    regex: Regex,
+    prefilter: Option<String>,
+}
+
+/// First match inside a segment: its byte offset, text and capture groups.
+#[derive(Debug)]
+pub struct RegexMatch {
+    pub position: usize,
+    pub text: String,
+    pub captures: Vec<LogSearchCapture>,
+}
+
+/// Literal fragments a match is guaranteed to contain.
+#[derive(Debug, PartialEq)]
+enum Required {
+    Anything,
+    Literal(String),
+    All(Vec<Required>),
+    Any(Vec<Required>),
+}
+
+impl LogRegex {
+    pub fn compile(pattern: &str) -> Result<Self, AppError> {
+        if pattern.chars().count() > MAX_REGEX_PATTERN_CHARS {
+            return Err(AppError::api(
+                StatusCode::BAD_REQUEST,
+                "REGEX_TOO_LONG",
+                "正则表达式过长",
+            ));
+        }
+        let invalid = |error: &dyn std::fmt::Display| {
+            AppError::public(
+                StatusCode::BAD_REQUEST,
+                "INVALID_REGEX",
+                format!("正则表达式无效：{error}"),
+            )
+        };
+        let hir = regex_syntax::ParserBuilder::new()
+            .multi_line(true)
+            .build()
+            .parse(pattern)
+            .map_err(|error| invalid(&error))?;
+        let regex = RegexBuilder::new(pattern)
+            .multi_line(true)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .build()
+            .map_err(|error| invalid(&error))?;
+        Ok(Self {
+            regex,
+            prefilter: fts_query(&required(&hir)),
+        })
+    }
+
+    /// FTS5 `MATCH` expression for the trigram index, or `None` when the
+    /// pattern has no indexable literal and every segment must be verified.
+    pub fn prefilter(&self) -> Option<&str> {
+        self.prefilter.as_deref()
+    }
+
+    /// Byte offsets where each non-overlapping match in `content` starts.
+    pub fn match_starts<'c>(&'c self, content: &'c str) -> impl Iterator<Item = usize> + 'c {
+        self.regex.find_iter(content).map(|found| found.start())
+    }
+
+    pub fn first_match(&self, content: &str) -> Option<RegexMatch> {
+        let captures = self.regex.captures(content)?;
+        let whole = captures.get(0)?;
+        let groups = self
+            .regex
+            .capture_names()
+            .enumerate()
+            .skip(1)
+            .map(|(index, name)| LogSearchCapture {
+                index,
+                name: name.map(str::to_string),
+                value: captures.get(index).map(|group| group.as_str().to_string()),
+            })
+            .collect();
+        Some(RegexMatch {
+            position: whole.start(),
+            text: whole.as_str().to_string(),
+            captures: groups,
+        })
+    }
+}
+
+fn required(hir: &Hir) -> Required {
+    match hir.kind() {
+        HirKind::Literal(literal) => match std::str::from_utf8(&literal.0) {
+            Ok(text) => Required::Literal(text.to_string()),
+            Err(_) => Required::Anything,
+        },
+        HirKind::Capture(capture) => required(&capture.sub),
+        HirKind::Repetition(repetition) if repetition.min == 1 && repetition.max == Some(1) => {
+            required(&repetition.sub)
+        }
+        // A repeated fragment must occur, but not joined to its neighbours as
+        // written: `o+` next to literals may match `oo`, so it stands alone.
+        HirKind::Repetition(repetition) if repetition.min > 0 => {
+            Required::All(vec![required(&repetition.sub)])
+        }
+        HirKind::Concat(parts) => {
+            // Adjacent literals join into one longer, more selective fragment.
+            let mut all = Vec::new();
+            let mut run = String::new();
+            for part in parts {
+                match required(part) {
+                    Required::Literal(text) => run.push_str(&text),
+                    other => {
+                        if !run.is_empty() {
+                            all.push(Required::Literal(std::mem::take(&mut run)));
+                        }
+                        all.push(other);
+                    }
+                }
+            }
+            if !run.is_empty() {
+                all.push(Required::Literal(run));
+            }
+            Required::All(all)
+        }
+        HirKind::Alternation(branches) => Required::Any(branches.iter().map(required).collect()),
+        _ => Required::Anything,
+    }
+}
+
+fn fts_query(required: &Required) -> Option<String> {
+    match required {
+        Required::Anything => None,
+        Required::Literal(text) => (text.chars().count() >= MIN_INDEXED_FRAGMENT_CHARS)
+            .then(|| format!("\"{}\"", text.replace('"', "\"\""))),
+        Required::All(parts) => {
+            let terms: Vec<String> = parts.iter().filter_map(fts_query).collect();
+            match terms.len() {
+                0 => None,
+                1 => terms.into_iter().next(),
+                _ => Some(format!("({})", terms.join(" AND "))),
+            }
+        }
+        Required::Any(branches) => {
+            // One branch without an indexable fragment can match anything.
+            let terms = branches
+                .iter()
+                .map(fts_query)
+                .collect::<Option<Vec<String>>>()?;
+            match terms.len() {
+                0 => None,
+                1 => terms.into_iter().next(),
+                _ => Some(format!("({})", terms.join(" OR "))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LogRegex;
+
+    #[test]
+    fn extracts_required_literal_fragments_for_the_trigram_prefilter() {
+        let prefilter = |pattern: &str| {
+            LogRegex::compile(pattern)
+                .unwrap()
+                .prefilter()
+                .map(str::to_string)
+        };
+        assert_eq!(
+            prefilter(r"timeout after \d+ms").as_deref(),
+            Some("\"timeout after \"")
+        );
+        assert_eq!(
+            prefilter(r"pid=(\d+) exited with code \d+").as_deref(),
+            Some("(\"pid=\" AND \" exited with code \")")
+        );
+        assert_eq!(
+            prefilter("(connection reset|broken pipe)").as_deref(),
+            Some("(\"connection reset\" OR \"broken pipe\")")
+        );
+        assert_eq!(prefilter(r"(reset|ab)\s+x"), None);
+        assert_eq!(prefilter(r"(?i)error"), None);
+        assert_eq!(prefilter(r"\d{3}-\d{4}"), None);
+    }
+
+    #[test]
+    fn repeated_fragments_do_not_join_adjacent_literals() {
+        let prefilter = |pattern: &str| {
+            LogRegex::compile(pattern)
+                .unwrap()
+                .prefilter()
+                .map(str::to_string)
+        };
+        assert_eq!(prefilter("timeo+ut").as_deref(), Some("\"time\""));
+        assert_eq!(
+            prefilter("retry(abc){2}done").as_deref(),
+            Some("(\"retry\" AND \"abc\" AND \"done\")")
+        );
+        assert_eq!(
+            prefilter("conn(ection){1,3} lost").as_deref(),
+            Some("(\"conn\" AND \"ection\" AND \" lost\")")
+        );
+    }
+
+    #[test]
+    fn reports_the_first_match_with_named_and_numbered_groups() {
+        let regex = LogRegex::compile(r"pid=(?P<pid>\d+) code=(\d+)?").unwrap();
+        let found = regex
+            .first_match("boot\nworker pid=42 code= done\npid=7 code=1")
+            .unwrap();
+
+        assert_eq!(found.position, "boot\nworker ".len());
+        assert_eq!(found.text, "pid=42 code=");
+        assert_eq!(found.captures.len(), 2);
+        assert_eq!(found.captures[0].name.as_deref(), Some("pid"));
+        assert_eq!(found.captures[0].value.as_deref(), Some("42"));
+        assert_eq!(found.captures[1].index, 2);
+        assert_eq!(found.captures[1].value, None);
+        assert!(LogRegex::compile("(unclosed").is_err());
+    }
+}
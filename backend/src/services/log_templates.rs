@@ -0,0 +1,746 @@
+use std::{collections::HashMap, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, QueryBuilder, Sqlite};
+
+use crate::{
+    AppState,
+    blob_store::BlobStore,
+    config::ApiConfig,
+    error::AppError,
+    ingest::{EventTimeParser, load_timestamp_context},
+    log_expression::{LineEvent, LineEventTracker},
+    services::log_context::{IndexedSegment, segment_line_numbers},
+};
+
+/// Token standing for the variable parts of a template.
+pub const WILDCARD: &str = "<*>";
+/// Most templates kept per bundle; further novel lines join their closest
+/// template or, without one, are left unclustered.
+pub const MAX_TEMPLATES_PER_BUNDLE: usize = 5_000;
+/// Tokens compared per line; longer lines end in a single `<*>`.
+const MAX_TEMPLATE_TOKENS: usize = 64;
+/// Leading literal tokens that, with the token count, pick a template group.
+const GROUP_KEY_TOKENS: usize = 2;
+/// Templates compared per group before lines are forced into the closest one.
+const MAX_GROUP_TEMPLATES: usize = 100;
+const MAX_TEMPLATE_EXAMPLES: usize = 3;
+const MAX_EXAMPLE_CHARS: usize = 1_000;
+const MINING_SCAN_BATCH_SIZE: i64 = 200;
+const TEMPLATE_INSERT_BATCH_SIZE: usize = 100;
+
+static MINING: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Splits a line into template tokens. Tokens holding digits are ids,
+/// counters or timestamps and become `<*>`, keeping any `key=` prefix.
+pub fn template_tokens(line: &str) -> Vec<String> {
+    let mut words = line.split_whitespace();
+    let mut tokens: Vec<String> = words
+        .by_ref()
+        .take(MAX_TEMPLATE_TOKENS)
+        .map(mask_token)
+        .collect();
+    if words.next().is_some() {
+        tokens.push(WILDCARD.to_string());
+    }
+    tokens
+}
+
+fn mask_token(token: &str) -> String {
+    if !token.bytes().any(|byte| byte.is_ascii_digit()) {
+        return token.to_string();
+    }
+    match token.split_once('=') {
+        Some((key, _)) if !key.is_empty() && !key.bytes().any(|byte| byte.is_ascii_digit()) => {
+            format!("{key}={WILDCARD}")
+        }
+        _ => WILDCARD.to_string(),
+    }
+}
+
+/// Whether a line's tokens fit `template`: the same token count, equal
+/// wherever the template is not `<*>`.
+pub fn matches_template(template: &[&str], tokens: &[String]) -> bool {
+    template.len() == tokens.len()
+        && template
+            .iter()
+            .zip(tokens)
+            .all(|(expected, token)| *expected == WILDCARD || *expected == token)
+}
+
+/// Stable identifier of a template's text across bundles and re-mining.
+pub fn template_key(template: &str) -> String {
+    Sha256::digest(template.as_bytes())[..8]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// A line recorded as an example of its template.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateExample {
+    pub file_id: i64,
+    pub line_number: Option<i64>,
+    pub content: String,
+}
+
+#[derive(Debug)]
+pub struct TemplateCluster {
+    pub tokens: Vec<String>,
+    pub line_count: i64,
+    /// [`crate::ingest::LogLevel::bit`]s of the events its lines belong to.
+    pub level_mask: i64,
+    pub first_event_time: Option<i64>,
+    pub last_event_time: Option<i64>,
+    pub examples: Vec<TemplateExample>,
+}
+
+impl TemplateCluster {
+    pub fn template(&self) -> String {
+        self.tokens.join(" ")
+    }
+
+    fn similarity(&self, tokens: &[String]) -> (usize, usize) {
+        let equal = self
+            .tokens
+            .iter()
+            .zip(tokens)
+            .filter(|(expected, token)| expected == token)
+            .count();
+        let wildcards = self
+            .tokens
+            .iter()
+            .filter(|token| *token == WILDCARD)
+            .count();
+        (equal, wildcards)
+    }
+
+    fn absorb(&mut self, tokens: &[String], event: LineEvent) {
+        for (expected, token) in self.tokens.iter_mut().zip(tokens) {
+            if expected != token {
+                *expected = WILDCARD.to_string();
+            }
+        }
+        self.line_count += 1;
+        if let Some(level) = event.level {
+            self.level_mask |= level.bit();
+        }
+        if let Some(time) = event.time_key {
+            self.first_event_time =
+                Some(self.first_event_time.map_or(time, |first| first.min(time)));
+            self.last_event_time = Some(self.last_event_time.map_or(time, |last| last.max(time)));
+        }
+    }
+}
+
+/// Online template clustering in the spirit of Drain: lines are grouped by
+/// token count and leading literal tokens, then join the most similar
+/// template of their group when at least 40% of the tokens agree, turning
+/// the tokens that differ into `<*>`.
+#[derive(Default)]
+pub struct TemplateMiner {
+    clusters: Vec<TemplateCluster>,
+    groups: HashMap<(usize, Vec<String>), Vec<usize>>,
+    pub unclustered_lines: u64,
+}
+
+impl TemplateMiner {
+    /// Adds a line, returning the index of the template it joined.
+    pub fn add(&mut self, tokens: Vec<String>, event: LineEvent) -> Option<usize> {
+        if tokens.is_empty() {
+            return None;
+        }
+        let key = (
+            tokens.len(),
+            tokens
+                .iter()
+                .filter(|token| *token != WILDCARD)
+                .take(GROUP_KEY_TOKENS)
+                .cloned()
+                .collect(),
+        );
+        let group = self.groups.entry(key).or_default();
+        let best = group
+            .iter()
+            .map(|index| (*index, self.clusters[*index].similarity(&tokens)))
+            .max_by_key(|(_, similarity)| *similarity);
+        let similar = best.filter(|(_, (equal, _))| equal * 5 >= tokens.len() * 2);
+        let full =
+            group.len() >= MAX_GROUP_TEMPLATES || self.clusters.len() >= MAX_TEMPLATES_PER_BUNDLE;
+        let index = match (similar, best) {
+            (Some((index, _)), _) => index,
+            (None, Some((index, _))) if full => index,
+            (None, _) if full => {
+                self.unclustered_lines += 1;
+                return None;
+            }
+            (None, _) => {
+                group.push(self.clusters.len());
+                self.clusters.push(TemplateCluster {
+                    tokens: tokens.clone(),
+                    line_count: 0,
+                    level_mask: 0,
+                    first_event_time: None,
+                    last_event_time: None,
+                    examples: Vec::new(),
+                });
+                self.clusters.len() - 1
+            }
+        };
+        self.clusters[index].absorb(&tokens, event);
+        Some(index)
+    }
+
+    pub fn wants_example(&self, index: usize) -> bool {
+        self.clusters[index].examples.len() < MAX_TEMPLATE_EXAMPLES
+    }
+
+    pub fn add_example(
+        &mut self,
+        index: usize,
+        file_id: i64,
+        line_number: Option<i64>,
+        line: &str,
+    ) {
+        self.clusters[index].examples.push(TemplateExample {
+            file_id,
+            line_number,
+            content: line.chars().take(MAX_EXAMPLE_CHARS).collect(),
+        });
+    }
+
+    pub fn into_clusters(self) -> Vec<TemplateCluster> {
+        self.clusters
+    }
+}
+
+/// Marks bundles of an issue whose templates were never mined, e.g. ones
+/// uploaded before mining existed. Returns how many were queued.
+pub async fn queue_unmined_bundles(
+    pool: &sqlx::SqlitePool,
+    issue_code: &str,
+) -> Result<u64, AppError> {
+    sqlx::query(
+        "UPDATE bundles SET template_state = 'PENDING' WHERE issue_code = ? AND status = 'READY' AND deleted_at IS NULL AND template_state = 'NONE'",
+    )
+    .bind(issue_code)
+    .execute(pool)
+    .await
+    .map(|result| result.rows_affected())
+    .map_err(AppError::Database)
+}
+
+/// What mining needs from [`AppState`], so upload jobs can start a pass.
+#[derive(Clone)]
+pub struct TemplateMining {
+    pub pool: sqlx::SqlitePool,
+    pub blob_store: Arc<dyn BlobStore>,
+    pub api: ApiConfig,
+}
+
+impl TemplateMining {
+    pub fn from_state(state: &AppState) -> Self {
+        Self {
+            pool: state.db.pool.clone(),
+            blob_store: state.storage.blob_store.clone(),
+            api: state.limits.api.clone(),
+        }
+    }
+}
+
+/// Mines every bundle queued as `PENDING`, one at a time. Returns the number
+/// of bundles mined.
+pub async fn mine_pending_bundles(mining: &TemplateMining) -> Result<u64, AppError> {
+    let _mining = MINING.lock().await;
+    let mut mined = 0_u64;
+    loop {
+        let bundle_id: Option<String> = sqlx::query_scalar(
+            "SELECT id FROM bundles WHERE template_state = 'PENDING' AND status = 'READY' AND deleted_at IS NULL ORDER BY created_at, id LIMIT 1",
+        )
+        .fetch_optional(&mining.pool)
+        .await
+        .map_err(AppError::Database)?;
+        let Some(bundle_id) = bundle_id else {
+            return Ok(mined);
+        };
+        match mine_bundle(mining, &bundle_id).await {
+            Ok(()) => mined += 1,
+            Err(error) => {
+                tracing::warn!(bundle_id, %error, "log template mining failed");
+                sqlx::query(
+                    "UPDATE bundles SET template_state = 'FAILED' WHERE id = ? AND template_state = 'PENDING'",
+                )
+                .bind(&bundle_id)
+                .execute(&mining.pool)
+                .await
+                .map_err(AppError::Database)?;
+            }
+        }
+    }
+}
+
+#[derive(FromRow)]
+struct MiningSegmentRow {
+    id: i64,
+    file_id: i64,
+    offset: Option<i64>,
+    line_end: Option<i64>,
+    content: String,
+    event_time_shift_ms: i64,
+}
+
+async fn mine_bundle(mining: &TemplateMining, bundle_id: &str) -> Result<(), AppError> {
+    let pool = &mining.pool;
+    let (recognizers, reference) = load_timestamp_context(pool, bundle_id).await?;
+    let mut miner = TemplateMiner::default();
+    let mut files = HashMap::new();
+    let mut events: Option<(i64, LineEventTracker<'_>)> = None;
+    let mut after_id = 0_i64;
+    loop {
+        let rows: Vec<MiningSegmentRow> = sqlx::query_as(
+            "SELECT ls.id, ls.file_id, lc.line_offset AS offset, lc.line_end, lc.content, ls.event_time_shift_ms FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE ls.bundle_id = ? AND ls.id > ? AND ls.file_id IS NOT NULL ORDER BY ls.id LIMIT ?",
+        )
+        .bind(bundle_id)
+        .bind(after_id)
+        .bind(MINING_SCAN_BATCH_SIZE)
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::Database)?;
+        if rows.is_empty() {
+            break;
+        }
+        for row in rows {
+            after_id = row.id;
+            // Segments of a file are indexed in order, so events carry over
+            // from one segment to the next until the file changes.
+            let tracker = match &mut events {
+                Some((file_id, tracker)) if *file_id == row.file_id => tracker,
+                _ => {
+                    let parser = EventTimeParser::new(&recognizers, reference)
+                        .with_shift_ms(row.event_time_shift_ms);
+                    &mut events
+                        .insert((row.file_id, LineEventTracker::new(parser)))
+                        .1
+                }
+            };
+            let mut line_numbers = None;
+            for (index, line) in row.content.split('\n').enumerate() {
+                let event = tracker.observe(line);
+                let Some(cluster) = miner.add(template_tokens(line), event) else {
+                    continue;
+                };
+                if !miner.wants_example(cluster) {
+                    continue;
+                }
+                if line_numbers.is_none() {
+                    line_numbers = Some(
+                        segment_line_numbers(
+                            pool,
+                            mining.blob_store.as_ref(),
+                            &mining.api,
+                            &mut files,
+                            IndexedSegment {
+                                bundle_id,
+                                file_id: row.file_id,
+                                offset: row.offset,
+                                line_end: row.line_end,
+                                content: &row.content,
+                            },
+                        )
+                        .await?,
+                    );
+                }
+                let line_number = line_numbers
+                    .as_ref()
+                    .and_then(|numbers| numbers.as_ref())
+                    .and_then(|numbers| numbers.get(index).copied())
+                    .or_else(|| row.offset.map(|offset| offset + index as i64));
+                miner.add_example(cluster, row.file_id, line_number, line);
+            }
+        }
+    }
+
+    if miner.unclustered_lines > 0 {
+        tracing::info!(
+            bundle_id,
+            unclustered_lines = miner.unclustered_lines,
+            "log template limit reached; some lines were left unclustered"
+        );
+    }
+    store_templates(pool, bundle_id, miner.into_clusters()).await
+}
+
+async fn store_templates(
+    pool: &sqlx::SqlitePool,
+    bundle_id: &str,
+    clusters: Vec<TemplateCluster>,
+) -> Result<(), AppError> {
+    let mut tx = pool.begin().await.map_err(AppError::Database)?;
+    sqlx::query("DELETE FROM log_templates WHERE bundle_id = ?")
+        .bind(bundle_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+    for batch in clusters.chunks(TEMPLATE_INSERT_BATCH_SIZE) {
+        let mut builder = QueryBuilder::<Sqlite>::new(
+            "INSERT INTO log_templates (bundle_id, template_key, template, line_count, level_mask, first_event_time_ms, last_event_time_ms, examples) ",
+        );
+        builder.push_values(batch, |mut row, cluster| {
+            let template = cluster.template();
+            row.push_bind(bundle_id)
+                .push_bind(template_key(&template))
+                .push_bind(template)
+                .push_bind(cluster.line_count)
+                .push_bind(cluster.level_mask)
+                .push_bind(cluster.first_event_time)
+                .push_bind(cluster.last_event_time)
+                .push_bind(
+                    serde_json::to_string(&cluster.examples).unwrap_or_else(|_| "[]".into()),
+                );
+        });
+        builder
+            .build()
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::Database)?;
+    }
+    sqlx::query(
+        "UPDATE bundles SET template_state = 'READY' WHERE id = ? AND template_state = 'PENDING'",
+    )
+    .bind(bundle_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(AppError::Database)?;
+    tx.commit().await.map_err(AppError::Database)
+}
+
+pub fn spawn_template_mining(mining: TemplateMining) -> tokio::task::JoinHandle<()> {
+    crate::spawn_periodic_job(
+        "log-template-mining",
+        std::time::Duration::ZERO,
+        std::time::Duration::from_secs(60),
+        move || {
+            let mining = mining.clone();
+            async move {
+                mine_pending_bundles(&mining)
+                    .await
+                    .map(|_| ())
+                    .map_err(|error| error.to_string())
+            }
+        },
+    )
+}
+
+/// Starts a mining pass right away instead of waiting for the periodic job.
+pub fn trigger_mining(mining: TemplateMining) {
+    tokio::spawn(async move {
+        if let Err(error) = mine_pending_bundles(&mining).await {
+            tracing::warn!(%error, "log template mining failed; periodic job will retry");
+        }
+    });
+}
+
+/// One stored template of a bundle, as compared by [`diff_templates`].
+#[derive(Debug, Clone, FromRow)]
+pub struct StoredTemplate {
+    pub template_key: String,
+    pub template: String,
+    pub line_count: i64,
+    pub level_mask: i64,
+    pub examples: String,
+}
+
+pub async fn load_bundle_templates(
+    pool: &sqlx::SqlitePool,
+    bundle_id: &str,
+) -> Result<Vec<StoredTemplate>, AppError> {
+    sqlx::query_as(
+        "SELECT template_key, template, line_count, level_mask, examples FROM log_templates WHERE bundle_id = ? ORDER BY line_count DESC, template_key",
+    )
+    .bind(bundle_id)
+    .fetch_all(pool)
+    .await
+    .map_err(AppError::Database)
+}
+
+/// A template present in both bundles whose share of the mined lines moved
+/// by at least the requested ratio.
+#[derive(Debug)]
+pub struct ChangedTemplate<'a> {
+    pub base: &'a StoredTemplate,
+    pub target: &'a StoredTemplate,
+    /// Target share of lines over base share; above 1 when more frequent.
+    pub ratio: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct TemplateDiff<'a> {
+    pub only_in_base: Vec<&'a StoredTemplate>,
+    pub only_in_target: Vec<&'a StoredTemplate>,
+    pub changed: Vec<ChangedTemplate<'a>>,
+}
+
+/// Whether two templates can describe the same lines: equal token counts,
+/// with every token pair equal or one of them `<*>`. Bundles mine templates
+/// independently, so a message varying in one bundle may still be literal
+/// in the other.
+pub fn templates_overlap(left: &str, right: &str) -> bool {
+    let (left, right): (Vec<&str>, Vec<&str>) =
+        (left.split(' ').collect(), right.split(' ').collect());
+    left.len() == right.len()
+        && left
+            .iter()
+            .zip(&right)
+            .all(|(left, right)| left == right || *left == WILDCARD || *right == WILDCARD)
+}
+
+/// Compares two bundles' templates, pairing equal keys first and then
+/// overlapping templates. Frequencies are shares of each bundle's mined
+/// lines so bundles of different sizes compare fairly; templates below
+/// `min_count` lines on both sides are ignored.
+pub fn diff_templates<'a>(
+    base: &'a [StoredTemplate],
+    target: &'a [StoredTemplate],
+    min_ratio: f64,
+    min_count: i64,
+) -> TemplateDiff<'a> {
+    let total = |templates: &[StoredTemplate]| {
+        templates
+            .iter()
+            .map(|template| template.line_count)
+            .sum::<i64>()
+            .max(1) as f64
+    };
+    let (base_total, target_total) = (total(base), total(target));
+    let base_by_key: HashMap<&str, usize> = base
+        .iter()
+        .enumerate()
+        .map(|(index, template)| (template.template_key.as_str(), index))
+        .collect();
+    let mut paired = vec![false; base.len()];
+    let mut pairs: Vec<(usize, Option<usize>)> = target
+        .iter()
+        .enumerate()
+        .map(|(index, template)| {
+            let same = base_by_key.get(template.template_key.as_str()).copied();
+            if let Some(same) = same {
+                paired[same] = true;
+            }
+            (index, same)
+        })
+        .collect();
+    for (index, counterpart) in &mut pairs {
+        if counterpart.is_some() {
+            continue;
+        }
+        *counterpart = (0..base.len()).find(|candidate| {
+            !paired[*candidate]
+                && templates_overlap(&base[*candidate].template, &target[*index].template)
+        });
+        if let Some(candidate) = *counterpart {
+            paired[candidate] = true;
+        }
+    }
+
+    let mut diff = TemplateDiff {
+        only_in_base: base
+            .iter()
+            .zip(&paired)
+            .filter(|(template, paired)| !**paired && template.line_count >= min_count)
+            .map(|(template, _)| template)
+            .collect(),
+        ..TemplateDiff::default()
+    };
+    for (index, counterpart) in pairs {
+        let template = &target[index];
+        let Some(previous) = counterpart.map(|counterpart| &base[counterpart]) else {
+            if template.line_count >= min_count {
+                diff.only_in_target.push(template);
+            }
+            continue;
+        };
+        if template.line_count.max(previous.line_count) < min_count {
+            continue;
+        }
+        let ratio =
+            (template.line_count as f64 / target_total) / (previous.line_count as f64 / base_total);
+        if ratio >= min_ratio || ratio <= 1.0 / min_ratio {
+            diff.changed.push(ChangedTemplate {
+                base: previous,
+                target: template,
+                ratio,
+            });
+        }
+    }
+    diff.changed
+        .sort_by(|left, right| right.ratio.ln().abs().total_cmp(&left.ratio.ln().abs()));
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        StoredTemplate, TemplateMiner, WILDCARD, diff_templates, matches_template, template_key,
+        template_tokens, templates_overlap,
+    };
+    use crate::{ingest::LogLevel, log_expression::LineEvent};
+
+    fn event(level: Option<LogLevel>, time_key: Option<i64>) -> LineEvent {
+        LineEvent { level, time_key }
+    }
+
+    #[test]
+    fn masks_tokens_with_digits_and_keeps_field_names() {
+        assert_eq!(
+            template_tokens("2026-03-01 10:00:01 ERROR user=42 id=abc retry 3x of  disk0"),
+            vec![
+                WILDCARD, WILDCARD, "ERROR", "user=<*>", "id=abc", "retry", WILDCARD, "of",
+                WILDCARD,
+            ]
+        );
+        let long = vec!["word"; 70].join(" ");
+        let tokens = template_tokens(&long);
+        assert_eq!(tokens.len(), 65);
+        assert_eq!(tokens.last().map(String::as_str), Some(WILDCARD));
+    }
+
+    #[test]
+    fn clusters_similar_lines_and_generalizes_differing_tokens() {
+        let mut miner = TemplateMiner::default();
+        let lines = [
+            (
+                "10:00:01 INFO connected to alpha as user=1",
+                Some(LogLevel::Info),
+                Some(5),
+            ),
+            (
+                "10:00:02 INFO connected to beta as user=2",
+                Some(LogLevel::Info),
+                Some(9),
+            ),
+            (
+                "10:00:03 ERROR disk full on sda",
+                Some(LogLevel::Error),
+                Some(7),
+            ),
+            (
+                "10:00:04 INFO connected to gamma as user=3",
+                Some(LogLevel::Warn),
+                None,
+            ),
+        ];
+        let joined: Vec<_> = lines
+            .iter()
+            .map(|(line, level, time)| miner.add(template_tokens(line), event(*level, *time)))
+            .collect();
+        assert_eq!(joined, vec![Some(0), Some(0), Some(1), Some(0)]);
+
+        let clusters = miner.into_clusters();
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(
+            clusters[0].template(),
+            "<*> INFO connected to <*> as user=<*>"
+        );
+        assert_eq!(clusters[0].line_count, 3);
+        assert_eq!(
+            clusters[0].level_mask,
+            LogLevel::Info.bit() | LogLevel::Warn.bit()
+        );
+        assert_eq!(
+            (clusters[0].first_event_time, clusters[0].last_event_time),
+            (Some(5), Some(9))
+        );
+        assert_eq!(clusters[1].template(), "<*> ERROR disk full on sda");
+    }
+
+    #[test]
+    fn keeps_dissimilar_lines_of_one_shape_apart() {
+        let mut miner = TemplateMiner::default();
+        miner.add(
+            template_tokens("ERROR disk full on sda"),
+            LineEvent::default(),
+        );
+        miner.add(
+            template_tokens("ERROR cache miss for key"),
+            LineEvent::default(),
+        );
+        assert_eq!(miner.into_clusters().len(), 2);
+    }
+
+    #[test]
+    fn matches_lines_against_a_stored_template() {
+        let template: Vec<&str> = "<*> INFO connected to <*> as user=<*>".split(' ').collect();
+        assert!(matches_template(
+            &template,
+            &template_tokens("10:00:09 INFO connected to delta as user=77")
+        ));
+        assert!(!matches_template(
+            &template,
+            &template_tokens("10:00:09 INFO connected to delta as admin=77")
+        ));
+        assert!(!matches_template(
+            &template,
+            &template_tokens("10:00:09 INFO connected to delta")
+        ));
+        assert_eq!(template_key("a <*>"), template_key("a <*>"));
+        assert_eq!(template_key("a <*>").len(), 16);
+    }
+
+    fn stored(key: &str, line_count: i64) -> StoredTemplate {
+        StoredTemplate {
+            template_key: key.to_string(),
+            template: format!("template {key}"),
+            line_count,
+            level_mask: 0,
+            examples: "[]".to_string(),
+        }
+    }
+
+    #[test]
+    fn diff_reports_one_sided_and_rescaled_frequency_changes() {
+        let base = vec![
+            stored("steady", 100),
+            stored("gone", 5),
+            stored("spike", 2),
+            stored("rare", 1),
+        ];
+        // Target has twice the lines, so "steady" keeps its share at 200.
+        let target = vec![
+            stored("steady", 200),
+            stored("new", 8),
+            stored("spike", 40),
+            stored("rare", 1),
+        ];
+        let diff = diff_templates(&base, &target, 2.0, 2);
+        let keys = |templates: &[&StoredTemplate]| {
+            templates
+                .iter()
+                .map(|template| template.template_key.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(keys(&diff.only_in_base), vec!["gone"]);
+        assert_eq!(keys(&diff.only_in_target), vec!["new"]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].target.template_key, "spike");
+        assert!(diff.changed[0].ratio > 8.0);
+
+        let mut literal = stored("literal", 30);
+        literal.template = "connected to alpha".to_string();
+        let mut general = stored("general", 10);
+        general.template = "connected to <*>".to_string();
+        let paired = diff_templates(
+            std::slice::from_ref(&general),
+            std::slice::from_ref(&literal),
+            2.0,
+            1,
+        );
+        assert!(paired.only_in_base.is_empty() && paired.only_in_target.is_empty());
+        assert!(templates_overlap("a <*> c", "a b <*>"));
+        assert!(!templates_overlap("a <*> c", "a b d"));
+
+        let reversed = diff_templates(&target, &base, 2.0, 2);
+        assert_eq!(reversed.changed[0].base.template_key, "spike");
+        assert!(reversed.changed[0].ratio < 0.5);
+    }
+}
@@ -0,0 +1,393 @@
+//! Chronological k-way merge of lines from several files.
+//!
+//! Each file is read sequentially with its bundle's [`EventTimeParser`], so a
+//! line without a timestamp of its own sorts at the time carried forward from
+//! the last timestamped line above it. Lines before the first timestamp sort
+//! ahead of everything else. Ties go to the file listed first, which keeps the
+//! order of each file intact and makes paging deterministic.
+
+use chrono::NaiveDateTime;
+
+use crate::{
+    error::AppError,
+    ingest::EventTimeParser,
+    services::{
+        file_reader::LineSource,
+        json_size::{JsonLinePageDecision, RESPONSE_TRUNCATED_LINE_MARKER, fit_json_line_to_page},
+        wall_clock,
+    },
+};
+
+/// Most files one merged view may combine.
+pub const MAX_MERGED_FILES: usize = 20;
+
+/// Serialized size of a merged line besides its content.
+const MERGED_LINE_FIXED_BYTES: u64 = 192;
+
+/// Where a merge input resumes: the next line to emit and the parser time
+/// carried into it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CursorPosition {
+    pub(crate) file_id: i64,
+    pub(crate) line: i64,
+    pub(crate) last: Option<NaiveDateTime>,
+}
+
+/// Encodes positions as `file_id:line:last,...`, `last` being the unshifted
+/// comparison key of the carried time, empty when none.
+pub(crate) fn encode_cursor(positions: &[CursorPosition]) -> String {
+    positions
+        .iter()
+        .map(|position| {
+            let last = position
+                .last
+                .and_then(wall_clock::comparison_key)
+                .map(|key| key.to_string())
+                .unwrap_or_default();
+            format!("{}:{}:{last}", position.file_id, position.line)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Parses a cursor issued for exactly `file_ids`, in that order.
+pub(crate) fn parse_cursor(
+    cursor: Option<&str>,
+    file_ids: &[i64],
+) -> Result<Vec<CursorPosition>, AppError> {
+    let Some(cursor) = cursor.map(str::trim).filter(|cursor| !cursor.is_empty()) else {
+        return Ok(file_ids
+            .iter()
+            .map(|&file_id| CursorPosition {
+                file_id,
+                line: 0,
+                last: None,
+            })
+            .collect());
+    };
+    let invalid = || AppError::BadRequest("cursor 参数无效".into());
+    let positions = cursor
+        .split(',')
+        .map(|part| {
+            let mut fields = part.splitn(3, ':');
+            let file_id = fields.next()?.parse().ok()?;
+            let line = fields
+                .next()?
+                .parse()
+                .ok()
+                .filter(|line: &i64| *line >= 0)?;
+            let last = match fields.next()? {
+                "" => None,
+                key => Some(wall_clock::from_comparison_key(key.parse().ok()?)?),
+            };
+            Some(CursorPosition {
+                file_id,
+                line,
+                last,
+            })
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(invalid)?;
+    if positions.len() != file_ids.len()
+        || positions
+            .iter()
+            .zip(file_ids)
+            .any(|(position, file_id)| position.file_id != *file_id)
+    {
+        return Err(AppError::BadRequest("cursor 与 files 参数不匹配".into()));
+    }
+    Ok(positions)
+}
+
+pub(crate) struct MergedLine {
+    /// Index of the input the line came from.
+    pub(crate) source: usize,
+    pub(crate) line_number: i64,
+    /// Shifted comparison key the line sorted at.
+    pub(crate) event_time_ms: Option<i64>,
+    /// Whether the line carries its own timestamp rather than an inherited one.
+    pub(crate) timed: bool,
+    pub(crate) content: String,
+    pub(crate) truncated: bool,
+}
+
+struct Head {
+    resume: CursorPosition,
+    line: MergedLine,
+}
+
+/// One file of the merge, positioned at a [`CursorPosition`].
+pub(crate) struct MergeInput<'a, L> {
+    file_id: i64,
+    lines: L,
+    parser: EventTimeParser<'a>,
+    next_line: i64,
+    head: Option<Head>,
+}
+
+impl<'a, L: LineSource> MergeInput<'a, L> {
+    /// `lines` must start at `position.line`, and `parser` carry the file's
+    /// recognizers and shift.
+    pub(crate) fn new(lines: L, parser: EventTimeParser<'a>, position: CursorPosition) -> Self {
+        Self {
+            file_id: position.file_id,
+            lines,
+            parser: parser.with_last(position.last),
+            next_line: position.line,
+            head: None,
+        }
+    }
+
+    fn position(&self) -> CursorPosition {
+        CursorPosition {
+            file_id: self.file_id,
+            line: self.next_line,
+            last: self.parser.last(),
+        }
+    }
+
+    async fn fill(&mut self, source: usize) -> Result<(), AppError> {
+        if self.head.is_some() {
+            return Ok(());
+        }
+        let resume = self.position();
+        let Some(line) = self.lines.next_line().await? else {
+            return Ok(());
+        };
+        let own = self.parser.parse_ms(&line.content);
+        self.head = Some(Head {
+            resume,
+            line: MergedLine {
+                source,
+                line_number: self.next_line,
+                event_time_ms: own.or_else(|| self.parser.last_ms()),
+                timed: own.is_some(),
+                content: line.content,
+                truncated: line.truncated,
+            },
+        });
+        self.next_line += 1;
+        Ok(())
+    }
+}
+
+pub(crate) struct MergedPage {
+    pub(crate) lines: Vec<MergedLine>,
+    /// Resume positions, `None` once every input is exhausted.
+    pub(crate) next_cursor: Option<Vec<CursorPosition>>,
+}
+
+/// Emits up to `limit` lines in event-time order, stopping early when the
+/// serialized page would exceed `max_page_bytes`.
+pub(crate) async fn merge_page<L: LineSource>(
+    inputs: &mut [MergeInput<'_, L>],
+    limit: usize,
+    page_base_bytes: u64,
+    max_page_bytes: u64,
+) -> Result<MergedPage, AppError> {
+    let mut lines = Vec::new();
+    let mut page_bytes = page_base_bytes;
+    while lines.len() < limit {
+        for (index, input) in inputs.iter_mut().enumerate() {
+            input.fill(index).await?;
+        }
+        let Some(next) = inputs
+            .iter_mut()
+            .filter(|input| input.head.is_some())
+            .min_by_key(|input| {
+                let head = input.head.as_ref().map(|head| &head.line);
+                head.map(|line| (line.event_time_ms, line.source))
+            })
+        else {
+            break;
+        };
+        let head = next.head.as_ref().expect("filtered on head");
+        match fit_json_line_to_page(
+            &head.line.content,
+            MERGED_LINE_FIXED_BYTES,
+            page_base_bytes,
+            page_bytes,
+            max_page_bytes,
+            RESPONSE_TRUNCATED_LINE_MARKER,
+        ) {
+            JsonLinePageDecision::Include {
+                content,
+                line_bytes,
+                response_truncated,
+            } => {
+                let mut line = next.head.take().expect("filtered on head").line;
+                line.content = content;
+                line.truncated |= response_truncated;
+                page_bytes = page_bytes.saturating_add(line_bytes);
+                lines.push(line);
+            }
+            JsonLinePageDecision::Defer | JsonLinePageDecision::TooLarge => {
+                if lines.is_empty() {
+                    return Err(AppError::public(
+                        actix_web::http::StatusCode::PAYLOAD_TOO_LARGE,
+                        "LINE_PAGE_TOO_LARGE",
+                        "单行或行分页结果超过字节限制",
+                    ));
+                }
+                break;
+            }
+        }
+    }
+
+    for (index, input) in inputs.iter_mut().enumerate() {
+        input.fill(index).await?;
+    }
+    let next_cursor = inputs.iter().any(|input| input.head.is_some()).then(|| {
+        inputs
+            .iter()
+            .map(|input| {
+                input
+                    .head
+                    .as_ref()
+                    .map_or_else(|| input.position(), |head| head.resume)
+            })
+            .collect()
+    });
+    Ok(MergedPage { lines, next_cursor })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::{ingest::TimestampRecognizers, services::file_reader::SourceLine};
+
+    struct VecSource(std::vec::IntoIter<&'static str>);
+
+    impl LineSource for VecSource {
+        async fn next_line(&mut self) -> Result<Option<SourceLine>, AppError> {
+            Ok(self.0.next().map(|content| SourceLine {
+                content: content.to_string(),
+                truncated: false,
+            }))
+        }
+    }
+
+    const LEFT: [&str; 4] = [
+        "2026-03-01 10:00:00 INFO left start",
+        "    at continuation",
+        "2026-03-01 10:00:05 INFO left later",
+        "2026-03-01 10:00:09 INFO left last",
+    ];
+    const RIGHT: [&str; 3] = [
+        "preamble",
+        "2026-03-01 10:00:02 INFO right first",
+        "2026-03-01 10:00:05 INFO right tie",
+    ];
+
+    fn reference() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 3, 2)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+    }
+
+    fn inputs<'a>(
+        recognizers: &'a TimestampRecognizers,
+        positions: &[CursorPosition],
+        shift_ms: i64,
+    ) -> Vec<MergeInput<'a, VecSource>> {
+        [&LEFT[..], &RIGHT[..]]
+            .iter()
+            .zip(positions)
+            .map(|(lines, position)| {
+                let rest = lines[position.line as usize..].to_vec();
+                MergeInput::new(
+                    VecSource(rest.into_iter()),
+                    EventTimeParser::new(recognizers, reference())
+                        .with_shift_ms(if position.file_id == 2 { shift_ms } else { 0 }),
+                    *position,
+                )
+            })
+            .collect()
+    }
+
+    fn order(lines: &[MergedLine]) -> Vec<(usize, i64)> {
+        lines
+            .iter()
+            .map(|line| (line.source, line.line_number))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn merges_by_event_time_carrying_untimed_lines_and_resumes_from_cursor() {
+        let recognizers = TimestampRecognizers::default();
+        let start = parse_cursor(None, &[1, 2]).unwrap();
+        let mut all = inputs(&recognizers, &start, 0);
+        let page = merge_page(&mut all, 100, 0, u64::MAX).await.unwrap();
+        assert_eq!(
+            order(&page.lines),
+            vec![(1, 0), (0, 0), (0, 1), (1, 1), (0, 2), (1, 2), (0, 3)]
+        );
+        assert!(page.next_cursor.is_none());
+        assert!(!page.lines[0].timed && page.lines[0].event_time_ms.is_none());
+        assert!(!page.lines[2].timed);
+        assert_eq!(page.lines[2].event_time_ms, page.lines[1].event_time_ms);
+
+        let mut paged = Vec::new();
+        let mut cursor = start;
+        loop {
+            let mut current = inputs(&recognizers, &cursor, 0);
+            let page = merge_page(&mut current, 3, 0, u64::MAX).await.unwrap();
+            paged.extend(order(&page.lines));
+            let Some(next) = page.next_cursor else {
+                break;
+            };
+            let encoded = encode_cursor(&next);
+            cursor = parse_cursor(Some(&encoded), &[1, 2]).unwrap();
+        }
+        assert_eq!(
+            paged,
+            order(
+                &merge_page(
+                    &mut inputs(&recognizers, &parse_cursor(None, &[1, 2]).unwrap(), 0),
+                    100,
+                    0,
+                    u64::MAX
+                )
+                .await
+                .unwrap()
+                .lines
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn file_shift_reorders_lines() {
+        let recognizers = TimestampRecognizers::default();
+        let start = parse_cursor(None, &[1, 2]).unwrap();
+        let mut shifted = inputs(&recognizers, &start, 10_000);
+        let page = merge_page(&mut shifted, 100, 0, u64::MAX).await.unwrap();
+        assert_eq!(
+            order(&page.lines),
+            vec![(1, 0), (0, 0), (0, 1), (0, 2), (0, 3), (1, 1), (1, 2)]
+        );
+    }
+
+    #[test]
+    fn cursor_must_match_requested_files() {
+        let positions = [
+            CursorPosition {
+                file_id: 4,
+                line: 7,
+                last: Some(reference()),
+            },
+            CursorPosition {
+                file_id: 9,
+                line: 0,
+                last: None,
+            },
+        ];
+        let encoded = encode_cursor(&positions);
+        assert_eq!(parse_cursor(Some(&encoded), &[4, 9]).unwrap(), positions);
+        assert!(parse_cursor(Some(&encoded), &[9, 4]).is_err());
+        assert!(parse_cursor(Some(&encoded), &[4]).is_err());
+        assert!(parse_cursor(Some("4:x:"), &[4]).is_err());
+    }
+}
@@ -1,6 +1,14 @@
+pub mod event_time;
 pub mod file_deletion;
+pub mod file_diff;
 pub mod file_reader;
+pub mod file_reindex;
 pub(crate) mod json_size;
+pub mod log_context;
+pub mod log_histogram;
+pub mod log_regex;
+pub mod log_templates;
+pub mod merged_timeline;
 pub mod skill_runner;
 pub mod skill_time_scope;
 pub mod skill_tools;
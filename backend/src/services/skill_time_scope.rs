@@ -6,6 +6,9 @@ use super::wall_clock;
 
 pub const MAX_CONTEXT_EXPANSION_MINUTES: i64 = 15;
 
+/// Caller-supplied time filter. The values are compared with stored event-time
+/// keys as they are, without any zone conversion: files with a declared UTC
+/// offset are keyed in UTC, files without one keep their own wall clock.
 #[derive(Debug, Clone, Default, Deserialize)]
 pub struct TimeScopeInput {
     pub start: Option<String>,
@@ -47,7 +50,30 @@ pub fn parse_time_scope(
     let Some(input) = input else {
         return Ok(None);
     };
+    parse_window(input, Some(Duration::hours(24))).map(Some)
+}
 
+/// Parses the time filter of the log search endpoints. It accepts the same
+/// range or incident-window shapes as a skill run but no 24 hour cap; `None`
+/// when no time field was given.
+pub fn parse_search_time_range(
+    input: TimeScopeInput,
+) -> Result<Option<SkillTimeScope>, TimeScopeError> {
+    let is_empty = input.start.is_none()
+        && input.end.is_none()
+        && input.incident_time.is_none()
+        && input.before_minutes.is_none()
+        && input.after_minutes.is_none();
+    if is_empty {
+        return Ok(None);
+    }
+    parse_window(input, None).map(Some)
+}
+
+fn parse_window(
+    input: TimeScopeInput,
+    max_span: Option<Duration>,
+) -> Result<SkillTimeScope, TimeScopeError> {
     let has_range_fields = input.start.is_some() || input.end.is_some();
     let has_incident_fields = input.incident_time.is_some()
         || input.before_minutes.is_some()
@@ -91,19 +117,19 @@ pub fn parse_time_scope(
         return Err(TimeScopeError::InvalidRange);
     }
 
-    if end.signed_duration_since(start) > Duration::hours(24) {
+    if max_span.is_some_and(|max_span| end.signed_duration_since(start) > max_span) {
         return Err(TimeScopeError::TooLarge);
     }
 
     let start_ms = wall_clock::comparison_key(start).ok_or(TimeScopeError::ArithmeticOverflow)?;
     let end_ms = wall_clock::comparison_key(end).ok_or(TimeScopeError::ArithmeticOverflow)?;
 
-    Ok(Some(SkillTimeScope {
+    Ok(SkillTimeScope {
         start: wall_clock::format(start),
         end: wall_clock::format(end),
         start_ms,
         end_ms,
-    }))
+    })
 }
 
 impl SkillTimeScope {
@@ -345,6 +371,26 @@ mod tests {
         assert!(matches!(invalid, Err(TimeScopeError::InvalidTimestamp)));
     }
 
+    #[test]
+    fn search_time_ranges_share_validation_without_the_24_hour_cap() {
+        assert_eq!(parse_search_time_range(TimeScopeInput::default()), Ok(None));
+        let week = parse_search_time_range(TimeScopeInput {
+            start: Some("2026-08-01 00:00:00".into()),
+            end: Some("2026-08-08 00:00:00".into()),
+            ..TimeScopeInput::default()
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(week.end, "2026-08-08 00:00:00");
+        assert_eq!(
+            parse_search_time_range(TimeScopeInput {
+                start: Some("2026-08-01 00:00:00".into()),
+                ..TimeScopeInput::default()
+            }),
+            Err(TimeScopeError::InvalidTimestamp)
+        );
+    }
+
     #[test]
     fn none_means_unscoped_and_expansion_is_limited() {
         assert_eq!(parse_time_scope(None).unwrap(), None);
@@ -1,16 +1,21 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::Arc};
 
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use tokio::{
     fs::File,
-    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    io::{AsyncBufReadExt, AsyncSeekExt, AsyncWriteExt, BufReader},
 };
 
 use crate::{
     config::MAX_TEMP_RESULT_LOGICAL_LINE_BYTES,
     error::AppError,
-    ingest::{LimitedLine, decode_log_line, read_line_bytes_limited_with_budget_and_callback},
-    log_expression::Expression,
+    ingest::{
+        EventTimeParser, FileEncoding, LimitedLine, LogLevel, StreamingDecoder, TextEncoding,
+        TimestampRecognizers, read_encoded_line_limited_with_budget_and_callback,
+    },
+    log_expression::{Expression, LineEvent, LineEventTracker},
+    services::wall_clock,
 };
 
 pub struct TempSource {
@@ -19,6 +24,18 @@ pub struct TempSource {
     pub label: String,
     pub bundle_hash: Option<String>,
     pub file_id: Option<String>,
+    /// Encoding of `path`; temporary result artifacts are always UTF-8.
+    pub encoding: FileEncoding,
+    /// Timestamp context of a log file source. Temporary result sources have
+    /// none and take each line's event from its metadata instead.
+    pub event_times: Option<SourceEventTimes>,
+}
+
+/// What the ingest pipeline used to read event times from a file.
+pub struct SourceEventTimes {
+    pub recognizers: Arc<TimestampRecognizers>,
+    pub reference: NaiveDateTime,
+    pub shift_ms: i64,
 }
 
 pub struct MaterializedPreview {
@@ -34,6 +51,30 @@ pub struct MatchMetadata {
     pub line_number: i64,
     #[serde(default)]
     pub truncated: bool,
+    /// Level of the event the line belongs to, for refining by `level:`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    /// Wall-clock time of the event the line belongs to, for refining by
+    /// `time` predicates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_time: Option<String>,
+}
+
+impl MatchMetadata {
+    /// Event recorded when the line was matched in its log file, if any.
+    fn event(&self) -> Option<LineEvent> {
+        if self.level.is_none() && self.event_time.is_none() {
+            return None;
+        }
+        Some(LineEvent {
+            level: self.level.as_deref().and_then(LogLevel::parse),
+            time_key: self
+                .event_time
+                .as_deref()
+                .and_then(wall_clock::parse)
+                .and_then(wall_clock::comparison_key),
+        })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -90,14 +131,26 @@ impl TempResultExecutor {
         let mut scanned_bytes = 0usize;
         let mut matcher = expression.chunk_matcher();
         for source in sources {
-            let file = File::open(&source.path).await.map_err(AppError::Io)?;
+            let encoding = source.encoding;
+            let mut file = File::open(&source.path).await.map_err(AppError::Io)?;
+            file.seek(std::io::SeekFrom::Start(encoding.bom_bytes))
+                .await
+                .map_err(AppError::Io)?;
             let mut reader = BufReader::new(file);
+            let mut decoder =
+                (encoding.name != TextEncoding::Utf8).then(|| StreamingDecoder::new(encoding.name));
             let mut source_metadata_reader = match source.metadata_path.as_ref() {
                 Some(path) => Some(BufReader::new(
                     File::open(path).await.map_err(AppError::Io)?,
                 )),
                 None => None,
             };
+            let mut events = source.event_times.as_ref().map(|times| {
+                LineEventTracker::new(
+                    EventTimeParser::new(&times.recognizers, times.reference)
+                        .with_shift_ms(times.shift_ms),
+                )
+            });
             let mut bytes = Vec::new();
             let mut source_metadata_line = String::new();
             let mut source_line = 0_i64;
@@ -105,12 +158,21 @@ impl TempResultExecutor {
                 bytes.clear();
                 let remaining_scan_bytes = max_scan_bytes.saturating_sub(scanned_bytes);
                 matcher.reset();
-                let truncated = match read_line_bytes_limited_with_budget_and_callback(
+                if let Some(decoder) = decoder.as_mut() {
+                    decoder.reset(encoding.name);
+                }
+                let truncated = match read_encoded_line_limited_with_budget_and_callback(
                     &mut reader,
+                    encoding.name,
                     &mut bytes,
                     max_logical_line_bytes,
                     remaining_scan_bytes,
-                    |chunk| matcher.feed_bytes(chunk),
+                    |chunk| match decoder.as_mut() {
+                        Some(decoder) => {
+                            matcher.feed_bytes(decoder.decode(chunk, false).as_bytes())
+                        }
+                        None => matcher.feed_bytes(chunk),
+                    },
                 )
                 .await
                 .map_err(AppError::Io)?
@@ -126,6 +188,9 @@ impl TempResultExecutor {
                     }
                     LimitedLine::ScanLimit { .. } => return Err(scan_limit()),
                 };
+                if let Some(decoder) = decoder.as_mut() {
+                    matcher.feed_bytes(decoder.decode(&[], true).as_bytes());
+                }
                 matcher.finish();
                 let inherited_metadata = if let Some(reader) = source_metadata_reader.as_mut() {
                     source_metadata_line.clear();
@@ -145,14 +210,34 @@ impl TempResultExecutor {
                 } else {
                     None
                 };
-                if matcher.matches(expression) {
-                    let content = decode_log_line(&bytes, truncated);
+                // Every line of a log file feeds the tracker so continuation
+                // lines inherit the time and level of their event.
+                let mut decoded = None;
+                let tracked = events.as_mut().map(|events| {
+                    events.observe(decoded.insert(encoding.decode_line(&bytes, truncated)))
+                });
+                let event =
+                    tracked.or_else(|| inherited_metadata.as_ref().and_then(MatchMetadata::event));
+                let is_match = match event {
+                    Some(event) => matcher.matches_event(expression, event),
+                    None => matcher.matches(expression),
+                };
+                if is_match {
+                    let content =
+                        decoded.unwrap_or_else(|| encoding.decode_line(&bytes, truncated));
                     let mut metadata = inherited_metadata.unwrap_or_else(|| MatchMetadata {
                         bundle_hash: source.bundle_hash.clone(),
                         file_id: source.file_id.clone(),
                         path: source.label.clone(),
                         line_number: source_line,
                         truncated,
+                        level: tracked
+                            .and_then(|event| event.level)
+                            .map(|level| level.as_str().to_string()),
+                        event_time: tracked
+                            .and_then(|event| event.time_key)
+                            .and_then(wall_clock::from_comparison_key)
+                            .map(wall_clock::format),
                     });
                     metadata.truncated |= truncated;
                     if matched % 1_000 == 0 {
@@ -336,7 +421,9 @@ mod tests {
     use tokio::fs::File;
     use uuid::Uuid;
 
-    use super::{SparseCheckpoint, TempResultExecutor, TempSource, select_checkpoint};
+    use super::{
+        FileEncoding, SparseCheckpoint, TempResultExecutor, TempSource, select_checkpoint,
+    };
     use crate::{error::AppError, ingest::TRUNCATED_LINE_MARKER, log_expression};
 
     fn test_path(suffix: &str) -> PathBuf {
@@ -355,6 +442,8 @@ mod tests {
             label: "missing.log".into(),
             bundle_hash: None,
             file_id: None,
+            encoding: FileEncoding::default(),
+            event_times: None,
         }];
         let expression = log_expression::parse("ERROR").unwrap();
         let mut log = File::create(&log_path).await.unwrap();
@@ -405,6 +494,8 @@ mod tests {
             label: "app.log".into(),
             bundle_hash: Some("bundle-1".into()),
             file_id: Some("42".into()),
+            encoding: FileEncoding::default(),
+            event_times: None,
         }];
         let expression = log_expression::parse("ERROR").unwrap();
         let mut log = File::create(&log_path).await.unwrap();
@@ -468,6 +559,8 @@ mod tests {
             label: "app.log".into(),
             bundle_hash: None,
             file_id: None,
+            encoding: FileEncoding::default(),
+            event_times: None,
         }];
         let expression = log_expression::parse("ERROR").unwrap();
         let mut log = File::create(&log_path).await.unwrap();
@@ -520,6 +613,8 @@ mod tests {
             label: "app.log".into(),
             bundle_hash: None,
             file_id: None,
+            encoding: FileEncoding::default(),
+            event_times: None,
         }];
         let expression = log_expression::parse("ERROR").unwrap();
         let mut log = File::create(&log_path).await.unwrap();
@@ -563,6 +658,8 @@ mod tests {
             label: "app.log".into(),
             bundle_hash: None,
             file_id: None,
+            encoding: FileEncoding::default(),
+            event_times: None,
         }];
         let expression = log_expression::parse("LATE").unwrap();
         let mut log = File::create(&log_path).await.unwrap();
@@ -607,6 +704,8 @@ mod tests {
             label: "app.log".into(),
             bundle_hash: Some("bundle".into()),
             file_id: Some("1".into()),
+            encoding: FileEncoding::default(),
+            event_times: None,
         }];
         let expression = log_expression::parse("ERROR").unwrap();
         let full_paths = [
@@ -687,6 +786,8 @@ mod tests {
             label: "original.log".into(),
             bundle_hash: Some("bundle-1".into()),
             file_id: Some("42".into()),
+            encoding: FileEncoding::default(),
+            event_times: None,
         }];
         let expression = log_expression::parse("ERROR").unwrap();
         let mut first_log = File::create(&first_log_path).await.unwrap();
@@ -715,6 +816,8 @@ mod tests {
             label: "filtered.log".into(),
             bundle_hash: None,
             file_id: None,
+            encoding: FileEncoding::default(),
+            event_times: None,
         }];
         let nested_expression = log_expression::parse("second").unwrap();
         let mut second_log = File::create(&second_log_path).await.unwrap();
@@ -50,3 +50,32 @@ pub(crate) fn comparison_key(value: NaiveDateTime) -> Option<i64> {
             key.checked_mul(base)?.checked_add(component)
         })
 }
+
+/// Decodes a key produced by [`comparison_key`] back into calendar fields.
+pub(crate) fn from_comparison_key(key: i64) -> Option<NaiveDateTime> {
+    let mut rest = key;
+    let mut take = |base: i64| {
+        let component = rest.rem_euclid(base);
+        rest = rest.div_euclid(base);
+        u32::try_from(component).ok()
+    };
+    let millis = take(1_000)?;
+    let second = take(60)?;
+    let minute = take(60)?;
+    let hour = take(24)?;
+    let day = take(32)?;
+    let month = take(13)?;
+    let year = i32::try_from(rest).ok()?;
+    chrono::NaiveDate::from_ymd_opt(year, month, day)?
+        .and_hms_milli_opt(hour, minute, second, millis)
+}
+
+/// Moves a comparison key by `delta_ms` of real time, crossing calendar
+/// boundaries as needed.
+pub(crate) fn shift_comparison_key(key: i64, delta_ms: i64) -> Option<i64> {
+    if delta_ms == 0 {
+        return Some(key);
+    }
+    let value = from_comparison_key(key)?;
+    comparison_key(value.checked_add_signed(chrono::Duration::milliseconds(delta_ms))?)
+}
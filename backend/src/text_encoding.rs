@@ -0,0 +1,339 @@
+use std::path::Path;
+
+use encoding_rs::{Decoder, Encoding, GB18030, SHIFT_JIS, UTF_8, UTF_16BE, UTF_16LE};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncReadExt;
+
+use crate::error::AppError;
+
+const DETECTION_SAMPLE_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextEncoding {
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    #[serde(rename = "gb18030")]
+    Gb18030,
+    #[serde(rename = "shift_jis")]
+    ShiftJis,
+}
+
+impl TextEncoding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf16Le => "utf-16le",
+            Self::Utf16Be => "utf-16be",
+            Self::Gb18030 => "gb18030",
+            Self::ShiftJis => "shift_jis",
+        }
+    }
+
+    /// Accepts the canonical names plus the common aliases users type for them.
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Some(Self::Utf8),
+            "utf-16le" | "utf-16" | "utf16le" | "utf16" => Some(Self::Utf16Le),
+            "utf-16be" | "utf16be" => Some(Self::Utf16Be),
+            "gb18030" | "gbk" | "gb2312" | "cp936" => Some(Self::Gb18030),
+            "shift-jis" | "sjis" | "cp932" | "windows-31j" => Some(Self::ShiftJis),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn encoding(self) -> &'static Encoding {
+        match self {
+            Self::Utf8 => UTF_8,
+            Self::Utf16Le => UTF_16LE,
+            Self::Utf16Be => UTF_16BE,
+            Self::Gb18030 => GB18030,
+            Self::ShiftJis => SHIFT_JIS,
+        }
+    }
+
+    /// Encodes `text` without a BOM, for test fixtures. encoding_rs outputs
+    /// UTF-8 when asked to encode UTF-16, so those are built by hand.
+    #[cfg(test)]
+    pub(crate) fn encode(self, text: &str) -> Vec<u8> {
+        match self {
+            Self::Utf16Le => text.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+            Self::Utf16Be => text.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+            _ => self.encoding().encode(text).0.into_owned(),
+        }
+    }
+
+    /// `Some(big_endian)` for encodings whose line breaks are two-byte code units.
+    pub(crate) fn utf16_big_endian(self) -> Option<bool> {
+        match self {
+            Self::Utf16Le => Some(false),
+            Self::Utf16Be => Some(true),
+            _ => None,
+        }
+    }
+
+    fn bom(self) -> &'static [u8] {
+        match self {
+            Self::Utf8 => b"\xEF\xBB\xBF",
+            Self::Utf16Le => b"\xFF\xFE",
+            Self::Utf16Be => b"\xFE\xFF",
+            Self::Gb18030 | Self::ShiftJis => b"",
+        }
+    }
+
+    pub fn new_decoder(self) -> Decoder {
+        self.encoding().new_decoder_without_bom_handling()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncodingSource {
+    Bom,
+    Detected,
+    Default,
+    Override,
+}
+
+/// Encoding recorded under `files.meta.encoding` for indexed text files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileEncoding {
+    pub name: TextEncoding,
+    pub source: EncodingSource,
+    #[serde(default)]
+    pub bom_bytes: u64,
+}
+
+impl Default for FileEncoding {
+    fn default() -> Self {
+        Self {
+            name: TextEncoding::Utf8,
+            source: EncodingSource::Default,
+            bom_bytes: 0,
+        }
+    }
+}
+
+impl FileEncoding {
+    pub fn from_meta(meta: Option<&str>) -> Self {
+        meta.and_then(|value| serde_json::from_str::<serde_json::Value>(value).ok())
+            .and_then(|value| value.get("encoding").cloned())
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    }
+
+    /// Applies a user-chosen encoding; a BOM is only skipped when it matches that encoding.
+    pub fn overridden(name: TextEncoding, sample: &[u8]) -> Self {
+        let bom = name.bom();
+        Self {
+            name,
+            source: EncodingSource::Override,
+            bom_bytes: if !bom.is_empty() && sample.starts_with(bom) {
+                bom.len() as u64
+            } else {
+                0
+            },
+        }
+    }
+}
+
+pub async fn detect_file_encoding(path: &Path) -> Result<FileEncoding, AppError> {
+    let sample = read_encoding_sample(path).await?;
+    Ok(detect_encoding(&sample))
+}
+
+pub async fn read_encoding_sample(path: &Path) -> Result<Vec<u8>, AppError> {
+    let file = tokio::fs::File::open(path).await.map_err(AppError::Io)?;
+    let mut sample = Vec::new();
+    file.take(DETECTION_SAMPLE_BYTES)
+        .read_to_end(&mut sample)
+        .await
+        .map_err(AppError::Io)?;
+    Ok(sample)
+}
+
+/// BOM sniffing first, then UTF-16 zero-byte layout, UTF-8 validity and a
+/// GB18030/Shift-JIS comparison for legacy double-byte logs.
+pub fn detect_encoding(sample: &[u8]) -> FileEncoding {
+    for name in [
+        TextEncoding::Utf8,
+        TextEncoding::Utf16Le,
+        TextEncoding::Utf16Be,
+    ] {
+        let bom = name.bom();
+        if sample.starts_with(bom) {
+            return FileEncoding {
+                name,
+                source: EncodingSource::Bom,
+                bom_bytes: bom.len() as u64,
+            };
+        }
+    }
+
+    let detected = |name| FileEncoding {
+        name,
+        source: EncodingSource::Detected,
+        bom_bytes: 0,
+    };
+    if let Some(name) = detect_utf16_layout(sample) {
+        return detected(name);
+    }
+    if is_utf8_prefix(sample) {
+        return FileEncoding::default();
+    }
+
+    let gb = score_double_byte(sample, TextEncoding::Gb18030);
+    let sjis = score_double_byte(sample, TextEncoding::ShiftJis);
+    let best = if (sjis.errors, -sjis.plausible) < (gb.errors, -gb.plausible) {
+        (TextEncoding::ShiftJis, sjis)
+    } else {
+        (TextEncoding::Gb18030, gb)
+    };
+    // Mostly undecodable either way: keep the lossy UTF-8 view rather than guess.
+    if best.1.errors * 20 > best.1.chars.max(1) {
+        return FileEncoding::default();
+    }
+    detected(best.0)
+}
+
+pub(crate) fn is_utf8_prefix(sample: &[u8]) -> bool {
+    match std::str::from_utf8(sample) {
+        Ok(_) => true,
+        Err(error) => error.error_len().is_none(),
+    }
+}
+
+fn detect_utf16_layout(sample: &[u8]) -> Option<TextEncoding> {
+    let pairs = sample.len() / 2;
+    if pairs < 2 {
+        return None;
+    }
+    let (mut even_zeros, mut odd_zeros) = (0usize, 0usize);
+    for pair in sample.chunks_exact(2) {
+        even_zeros += usize::from(pair[0] == 0);
+        odd_zeros += usize::from(pair[1] == 0);
+    }
+    // ASCII-heavy UTF-16 has a zero in almost every unit on one side only.
+    if odd_zeros * 10 >= pairs * 3 && even_zeros * 20 <= pairs {
+        Some(TextEncoding::Utf16Le)
+    } else if even_zeros * 10 >= pairs * 3 && odd_zeros * 20 <= pairs {
+        Some(TextEncoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+struct DoubleByteScore {
+    errors: i64,
+    plausible: i64,
+    chars: i64,
+}
+
+fn score_double_byte(sample: &[u8], name: TextEncoding) -> DoubleByteScore {
+    // Drop a possibly split trailing character so it does not count as an error.
+    let end = sample
+        .iter()
+        .rposition(|byte| *byte == b'\n')
+        .map_or(sample.len(), |position| position + 1);
+    let (decoded, _) = name.encoding().decode_without_bom_handling(&sample[..end]);
+    let mut score = DoubleByteScore {
+        errors: 0,
+        plausible: 0,
+        chars: 0,
+    };
+    for ch in decoded.chars() {
+        score.chars += 1;
+        match (name, ch) {
+            (_, '\u{FFFD}') => score.errors += 1,
+            (TextEncoding::ShiftJis, '\u{3040}'..='\u{30FF}') => score.plausible += 2,
+            (TextEncoding::ShiftJis, '\u{FF61}'..='\u{FF9F}') => score.plausible -= 1,
+            (TextEncoding::Gb18030, '\u{4E00}'..='\u{9FFF}' | '\u{3000}'..='\u{303F}') => {
+                score.plausible += 1
+            }
+            _ => {}
+        }
+    }
+    if name == TextEncoding::Gb18030 {
+        // Characters outside the GB2312 block are rare in real Chinese text and
+        // are what Shift-JIS kana turn into when read as GBK.
+        score.plausible -= count_gbk_extension_pairs(&sample[..end]);
+    }
+    score
+}
+
+fn count_gbk_extension_pairs(bytes: &[u8]) -> i64 {
+    let mut count = 0;
+    let mut index = 0;
+    while index + 1 < bytes.len() {
+        let lead = bytes[index];
+        if lead < 0x80 {
+            index += 1;
+            continue;
+        }
+        let trail = bytes[index + 1];
+        if !(0xA1..=0xF7).contains(&lead) || trail < 0xA1 {
+            count += 1;
+        }
+        index += 2;
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EncodingSource, FileEncoding, TextEncoding, detect_encoding};
+
+    #[test]
+    fn detects_bom_and_statistical_encodings() {
+        let mut bom = vec![0xFF, 0xFE];
+        bom.extend(TextEncoding::Utf16Le.encode("上传失败\n"));
+        assert_eq!(
+            detect_encoding(&bom),
+            FileEncoding {
+                name: TextEncoding::Utf16Le,
+                source: EncodingSource::Bom,
+                bom_bytes: 2,
+            }
+        );
+
+        for (name, text) in [
+            (TextEncoding::Utf16Le, "ERROR service stopped\r\n"),
+            (TextEncoding::Utf16Be, "ERROR service stopped\r\n"),
+            (
+                TextEncoding::Gb18030,
+                "2026-08-14 错误：固件升级失败，设备重启\n",
+            ),
+            (
+                TextEncoding::ShiftJis,
+                "2026-08-14 エラー：ファームウェアの更新に失敗しました\n",
+            ),
+        ] {
+            let detected = detect_encoding(&name.encode(text));
+            assert_eq!(detected.name, name, "{text}");
+            assert_eq!(detected.source, EncodingSource::Detected);
+        }
+
+        assert_eq!(
+            detect_encoding("plain utf-8 日志\n".as_bytes()),
+            FileEncoding::default()
+        );
+    }
+
+    #[test]
+    fn override_only_skips_a_matching_bom() {
+        let sample = [0xFF, 0xFE, b'a', 0];
+        assert_eq!(
+            FileEncoding::overridden(TextEncoding::Utf16Le, &sample).bom_bytes,
+            2
+        );
+        assert_eq!(
+            FileEncoding::overridden(TextEncoding::Gb18030, &sample).bom_bytes,
+            0
+        );
+        assert_eq!(TextEncoding::from_label("GBK"), Some(TextEncoding::Gb18030));
+        assert_eq!(TextEncoding::from_label("latin-1"), None);
+    }
+}
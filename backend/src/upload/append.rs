@@ -0,0 +1,251 @@
+//! Appending uploaded files to a bundle that is already READY.
+//!
+//! The bundle stays READY while the new files are processed, so its existing
+//! content remains readable. Only one append per bundle runs at a time; a
+//! failed append removes the file rows it created and gives back the quota it
+//! charged, leaving the bundle as it was.
+
+use std::{
+    collections::HashSet,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use actix_web::http::StatusCode;
+use tokio::fs;
+
+use crate::{
+    blob_store::BlobStore,
+    config::{ArchiveConfig, IndexingConfig},
+    error::AppError,
+    ingest::{
+        ArchiveBudget, IssueQuota, ProcessFileOptions, ensure_bundle_directory,
+        process_uploaded_file,
+    },
+};
+
+use super::{
+    filename::sanitize_filename,
+    multipart::{ReceiveReservation, UploadedFile},
+};
+
+/// Deepest subdirectory an append may create below the bundle root.
+pub const MAX_APPEND_SUBDIRECTORY_DEPTH: usize = 16;
+
+/// Splits a `/`-separated subdirectory into sanitized segments. Empty input
+/// means the bundle root.
+pub fn parse_subdirectory(value: Option<&str>) -> Result<Vec<String>, AppError> {
+    let segments = value
+        .unwrap_or_default()
+        .split(['/', '\\'])
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| match segment {
+            "." | ".." => Err(AppError::BadRequest("subdirectory 不能包含 . 或 ..".into())),
+            segment => Ok(sanitize_filename(segment)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if segments.len() > MAX_APPEND_SUBDIRECTORY_DEPTH {
+        return Err(AppError::BadRequest(format!(
+            "subdirectory 层级不能超过 {MAX_APPEND_SUBDIRECTORY_DEPTH}"
+        )));
+    }
+    Ok(segments)
+}
+
+/// Marks a bundle as having an append in progress until dropped. Bundle and
+/// file deletion claim the same lease, so neither can start mid-append.
+pub struct AppendLease {
+    bundle_id: String,
+    registry: Arc<Mutex<HashSet<String>>>,
+}
+
+impl Drop for AppendLease {
+    fn drop(&mut self) {
+        if let Ok(mut appending) = self.registry.lock() {
+            appending.remove(&self.bundle_id);
+        }
+    }
+}
+
+pub fn claim_bundle_append(
+    registry: &Arc<Mutex<HashSet<String>>>,
+    bundle_id: &str,
+) -> Result<AppendLease, AppError> {
+    let busy = || {
+        AppError::api(
+            StatusCode::CONFLICT,
+            "BUNDLE_APPEND_IN_PROGRESS",
+            "该日志包正在追加文件，请稍后重试",
+        )
+    };
+    let mut appending = registry.lock().map_err(|_| busy())?;
+    if !appending.insert(bundle_id.to_string()) {
+        return Err(busy());
+    }
+    Ok(AppendLease {
+        bundle_id: bundle_id.to_string(),
+        registry: registry.clone(),
+    })
+}
+
+pub struct AppendFiles<'a> {
+    pub pool: &'a sqlx::SqlitePool,
+    pub blob_store: Arc<dyn BlobStore>,
+    pub staging_root: &'a Path,
+    pub issue_code: &'a str,
+    pub issue_max_content_size: u64,
+    pub bundle_id: &'a str,
+    pub bundle_hash: &'a str,
+    pub subdirectory: &'a [String],
+    pub files: &'a [UploadedFile],
+    pub archive_password: Option<String>,
+    pub archive_config: ArchiveConfig,
+    pub indexing: &'a IndexingConfig,
+    pub receive_reservation: &'a ReceiveReservation,
+}
+
+pub struct AppendedFiles {
+    /// Directory the files were placed under, `None` for the bundle root.
+    pub directory_id: Option<i64>,
+    pub added_content_bytes: u64,
+}
+
+/// Processes `files` into the bundle and queues its templates for re-mining.
+/// On failure every row created by this append is removed again. The caller
+/// must hold the bundle's [`AppendLease`].
+pub async fn append_files(options: AppendFiles<'_>) -> Result<AppendedFiles, AppError> {
+    let pool = options.pool;
+    let bundle_id = options.bundle_id;
+    let (content_before, last_file_id): (i64, i64) = sqlx::query_as(
+        "SELECT content_size_bytes, (SELECT COALESCE(MAX(id), 0) FROM files) FROM bundles WHERE id = ? AND status = 'READY'",
+    )
+    .bind(bundle_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(AppError::Database)?
+    .ok_or_else(bundle_not_ready)?;
+
+    let result = append_files_inner(&options).await;
+    let _ = fs::remove_dir_all(options.staging_root.join(options.bundle_hash)).await;
+    let directory_id = match result {
+        Ok(directory_id) => directory_id,
+        Err(error) => {
+            rollback_append(pool, bundle_id, content_before, last_file_id).await;
+            return Err(error);
+        }
+    };
+
+    let content_after: i64 = sqlx::query_scalar(
+        "UPDATE bundles SET template_state = 'PENDING' WHERE id = ? RETURNING content_size_bytes",
+    )
+    .bind(bundle_id)
+    .fetch_one(pool)
+    .await
+    .map_err(AppError::Database)?;
+    Ok(AppendedFiles {
+        directory_id,
+        added_content_bytes: content_after.saturating_sub(content_before).max(0) as u64,
+    })
+}
+
+async fn append_files_inner(options: &AppendFiles<'_>) -> Result<Option<i64>, AppError> {
+    let directory = ensure_bundle_directory(
+        options.pool,
+        options.bundle_id,
+        options.bundle_hash,
+        options.subdirectory,
+    )
+    .await?;
+    let archive_budget = ArchiveBudget::new(options.archive_config.clone())
+        .with_temp_budget(options.receive_reservation.temp_budget())
+        .with_password(options.archive_password.clone());
+    let issue_quota = IssueQuota::for_ready_bundle(
+        options.pool.clone(),
+        options.issue_code,
+        options.bundle_id,
+        options.issue_max_content_size,
+    );
+    for uploaded in options.files {
+        process_uploaded_file(ProcessFileOptions {
+            pool: options.pool,
+            bundle_id: options.bundle_id,
+            bundle_hash: options.bundle_hash,
+            data_root: options.staging_root,
+            blob_store: options.blob_store.clone(),
+            storage_name: &uploaded.storage_name,
+            original_name: &uploaded.original_name,
+            display_name: &uploaded.display_name,
+            content_type: uploaded.content_type.as_deref(),
+            source_path: &uploaded.temp_path,
+            size_bytes: uploaded.size_bytes,
+            archive_budget: archive_budget.clone(),
+            issue_quota: issue_quota.clone(),
+            indexing: options.indexing,
+            parent: directory.as_ref(),
+        })
+        .await?;
+    }
+    Ok(directory.map(|dir| dir.id))
+}
+
+/// Appends hold the bundle exclusively, so every file row newer than the
+/// snapshot belongs to this append. Deleting them cascades to their segments,
+/// line offsets and children; orphaned blobs are left to blob GC.
+async fn rollback_append(
+    pool: &sqlx::SqlitePool,
+    bundle_id: &str,
+    content_before: i64,
+    last_file_id: i64,
+) {
+    if let Err(error) = sqlx::query("DELETE FROM files WHERE bundle_id = ? AND id > ?")
+        .bind(bundle_id)
+        .bind(last_file_id)
+        .execute(pool)
+        .await
+    {
+        tracing::error!(bundle_id, %error, "failed to remove files of a failed bundle append");
+    }
+    if let Err(error) = sqlx::query("UPDATE bundles SET content_size_bytes = ? WHERE id = ?")
+        .bind(content_before)
+        .bind(bundle_id)
+        .execute(pool)
+        .await
+    {
+        tracing::error!(bundle_id, %error, "failed to release Issue content quota of a failed bundle append");
+    }
+}
+
+fn bundle_not_ready() -> AppError {
+    AppError::api(
+        StatusCode::CONFLICT,
+        "BUNDLE_NOT_READY",
+        "只能向已就绪的日志包追加文件",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subdirectory_segments_are_sanitized_and_bounded() {
+        assert!(parse_subdirectory(None).unwrap().is_empty());
+        assert_eq!(
+            parse_subdirectory(Some("/node-1//logs\\a:b ")).unwrap(),
+            ["node-1", "logs", "a_b"]
+        );
+        assert!(parse_subdirectory(Some("logs/../etc")).is_err());
+        assert!(parse_subdirectory(Some(&"d/".repeat(MAX_APPEND_SUBDIRECTORY_DEPTH + 1))).is_err());
+    }
+
+    #[test]
+    fn only_one_append_per_bundle_at_a_time() {
+        let registry = Arc::new(Mutex::new(HashSet::new()));
+        let lease = claim_bundle_append(&registry, "bundle").unwrap();
+        assert!(claim_bundle_append(&registry, "bundle").is_err());
+        assert!(claim_bundle_append(&registry, "other").is_ok());
+        drop(lease);
+        assert!(claim_bundle_append(&registry, "bundle").is_ok());
+    }
+}
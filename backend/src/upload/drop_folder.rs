@@ -0,0 +1,343 @@
+//! Ingestion of log files dropped into a server-side directory.
+//!
+//! Each subdirectory of the drop folder is named after an Issue code; a file
+//! placed in `<root>/<ISSUE_CODE>/` becomes a bundle of that Issue once it
+//! has gone unmodified for the settle period. The file is copied into the
+//! upload temp area and processed by the same job as a browser upload, so
+//! quotas and archive budgets apply unchanged. The original is then moved to
+//! `.ingested/` (or `.failed/` when it can never be accepted) so it is not
+//! picked up again.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use actix_web::web;
+use tokio::fs;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    AppState,
+    config::{ArchiveConfig, DropFolderConfig},
+    error::AppError,
+    routes::{normalize_issue_code, touch_issue_activity_best_effort},
+    services::log_templates::TemplateMining,
+};
+
+use super::{
+    filename::{sanitize_filename, unique_storage_name},
+    job::{UploadJob, spawn_upload_job},
+    lifecycle::create_processing_bundle,
+    multipart::{ReceiveReservation, UploadedFile},
+};
+
+const INGESTED_DIR: &str = ".ingested";
+const FAILED_DIR: &str = ".failed";
+/// Suffixes writers commonly use while a file is still being copied.
+const IN_PROGRESS_SUFFIXES: [&str; 3] = [".part", ".partial", ".tmp"];
+
+pub fn spawn_drop_folder_ingestion(
+    state: web::Data<AppState>,
+    config: DropFolderConfig,
+) -> tokio::task::JoinHandle<()> {
+    let interval = config.poll_interval;
+    crate::spawn_periodic_job("drop-folder-ingestion", interval, interval, move || {
+        let state = state.clone();
+        let config = config.clone();
+        async move {
+            ingest_drop_folder(&state, &config)
+                .await
+                .map(|_| ())
+                .map_err(|error| error.to_string())
+        }
+    })
+}
+
+/// Queues every settled file below the drop folder. Returns the number of
+/// bundles queued.
+pub async fn ingest_drop_folder(
+    state: &AppState,
+    config: &DropFolderConfig,
+) -> Result<u64, AppError> {
+    let mut issue_dirs = match fs::read_dir(&config.root).await {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(error) => return Err(AppError::Io(error)),
+    };
+    let mut queued = 0_u64;
+    while let Some(entry) = issue_dirs.next_entry().await.map_err(AppError::Io)? {
+        if !entry.file_type().await.map_err(AppError::Io)?.is_dir() {
+            continue;
+        }
+        let Some(issue_code) = entry
+            .file_name()
+            .to_str()
+            .filter(|name| !name.starts_with('.'))
+            .and_then(|name| normalize_issue_code(name).ok())
+        else {
+            continue;
+        };
+        let owner: Option<Option<String>> = sqlx::query_scalar(
+            "SELECT owner_user_id FROM issues WHERE code = ? AND status = 'ACTIVE'",
+        )
+        .bind(&issue_code)
+        .fetch_optional(&state.db.pool)
+        .await
+        .map_err(AppError::Database)?;
+        let Some(owner) = owner else {
+            debug!(
+                issue_code,
+                "skipping drop folder of missing or inactive issue"
+            );
+            continue;
+        };
+
+        let issue_dir = entry.path();
+        for source in settled_files(&issue_dir, config.settle).await? {
+            match ingest_dropped_file(state, &issue_code, owner.as_deref(), &issue_dir, &source)
+                .await
+            {
+                Ok(true) => queued += 1,
+                Ok(false) => {}
+                Err(error) => warn!(
+                    issue_code,
+                    path = %source.display(),
+                    %error,
+                    "drop folder file was not ingested; will retry"
+                ),
+            }
+        }
+    }
+    Ok(queued)
+}
+
+/// Regular files directly inside `dir` that have not been modified for
+/// `settle`, in name order.
+async fn settled_files(dir: &Path, settle: std::time::Duration) -> Result<Vec<PathBuf>, AppError> {
+    let mut entries = fs::read_dir(dir).await.map_err(AppError::Io)?;
+    let mut files = Vec::new();
+    while let Some(entry) = entries.next_entry().await.map_err(AppError::Io)? {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        if name.starts_with('.')
+            || IN_PROGRESS_SUFFIXES
+                .iter()
+                .any(|suffix| name.to_ascii_lowercase().ends_with(suffix))
+        {
+            continue;
+        }
+        let metadata = entry.metadata().await.map_err(AppError::Io)?;
+        let settled = metadata
+            .modified()
+            .ok()
+            .map(|modified| {
+                SystemTime::now()
+                    .duration_since(modified)
+                    .unwrap_or_default()
+            })
+            .is_some_and(|age| age >= settle);
+        if metadata.is_file() && metadata.len() > 0 && settled {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Copies `source` into the temp area, moves it out of the watched
+/// directory and queues its bundle, which records the moved path. The file
+/// is moved back when the bundle cannot be created. Returns `Ok(false)` when the file was
+/// left for a later poll or set aside as unacceptable.
+async fn ingest_dropped_file(
+    state: &AppState,
+    issue_code: &str,
+    owner_user_id: Option<&str>,
+    issue_dir: &Path,
+    source: &Path,
+) -> Result<bool, AppError> {
+    let filename = source
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("upload.log")
+        .to_string();
+    let size_bytes = fs::metadata(source).await.map_err(AppError::Io)?.len();
+    let max_size = state.limits.issue_max_content_size.saturating_mul(2);
+    if size_bytes > max_size {
+        warn!(
+            issue_code,
+            path = %source.display(),
+            size_bytes,
+            max_size,
+            "drop folder file exceeds the upload size limit; moving it aside"
+        );
+        set_aside(issue_dir, FAILED_DIR, &filename, source).await?;
+        return Ok(false);
+    }
+    let receive_reservation = ReceiveReservation::new(
+        state.upload.tmp_bytes.clone(),
+        state.limits.upload.max_tmp_bytes,
+    );
+    if receive_reservation.reserve(size_bytes).is_err() {
+        debug!(issue_code, path = %source.display(), "upload temp budget exhausted; deferring drop folder file");
+        return Ok(false);
+    }
+
+    let temp_dir = state
+        .storage
+        .data_root
+        .join(".tmp")
+        .join(Uuid::new_v4().simple().to_string());
+    let storage_name = unique_storage_name(&filename);
+    let temp_path = temp_dir.join(&storage_name);
+    let copied = async {
+        fs::create_dir_all(&temp_dir).await?;
+        fs::copy(source, &temp_path).await
+    }
+    .await;
+    match copied {
+        Ok(bytes) if bytes == size_bytes => {}
+        Ok(_) => {
+            // Still being written despite the settle period.
+            let _ = fs::remove_dir_all(&temp_dir).await;
+            return Ok(false);
+        }
+        Err(error) => {
+            let _ = fs::remove_dir_all(&temp_dir).await;
+            return Err(AppError::Io(error));
+        }
+    }
+
+    let bundle_id = Uuid::new_v4().simple().to_string();
+    let bundle_hash = Uuid::new_v4().simple().to_string();
+    let ingested_path = match set_aside(
+        issue_dir,
+        INGESTED_DIR,
+        &format!("{bundle_hash}-{filename}"),
+        source,
+    )
+    .await
+    {
+        Ok(path) => path,
+        Err(error) => {
+            let _ = fs::remove_dir_all(&temp_dir).await;
+            return Err(error);
+        }
+    };
+    let source_path = ingested_path.display().to_string();
+    let display_name = sanitize_filename(&filename);
+    if let Err(error) = create_processing_bundle(
+        &state.db.pool,
+        &bundle_id,
+        issue_code,
+        &bundle_hash,
+        &display_name,
+        size_bytes,
+        owner_user_id,
+        Some(&source_path),
+    )
+    .await
+    {
+        // Puts the file back so the next poll picks it up again.
+        if let Err(restore_error) = fs::rename(&ingested_path, source).await {
+            warn!(
+                issue_code,
+                path = %ingested_path.display(),
+                error = %restore_error,
+                "failed to move drop folder file back after bundle creation failed"
+            );
+        }
+        let _ = fs::remove_dir_all(&temp_dir).await;
+        return Err(error);
+    }
+
+    spawn_upload_job(UploadJob {
+        pool: state.db.pool.clone(),
+        data_root: state.storage.data_root.clone(),
+        blob_store: state.storage.blob_store.clone(),
+        staging_root: temp_dir.join("staging"),
+        temp_dir,
+        processing_permits: state.upload.processing_permits.clone(),
+        archive_config: ArchiveConfig::for_content_limit(state.limits.issue_max_content_size),
+        indexing_config: state.limits.indexing.clone(),
+        request_id: None,
+        issue_code: issue_code.to_string(),
+        issue_max_content_size: state.limits.issue_max_content_size,
+        bundle_id: bundle_id.clone(),
+        bundle_hash: bundle_hash.clone(),
+        files: vec![UploadedFile {
+            original_name: filename,
+            display_name,
+            storage_name,
+            temp_path,
+            size_bytes,
+            content_type: None,
+        }],
+        archive_password: None,
+        template_mining: TemplateMining::from_state(state),
+        receive_reservation,
+        temp_cleanup_queue: state.upload.temp_cleanup_queue.clone(),
+    });
+    touch_issue_activity_best_effort(&state.db.pool, issue_code, "drop folder ingestion").await;
+    info!(
+        issue_code,
+        bundle_id = %bundle_id,
+        bundle_hash = %bundle_hash,
+        source_path,
+        size_bytes,
+        "drop folder file queued for processing"
+    );
+    Ok(true)
+}
+
+async fn set_aside(
+    issue_dir: &Path,
+    subdir: &str,
+    name: &str,
+    source: &Path,
+) -> Result<PathBuf, AppError> {
+    let target_dir = issue_dir.join(subdir);
+    fs::create_dir_all(&target_dir)
+        .await
+        .map_err(AppError::Io)?;
+    let target = target_dir.join(name);
+    fs::rename(source, &target).await.map_err(AppError::Io)?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn only_settled_regular_files_are_picked_up() {
+        let dir = std::env::temp_dir().join(format!("rain-drop-{}", Uuid::new_v4().simple()));
+        fs::create_dir_all(dir.join("nested")).await.unwrap();
+        for name in [
+            "b.log",
+            "a.log",
+            ".hidden.log",
+            "copying.log.part",
+            "upload.TMP",
+        ] {
+            fs::write(dir.join(name), b"line\n").await.unwrap();
+        }
+        fs::write(dir.join("empty.log"), b"").await.unwrap();
+
+        let files = settled_files(&dir, Duration::ZERO).await.unwrap();
+        assert_eq!(files, [dir.join("a.log"), dir.join("b.log")]);
+        assert!(
+            settled_files(&dir, Duration::from_secs(3600))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -221,7 +221,7 @@ pub async fn finalize_bundle_ready_with_retry(
 
 async fn finalize_bundle_ready(pool: &sqlx::SqlitePool, bundle_id: &str) -> Result<(), AppError> {
     set_bundle_stage(pool, bundle_id, "PUBLISHING").await?;
-    sqlx::query("UPDATE bundles SET status = 'READY' WHERE id = ? AND status = 'PROCESSING'")
+    sqlx::query("UPDATE bundles SET status = 'READY', template_state = 'PENDING' WHERE id = ? AND status = 'PROCESSING'")
         .bind(bundle_id)
         .execute(pool)
         .await
@@ -13,6 +13,7 @@ use crate::{
     config::{ArchiveConfig, IndexingConfig},
     error::AppError,
     ingest::{ArchiveBudget, IssueQuota, ProcessFileOptions, process_uploaded_file},
+    services::log_templates::{TemplateMining, trigger_mining},
 };
 
 use super::{
@@ -82,6 +83,8 @@ pub struct UploadJob {
     pub bundle_id: String,
     pub bundle_hash: String,
     pub files: Vec<UploadedFile>,
+    pub archive_password: Option<String>,
+    pub template_mining: TemplateMining,
     pub receive_reservation: ReceiveReservation,
     pub temp_cleanup_queue: TempCleanupQueue,
 }
@@ -192,7 +195,8 @@ pub fn spawn_upload_job(job: UploadJob) {
 
 async fn process_upload_job(job: &UploadJob) -> Result<(), AppError> {
     let archive_budget = ArchiveBudget::new(job.archive_config.clone())
-        .with_temp_budget(job.receive_reservation.temp_budget());
+        .with_temp_budget(job.receive_reservation.temp_budget())
+        .with_password(job.archive_password.clone());
     let issue_quota = IssueQuota::new(
         job.pool.clone(),
         &job.issue_code,
@@ -223,6 +227,7 @@ async fn process_upload_job(job: &UploadJob) -> Result<(), AppError> {
             archive_budget: archive_budget.clone(),
             issue_quota: issue_quota.clone(),
             indexing: &job.indexing_config,
+            parent: None,
         })
         .await?;
         debug!(
@@ -236,6 +241,7 @@ async fn process_upload_job(job: &UploadJob) -> Result<(), AppError> {
     }
 
     finalize_bundle_ready_with_retry(&job.pool, &job.bundle_id).await?;
+    trigger_mining(job.template_mining.clone());
     let _ = fs::remove_dir_all(job.staging_root.join(&job.bundle_hash)).await;
     Ok(())
 }
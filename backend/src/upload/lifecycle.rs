@@ -64,6 +64,7 @@ pub async fn remove_upload_reservation(pool: &sqlx::SqlitePool, bundle_id: &str)
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn create_processing_bundle(
     pool: &sqlx::SqlitePool,
     bundle_id: &str,
@@ -72,11 +73,12 @@ pub async fn create_processing_bundle(
     bundle_name: &str,
     total_bytes: u64,
     uploader_user_id: Option<&str>,
+    source_path: Option<&str>,
 ) -> Result<(), AppError> {
     let result = sqlx::query(
         r#"
-        INSERT INTO bundles (id, issue_code, hash, name, status, process_stage, uploader_user_id, size_bytes)
-        SELECT ?, code, ?, ?, 'PROCESSING', 'RECEIVING', ?, ?
+        INSERT INTO bundles (id, issue_code, hash, name, status, process_stage, uploader_user_id, size_bytes, source_path)
+        SELECT ?, code, ?, ?, 'PROCESSING', 'RECEIVING', ?, ?, ?
         FROM issues
         WHERE code = ? AND status = 'ACTIVE'
         "#,
@@ -86,6 +88,7 @@ pub async fn create_processing_bundle(
     .bind(bundle_name)
     .bind(uploader_user_id)
     .bind(Some(total_bytes as i64))
+    .bind(source_path)
     .bind(issue_code)
     .execute(pool)
     .await
@@ -179,7 +182,7 @@ mod tests {
             .unwrap();
 
         assert!(
-            create_processing_bundle(&pool, "bundle", "RACE", "hash", "name", 1, None)
+            create_processing_bundle(&pool, "bundle", "RACE", "hash", "name", 1, None, None)
                 .await
                 .is_err()
         );
@@ -1,5 +1,8 @@
+pub mod append;
+pub mod drop_folder;
 pub mod filename;
 pub mod finalizer;
 pub mod job;
 pub mod lifecycle;
 pub mod multipart;
+pub mod resumable;
@@ -25,7 +25,7 @@ use crate::{
 
 use super::filename::{format_bytes, sanitize_filename, unique_storage_name};
 
-pub const MAX_MULTIPART_FIELDS: usize = MAX_UPLOAD_FILES + 1;
+pub const MAX_MULTIPART_FIELDS: usize = MAX_UPLOAD_FILES + 2;
 const MAX_MULTIPART_OVERHEAD_BYTES: u64 = 256 * 1024;
 
 pub struct UploadedFile {
@@ -40,6 +40,8 @@ pub struct UploadedFile {
 pub struct MultipartUpload {
     pub files: Vec<UploadedFile>,
     pub total_bytes: u64,
+    /// Held in memory for the extraction job only; never written to the database or logs.
+    pub archive_password: Option<String>,
     pub receive_reservation: ReceiveReservation,
 }
 
@@ -70,7 +72,7 @@ impl ReceiveReservation {
         }
     }
 
-    fn reserve(&self, bytes: u64) -> Result<(), AppError> {
+    pub(crate) fn reserve(&self, bytes: u64) -> Result<(), AppError> {
         self.budget.reserve(bytes)
     }
 
@@ -123,9 +125,10 @@ pub async fn collect_multipart_upload(
     match collect_multipart_upload_inner(payload, temp_dir, max_total_bytes, &receive_reservation)
         .await
     {
-        Ok((files, total_bytes)) => Ok(MultipartUpload {
+        Ok((files, total_bytes, archive_password)) => Ok(MultipartUpload {
             files,
             total_bytes,
+            archive_password,
             receive_reservation,
         }),
         Err(error) => Err(MultipartUploadError {
@@ -140,13 +143,15 @@ async fn collect_multipart_upload_inner(
     temp_dir: &Path,
     max_total_bytes: u64,
     receive_reservation: &ReceiveReservation,
-) -> Result<(Vec<UploadedFile>, u64), AppError> {
+) -> Result<(Vec<UploadedFile>, u64, Option<String>), AppError> {
     let mut files: Vec<UploadedFile> = Vec::new();
+    let mut archive_password = None;
     let mut total_file_bytes: u64 = 0;
     let mut total_request_bytes: u64 = 0;
     let mut file_fields = 0;
     let mut field_count = 0;
     let mut issue_code_fields = 0;
+    let mut archive_password_fields = 0;
 
     while let Some(mut field) = payload
         .try_next()
@@ -179,6 +184,26 @@ async fn collect_multipart_upload_inner(
                 )
                 .await?;
             }
+            "archive_password" => {
+                archive_password_fields += 1;
+                if archive_password_fields > 1 {
+                    return Err(AppError::BadRequest(
+                        "archive_password field may only appear once".into(),
+                    ));
+                }
+                let bytes = collect_binary_field(
+                    &mut field,
+                    MAX_MULTIPART_TEXT_FIELD_SIZE,
+                    "archive_password",
+                    &mut total_request_bytes,
+                    max_total_bytes,
+                )
+                .await?;
+                let password = String::from_utf8(bytes).map_err(|_| {
+                    AppError::BadRequest("archive_password is not valid UTF-8".into())
+                })?;
+                archive_password = Some(password).filter(|value| !value.is_empty());
+            }
             "files" => {
                 if file_fields >= MAX_UPLOAD_FILES {
                     return Err(AppError::BadRequest(format!(
@@ -245,7 +270,7 @@ async fn collect_multipart_upload_inner(
         return Err(AppError::BadRequest("no files provided".into()));
     }
 
-    Ok((files, total_file_bytes))
+    Ok((files, total_file_bytes, archive_password))
 }
 
 pub fn raw_payload_limit(max_total_bytes: u64) -> u64 {
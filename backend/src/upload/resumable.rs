@@ -0,0 +1,451 @@
+//! Resumable single-file uploads received over several requests.
+//!
+//! A session is created with the file's size and SHA-256 and then filled by
+//! appending chunks at the offset the server reports, so a dropped connection
+//! only costs the chunk in flight. Sessions live in memory, as does the
+//! temporary directory they stage into, which startup clears anyway. Each
+//! session reserves its declared size from the temporary storage budget when
+//! it is created, so accepted sessions cannot run out of room halfway.
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use actix_web::{error::PayloadError, http::StatusCode, web::Bytes};
+use futures_util::{Stream, TryStreamExt};
+use sha2::{Digest, Sha256};
+use tokio::{
+    fs,
+    io::{AsyncSeekExt, AsyncWriteExt},
+    sync::Mutex as AsyncMutex,
+};
+
+use crate::error::AppError;
+
+use super::{
+    filename::{format_bytes, sanitize_filename, unique_storage_name},
+    job::TempCleanupQueue,
+    multipart::{MultipartUpload, MultipartUploadError, ReceiveReservation, UploadedFile},
+};
+
+/// Sessions without a chunk for this long are discarded.
+pub const RESUMABLE_UPLOAD_IDLE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+pub const MAX_RESUMABLE_UPLOADS_PER_USER: usize = 8;
+
+pub struct ResumableUpload {
+    pub issue_code: String,
+    original_name: String,
+    storage_name: String,
+    content_type: Option<String>,
+    size: u64,
+    sha256: String,
+    /// Held in memory for the extraction job only; never written to the database or logs.
+    archive_password: Option<String>,
+    pub temp_dir: PathBuf,
+    temp_path: PathBuf,
+    hasher: Sha256,
+    offset: Arc<AtomicU64>,
+    last_activity: Arc<Mutex<Instant>>,
+    receive_reservation: ReceiveReservation,
+}
+
+pub struct NewResumableUpload {
+    pub issue_code: String,
+    pub filename: String,
+    pub content_type: Option<String>,
+    pub size: u64,
+    pub sha256: String,
+    pub archive_password: Option<String>,
+    pub temp_dir: PathBuf,
+    pub receive_reservation: ReceiveReservation,
+}
+
+impl ResumableUpload {
+    /// Reserves the declared size from `receive_reservation`; the reservation
+    /// is released when the session is dropped or handed to the upload job.
+    pub fn new(upload: NewResumableUpload) -> Result<Self, AppError> {
+        upload.receive_reservation.reserve(upload.size)?;
+        let storage_name = unique_storage_name(&upload.filename);
+        Ok(Self {
+            temp_path: upload.temp_dir.join(format!("0-{storage_name}")),
+            issue_code: upload.issue_code,
+            original_name: upload.filename,
+            storage_name,
+            content_type: upload.content_type,
+            size: upload.size,
+            sha256: upload.sha256.to_ascii_lowercase(),
+            archive_password: upload.archive_password,
+            temp_dir: upload.temp_dir,
+            hasher: Sha256::new(),
+            offset: Arc::new(AtomicU64::new(0)),
+            last_activity: Arc::new(Mutex::new(Instant::now())),
+            receive_reservation: upload.receive_reservation,
+        })
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset.load(Ordering::Acquire)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.offset() == self.size
+    }
+
+    /// Appends `chunks` at `offset`, which must match the bytes received so
+    /// far. Bytes written before a failure are kept, so the client can resume
+    /// from the offset it reads back.
+    pub async fn append<S>(&mut self, offset: u64, mut chunks: S) -> Result<(), AppError>
+    where
+        S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+    {
+        let current = self.offset();
+        if offset != current {
+            return Err(AppError::public(
+                StatusCode::CONFLICT,
+                "UPLOAD_OFFSET_MISMATCH",
+                format!("上传偏移量不匹配，服务端已接收 {current} 字节"),
+            ));
+        }
+        self.touch();
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(&self.temp_path)
+            .await
+            .map_err(AppError::Io)?;
+        // Drops any partial chunk left by an interrupted write.
+        file.set_len(current).await.map_err(AppError::Io)?;
+        file.seek(std::io::SeekFrom::Start(current))
+            .await
+            .map_err(AppError::Io)?;
+        let result = async {
+            while let Some(chunk) = chunks
+                .try_next()
+                .await
+                .map_err(|err| AppError::BadRequest(format!("failed to read chunk: {err}")))?
+            {
+                let next = self
+                    .offset()
+                    .checked_add(chunk.len() as u64)
+                    .filter(|next| *next <= self.size)
+                    .ok_or_else(|| {
+                        AppError::BadRequest(format!(
+                            "upload exceeds its declared size of {}",
+                            format_bytes(self.size)
+                        ))
+                    })?;
+                // The next append truncates back to the last full chunk.
+                file.write_all(&chunk).await.map_err(AppError::Io)?;
+                self.hasher.update(&chunk);
+                self.offset.store(next, Ordering::Release);
+            }
+            Ok(())
+        }
+        .await;
+        let flushed = file.flush().await.map_err(AppError::Io);
+        self.touch();
+        result.and(flushed)
+    }
+
+    /// Checks the received file against the declared SHA-256 and turns it into
+    /// the input of an upload job.
+    pub fn finish(self) -> Result<MultipartUpload, MultipartUploadError> {
+        let complete = self.is_complete();
+        let digest = format!("{:x}", self.hasher.finalize());
+        if !complete || digest != self.sha256 {
+            return Err(MultipartUploadError {
+                error: AppError::api(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "UPLOAD_CHECKSUM_MISMATCH",
+                    "上传文件的 SHA-256 与创建时声明的不一致，请重新上传",
+                ),
+                receive_reservation: self.receive_reservation,
+            });
+        }
+        Ok(MultipartUpload {
+            files: vec![UploadedFile {
+                display_name: sanitize_filename(&self.original_name),
+                storage_name: self.storage_name,
+                original_name: self.original_name,
+                temp_path: self.temp_path,
+                size_bytes: self.size,
+                content_type: self.content_type,
+            }],
+            total_bytes: self.size,
+            archive_password: self.archive_password,
+            receive_reservation: self.receive_reservation,
+        })
+    }
+
+    fn touch(&self) {
+        if let Ok(mut last_activity) = self.last_activity.lock() {
+            *last_activity = Instant::now();
+        }
+    }
+}
+
+/// Lock-free view of a session, readable while a chunk is being appended.
+#[derive(Clone)]
+pub struct ResumableUploadHandle {
+    pub user_id: String,
+    pub size: u64,
+    offset: Arc<AtomicU64>,
+    last_activity: Arc<Mutex<Instant>>,
+    /// `None` once the upload has been finished or aborted.
+    pub upload: Arc<AsyncMutex<Option<ResumableUpload>>>,
+}
+
+impl ResumableUploadHandle {
+    pub fn offset(&self) -> u64 {
+        self.offset.load(Ordering::Acquire)
+    }
+
+    fn idle_for(&self, now: Instant) -> Duration {
+        self.last_activity
+            .lock()
+            .map(|last_activity| now.saturating_duration_since(*last_activity))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ResumableUploads(Arc<Mutex<HashMap<String, ResumableUploadHandle>>>);
+
+impl ResumableUploads {
+    pub fn insert(
+        &self,
+        id: &str,
+        user_id: &str,
+        upload: ResumableUpload,
+    ) -> Result<ResumableUploadHandle, AppError> {
+        let mut uploads = self.0.lock().map_err(|_| {
+            AppError::Io(std::io::Error::other("resumable upload registry poisoned"))
+        })?;
+        if uploads
+            .values()
+            .filter(|handle| handle.user_id == user_id)
+            .count()
+            >= MAX_RESUMABLE_UPLOADS_PER_USER
+        {
+            return Err(AppError::public(
+                StatusCode::TOO_MANY_REQUESTS,
+                "RESUMABLE_UPLOAD_LIMIT",
+                format!(
+                    "每个用户最多同时进行 {MAX_RESUMABLE_UPLOADS_PER_USER} 个可续传上传，请先完成或取消已有上传"
+                ),
+            ));
+        }
+        let handle = ResumableUploadHandle {
+            user_id: user_id.to_string(),
+            size: upload.size,
+            offset: upload.offset.clone(),
+            last_activity: upload.last_activity.clone(),
+            upload: Arc::new(AsyncMutex::new(Some(upload))),
+        };
+        uploads.insert(id.to_string(), handle.clone());
+        Ok(handle)
+    }
+
+    /// Session `id` if it belongs to `user_id`; other users' sessions are
+    /// reported as missing.
+    pub fn get(&self, id: &str, user_id: &str) -> Result<ResumableUploadHandle, AppError> {
+        self.0
+            .lock()
+            .ok()
+            .and_then(|uploads| uploads.get(id).cloned())
+            .filter(|handle| handle.user_id == user_id)
+            .ok_or_else(|| AppError::NotFound(format!("resumable upload {id}")))
+    }
+
+    pub fn remove(&self, id: &str) {
+        if let Ok(mut uploads) = self.0.lock() {
+            uploads.remove(id);
+        }
+    }
+
+    /// Discards sessions idle for longer than `ttl`, skipping any that is
+    /// receiving a chunk right now.
+    pub async fn expire_idle(&self, ttl: Duration, cleanup_queue: &TempCleanupQueue) -> usize {
+        let now = Instant::now();
+        let idle: Vec<(String, ResumableUploadHandle)> = self
+            .0
+            .lock()
+            .map(|uploads| {
+                uploads
+                    .iter()
+                    .filter(|(_, handle)| handle.idle_for(now) > ttl)
+                    .map(|(id, handle)| (id.clone(), handle.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut expired = 0;
+        for (id, handle) in idle {
+            let Ok(mut guard) = handle.upload.try_lock() else {
+                continue;
+            };
+            if handle.idle_for(Instant::now()) <= ttl {
+                continue;
+            }
+            self.remove(&id);
+            if let Some(upload) = guard.take() {
+                discard_upload(upload, cleanup_queue).await;
+                expired += 1;
+            }
+        }
+        expired
+    }
+}
+
+/// Removes a session's staged bytes, queueing a retry that keeps the budget
+/// reservation when the directory cannot be removed yet.
+pub async fn discard_upload(upload: ResumableUpload, cleanup_queue: &TempCleanupQueue) {
+    discard_temp_dir(upload.temp_dir, upload.receive_reservation, cleanup_queue).await;
+}
+
+pub async fn discard_temp_dir(
+    temp_dir: PathBuf,
+    reservation: ReceiveReservation,
+    cleanup_queue: &TempCleanupQueue,
+) {
+    match fs::remove_dir_all(&temp_dir).await {
+        Ok(()) => {}
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+        Err(error) => {
+            tracing::warn!(path = %temp_dir.display(), error = %error, "failed to remove resumable upload directory; queueing retry");
+            cleanup_queue.enqueue(temp_dir, reservation);
+        }
+    }
+}
+
+pub fn spawn_resumable_upload_cleanup(
+    uploads: ResumableUploads,
+    cleanup_queue: TempCleanupQueue,
+) -> tokio::task::JoinHandle<()> {
+    crate::spawn_periodic_job(
+        "resumable-upload-cleanup",
+        Duration::from_secs(60),
+        Duration::from_secs(10 * 60),
+        move || {
+            let uploads = uploads.clone();
+            let cleanup_queue = cleanup_queue.clone();
+            async move {
+                let expired = uploads
+                    .expire_idle(RESUMABLE_UPLOAD_IDLE_TTL, &cleanup_queue)
+                    .await;
+                if expired > 0 {
+                    tracing::info!(expired, "discarded idle resumable uploads");
+                }
+                Ok(())
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::stream;
+
+    use super::*;
+
+    fn chunks(parts: &[&'static [u8]]) -> impl Stream<Item = Result<Bytes, PayloadError>> + Unpin {
+        stream::iter(
+            parts
+                .iter()
+                .map(|part| Ok(Bytes::from_static(part)))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    async fn new_upload(
+        content: &[u8],
+        max_tmp_bytes: u64,
+    ) -> Result<(ResumableUpload, Arc<AtomicU64>), AppError> {
+        let temp_dir =
+            std::env::temp_dir().join(format!("rain-resumable-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&temp_dir).await.unwrap();
+        let used = Arc::new(AtomicU64::new(0));
+        let upload = ResumableUpload::new(NewResumableUpload {
+            issue_code: "RESUME".into(),
+            filename: "dump.log".into(),
+            content_type: None,
+            size: content.len() as u64,
+            sha256: format!("{:X}", Sha256::digest(content)),
+            archive_password: None,
+            temp_dir: temp_dir.clone(),
+            receive_reservation: ReceiveReservation::new(used.clone(), max_tmp_bytes),
+        });
+        if upload.is_err() {
+            fs::remove_dir_all(&temp_dir).await.unwrap();
+        }
+        Ok((upload?, used))
+    }
+
+    #[tokio::test]
+    async fn appends_at_matching_offsets_and_verifies_checksum() {
+        let (mut upload, used) = new_upload(b"first\nsecond\n", 1024).await.unwrap();
+        assert_eq!(used.load(Ordering::Acquire), 13);
+        let temp_dir = upload.temp_dir.clone();
+        upload.append(0, chunks(&[b"fir", b"st\n"])).await.unwrap();
+        assert_eq!(upload.offset(), 6);
+        let error = upload.append(0, chunks(&[b"first\n"])).await.unwrap_err();
+        assert!(error.to_string().contains("偏移量"));
+        let error = upload
+            .append(6, chunks(&[b"second\nand more"]))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("declared size"));
+        assert_eq!(upload.offset(), 6);
+        upload.append(6, chunks(&[b"second\n"])).await.unwrap();
+        assert!(upload.is_complete());
+        assert_eq!(used.load(Ordering::Acquire), 13);
+
+        let received = match upload.finish() {
+            Ok(received) => received,
+            Err(error) => panic!("checksum rejected: {}", error.error),
+        };
+        assert_eq!(received.total_bytes, 13);
+        assert_eq!(received.files[0].display_name, "dump.log");
+        assert_eq!(
+            fs::read(&received.files[0].temp_path).await.unwrap(),
+            b"first\nsecond\n"
+        );
+        drop(received);
+        assert_eq!(used.load(Ordering::Acquire), 0);
+        fs::remove_dir_all(temp_dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn interrupted_chunk_keeps_received_bytes_and_budget_is_enforced() {
+        let Err(error) = new_upload(b"abcdef", 4).await else {
+            panic!("session larger than the budget was accepted");
+        };
+        assert!(error.to_string().contains("配额"));
+
+        let (mut upload, used) = new_upload(b"abcdef", 6).await.unwrap();
+        let temp_dir = upload.temp_dir.clone();
+        let broken = stream::iter(vec![
+            Ok(Bytes::from_static(b"ab")),
+            Err(PayloadError::Incomplete(None)),
+        ]);
+        assert!(upload.append(0, broken).await.is_err());
+        assert_eq!(upload.offset(), 2);
+        upload.append(2, chunks(&[b"cdef"])).await.unwrap();
+        assert_eq!(used.load(Ordering::Acquire), 6);
+        drop(upload);
+        assert_eq!(used.load(Ordering::Acquire), 0);
+
+        let (mut mismatched, _) = new_upload(b"abc", 1024).await.unwrap();
+        mismatched.append(0, chunks(&[b"abd"])).await.unwrap();
+        let mismatch_dir = mismatched.temp_dir.clone();
+        assert!(mismatched.finish().is_err());
+        fs::remove_dir_all(temp_dir).await.unwrap();
+        fs::remove_dir_all(mismatch_dir).await.unwrap();
+    }
+}
@@ -639,3 +639,128 @@ async fn administrator_cannot_change_password() {
     let body: serde_json::Value = test::read_body_json(response).await;
     assert_eq!(body["code"], "BUSINESS_USER_REQUIRED");
 }
+
+#[actix_web::test]
+async fn admin_manages_custom_timestamp_formats_with_audit() {
+    let pool = db::init_pool("sqlite::memory:").expect("pool");
+    db::prepare_schema(&pool, true).await.expect("schema");
+    bootstrap_admin::bootstrap_admin(&pool, "admin", "strong-password")
+        .await
+        .expect("bootstrap");
+    let admin_id: String = sqlx::query_scalar("SELECT id FROM users WHERE role='ADMIN'")
+        .fetch_one(&pool)
+        .await
+        .expect("admin");
+    let token = generate_session_token();
+    sessions::create_session(
+        &pool,
+        &admin_id,
+        &hash_session_token(&token),
+        Utc::now() + Duration::hours(1),
+        None,
+        None,
+    )
+    .await
+    .expect("session");
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                PathBuf::from("data"),
+                AppLimits::default(),
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let cookie = Cookie::new(SESSION_COOKIE_NAME, token);
+    let definition = serde_json::json!({
+        "name": "radio",
+        "pattern": r"I\((?P<ts>\d{2}/\d{2} \d{2}:\d{2}:\d{2})\)",
+        "format": "%m/%d %H:%M:%S",
+        "sample": "I(10/15 11:12:13) radio on",
+    });
+
+    let created = test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/admin/timestamp-formats")
+            .cookie(cookie.clone())
+            .set_json(&definition)
+            .to_request(),
+    )
+    .await;
+    assert_eq!(created.status(), StatusCode::CREATED);
+    let created: serde_json::Value = test::read_body_json(created).await;
+    let id = created["id"].as_str().expect("format id").to_owned();
+
+    for (body, expected) in [
+        (definition.clone(), "TIMESTAMP_FORMAT_EXISTS"),
+        (
+            serde_json::json!({"name": "bad", "pattern": "(", "format": "%H", "sample": "x"}),
+            "INVALID_TIMESTAMP_FORMAT",
+        ),
+        (
+            serde_json::json!({
+                "name": "mismatch",
+                "pattern": r"\d{2}:\d{2}:\d{2}",
+                "format": "%H:%M:%S",
+                "sample": "no time here",
+            }),
+            "TIMESTAMP_SAMPLE_MISMATCH",
+        ),
+    ] {
+        let rejected = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri("/api/admin/timestamp-formats")
+                .cookie(cookie.clone())
+                .set_json(body)
+                .to_request(),
+        )
+        .await;
+        assert!(rejected.status().is_client_error());
+        let rejected: serde_json::Value = test::read_body_json(rejected).await;
+        assert_eq!(rejected["code"], expected);
+    }
+
+    let list: serde_json::Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/admin/timestamp-formats")
+            .cookie(cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(list["items"].as_array().expect("items").len(), 1);
+    assert_eq!(list["items"][0]["format"], "%m/%d %H:%M:%S");
+
+    let deleted = test::call_service(
+        &app,
+        test::TestRequest::delete()
+            .uri(&format!("/api/admin/timestamp-formats/{id}"))
+            .cookie(cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+    let missing = test::call_service(
+        &app,
+        test::TestRequest::delete()
+            .uri(&format!("/api/admin/timestamp-formats/{id}"))
+            .cookie(cookie)
+            .to_request(),
+    )
+    .await;
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+    let actions: Vec<String> = sqlx::query_scalar(
+        "SELECT action FROM admin_audit_logs WHERE action LIKE 'TIMESTAMP_FORMAT_%' ORDER BY created_at, action",
+    )
+    .fetch_all(&pool)
+    .await
+    .expect("audit");
+    assert_eq!(
+        actions,
+        vec!["TIMESTAMP_FORMAT_CREATED", "TIMESTAMP_FORMAT_DELETED"]
+    );
+}
@@ -0,0 +1,179 @@
+use std::{fs, net::TcpListener, path::PathBuf, process::Output};
+
+use actix_web::{App, HttpServer, web};
+use backend::{AppState, auth::password::hash_password, config::AppLimits, db, routes};
+use serde_json::Value;
+use uuid::Uuid;
+
+#[actix_web::test]
+async fn cli_logs_in_creates_issue_uploads_searches_and_downloads() {
+    let test_dir = std::env::temp_dir().join(format!("rain-cli-{}", Uuid::new_v4().simple()));
+    let data_root = test_dir.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+    let pool = db::init_pool(&format!(
+        "sqlite://{}",
+        test_dir
+            .join("rain.db")
+            .display()
+            .to_string()
+            .replace('\\', "/")
+    ))
+    .expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    let password_hash = hash_password("password123").expect("hash password");
+    backend::repositories::users::create_user(&pool, "ci-bot", &password_hash)
+        .await
+        .expect("create user");
+
+    let state = web::Data::new(AppState::new(pool, data_root, AppLimits::default()));
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind test server");
+    let server_url = format!("http://{}", listener.local_addr().unwrap());
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(state.clone())
+            .configure(routes::register)
+    })
+    .workers(1)
+    .listen(listener)
+    .expect("listen")
+    .run();
+    let server_handle = server.handle();
+    actix_web::rt::spawn(server);
+
+    let login = rain_cli(&server_url, &["login", "--username", "ci-bot"]).await;
+    assert!(login.status.success(), "{login:?}");
+    let login = stdout_json(&login);
+    assert_eq!(login["user"]["username"], "ci-bot");
+    let session = login["session"]
+        .as_str()
+        .expect("session token")
+        .to_string();
+
+    let wrong = rain_cli(&server_url, &["issue", "create", "CLI1"]).await;
+    assert_eq!(wrong.status.code(), Some(1));
+    let error: Value = serde_json::from_slice(&wrong.stderr).expect("error json");
+    assert_eq!(error["error"]["status"], 401);
+
+    let created = rain_cli(
+        &server_url,
+        &["--session", &session, "issue", "create", "CLI1"],
+    )
+    .await;
+    assert!(created.status.success(), "{created:?}");
+    assert_eq!(stdout_json(&created)["code"], "CLI1");
+
+    let log_path = test_dir.join("bench.log");
+    fs::write(
+        &log_path,
+        "INFO boot\nERROR sensor timeout on bench 7\nINFO done\n",
+    )
+    .expect("write log");
+    let upload = rain_cli(
+        &server_url,
+        &[
+            "--username=ci-bot",
+            "upload",
+            "CLI1",
+            log_path.to_str().unwrap(),
+            "--poll-interval",
+            "1",
+        ],
+    )
+    .await;
+    assert!(upload.status.success(), "{upload:?}");
+    assert!(String::from_utf8_lossy(&upload.stderr).contains("已上传 100%"));
+    let upload = stdout_json(&upload);
+    assert_eq!(upload["status"], "READY");
+    let bundle_hash = upload["bundle_hash"].as_str().unwrap().to_string();
+
+    let search = rain_cli(
+        &server_url,
+        &[
+            "--session",
+            &session,
+            "search",
+            "CLI1",
+            "timeout",
+            "--level",
+            "ERROR",
+        ],
+    )
+    .await;
+    assert!(search.status.success(), "{search:?}");
+    let search = stdout_json(&search);
+    assert_eq!(search["total"], 1);
+    assert_eq!(search["hits"][0]["bundle_hash"], bundle_hash.as_str());
+    let file_id = search["hits"][0]["file_id"].as_str().unwrap().to_string();
+
+    let output = test_dir.join("downloaded.log");
+    let download = rain_cli(
+        &server_url,
+        &[
+            "--session",
+            &session,
+            "download",
+            "file",
+            &bundle_hash,
+            &file_id,
+            "--output",
+            output.to_str().unwrap(),
+        ],
+    )
+    .await;
+    assert!(download.status.success(), "{download:?}");
+    assert_eq!(
+        stdout_json(&download)["path"],
+        output.display().to_string().as_str()
+    );
+    assert_eq!(
+        fs::read(&output).unwrap(),
+        fs::read(&log_path).unwrap(),
+        "downloaded file matches the upload"
+    );
+
+    let to_stdout = rain_cli(
+        &server_url,
+        &[
+            "--session",
+            &session,
+            "download",
+            "file",
+            &bundle_hash,
+            &file_id,
+            "--output",
+            "-",
+        ],
+    )
+    .await;
+    assert!(to_stdout.status.success(), "{to_stdout:?}");
+    assert_eq!(to_stdout.stdout, fs::read(&log_path).unwrap());
+
+    server_handle.stop(false).await;
+    let _ = fs::remove_dir_all(&test_dir);
+}
+
+#[actix_web::test]
+async fn cli_rejects_unknown_commands_with_usage_exit_code() {
+    let output = rain_cli("http://127.0.0.1:9", &["frobnicate"]).await;
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("用法"));
+}
+
+async fn rain_cli(server_url: &str, args: &[&str]) -> Output {
+    let mut command = std::process::Command::new(PathBuf::from(env!("CARGO_BIN_EXE_rain-cli")));
+    command
+        .args(args)
+        .env("RAIN_SERVER", server_url)
+        .env("RAIN_PASSWORD", "password123")
+        .env_remove("RAIN_USERNAME")
+        .env_remove("RAIN_SESSION");
+    tokio::task::spawn_blocking(move || command.output().expect("run rain-cli"))
+        .await
+        .expect("join rain-cli")
+}
+
+fn stdout_json(output: &Output) -> Value {
+    serde_json::from_slice(&output.stdout).expect("stdout is JSON")
+}
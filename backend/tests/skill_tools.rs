@@ -326,19 +326,19 @@ async fn search_logs_supports_scoped_filters_short_terms_and_bounded_snippets()
         ("a-pending", pending_file, "timeout hidden pending", 50),
         ("b-one", foreign_file, "timeout hidden foreign rv", 60),
     ] {
-        sqlx::query("INSERT INTO log_segments(bundle_id,file_id,content,line_offset,line_end,chunk_index) VALUES(?,?,?,?,?,0)")
+        let content_id = insert_segment_content(&pool, content, line, line + 5, 0).await;
+        sqlx::query("INSERT INTO log_segments(bundle_id,file_id,content_id) VALUES(?,?,?)")
             .bind(bundle)
             .bind(file_id)
-            .bind(content)
-            .bind(line)
-            .bind(line + 5)
+            .bind(content_id)
             .execute(&pool)
             .await
             .unwrap();
     }
-    sqlx::query("INSERT INTO log_segments(bundle_id,file_id,content,line_offset,line_end,chunk_index) VALUES('a-one',?,?,70,70,1)")
+    let long_content_id = insert_segment_content(&pool, &"x".repeat(200), 70, 70, 1).await;
+    sqlx::query("INSERT INTO log_segments(bundle_id,file_id,content_id) VALUES('a-one',?,?)")
         .bind(file_a)
-        .bind("x".repeat(200))
+        .bind(long_content_id)
         .execute(&pool)
         .await
         .unwrap();
@@ -454,11 +454,11 @@ async fn search_logs_caps_hits_and_marks_truncation() {
     let file_id: i64 = sqlx::query_scalar("INSERT INTO files(bundle_id,name,path,is_dir) VALUES('a','app.log','/app.log',0) RETURNING id")
         .fetch_one(&pool).await.unwrap();
     for index in 0..25_i64 {
-        sqlx::query("INSERT INTO log_segments(bundle_id,file_id,content,line_offset,line_end,chunk_index) VALUES('a',?,'repeated marker',?,?,?)")
+        let content_id =
+            insert_segment_content(&pool, "repeated marker", index, index, index).await;
+        sqlx::query("INSERT INTO log_segments(bundle_id,file_id,content_id) VALUES('a',?,?)")
             .bind(file_id)
-            .bind(index)
-            .bind(index)
-            .bind(index)
+            .bind(content_id)
             .execute(&pool)
             .await
             .unwrap();
@@ -496,8 +496,13 @@ async fn search_logs_duplicate_key_includes_normalized_filters() {
         .execute(&pool).await.unwrap();
     let file_id: i64 = sqlx::query_scalar("INSERT INTO files(bundle_id,name,path,is_dir) VALUES('a','app.log','/qnx/app.log',0) RETURNING id")
         .fetch_one(&pool).await.unwrap();
-    sqlx::query("INSERT INTO log_segments(bundle_id,file_id,content,line_offset,line_end,chunk_index) VALUES('a',?,'timeout happened ÄB failure',1,1,0)")
-        .bind(file_id).execute(&pool).await.unwrap();
+    let content_id = insert_segment_content(&pool, "timeout happened ÄB failure", 1, 1, 0).await;
+    sqlx::query("INSERT INTO log_segments(bundle_id,file_id,content_id) VALUES('a',?,?)")
+        .bind(file_id)
+        .bind(content_id)
+        .execute(&pool)
+        .await
+        .unwrap();
     let state = AppState::new(pool, PathBuf::from("data"), AppLimits::default());
     let mut executor = SkillToolExecutor::new(
         &state,
@@ -604,21 +609,42 @@ async fn search_logs_applies_run_scope_to_fts_and_short_literal_hits() {
     }))
     .unwrap()
     .unwrap();
-    sqlx::query("INSERT INTO log_segments(bundle_id,file_id,content,line_offset,line_end,chunk_index,event_time_start_ms,event_time_end_ms,event_time_indexed) VALUES('a',?,'[E][2026-08-14 09:32:15][worker] timeout wallclock no timezone',0,0,0,?,?,1),('a',?,'2026-08-14 09:50:00 timeout outside',1,1,1,?,?,1),('a',?,'timeout unknown time',2,2,2,NULL,NULL,1),('a',?,'zz unindexed',3,3,3,NULL,NULL,1),('a',?,'2026-08-14 09:35:00 pending key',4,4,4,?,?,0)")
-        .bind(file_id)
-        .bind(timeout_inside.start_ms)
-        .bind(timeout_inside.end_ms)
-        .bind(file_id)
-        .bind(timeout_outside.start_ms)
-        .bind(timeout_outside.end_ms)
-        .bind(file_id)
-        .bind(file_id)
-        .bind(file_id)
-        .bind(pending_inside.start_ms)
-        .bind(pending_inside.end_ms)
-        .execute(&pool)
-        .await
-        .unwrap();
+    for (line, content, start_ms, end_ms, indexed) in [
+        (
+            0_i64,
+            "[E][2026-08-14 09:32:15][worker] timeout wallclock no timezone",
+            Some(timeout_inside.start_ms),
+            Some(timeout_inside.end_ms),
+            1_i64,
+        ),
+        (
+            1,
+            "2026-08-14 09:50:00 timeout outside",
+            Some(timeout_outside.start_ms),
+            Some(timeout_outside.end_ms),
+            1,
+        ),
+        (2, "timeout unknown time", None, None, 1),
+        (3, "zz unindexed", None, None, 1),
+        (
+            4,
+            "2026-08-14 09:35:00 pending key",
+            Some(pending_inside.start_ms),
+            Some(pending_inside.end_ms),
+            0,
+        ),
+    ] {
+        let content_id = insert_segment_content(&pool, content, line, line, line).await;
+        sqlx::query("INSERT INTO log_segments(bundle_id,file_id,content_id,event_time_start_ms,event_time_end_ms,event_time_indexed) VALUES('a',?,?,?,?,?)")
+            .bind(file_id)
+            .bind(content_id)
+            .bind(start_ms)
+            .bind(end_ms)
+            .bind(indexed)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
     let state = AppState::new(pool, PathBuf::from("data"), AppLimits::default());
     let mut scoped = SkillToolExecutor::new(
         &state,
@@ -870,3 +896,20 @@ async fn read_file_lines_exposes_bounded_long_lines_and_records_only_returned_ev
 
     tokio::fs::remove_dir_all(data_root).await.unwrap();
 }
+
+async fn insert_segment_content(
+    pool: &sqlx::SqlitePool,
+    content: &str,
+    line_offset: i64,
+    line_end: i64,
+    chunk_index: i64,
+) -> i64 {
+    sqlx::query_scalar("INSERT INTO log_segment_contents(content,line_offset,line_end,chunk_index) VALUES(?,?,?,?) RETURNING id")
+        .bind(content)
+        .bind(line_offset)
+        .bind(line_end)
+        .bind(chunk_index)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
@@ -12,6 +12,7 @@ use backend::{
     db,
     repositories::{sessions, users},
     routes,
+    services::log_templates,
 };
 use chrono::{Duration, Utc};
 use flate2::{Compression, write::GzEncoder};
@@ -570,7 +571,7 @@ async fn upload_search_tree_and_delete_issue() {
                 (SELECT COUNT(*) FROM log_line_offsets WHERE file_id = ?),
                 (SELECT COUNT(*) FROM log_segments WHERE file_id = ?),
                 (SELECT COUNT(*) FROM log_segments_fts fts
-                 JOIN log_segments ls ON ls.id = fts.rowid WHERE ls.file_id = ?)
+                 JOIN log_segments ls ON ls.content_id = fts.rowid WHERE ls.file_id = ?)
             "#,
         )
         .bind(file_id)
@@ -1770,11 +1771,18 @@ async fn bundle_content_cleanup_runs_in_batches_and_preserves_bundle() {
         .execute(&pool)
         .await
         .expect("insert offset");
+        let content_id: i64 = sqlx::query_scalar(
+            "INSERT INTO log_segment_contents (content) VALUES (?) RETURNING id",
+        )
+        .bind(format!("ERROR cleanup {index}"))
+        .fetch_one(&pool)
+        .await
+        .expect("insert segment content");
         let _segment_id: i64 = sqlx::query_scalar(
-            "INSERT INTO log_segments (bundle_id, file_id, content) VALUES ('cleanup', ?, ?) RETURNING id",
+            "INSERT INTO log_segments (bundle_id, file_id, content_id) VALUES ('cleanup', ?, ?) RETURNING id",
         )
         .bind(file_id)
-        .bind(format!("ERROR cleanup {index}"))
+        .bind(content_id)
         .fetch_one(&pool)
         .await
         .expect("insert segment");
@@ -1794,6 +1802,7 @@ async fn bundle_content_cleanup_runs_in_batches_and_preserves_bundle() {
         "log_line_offsets",
         "log_segments_fts",
         "log_segments",
+        "log_segment_contents",
         "files",
     ] {
         let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table}"))
@@ -1864,10 +1873,17 @@ async fn run_wal_cleanup_round(pool: &sqlx::SqlitePool, round: usize) {
         .await
         .expect("begin WAL seed");
     for chunk in 0..250i64 {
-        sqlx::query("INSERT INTO log_segments(bundle_id,file_id,content,chunk_index) VALUES(?, ?, 'WAL reuse payload', ?)")
+        let content_id: i64 = sqlx::query_scalar(
+            "INSERT INTO log_segment_contents(content,chunk_index) VALUES('WAL reuse payload', ?) RETURNING id",
+        )
+        .bind(chunk)
+        .fetch_one(&mut *writer)
+        .await
+        .expect("seed WAL segment content");
+        sqlx::query("INSERT INTO log_segments(bundle_id,file_id,content_id) VALUES(?, ?, ?)")
             .bind(&bundle_id)
             .bind(file_id)
-            .bind(chunk)
+            .bind(content_id)
             .execute(&mut *writer)
             .await
             .expect("seed WAL segment");
@@ -1985,12 +2001,21 @@ async fn cleanup_latency_benchmark_reports_foreground_write_percentiles() {
         .await
         .expect("begin benchmark seed");
     for row in 0..rows {
-        sqlx::query("INSERT INTO log_segments (bundle_id, file_id, content, chunk_index) VALUES ('bench', ?, 'benchmark log content', ?)")
-            .bind(file_id)
-            .bind(row as i64)
-            .execute(&mut *seed_connection)
-            .await
-            .expect("seed log segment");
+        let content_id: i64 = sqlx::query_scalar(
+            "INSERT INTO log_segment_contents (content, chunk_index) VALUES ('benchmark log content', ?) RETURNING id",
+        )
+        .bind(row as i64)
+        .fetch_one(&mut *seed_connection)
+        .await
+        .expect("seed log segment content");
+        sqlx::query(
+            "INSERT INTO log_segments (bundle_id, file_id, content_id) VALUES ('bench', ?, ?)",
+        )
+        .bind(file_id)
+        .bind(content_id)
+        .execute(&mut *seed_connection)
+        .await
+        .expect("seed log segment");
     }
     sqlx::query("COMMIT")
         .execute(&mut *seed_connection)
@@ -2177,16 +2202,22 @@ async fn processing_bundles_cannot_be_deleted() {
     .fetch_one(&pool)
     .await
     .expect("insert processing file");
+    let content_id: i64 =
+        sqlx::query_scalar("INSERT INTO log_segment_contents (content, line_offset, line_end, chunk_index) VALUES (?, 0, 1, 0) RETURNING id")
+            .bind("ERROR processing partial index")
+            .fetch_one(&pool)
+            .await
+            .expect("insert processing segment content");
     let _segment_id: i64 = sqlx::query_scalar(
         r#"
-        INSERT INTO log_segments (bundle_id, file_id, content, line_offset, line_end, chunk_index)
-        VALUES (?, ?, ?, 0, 1, 0)
+        INSERT INTO log_segments (bundle_id, file_id, content_id)
+        VALUES (?, ?, ?)
         RETURNING id
         "#,
     )
     .bind("busy-bundle")
     .bind(file_id)
-    .bind("ERROR processing partial index")
+    .bind(content_id)
     .fetch_one(&pool)
     .await
     .expect("insert processing segment");
@@ -2254,267 +2285,3710 @@ async fn processing_bundles_cannot_be_deleted() {
     assert_eq!(delete_issue.status(), StatusCode::CONFLICT);
 }
 
-async fn wait_for_issue_ready(pool: &sqlx::SqlitePool, issue_code: &str) {
-    wait_for_issue_status(pool, issue_code, "READY").await;
-}
+#[actix_web::test]
+async fn encrypted_zip_uses_archive_password_and_reports_distinct_failures() {
+    let test_dir = TestDir::new("rain-zip-password");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
 
-async fn wait_for_issue_status(pool: &sqlx::SqlitePool, issue_code: &str, status: &str) {
-    for _ in 0..100 {
-        let ready: bool = sqlx::query_scalar(
-            r#"
-            SELECT EXISTS (
-                SELECT 1
-                FROM bundles
-                WHERE issue_code = ? AND status = ?
-            )
-            "#,
-        )
-        .bind(issue_code)
-        .bind(status)
-        .fetch_one(pool)
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
         .await
-        .expect("poll issue status");
-        if ready {
-            return;
-        }
-        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
-    }
-    let states: Vec<(String, String)> =
-        sqlx::query_as("SELECT status, process_stage FROM bundles WHERE issue_code = ?")
-            .bind(issue_code)
-            .fetch_all(pool)
-            .await
-            .expect("inspect timed out issue status");
-    panic!("issue {issue_code} did not become {status}; observed {states:?}");
-}
-
-async fn wait_for_issue_deleted(pool: &sqlx::SqlitePool, issue_code: &str) {
-    for _ in 0..100 {
-        let exists: bool =
-            sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM issues WHERE code = ?)")
-                .bind(issue_code)
-                .fetch_one(pool)
-                .await
-                .expect("poll issue deletion");
-        if !exists {
-            return;
-        }
-        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
-    }
-    panic!("issue {issue_code} was not deleted");
-}
+        .expect("prepare schema");
+    insert_issues(&pool, &["ZIPNOPASS", "ZIPBADPASS", "ZIPPASS"]).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                AppLimits::default(),
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let archive = zip_crypto_bytes("secret/device.log", b"ERROR encrypted payload\n", "s3cret");
 
-async fn wait_for_bundle_status(pool: &sqlx::SqlitePool, bundle_hash: &str, status: &str) {
-    for _ in 0..100 {
-        let current: Option<String> =
-            sqlx::query_scalar("SELECT status FROM bundles WHERE hash = ?")
-                .bind(bundle_hash)
-                .fetch_optional(pool)
-                .await
-                .expect("poll bundle status");
-        if current.as_deref() == Some(status) {
-            return;
+    for (issue_code, password, expected_code) in [
+        ("ZIPNOPASS", None, Some("ARCHIVE_PASSWORD_REQUIRED")),
+        (
+            "ZIPBADPASS",
+            Some("wrong"),
+            Some("ARCHIVE_PASSWORD_INVALID"),
+        ),
+        ("ZIPPASS", Some("s3cret"), None),
+    ] {
+        let boundary = format!("rain-{}", Uuid::new_v4().simple());
+        let mut body = Vec::new();
+        if let Some(password) = password {
+            body.extend_from_slice(
+                format!(
+                    "--{boundary}\r\n\
+Content-Disposition: form-data; name=\"archive_password\"\r\n\r\n\
+{password}\r\n"
+                )
+                .as_bytes(),
+            );
         }
-        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
-    }
-    panic!("bundle {bundle_hash} did not become {status}");
-}
-
-fn multipart_body(boundary: &str, issue_code: &str, filename: &str, content: &str) -> Vec<u8> {
-    multipart_body_bytes(
-        boundary,
-        issue_code,
-        filename,
-        "text/plain",
-        content.as_bytes(),
-    )
-}
+        body.extend_from_slice(&multipart_body_bytes(
+            &boundary,
+            issue_code,
+            "locked.zip",
+            "application/zip",
+            &archive,
+        ));
+        let upload: Value = test::call_and_read_body_json(
+            &app,
+            test::TestRequest::post()
+                .uri(&format!("/api/issues/{issue_code}/uploads"))
+                .insert_header((
+                    "content-type",
+                    format!("multipart/form-data; boundary={boundary}"),
+                ))
+                .set_payload(body)
+                .cookie(auth_cookie.clone())
+                .to_request(),
+        )
+        .await;
+        let bundle_hash = upload["bundle_hash"].as_str().expect("bundle hash");
 
-async fn insert_issues(pool: &sqlx::SqlitePool, issue_codes: &[&str]) {
-    for code in issue_codes {
-        sqlx::query("INSERT INTO issues (code, name) VALUES (?, ?)")
-            .bind(code)
-            .bind(code)
-            .execute(pool)
+        let Some(expected_code) = expected_code else {
+            wait_for_issue_ready(&pool, issue_code).await;
+            let extracted: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM files WHERE name = 'device.log' AND line_count = 1",
+            )
+            .fetch_one(&pool)
             .await
-            .expect("insert issue");
+            .expect("count decrypted entry");
+            assert_eq!(extracted, 1);
+            continue;
+        };
+        wait_for_issue_status(&pool, issue_code, "FAILED").await;
+        let task: Value = test::call_and_read_body_json(
+            &app,
+            test::TestRequest::get()
+                .uri(&format!("/api/uploads/{bundle_hash}"))
+                .cookie(auth_cookie.clone())
+                .to_request(),
+        )
+        .await;
+        assert_eq!(task["failure_code"], expected_code, "{issue_code}");
+        assert_eq!(task["retryable"], false);
+        assert!(
+            task["failure_reason"]
+                .as_str()
+                .unwrap_or_default()
+                .contains("压缩包 locked.zip "),
+            "{task}"
+        );
+        assert!(
+            !task["failure_reason"]
+                .as_str()
+                .unwrap_or_default()
+                .contains("wrong")
+        );
     }
 }
 
-async fn test_auth_cookie(pool: &sqlx::SqlitePool) -> Cookie<'static> {
-    let username = format!("smoke-{}", Uuid::new_v4().simple());
-    let user = match users::create_user(pool, &username, "test-password-hash")
-        .await
-        .expect("create smoke test user")
-    {
-        users::CreateUserOutcome::Created(user) => user,
-        users::CreateUserOutcome::DuplicateUsername => panic!("unexpected duplicate test user"),
-    };
-    sqlx::query("UPDATE issues SET owner_user_id = ? WHERE owner_user_id IS NULL")
-        .bind(&user.id)
-        .execute(pool)
+#[actix_web::test]
+async fn legacy_encoded_logs_are_decoded_and_encoding_can_be_overridden() {
+    let test_dir = TestDir::new("rain-encoding");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
         .await
-        .expect("assign smoke issue ownership");
-    let token = generate_session_token();
-    sessions::create_session(
-        pool,
-        &user.id,
-        &hash_session_token(&token),
-        Utc::now() + Duration::hours(1),
-        None,
-        None,
+        .expect("prepare schema");
+    insert_issues(&pool, &["ENCODING"]).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                AppLimits::default(),
+            )))
+            .configure(routes::register),
     )
-    .await
-    .expect("create smoke test session");
-    Cookie::new(SESSION_COOKIE_NAME, token)
-}
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let (content, _, _) = encoding_rs::GBK
+        .encode("2024-01-02 10:00:00 错误 设备启动失败\n2024-01-02 10:00:01 信息 重试成功\n");
+    let boundary = format!("rain-{}", Uuid::new_v4().simple());
+    let upload: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/issues/ENCODING/uploads")
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(multipart_body_bytes(
+                &boundary,
+                "ENCODING",
+                "device.log",
+                "text/plain",
+                &content,
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    let bundle_hash = upload["bundle_hash"].as_str().expect("bundle hash");
+    wait_for_issue_ready(&pool, "ENCODING").await;
 
-fn multipart_body_bytes(
-    boundary: &str,
-    issue_code: &str,
-    filename: &str,
-    content_type: &str,
-    content: &[u8],
-) -> Vec<u8> {
-    let mut body = Vec::new();
-    body.extend_from_slice(
-        format!(
-            "--{boundary}\r\n\
-Content-Disposition: form-data; name=\"issue_code\"\r\n\r\n\
-{issue_code}\r\n\
---{boundary}\r\n\
-Content-Disposition: form-data; name=\"files\"; filename=\"{filename}\"\r\n\
-Content-Type: {content_type}\r\n\r\n"
-        )
-        .as_bytes(),
-    );
-    body.extend_from_slice(content);
-    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
-    body
-}
+    let tree: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!("/api/files/v1/{bundle_hash}/files/root"))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    let node = &tree["children"][0];
+    assert_eq!(node["meta"]["encoding"]["name"], "gb18030");
+    assert_eq!(node["meta"]["encoding"]["source"], "detected");
+    let file_id = node["id"].as_str().expect("file id");
 
-fn multipart_body_multi(boundary: &str, issue_code: &str, files: &[(&str, &str)]) -> Vec<u8> {
-    let mut body = Vec::new();
-    body.extend_from_slice(
-        format!(
-            "--{boundary}\r\n\
-Content-Disposition: form-data; name=\"issue_code\"\r\n\r\n\
-{issue_code}\r\n"
-        )
-        .as_bytes(),
+    let search: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/issues/ENCODING/search?q=%E5%90%AF%E5%8A%A8%E5%A4%B1%E8%B4%A5&size=10")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(search["total"], 1);
+    let lines: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/files/v1/{bundle_hash}/files/{file_id}/lines"
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(
+        lines["lines"][1]["content"],
+        "2024-01-02 10:00:01 信息 重试成功"
     );
-    for (filename, content) in files {
-        body.extend_from_slice(
-            format!(
-                "--{boundary}\r\n\
-Content-Disposition: form-data; name=\"files\"; filename=\"{filename}\"\r\n\
-Content-Type: text/plain\r\n\r\n"
-            )
-            .as_bytes(),
-        );
-        body.extend_from_slice(content.as_bytes());
-        body.extend_from_slice(b"\r\n");
-    }
-    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
-    body
-}
 
-fn multipart_body_multi_bytes(
-    boundary: &str,
-    issue_code: &str,
-    files: &[(&str, &str, &[u8])],
-) -> Vec<u8> {
-    let mut body = Vec::new();
-    body.extend_from_slice(
-        format!(
-            "--{boundary}\r\n\
-Content-Disposition: form-data; name=\"issue_code\"\r\n\r\n\
-{issue_code}\r\n"
-        )
-        .as_bytes(),
-    );
-    for (filename, content_type, content) in files {
-        body.extend_from_slice(
-            format!(
-                "--{boundary}\r\n\
-Content-Disposition: form-data; name=\"files\"; filename=\"{filename}\"\r\n\
-Content-Type: {content_type}\r\n\r\n"
-            )
-            .as_bytes(),
-        );
-        body.extend_from_slice(content);
-        body.extend_from_slice(b"\r\n");
-    }
-    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
-    body
-}
+    let unsupported = test::call_service(
+        &app,
+        test::TestRequest::put()
+            .uri(&format!(
+                "/api/files/v1/{bundle_hash}/files/{file_id}/encoding"
+            ))
+            .set_json(serde_json::json!({ "encoding": "ebcdic" }))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(unsupported.status(), StatusCode::BAD_REQUEST);
 
-fn gzip_bytes(content: &str) -> Vec<u8> {
-    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-    encoder.write_all(content.as_bytes()).expect("write gzip");
-    encoder.finish().expect("finish gzip")
-}
+    let overridden = test::call_service(
+        &app,
+        test::TestRequest::put()
+            .uri(&format!(
+                "/api/files/v1/{bundle_hash}/files/{file_id}/encoding"
+            ))
+            .set_json(serde_json::json!({ "encoding": "shift_jis" }))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(overridden.status(), StatusCode::ACCEPTED);
+    let overridden: Value = test::read_body_json(overridden).await;
+    assert_eq!(overridden["status"], "REINDEXING");
+    assert_eq!(overridden["meta"]["encoding"]["name"], "shift_jis");
+    assert_eq!(overridden["meta"]["encoding"]["source"], "override");
+    wait_for_file_status(&pool, file_id, "READY").await;
+    let search: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/issues/ENCODING/search?q=%E5%90%AF%E5%8A%A8%E5%A4%B1%E8%B4%A5&size=10")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(search["total"], 0);
 
-fn tar_gz_bytes(path: &str, content: &str) -> Vec<u8> {
-    tar_gz_multi(&[(path, content)])
+    let restored: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::put()
+            .uri(&format!(
+                "/api/files/v1/{bundle_hash}/files/{file_id}/encoding"
+            ))
+            .set_json(serde_json::json!({ "encoding": "gbk" }))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(restored["meta"]["encoding"]["name"], "gb18030");
+    wait_for_file_status(&pool, file_id, "READY").await;
+    let search: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/issues/ENCODING/search?q=%E5%90%AF%E5%8A%A8%E5%A4%B1%E8%B4%A5&size=10")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(search["total"], 1);
 }
 
-fn tar_gz_multi(files: &[(&str, &str)]) -> Vec<u8> {
-    let binary_files = files
-        .iter()
-        .map(|(path, content)| (*path, content.as_bytes()))
-        .collect::<Vec<_>>();
-    tar_gz_multi_bytes(&binary_files)
-}
+#[actix_web::test]
+async fn logcat_syslog_and_epoch_lines_populate_event_time_bounds() {
+    let test_dir = TestDir::new("rain-timestamps");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
 
-fn tar_gz_multi_bytes(files: &[(&str, &[u8])]) -> Vec<u8> {
-    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-    {
-        let mut builder = tar::Builder::new(&mut encoder);
-        for (path, content) in files {
-            let mut header = tar::Header::new_gnu();
-            header.set_size(content.len() as u64);
-            header.set_mode(0o644);
-            header.set_cksum();
-            builder
-                .append_data(&mut header, *path, *content)
-                .expect("append tar entry");
-        }
-        builder.finish().expect("finish tar");
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["TIMESTAMPS"]).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                AppLimits::default(),
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let files = [
+        (
+            "logcat.txt",
+            "10-16 08:01:02.345  1234  5678 E ActivityManager: ANR\n10-16 08:05:00.000  1234  5678 I ActivityManager: recovered\n",
+        ),
+        (
+            "syslog.txt",
+            "Oct 16 08:01:03 host kernel: oops\nOct 16 09:00:00 host kernel: fine\n",
+        ),
+        ("epoch.txt", "1792137600 boot\n1792137660123 ready\n"),
+        ("clock.txt", "08:00:00 start\n08:30:00 stop\n"),
+    ];
+    let boundary = format!("rain-{}", Uuid::new_v4().simple());
+    test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/issues/TIMESTAMPS/uploads")
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(multipart_body_multi(&boundary, "TIMESTAMPS", &files))
+            .cookie(auth_cookie)
+            .to_request(),
+    )
+    .await;
+    wait_for_issue_ready(&pool, "TIMESTAMPS").await;
+
+    let bounds: Vec<(String, Option<i64>, Option<i64>)> = sqlx::query_as(
+        "SELECT f.name, ls.event_time_start_ms, ls.event_time_end_ms FROM log_segments ls JOIN files f ON f.id = ls.file_id ORDER BY f.name",
+    )
+    .fetch_all(&pool)
+    .await
+    .expect("event time bounds");
+    assert_eq!(bounds.len(), files.len());
+    for (name, start, end) in bounds {
+        let (Some(start), Some(end)) = (start, end) else {
+            panic!("{name} has no event time bounds");
+        };
+        assert!(start < end, "{name}");
     }
-    encoder.finish().expect("finish tar.gz")
 }
 
-fn zip_bytes(files: &[(&str, &[u8])]) -> Vec<u8> {
-    let cursor = Cursor::new(Vec::new());
-    let mut writer = zip::ZipWriter::new(cursor);
-    let options =
-        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
-    for (path, content) in files {
-        writer.start_file(*path, options).expect("start zip entry");
-        writer.write_all(content).expect("write zip entry");
+#[actix_web::test]
+async fn search_returns_whole_stack_trace_events_with_their_timestamp() {
+    let test_dir = TestDir::new("rain-events");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["EVENTS"]).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                AppLimits::default(),
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let mut log = String::new();
+    for index in 0..198 {
+        log.push_str(&format!(
+            "2026-03-01 10:00:{:02} INFO tick {index}\n",
+            index % 60
+        ));
     }
-    writer.finish().expect("finish zip").into_inner()
-}
+    log.push_str(
+        "2026-03-01 10:05:00 ERROR request failed\n\
+java.lang.IllegalStateException: worker stopped\n\
+\tat com.example.Worker.run(Worker.java:42)\n\
+Caused by: java.io.IOException: socket closed\n\
+\tat com.example.Socket.read(Socket.java:7)\n\
+\t... 3 more\n\
+2026-03-01 10:05:01 INFO recovered\n",
+    );
+    let boundary = format!("rain-{}", Uuid::new_v4().simple());
+    test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/issues/EVENTS/uploads")
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(multipart_body_multi(
+                &boundary,
+                "EVENTS",
+                &[("app.log", log.as_str())],
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    wait_for_issue_ready(&pool, "EVENTS").await;
+    let bundle_hash: String = sqlx::query_scalar("SELECT hash FROM bundles")
+        .fetch_one(&pool)
+        .await
+        .expect("bundle hash");
 
-fn sqlite_url(path: &Path) -> String {
-    format!("sqlite://{}", path.display().to_string().replace('\\', "/"))
-}
+    // The trace starts at line 198, so the 200-line chunk target falls inside it.
+    let segments: Vec<(i64, i64)> = sqlx::query_as(
+        "SELECT line_offset, line_end FROM log_segment_contents ORDER BY line_offset",
+    )
+    .fetch_all(&pool)
+    .await
+    .expect("segments");
+    assert_eq!(segments, vec![(0, 203), (204, 204)]);
 
-struct TestDir {
-    path: PathBuf,
+    let search: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/log/v2/{bundle_hash}/search?q=socket%20closed"
+            ))
+            .cookie(auth_cookie)
+            .to_request(),
+    )
+    .await;
+    assert_eq!(search["total"], 1);
+    let hit = &search["hits"][0];
+    assert_eq!(hit["line_number"], 198);
+    assert_eq!(hit["line_end"], 203);
+    assert_eq!(hit["event_time"], "2026-03-01 10:05:00");
+    let snippet = hit["snippet"].as_str().expect("snippet");
+    assert!(snippet.starts_with("2026-03-01 10:05:00 ERROR request failed\n"));
+    assert!(snippet.ends_with("\nat com.example.Socket.read(Socket.java:7)\n... 3 more"));
 }
 
-impl TestDir {
-    fn new(prefix: &str) -> Self {
-        let path = std::env::temp_dir().join(format!("{prefix}-{}", Uuid::new_v4().simple()));
-        fs::create_dir_all(&path).expect("create temp test dir");
-        Self { path }
+#[actix_web::test]
+async fn identical_blobs_share_indexed_content_until_the_last_file_is_deleted() {
+    let test_dir = TestDir::new("rain-shared-index");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["SHAREA", "SHAREB"]).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                AppLimits::default(),
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let mut log = String::new();
+    for index in 0..450 {
+        log.push_str(&format!(
+            "2026-03-01 10:{:02}:{:02} INFO tick {index}\n",
+            index / 60,
+            index % 60
+        ));
+    }
+    log.push_str("2026-03-01 10:08:00 ERROR sharedmarker failed\n");
+    for issue_code in ["SHAREA", "SHAREB"] {
+        let boundary = format!("rain-{}", Uuid::new_v4().simple());
+        test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri(&format!("/api/issues/{issue_code}/uploads"))
+                .insert_header((
+                    "content-type",
+                    format!("multipart/form-data; boundary={boundary}"),
+                ))
+                .set_payload(multipart_body(&boundary, issue_code, "app.log", &log))
+                .cookie(auth_cookie.clone())
+                .to_request(),
+        )
+        .await;
+        wait_for_issue_ready(&pool, issue_code).await;
     }
-}
 
-impl Drop for TestDir {
-    fn drop(&mut self) {
-        let _ = fs::remove_dir_all(&self.path);
+    let counts: (i64, i64, i64, i64) = sqlx::query_as(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM blob_indexes WHERE state = 'READY'),
+            (SELECT COUNT(*) FROM files WHERE blob_index_id IS NOT NULL),
+            (SELECT COUNT(*) FROM log_segment_contents),
+            (SELECT COUNT(*) FROM log_segments)
+        "#,
+    )
+    .fetch_one(&pool)
+    .await
+    .expect("count shared index rows");
+    assert_eq!(counts.0, 1);
+    assert_eq!(counts.1, 2);
+    assert!(counts.2 > 1, "the log should span several chunks");
+    assert_eq!(counts.3, counts.2 * 2);
+    let per_file: Vec<(i64, i64, Option<i64>, Option<i64>)> = sqlx::query_as(
+        r#"
+        SELECT f.line_count, COUNT(ls.id), MIN(ls.event_time_start_ms), MAX(ls.event_time_end_ms)
+        FROM files f
+        JOIN log_segments ls ON ls.file_id = f.id
+        GROUP BY f.id
+        ORDER BY f.id
+        "#,
+    )
+    .fetch_all(&pool)
+    .await
+    .expect("per-file segments");
+    assert_eq!(per_file.len(), 2);
+    assert_eq!(per_file[0], per_file[1]);
+    assert!(per_file[0].2.is_some());
+
+    for issue_code in ["SHAREA", "SHAREB"] {
+        let search: Value = test::call_and_read_body_json(
+            &app,
+            test::TestRequest::get()
+                .uri(&format!(
+                    "/api/issues/{issue_code}/search?q=sharedmarker&mode=content&size=10"
+                ))
+                .cookie(auth_cookie.clone())
+                .to_request(),
+        )
+        .await;
+        assert_eq!(search["total"], 1, "{issue_code}");
+        assert!(
+            search["hits"][0]["snippet"]
+                .as_str()
+                .expect("snippet")
+                .contains("ERROR sharedmarker failed"),
+            "{issue_code}"
+        );
     }
+
+    let delete_response = test::call_service(
+        &app,
+        test::TestRequest::delete()
+            .uri("/api/issues/SHAREA")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(delete_response.status(), StatusCode::ACCEPTED);
+    wait_for_issue_deleted(&pool, "SHAREA").await;
+    assert_eq!(
+        backend::blob_store::release_unlinked_blob_indexes(&pool)
+            .await
+            .expect("release unused indexes"),
+        0
+    );
+    let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM log_segment_contents")
+        .fetch_one(&pool)
+        .await
+        .expect("count contents after first delete");
+    assert_eq!(remaining, counts.2);
+    let search: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/issues/SHAREB/search?q=sharedmarker&mode=content&size=10")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(search["total"], 1);
+
+    let delete_response = test::call_service(
+        &app,
+        test::TestRequest::delete()
+            .uri("/api/issues/SHAREB")
+            .cookie(auth_cookie)
+            .to_request(),
+    )
+    .await;
+    assert_eq!(delete_response.status(), StatusCode::ACCEPTED);
+    wait_for_issue_deleted(&pool, "SHAREB").await;
+    assert_eq!(
+        backend::blob_store::release_unlinked_blob_indexes(&pool)
+            .await
+            .expect("release the last index"),
+        1
+    );
+    let leftovers: (i64, i64, i64) = sqlx::query_as(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM blob_indexes),
+            (SELECT COUNT(*) FROM log_segment_contents),
+            (SELECT COUNT(*) FROM log_segments_fts)
+        "#,
+    )
+    .fetch_one(&pool)
+    .await
+    .expect("count released rows");
+    assert_eq!(leftovers, (0, 0, 0));
+}
+
+#[actix_web::test]
+async fn log_search_filters_and_counts_by_level() {
+    let test_dir = TestDir::new("rain-levels");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["LEVELS"]).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                AppLimits::default(),
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let files = [
+        ("app.log", "2026-03-01 10:00:00 INFO payment accepted\n"),
+        (
+            "android.txt",
+            "03-01 10:00:01.000  1234  5678 W Payments: payment retry\n",
+        ),
+        (
+            "error.log",
+            "[2026-03-01 10:00:02][E][pay] payment failed\n",
+        ),
+        (
+            "mixed.log",
+            "2026-03-01 10:00:03 ERROR gateway down\n2026-03-01 10:00:04 INFO checkout queued\n",
+        ),
+    ];
+    let boundary = format!("rain-{}", Uuid::new_v4().simple());
+    test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/issues/LEVELS/uploads")
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(multipart_body_multi(&boundary, "LEVELS", &files))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    wait_for_issue_ready(&pool, "LEVELS").await;
+    let bundle_hash: String = sqlx::query_scalar("SELECT hash FROM bundles")
+        .fetch_one(&pool)
+        .await
+        .expect("bundle hash");
+
+    let all: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!("/api/log/v2/{bundle_hash}/search?q=payment"))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(all["total"], 3);
+    assert_eq!(all["level_counts"]["INFO"], 1);
+    assert_eq!(all["level_counts"]["WARN"], 1);
+    assert_eq!(all["level_counts"]["ERROR"], 1);
+    assert_eq!(all["level_counts"]["DEBUG"], 0);
+
+    let errors: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/log/v2/{bundle_hash}/search?q=payment&level=error,fatal"
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(errors["total"], 1);
+    assert_eq!(errors["hits"][0]["level"], "ERROR");
+    assert_eq!(errors["level_counts"]["INFO"], 1);
+
+    let at_least_warn: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/issues/LEVELS/search?q=payment&min_level=warning")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(at_least_warn["total"], 2);
+    let mut levels: Vec<&str> = at_least_warn["hits"]
+        .as_array()
+        .expect("hits")
+        .iter()
+        .map(|hit| hit["level"].as_str().expect("hit level"))
+        .collect();
+    levels.sort_unstable();
+    assert_eq!(levels, ["ERROR", "WARN"]);
+
+    // The ERROR line shares the chunk but does not match, so it selects nothing.
+    let other_line: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/issues/LEVELS/search?q=checkout&level=error")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(other_line["total"], 0);
+    assert_eq!(other_line["level_counts"]["INFO"], 1);
+    assert_eq!(other_line["level_counts"]["ERROR"], 0);
+    let matching_line: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/issues/LEVELS/search?q=checkout&level=info")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(matching_line["total"], 1);
+    assert_eq!(matching_line["hits"][0]["level"], "INFO");
+
+    let invalid = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/log/v2/{bundle_hash}/search?q=payment&min_level=loud"
+            ))
+            .cookie(auth_cookie)
+            .to_request(),
+    )
+    .await;
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn regex_search_verifies_prefiltered_segments_and_returns_captures() {
+    let test_dir = TestDir::new("rain-regex");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["REGEX"]).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                AppLimits::default(),
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let files = [
+        ("a.log", "worker pid=42 timeout after 1500ms\n"),
+        ("b.log", "worker pid=7 timeout after 30ms\n"),
+        ("c.log", "worker pid=9 timeout after slow\n"),
+    ];
+    let boundary = format!("rain-{}", Uuid::new_v4().simple());
+    test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/issues/REGEX/uploads")
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(multipart_body_multi(&boundary, "REGEX", &files))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    wait_for_issue_ready(&pool, "REGEX").await;
+    let bundle_hash: String = sqlx::query_scalar("SELECT hash FROM bundles")
+        .fetch_one(&pool)
+        .await
+        .expect("bundle hash");
+
+    let timeouts: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/log/v2/{bundle_hash}/search?mode=regex&q=timeout%20after%20(%5Cd%2B)ms"
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(timeouts["total"], 2);
+    assert_eq!(timeouts["truncated"], false);
+    let mut durations: Vec<&str> = timeouts["hits"]
+        .as_array()
+        .expect("hits")
+        .iter()
+        .map(|hit| hit["captures"][0]["value"].as_str().expect("capture"))
+        .collect();
+    durations.sort_unstable();
+    assert_eq!(durations, ["1500", "30"]);
+
+    let named: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/issues/REGEX/search?mode=regex&q=pid%3D(%3FP%3Cpid%3E%5Cd)%5Cs&size=1")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(named["total"], 2);
+    assert_eq!(named["hits"].as_array().expect("hits").len(), 1);
+    assert_eq!(named["hits"][0]["captures"][0]["name"], "pid");
+
+    let without_literals: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/log/v2/{bundle_hash}/search?mode=regex&q=%5Cd%7B4%7Dms"
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(without_literals["total"], 1);
+
+    let invalid = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/log/v2/{bundle_hash}/search?mode=regex&q=(unclosed"
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+    let invalid: Value = test::read_body_json(invalid).await;
+    assert_eq!(invalid["code"], "INVALID_REGEX");
+
+    let mut limits = AppLimits::default();
+    limits.api.max_regex_scan_bytes = 40;
+    let budgeted = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                limits,
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let partial: Value = test::call_and_read_body_json(
+        &budgeted,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/log/v2/{bundle_hash}/search?mode=regex&q=pid%3D%5Cd%2B"
+            ))
+            .cookie(auth_cookie)
+            .to_request(),
+    )
+    .await;
+    assert_eq!(partial["total"], 1);
+    assert_eq!(partial["truncated"], true);
+}
+
+#[actix_web::test]
+async fn log_search_filters_by_event_time_and_counts_untimed_hits() {
+    let test_dir = TestDir::new("rain-time-range");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["TIMED"]).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                AppLimits::default(),
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let files = [
+        (
+            "early.log",
+            "2026-03-01 10:00:00 ERROR disk failure early\n",
+        ),
+        ("late.log", "2026-03-01 12:00:00 ERROR disk failure late\n"),
+        ("plain.log", "disk failure without any clock\n"),
+        (
+            "span.log",
+            "2026-03-01 10:00:00 INFO service started\n2026-03-01 12:00:00 WARN cache failure\n",
+        ),
+    ];
+    let boundary = format!("rain-{}", Uuid::new_v4().simple());
+    test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/issues/TIMED/uploads")
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(multipart_body_multi(&boundary, "TIMED", &files))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    wait_for_issue_ready(&pool, "TIMED").await;
+    let bundle_hash: String = sqlx::query_scalar("SELECT hash FROM bundles")
+        .fetch_one(&pool)
+        .await
+        .expect("bundle hash");
+
+    let unfiltered: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/log/v2/{bundle_hash}/search?q=disk%20failure"
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(unfiltered["total"], 3);
+    assert_eq!(unfiltered["untimed_hits"], Value::Null);
+
+    let ranged: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/log/v2/{bundle_hash}/search?q=disk%20failure\
+                 &start=2026-03-01%2009:30:00&end=2026-03-01T10:30:00"
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(ranged["total"], 1);
+    assert!(
+        ranged["hits"][0]["snippet"]
+            .as_str()
+            .expect("snippet")
+            .contains("early")
+    );
+    assert_eq!(ranged["untimed_hits"], 1);
+    assert_eq!(ranged["time_range"]["start"], "2026-03-01 09:30:00");
+    assert_eq!(ranged["time_range"]["end"], "2026-03-01 10:30:00");
+
+    let incident: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(
+                "/api/issues/TIMED/search?q=disk%20failure\
+                 &incident_time=2026-03-01%2012:00:00&before_minutes=5&after_minutes=5",
+            )
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(incident["total"], 1);
+    assert!(
+        incident["hits"][0]["snippet"]
+            .as_str()
+            .expect("snippet")
+            .contains("late")
+    );
+    assert_eq!(incident["untimed_hits"], 1);
+
+    let regex: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/log/v2/{bundle_hash}/search?mode=regex&q=disk%20failure%20(%5Cw%2B)\
+                 &start=2026-03-01%2011:00:00&end=2026-03-01%2013:00:00"
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(regex["total"], 1);
+    assert_eq!(regex["hits"][0]["captures"][0]["value"], "late");
+    assert_eq!(regex["untimed_hits"], 1);
+
+    // The chunk overlaps the range, but its matching line does not.
+    let outside: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/log/v2/{bundle_hash}/search?q=cache%20failure\
+                 &start=2026-03-01%2009:30:00&end=2026-03-01%2010:30:00"
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(outside["total"], 0);
+    assert_eq!(outside["untimed_hits"], 0);
+    let inside: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/log/v2/{bundle_hash}/search?q=cache%20failure\
+                 &start=2026-03-01%2011:30:00&end=2026-03-01%2012:30:00"
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(inside["total"], 1);
+    assert_eq!(inside["hits"][0]["level"], "WARN");
+
+    for query in [
+        "start=2026-03-01%2010:00:00",
+        "start=2026-03-01%2012:00:00&end=2026-03-01%2010:00:00",
+        "incident_time=2026-03-01%2012:00:00&before_minutes=-1&after_minutes=5",
+    ] {
+        let invalid = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri(&format!(
+                    "/api/log/v2/{bundle_hash}/search?q=disk%20failure&{query}"
+                ))
+                .cookie(auth_cookie.clone())
+                .to_request(),
+        )
+        .await;
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST, "{query}");
+        let invalid: Value = test::read_body_json(invalid).await;
+        assert_eq!(invalid["code"], "INVALID_TIME_RANGE", "{query}");
+    }
+}
+
+#[actix_web::test]
+async fn time_range_search_and_histogram_resolve_time_only_lines_from_earlier_chunks() {
+    let test_dir = TestDir::new("rain-carried-time");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["CARRY"]).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                AppLimits::default(),
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    // Only the first line has a date; later chunks take it from earlier ones.
+    let mut log = String::from("2024-03-01 10:00:00 INFO service started\n");
+    for index in 0..300 {
+        log.push_str(&format!("10:01:{:02} INFO tick {index}\n", index % 60));
+    }
+    log.push_str("10:05:00 ERROR disk failure late\n");
+    let boundary = format!("rain-{}", Uuid::new_v4().simple());
+    test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/issues/CARRY/uploads")
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(multipart_body(&boundary, "CARRY", "carry.log", &log))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    wait_for_issue_ready(&pool, "CARRY").await;
+    let chunks: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM log_segments")
+        .fetch_one(&pool)
+        .await
+        .expect("count segments");
+    assert!(chunks > 1);
+
+    let ranged: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(
+                "/api/issues/CARRY/search?q=disk%20failure\
+                 &start=2024-03-01%2010:00:00&end=2024-03-01%2011:00:00",
+            )
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(ranged["total"], 1);
+    assert_eq!(ranged["untimed_hits"], 0);
+
+    let histogram: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(
+                "/api/issues/CARRY/search/histogram?q=disk%20failure\
+                 &start=2024-03-01%2010:00:00&end=2024-03-01%2011:00:00",
+            )
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(histogram["total"], 1);
+    assert_eq!(histogram["untimed_hits"], 0);
+}
+
+#[actix_web::test]
+async fn issue_histogram_buckets_hits_by_event_time_per_bundle_and_file() {
+    let test_dir = TestDir::new("rain-histogram");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["HISTO"]).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                AppLimits::default(),
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let uploads: [&[(&str, &str)]; 2] = [
+        &[
+            ("a.log", "2026-03-01 10:00:00 ERROR disk failure one\n"),
+            ("b.log", "2026-03-01 10:30:00 WARN disk failure two\n"),
+            ("plain.log", "disk failure without any clock\n"),
+        ],
+        // One chunk holding three matching lines.
+        &[(
+            "c.log",
+            "2026-03-01 10:05:00 ERROR disk failure three\n\
+             2026-03-01 10:05:10 ERROR disk failure four\n\
+             2026-03-01 10:05:20 ERROR disk failure five\n",
+        )],
+    ];
+    let mut bundle_hashes = Vec::new();
+    for files in uploads {
+        let boundary = format!("rain-{}", Uuid::new_v4().simple());
+        let upload: Value = test::call_and_read_body_json(
+            &app,
+            test::TestRequest::post()
+                .uri("/api/issues/HISTO/uploads")
+                .insert_header((
+                    "content-type",
+                    format!("multipart/form-data; boundary={boundary}"),
+                ))
+                .set_payload(multipart_body_multi(&boundary, "HISTO", files))
+                .cookie(auth_cookie.clone())
+                .to_request(),
+        )
+        .await;
+        let bundle_hash = upload["bundle_hash"].as_str().expect("bundle hash");
+        wait_for_bundle_status(&pool, bundle_hash, "READY").await;
+        bundle_hashes.push(bundle_hash.to_string());
+    }
+    let per_bundle: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/issues/HISTO/search/histogram?q=disk%20failure")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(per_bundle["bucket_ms"], 60_000);
+    assert_eq!(per_bundle["buckets"].as_array().expect("buckets").len(), 31);
+    assert_eq!(per_bundle["buckets"][0], "2026-03-01 10:00:00");
+    assert_eq!(per_bundle["totals"][0], 1);
+    assert_eq!(per_bundle["totals"][5], 3);
+    assert_eq!(per_bundle["totals"][30], 1);
+    assert_eq!(per_bundle["total"], 5);
+    assert_eq!(per_bundle["untimed_hits"], 1);
+    assert_eq!(per_bundle["truncated"], false);
+    assert_eq!(per_bundle["series"].as_array().expect("series").len(), 2);
+    assert_eq!(per_bundle["series"][0]["total"], 3);
+    assert_eq!(per_bundle["series"][1]["bundle_hash"], bundle_hashes[0]);
+    assert_eq!(per_bundle["series"][1]["file_id"], Value::Null);
+
+    let per_file: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/issues/HISTO/search/histogram?q=disk%20failure&series=file&buckets=3")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(per_file["bucket_ms"], 15 * 60_000);
+    assert_eq!(
+        per_file["buckets"],
+        serde_json::json!([
+            "2026-03-01 10:00:00",
+            "2026-03-01 10:15:00",
+            "2026-03-01 10:30:00"
+        ])
+    );
+    assert_eq!(per_file["totals"], serde_json::json!([4, 0, 1]));
+    let series = per_file["series"].as_array().expect("series");
+    assert_eq!(series.len(), 3);
+    assert!(series.iter().all(|series| series["file_id"].is_string()));
+
+    let errors_in_window: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(
+                "/api/issues/HISTO/search/histogram?q=disk%20failure&min_level=error\
+                 &start=2026-03-01%2009:00:00&end=2026-03-01%2011:00:00",
+            )
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(errors_in_window["buckets"][0], "2026-03-01 09:00:00");
+    assert_eq!(errors_in_window["total"], 4);
+
+    let a_log: i64 =
+        sqlx::query_scalar("SELECT ls.file_id FROM log_segments ls JOIN log_segment_contents lc ON lc.id = ls.content_id WHERE lc.content LIKE '%failure one%'")
+            .fetch_one(&pool)
+            .await
+            .expect("a.log id");
+    let single_file: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/issues/HISTO/search/histogram?q=disk%20failure&file_id={a_log}"
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(single_file["total"], 1);
+    assert_eq!(single_file["untimed_hits"], 0);
+
+    let nothing: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/issues/HISTO/search/histogram?q=no%20such%20text")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(nothing["bucket_ms"], Value::Null);
+    assert_eq!(nothing["total"], 0);
+
+    let short = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/issues/HISTO/search/histogram?q=ab")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(short.status(), StatusCode::BAD_REQUEST);
+
+    let mut limits = AppLimits::default();
+    limits.api.max_histogram_scan_bytes = 1;
+    let budgeted = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                limits,
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let partial: Value = test::call_and_read_body_json(
+        &budgeted,
+        test::TestRequest::get()
+            .uri("/api/issues/HISTO/search/histogram?q=disk%20failure")
+            .cookie(auth_cookie)
+            .to_request(),
+    )
+    .await;
+    assert_eq!(partial["truncated"], true);
+    assert_eq!(partial["total"], 0);
+}
+
+#[actix_web::test]
+async fn log_search_attaches_merged_context_lines_within_the_page_budget() {
+    let test_dir = TestDir::new("rain-context");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["CTX"]).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                AppLimits::default(),
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    // The first chunk ends at line 199; the blank line 202 is not indexed,
+    // so the second match has to be located in the file itself.
+    let mut app_log = String::new();
+    for line in 0..211 {
+        match line {
+            198 => app_log.push_str("disk failure alpha\n"),
+            202 => app_log.push('\n'),
+            203 => app_log.push_str("disk failure beta\n"),
+            _ => app_log.push_str(&format!("filler {line}\n")),
+        }
+    }
+    let files = [
+        ("app.log", app_log.as_str()),
+        ("other.log", "start\ndisk failure gamma\nend\n"),
+    ];
+    let boundary = format!("rain-{}", Uuid::new_v4().simple());
+    test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/issues/CTX/uploads")
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(multipart_body_multi(&boundary, "CTX", &files))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    wait_for_issue_ready(&pool, "CTX").await;
+    let bundle_hash: String = sqlx::query_scalar("SELECT hash FROM bundles")
+        .fetch_one(&pool)
+        .await
+        .expect("bundle hash");
+
+    let with_context: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/log/v2/{bundle_hash}/search?q=disk%20failure&before=2&after=2"
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(with_context["total"], 3);
+    assert_eq!(with_context["context_truncated"], false);
+    let contexts = with_context["contexts"].as_array().expect("contexts");
+    assert_eq!(contexts.len(), 2);
+    let hits = with_context["hits"].as_array().expect("hits");
+    let block_of = |text: &str| {
+        let hit = hits
+            .iter()
+            .find(|hit| hit["snippet"].as_str().expect("snippet").contains(text))
+            .expect("hit");
+        &contexts[hit["context_index"].as_u64().expect("context index") as usize]
+    };
+    let shared = block_of("alpha");
+    assert_eq!(shared["start"], 196);
+    let numbers: Vec<i64> = shared["lines"]
+        .as_array()
+        .expect("lines")
+        .iter()
+        .map(|line| line["line_number"].as_i64().expect("line number"))
+        .collect();
+    assert_eq!(numbers, (196..=205).collect::<Vec<_>>());
+    assert_eq!(shared["lines"][2]["content"], "disk failure alpha");
+    assert_eq!(shared["lines"][6]["content"], "");
+    assert_eq!(shared["lines"][7]["content"], "disk failure beta");
+    assert_eq!(block_of("beta"), shared);
+    let other = block_of("gamma");
+    assert_eq!(other["start"], 0);
+    assert_eq!(other["lines"].as_array().expect("lines").len(), 3);
+
+    let regex: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/issues/CTX/search?mode=regex&q=failure%20gamma&after=1")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(regex["hits"][0]["context_index"], 0);
+    assert_eq!(regex["contexts"][0]["start"], 1);
+    assert_eq!(regex["contexts"][0]["lines"][1]["content"], "end");
+
+    let plain: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/issues/CTX/search?q=disk%20failure")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(plain["contexts"], Value::Null);
+    assert_eq!(plain["hits"][0]["context_index"], Value::Null);
+
+    let invalid = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/issues/CTX/search?q=disk%20failure&before=101")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+
+    let mut limits = AppLimits::default();
+    limits.api.max_line_page_bytes = 1_500;
+    let budgeted = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                limits,
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let limited: Value = test::call_and_read_body_json(
+        &budgeted,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/log/v2/{bundle_hash}/search?q=disk%20failure&before=2&after=2"
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(limited["total"], 3);
+    assert_eq!(limited["context_truncated"], true);
+    let first = &limited["contexts"][0];
+    assert_eq!(first["truncated"], true);
+    assert!(first["lines"].as_array().expect("lines").len() < 10);
+}
+
+#[actix_web::test]
+async fn log_search_export_streams_every_matching_line_as_ndjson_or_csv() {
+    let test_dir = TestDir::new("rain-export");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["EXPORT"]).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                AppLimits::default(),
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let files = [
+        (
+            "app.log",
+            "2026-03-01 10:00:00 INFO boot\n\
+             2026-03-01 10:00:01 ERROR disk failure alpha, retrying\n\
+             \x20 at disk failure frame\n\
+             \n\
+             2026-03-01 10:05:00 WARN disk failure beta\n",
+        ),
+        ("other.log", "untimed disk failure gamma\n"),
+    ];
+    let boundary = format!("rain-{}", Uuid::new_v4().simple());
+    test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/issues/EXPORT/uploads")
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(multipart_body_multi(&boundary, "EXPORT", &files))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    wait_for_issue_ready(&pool, "EXPORT").await;
+    let bundle_hash: String = sqlx::query_scalar("SELECT hash FROM bundles")
+        .fetch_one(&pool)
+        .await
+        .expect("bundle hash");
+    let export_lines = |body: actix_web::web::Bytes| -> Vec<Value> {
+        std::str::from_utf8(&body)
+            .expect("utf-8 export")
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("ndjson line"))
+            .collect()
+    };
+
+    let response = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/log/v2/{bundle_hash}/search/export?q=disk%20failure"
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response
+            .headers()
+            .get("content-type")
+            .expect("content type"),
+        "application/x-ndjson"
+    );
+    let records = export_lines(test::read_body(response).await);
+    assert_eq!(records.len(), 5);
+    assert_eq!(
+        records[4],
+        serde_json::json!({ "summary": { "exported": 4, "truncated": false } })
+    );
+    let record = |text: &str| {
+        records
+            .iter()
+            .find(|record| {
+                record["content"]
+                    .as_str()
+                    .is_some_and(|content| content.contains(text))
+            })
+            .expect("exported record")
+    };
+    assert_eq!(record("alpha")["line_number"], 1);
+    assert_eq!(record("alpha")["event_time"], "2026-03-01 10:00:01");
+    assert_eq!(record("alpha")["level"], "ERROR");
+    assert_eq!(record("alpha")["bundle_hash"], bundle_hash.as_str());
+    assert_eq!(record("frame")["line_number"], 2);
+    assert_eq!(record("frame")["event_time"], "2026-03-01 10:00:01");
+    assert_eq!(record("beta")["line_number"], 4);
+    assert_eq!(record("beta")["level"], "WARN");
+    assert_eq!(record("gamma")["event_time"], Value::Null);
+
+    let filtered = export_lines(
+        test::call_and_read_body(
+            &app,
+            test::TestRequest::get()
+                .uri(
+                    "/api/issues/EXPORT/search/export?q=disk%20failure\
+                     &start=2026-03-01%2010:04:00&end=2026-03-01%2010:06:00",
+                )
+                .cookie(auth_cookie.clone())
+                .to_request(),
+        )
+        .await,
+    );
+    assert_eq!(filtered.len(), 2);
+    assert_eq!(filtered[0]["line_number"], 4);
+    let by_level = export_lines(
+        test::call_and_read_body(
+            &app,
+            test::TestRequest::get()
+                .uri("/api/issues/EXPORT/search/export?q=disk%20failure&level=error")
+                .cookie(auth_cookie.clone())
+                .to_request(),
+        )
+        .await,
+    );
+    assert_eq!(by_level.len(), 3);
+
+    let csv = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(
+                "/api/issues/EXPORT/search/export?mode=regex&format=csv&q=failure%20(alpha%7Cbeta)",
+            )
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(csv.status(), StatusCode::OK);
+    assert_eq!(
+        csv.headers().get("content-type").expect("content type"),
+        "text/csv; charset=utf-8"
+    );
+    assert_eq!(csv.headers().get("x-export-rows").expect("row count"), "2");
+    assert_eq!(
+        csv.headers().get("x-export-truncated").expect("truncation"),
+        "false"
+    );
+    let csv = String::from_utf8(test::read_body(csv).await.to_vec()).expect("utf-8 csv");
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        rows[0],
+        "bundle_hash,path,line_number,event_time,level,content"
+    );
+    assert_eq!(rows.len(), 3);
+    assert!(rows[1].ends_with(
+        ",1,2026-03-01 10:00:01,ERROR,\"2026-03-01 10:00:01 ERROR disk failure alpha, retrying\""
+    ));
+    assert!(
+        rows[2].ends_with(",4,2026-03-01 10:05:00,WARN,2026-03-01 10:05:00 WARN disk failure beta")
+    );
+    assert!(!csv.contains("# truncated"));
+
+    let short = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/issues/EXPORT/search/export?q=ab")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(short.status(), StatusCode::BAD_REQUEST);
+
+    let mut limits = AppLimits::default();
+    limits.api.max_export_scan_bytes = 1;
+    let budgeted = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                limits,
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let partial = export_lines(
+        test::call_and_read_body(
+            &budgeted,
+            test::TestRequest::get()
+                .uri("/api/issues/EXPORT/search/export?q=disk%20failure")
+                .cookie(auth_cookie.clone())
+                .to_request(),
+        )
+        .await,
+    );
+    assert_eq!(
+        partial,
+        [serde_json::json!({ "summary": { "exported": 0, "truncated": true } })]
+    );
+
+    let partial_csv = test::call_service(
+        &budgeted,
+        test::TestRequest::get()
+            .uri("/api/issues/EXPORT/search/export?q=disk%20failure&format=csv")
+            .cookie(auth_cookie)
+            .to_request(),
+    )
+    .await;
+    assert_eq!(partial_csv.status(), StatusCode::OK);
+    assert_eq!(
+        partial_csv
+            .headers()
+            .get("x-export-rows")
+            .expect("row count"),
+        "0"
+    );
+    assert_eq!(
+        partial_csv
+            .headers()
+            .get("x-export-truncated")
+            .expect("truncation"),
+        "true"
+    );
+    assert_eq!(
+        test::read_body(partial_csv).await,
+        "bundle_hash,path,line_number,event_time,level,content\n"
+    );
+    let spooled = fs::read_dir(data_root.join(".tmp"))
+        .expect("read temp dir")
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("export-"))
+        .count();
+    assert_eq!(spooled, 0);
+}
+
+#[actix_web::test]
+async fn log_templates_collapse_repeated_lines_and_page_through_matches() {
+    let test_dir = TestDir::new("rain-templates");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["TEMPLATES"]).await;
+    let state = web::Data::new(AppState::new(
+        pool.clone(),
+        data_root.clone(),
+        AppLimits::default(),
+    ));
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let files = [(
+        "app.log",
+        "2026-03-01 10:00:00 INFO connected to alpha as user=1\n\
+         2026-03-01 10:00:01 INFO connected to beta as user=2\n\
+         \n\
+         2026-03-01 10:00:02 ERROR disk full on sda\n\
+         2026-03-01 10:00:03 INFO connected to gamma as user=3\n",
+    )];
+    let boundary = format!("rain-{}", Uuid::new_v4().simple());
+    test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/issues/TEMPLATES/uploads")
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(multipart_body_multi(&boundary, "TEMPLATES", &files))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    wait_for_issue_ready(&pool, "TEMPLATES").await;
+    log_templates::mine_pending_bundles(&log_templates::TemplateMining::from_state(&state))
+        .await
+        .expect("mine templates");
+
+    let response = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/issues/TEMPLATES/templates")
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["mined_bundles"], 1);
+    assert_eq!(body["pending_bundles"], 0);
+    assert_eq!(body["total_templates"], 2);
+    let top = &body["templates"][0];
+    assert_eq!(top["template"], "<*> <*> INFO connected to <*> as user=<*>");
+    assert_eq!(top["line_count"], 3);
+    assert_eq!(top["bundle_count"], 1);
+    assert_eq!(top["levels"], serde_json::json!(["INFO"]));
+    assert_eq!(top["first_event_time"], "2026-03-01 10:00:00");
+    assert_eq!(top["last_event_time"], "2026-03-01 10:00:03");
+    assert_eq!(top["examples"].as_array().map(Vec::len), Some(3));
+    assert_eq!(top["examples"][2]["line_number"], 4);
+    assert_eq!(
+        body["templates"][1]["template"],
+        "<*> <*> ERROR disk full on sda"
+    );
+    let key = top["key"].as_str().expect("template key").to_string();
+
+    let response = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/issues/TEMPLATES/templates/{key}/lines?size=2"
+            ))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let page: Value = test::read_body_json(response).await;
+    let lines = page["lines"].as_array().expect("template lines");
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["line_number"], 0);
+    assert_eq!(lines[1]["line_number"], 1);
+    assert_eq!(lines[1]["level"], "INFO");
+    let cursor = page["next_cursor"].as_str().expect("next cursor");
+
+    let response = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/issues/TEMPLATES/templates/{key}/lines?size=2&cursor={cursor}"
+            ))
+            .to_request(),
+    )
+    .await;
+    let page: Value = test::read_body_json(response).await;
+    let lines = page["lines"].as_array().expect("template lines");
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["line_number"], 4);
+    assert_eq!(lines[0]["event_time"], "2026-03-01 10:00:03");
+    assert_eq!(
+        lines[0]["content"],
+        "2026-03-01 10:00:03 INFO connected to gamma as user=3"
+    );
+    assert!(page["next_cursor"].is_null());
+
+    let response = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/issues/TEMPLATES/templates/0000000000000000/lines")
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn bundle_template_diff_reports_one_sided_and_shifted_templates() {
+    let test_dir = TestDir::new("rain-template-diff");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["DIFF"]).await;
+    let state = web::Data::new(AppState::new(
+        pool.clone(),
+        data_root.clone(),
+        AppLimits::default(),
+    ));
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let bundles = [
+        "INFO connected to alpha as user=1\n\
+         INFO connected to beta as user=2\n\
+         INFO connected to gamma as user=3\n\
+         ERROR disk full on sda\n\
+         INFO cache warmed\n",
+        "INFO connected to alpha as user=4\n\
+         ERROR disk full on sda\n\
+         ERROR disk full on sda\n\
+         ERROR disk full on sda\n\
+         ERROR disk full on sda\n\
+         ERROR fan stopped\n",
+    ];
+    let mut hashes = Vec::new();
+    for content in bundles {
+        let boundary = format!("rain-{}", Uuid::new_v4().simple());
+        let response = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri("/api/issues/DIFF/uploads")
+                .insert_header((
+                    "content-type",
+                    format!("multipart/form-data; boundary={boundary}"),
+                ))
+                .set_payload(multipart_body_multi(
+                    &boundary,
+                    "DIFF",
+                    &[("app.log", content)],
+                ))
+                .cookie(auth_cookie.clone())
+                .to_request(),
+        )
+        .await;
+        let body: Value = test::read_body_json(response).await;
+        let hash = body["bundle_hash"]
+            .as_str()
+            .expect("bundle hash")
+            .to_string();
+        for _ in 0..100 {
+            let status: String = sqlx::query_scalar("SELECT status FROM bundles WHERE hash = ?")
+                .bind(&hash)
+                .fetch_one(&pool)
+                .await
+                .expect("bundle status");
+            if status == "READY" {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        hashes.push(hash);
+    }
+    log_templates::mine_pending_bundles(&log_templates::TemplateMining::from_state(&state))
+        .await
+        .expect("mine templates");
+    let diff_uri = |extra: &str| {
+        format!(
+            "/api/issues/DIFF/bundles/diff?base={}&target={}{extra}",
+            hashes[0], hashes[1]
+        )
+    };
+
+    let response = test::call_service(
+        &app,
+        test::TestRequest::get().uri(&diff_uri("")).to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&diff_uri(""))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["base"]["line_count"], 5);
+    assert_eq!(body["target"]["line_count"], 6);
+    assert_eq!(body["only_in_base_total"], 1);
+    assert_eq!(body["only_in_base"][0]["template"], "INFO cache warmed");
+    assert_eq!(body["only_in_base"][0]["target_count"], 0);
+    assert_eq!(body["only_in_target"][0]["template"], "ERROR fan stopped");
+    assert_eq!(
+        body["only_in_target"][0]["examples"][0]["bundle_hash"],
+        hashes[1]
+    );
+    assert_eq!(body["changed_total"], 2);
+    let changed = body["changed"].as_array().expect("changed templates");
+    assert_eq!(changed[0]["template"], "INFO connected to <*> as user=<*>");
+    assert_eq!(
+        (
+            changed[0]["base_count"].as_i64(),
+            changed[0]["target_count"].as_i64()
+        ),
+        (Some(3), Some(1))
+    );
+    assert_eq!(changed[1]["template"], "ERROR disk full on sda");
+    assert!(changed[1]["ratio"].as_f64().expect("ratio") > 3.0);
+    assert_eq!(changed[1]["examples"].as_array().map(Vec::len), Some(3));
+
+    let response = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&diff_uri("&min_level=error"))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["only_in_base_total"], 0);
+    assert_eq!(body["only_in_target_total"], 1);
+    assert_eq!(body["changed_total"], 1);
+    assert_eq!(body["changed"][0]["levels"], serde_json::json!(["ERROR"]));
+
+    let response = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/issues/DIFF/bundles/diff?base={0}&target={0}",
+                hashes[0]
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn file_diff_compares_files_across_bundles_with_masking_and_paging() {
+    let test_dir = TestDir::new("rain-file-diff");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["FILEDIFF"]).await;
+    let mut limits = AppLimits::default();
+    limits.api.max_diff_file_size = 1024;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                limits,
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let large = "x".repeat(2048);
+    let uploads = [
+        vec![
+            (
+                "dmesg.log",
+                "[    0.100] boot cpu 0\n[    0.200] usb 1-1: new device\n[    0.300] eth0 up\n",
+            ),
+            ("large.log", large.as_str()),
+        ],
+        vec![(
+            "dmesg.log",
+            "[    0.150] boot cpu 0\n[    0.250] usb 1-1: device lost\n[    0.350] eth0 up\n[    0.400] wlan0 up\n",
+        )],
+    ];
+    let mut hashes = Vec::new();
+    for files in &uploads {
+        let boundary = format!("rain-{}", Uuid::new_v4().simple());
+        let response = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri("/api/issues/FILEDIFF/uploads")
+                .insert_header((
+                    "content-type",
+                    format!("multipart/form-data; boundary={boundary}"),
+                ))
+                .set_payload(multipart_body_multi(&boundary, "FILEDIFF", files))
+                .cookie(auth_cookie.clone())
+                .to_request(),
+        )
+        .await;
+        let body: Value = test::read_body_json(response).await;
+        let hash = body["bundle_hash"]
+            .as_str()
+            .expect("bundle hash")
+            .to_string();
+        for _ in 0..100 {
+            let status: String = sqlx::query_scalar("SELECT status FROM bundles WHERE hash = ?")
+                .bind(&hash)
+                .fetch_one(&pool)
+                .await
+                .expect("bundle status");
+            if status == "READY" {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        hashes.push(hash);
+    }
+    let file_id = |hash: &str, name: &str| {
+        let pool = pool.clone();
+        let (hash, name) = (hash.to_string(), name.to_string());
+        async move {
+            sqlx::query_scalar::<_, i64>(
+                "SELECT f.id FROM files f JOIN bundles b ON b.id = f.bundle_id WHERE b.hash = ? AND f.name = ?",
+            )
+            .bind(hash)
+            .bind(name)
+            .fetch_one(&pool)
+            .await
+            .expect("file id")
+        }
+    };
+    let left = file_id(&hashes[0], "dmesg.log").await;
+    let right = file_id(&hashes[1], "dmesg.log").await;
+    let diff = |query: String| {
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/files/v1/{}/files/{left}/diff?against_bundle={}&against_file={right}{query}",
+                hashes[0], hashes[1]
+            ))
+            .to_request()
+    };
+
+    let response = test::call_service(&app, diff(String::new())).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["identical"], false);
+    assert_eq!(body["summary"]["replaced"], 3);
+    assert_eq!(body["summary"]["inserted"], 1);
+    assert_eq!(body["right"]["line_count"], 4);
+
+    let response = test::call_service(&app, diff("&ignore_timestamps=true".into())).await;
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["summary"]["equal"], 2);
+    assert_eq!(body["summary"]["replaced"], 1);
+    assert_eq!(body["summary"]["inserted"], 1);
+    let rows = body["rows"].as_array().expect("diff rows");
+    assert_eq!(rows[1]["kind"], "replace");
+    assert_eq!(
+        rows[1]["left"]["content"],
+        "[    0.200] usb 1-1: new device"
+    );
+    assert_eq!(
+        rows[1]["right"]["content"],
+        "[    0.250] usb 1-1: device lost"
+    );
+    assert_eq!(rows[3]["kind"], "insert");
+    assert!(rows[3]["left"].is_null());
+    assert_eq!(rows[3]["right"]["line_number"], 3);
+
+    let response = test::call_service(
+        &app,
+        diff("&ignore_timestamps=true&context=0&limit=1".into()),
+    )
+    .await;
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["total_rows"], 2);
+    assert_eq!(body["rows"].as_array().map(Vec::len), Some(1));
+    assert_eq!(body["next_start"], 1);
+
+    let large_id = file_id(&hashes[0], "large.log").await;
+    let response = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/files/v1/{}/files/{large_id}/diff?against_file={left}",
+                hashes[0]
+            ))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[actix_web::test]
+async fn global_search_groups_matches_by_issue_and_runs_saved_searches() {
+    let test_dir = TestDir::new("rain-global-search");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["MINE", "THEIRS"]).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                AppLimits::default(),
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let uploads: [(&str, &[(&str, &str)]); 2] = [
+        (
+            "MINE",
+            &[(
+                "kernel.log",
+                "boot\nERROR kernel panic at cpu0\nkernel panic at cpu1 after timeout\n",
+            )],
+        ),
+        (
+            "THEIRS",
+            &[("a.log", "kernel panic again\n"), ("b.log", "all fine\n")],
+        ),
+    ];
+    for (issue_code, files) in uploads {
+        let boundary = format!("rain-{}", Uuid::new_v4().simple());
+        let upload: Value = test::call_and_read_body_json(
+            &app,
+            test::TestRequest::post()
+                .uri(&format!("/api/issues/{issue_code}/uploads"))
+                .insert_header((
+                    "content-type",
+                    format!("multipart/form-data; boundary={boundary}"),
+                ))
+                .set_payload(multipart_body_multi(&boundary, issue_code, files))
+                .cookie(auth_cookie.clone())
+                .to_request(),
+        )
+        .await;
+        wait_for_bundle_status(
+            &pool,
+            upload["bundle_hash"].as_str().expect("bundle hash"),
+            "READY",
+        )
+        .await;
+    }
+    sqlx::query("UPDATE issues SET owner_user_id = NULL WHERE code = 'THEIRS'")
+        .execute(&pool)
+        .await
+        .expect("release issue ownership");
+    let other_cookie = test_auth_cookie(&pool).await;
+
+    let all: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/search?q=kernel%20panic")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(all["total"], 2);
+    assert_eq!(all["issue_count"], 2);
+    assert_eq!(all["truncated"], false);
+    let issues = all["issues"].as_array().expect("issues");
+    assert_eq!(issues[0]["issue_code"], "MINE");
+    assert_eq!(issues[0]["owned"], true);
+    assert_eq!(issues[1]["issue_code"], "THEIRS");
+    assert_eq!(issues[1]["owned"], false);
+    let bundle = &issues[0]["bundles"][0];
+    assert_eq!(bundle["total"], 1);
+    assert_eq!(bundle["hits"][0]["level"], "ERROR");
+    assert!(
+        bundle["hits"][0]["snippet"]
+            .as_str()
+            .expect("snippet")
+            .contains("kernel panic at cpu0")
+    );
+
+    let owned: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/search?q=kernel%20panic&owned=true")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(owned["issue_count"], 1);
+    assert_eq!(owned["issues"][0]["issue_code"], "MINE");
+
+    let expression: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/search?mode=expression&q=panic%20AND%20NOT%20cpu")
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(expression["issue_count"], 1);
+    assert_eq!(expression["issues"][0]["issue_code"], "THEIRS");
+
+    let saved: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/me/saved-searches")
+            .set_json(serde_json::json!({
+                "name": "panic with timeout",
+                "search_type": "DETAIL",
+                "query_text": "panic AND timeout",
+                "options": {},
+            }))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    let saved_id = saved["id"].as_str().expect("saved search id");
+    let from_saved: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!("/api/search?saved_search_id={saved_id}"))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(from_saved["query"], "panic AND timeout");
+    assert_eq!(from_saved["saved_search_id"], saved_id);
+    assert_eq!(from_saved["total"], 1);
+    assert!(
+        from_saved["issues"][0]["bundles"][0]["hits"][0]["snippet"]
+            .as_str()
+            .expect("snippet")
+            .contains("after timeout")
+    );
+    let last_used: Option<String> =
+        sqlx::query_scalar("SELECT last_used_at FROM saved_searches WHERE id = ?")
+            .bind(saved_id)
+            .fetch_one(&pool)
+            .await
+            .expect("saved search usage");
+    assert!(last_used.is_some());
+
+    let foreign = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!("/api/search?saved_search_id={saved_id}"))
+            .cookie(other_cookie)
+            .to_request(),
+    )
+    .await;
+    assert_eq!(foreign.status(), StatusCode::NOT_FOUND);
+
+    let filename: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/me/saved-searches")
+            .set_json(serde_json::json!({
+                "name": "kernel logs",
+                "search_type": "FILENAME",
+                "query_text": "kernel",
+                "options": {},
+            }))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    let rejected = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/search?saved_search_id={}",
+                filename["id"].as_str().expect("saved search id")
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(rejected.status(), StatusCode::BAD_REQUEST);
+    let rejected: Value = test::read_body_json(rejected).await;
+    assert_eq!(rejected["code"], "SAVED_SEARCH_NOT_GLOBAL");
+
+    let unauthenticated = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri("/api/search?q=kernel%20panic")
+            .to_request(),
+    )
+    .await;
+    assert_eq!(unauthenticated.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn temp_result_expressions_filter_by_event_level_time_and_regex() {
+    let test_dir = TestDir::new("rain-expression-fields");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["FIELDS"]).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                AppLimits::default(),
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let files = [
+        (
+            "app.log",
+            "2026-03-01 10:00:00 INFO boot Disk ok\n\
+             2026-03-01 10:00:01 ERROR Disk failure code=507\n\
+             \x20 at Disk.write frame\n\
+             2026-03-01 10:05:00 WARN disk failure code=503\n",
+        ),
+        ("other.log", "untimed ERROR Disk failure code=500\n"),
+    ];
+    let boundary = format!("rain-{}", Uuid::new_v4().simple());
+    test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/issues/FIELDS/uploads")
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(multipart_body_multi(&boundary, "FIELDS", &files))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    wait_for_issue_ready(&pool, "FIELDS").await;
+    let preview = |expression: &str, source: Value| {
+        let mut payload = serde_json::json!({ "expression": expression, "from": 0, "size": 50 });
+        payload
+            .as_object_mut()
+            .expect("payload object")
+            .extend(source.as_object().expect("source object").clone());
+        test::TestRequest::post()
+            .uri("/api/temp-results/preview")
+            .set_json(payload)
+            .to_request()
+    };
+    let contents = |body: &Value| -> Vec<String> {
+        body["lines"]
+            .as_array()
+            .expect("preview lines")
+            .iter()
+            .map(|line| line["content"].as_str().expect("content").to_string())
+            .collect()
+    };
+
+    let errors: Value = test::call_and_read_body_json(
+        &app,
+        preview(
+            "level:ERROR AND time>=2026-03-01T10:00:01 AND \"Disk\"",
+            serde_json::json!({ "issue_code": "FIELDS" }),
+        ),
+    )
+    .await;
+    assert_eq!(
+        contents(&errors),
+        vec![
+            "2026-03-01 10:00:01 ERROR Disk failure code=507",
+            "  at Disk.write frame",
+        ]
+    );
+
+    let regex: Value = test::call_and_read_body_json(
+        &app,
+        preview(
+            r"/code=50[37]$/ AND level>=WARN",
+            serde_json::json!({ "issue_code": "FIELDS" }),
+        ),
+    )
+    .await;
+    assert_eq!(regex["total"], 2);
+
+    let refined: Value = test::call_and_read_body_json(
+        &app,
+        preview(
+            "time<\"2026-03-01 10:00:02\" AND /frame$/",
+            serde_json::json!({ "source_temp_id": errors["result_id"] }),
+        ),
+    )
+    .await;
+    assert_eq!(contents(&refined), vec!["  at Disk.write frame"]);
+
+    let invalid = test::call_service(
+        &app,
+        preview(
+            "level:ERROR AND /code=(/",
+            serde_json::json!({ "issue_code": "FIELDS" }),
+        ),
+    )
+    .await;
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+    let invalid: Value = test::read_body_json(invalid).await;
+    assert_eq!(invalid["code"], "SEARCH_EXPRESSION_INVALID");
+    assert!(
+        invalid["message"]
+            .as_str()
+            .expect("message")
+            .contains("位置 22")
+    );
+}
+
+#[actix_web::test]
+async fn time_settings_rekey_event_times_across_devices() {
+    let test_dir = TestDir::new("rain-time-settings");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["TIMEZONE"]).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                AppLimits::default(),
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let boundary = format!("rain-{}", Uuid::new_v4().simple());
+    let upload: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/issues/TIMEZONE/uploads")
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(multipart_body_multi(
+                &boundary,
+                "TIMEZONE",
+                &[
+                    ("phone.log", "2026-01-01 08:00:00 phone event\n"),
+                    ("server.log", "2026-01-01 00:00:00 server event\n"),
+                ],
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    let bundle_hash = upload["bundle_hash"].as_str().expect("bundle hash");
+    wait_for_issue_ready(&pool, "TIMEZONE").await;
+    let event_time = |name: &'static str| {
+        let pool = pool.clone();
+        async move {
+            sqlx::query_scalar::<_, i64>(
+                "SELECT ls.event_time_start_ms FROM log_segments ls JOIN files f ON f.id = ls.file_id WHERE f.name = ?",
+            )
+            .bind(name)
+            .fetch_one(&pool)
+            .await
+            .expect("event time")
+        }
+    };
+    let original_phone = event_time("phone.log").await;
+    let server = event_time("server.log").await;
+    assert!(original_phone > server);
+    let phone_id: i64 = sqlx::query_scalar("SELECT id FROM files WHERE name = 'phone.log'")
+        .fetch_one(&pool)
+        .await
+        .expect("phone file id");
+    let uri = format!("/api/files/v1/{bundle_hash}/files/{phone_id}/time-settings");
+
+    let invalid = test::call_service(
+        &app,
+        test::TestRequest::put()
+            .uri(&uri)
+            .set_json(serde_json::json!({ "utc_offset_minutes": 900 }))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+
+    let updated: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::put()
+            .uri(&uri)
+            .set_json(serde_json::json!({ "utc_offset_minutes": 480 }))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(updated["effective"]["utc_offset_minutes"], 480);
+    assert_eq!(updated["effective"]["clock_skew_ms"], 0);
+    wait_for_event_time_rekey(&pool).await;
+    assert_eq!(event_time("phone.log").await, server);
+    assert_eq!(event_time("server.log").await, server);
+
+    let bundle_setting: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/files/v1/{bundle_hash}/files/root/time-settings"
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(bundle_setting["setting"], Value::Null);
+
+    let reset: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::delete()
+            .uri(&uri)
+            .cookie(auth_cookie)
+            .to_request(),
+    )
+    .await;
+    assert_eq!(reset["effective"], Value::Null);
+    wait_for_event_time_rekey(&pool).await;
+    assert_eq!(event_time("phone.log").await, original_phone);
+}
+
+async fn wait_for_event_time_rekey(pool: &sqlx::SqlitePool) {
+    for _ in 0..200 {
+        let pending: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM bundles WHERE event_time_rekey_pending != 0")
+                .fetch_one(pool)
+                .await
+                .expect("pending re-key count");
+        if pending == 0 {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(25)).await;
+    }
+    panic!("event time re-key did not finish");
+}
+
+async fn wait_for_issue_ready(pool: &sqlx::SqlitePool, issue_code: &str) {
+    wait_for_issue_status(pool, issue_code, "READY").await;
+}
+
+async fn wait_for_issue_status(pool: &sqlx::SqlitePool, issue_code: &str, status: &str) {
+    for _ in 0..100 {
+        let ready: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM bundles
+                WHERE issue_code = ? AND status = ?
+            )
+            "#,
+        )
+        .bind(issue_code)
+        .bind(status)
+        .fetch_one(pool)
+        .await
+        .expect("poll issue status");
+        if ready {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    let states: Vec<(String, String)> =
+        sqlx::query_as("SELECT status, process_stage FROM bundles WHERE issue_code = ?")
+            .bind(issue_code)
+            .fetch_all(pool)
+            .await
+            .expect("inspect timed out issue status");
+    panic!("issue {issue_code} did not become {status}; observed {states:?}");
+}
+
+async fn wait_for_issue_deleted(pool: &sqlx::SqlitePool, issue_code: &str) {
+    for _ in 0..100 {
+        let exists: bool =
+            sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM issues WHERE code = ?)")
+                .bind(issue_code)
+                .fetch_one(pool)
+                .await
+                .expect("poll issue deletion");
+        if !exists {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    panic!("issue {issue_code} was not deleted");
+}
+
+async fn wait_for_file_status(pool: &sqlx::SqlitePool, file_id: &str, status: &str) {
+    for _ in 0..100 {
+        let current: Option<String> = sqlx::query_scalar("SELECT status FROM files WHERE id = ?")
+            .bind(file_id.parse::<i64>().expect("numeric file id"))
+            .fetch_one(pool)
+            .await
+            .expect("poll file status");
+        if current.as_deref() == Some(status) {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    panic!("file {file_id} did not become {status}");
+}
+
+async fn wait_for_bundle_status(pool: &sqlx::SqlitePool, bundle_hash: &str, status: &str) {
+    for _ in 0..100 {
+        let current: Option<String> =
+            sqlx::query_scalar("SELECT status FROM bundles WHERE hash = ?")
+                .bind(bundle_hash)
+                .fetch_optional(pool)
+                .await
+                .expect("poll bundle status");
+        if current.as_deref() == Some(status) {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    panic!("bundle {bundle_hash} did not become {status}");
+}
+
+fn multipart_body(boundary: &str, issue_code: &str, filename: &str, content: &str) -> Vec<u8> {
+    multipart_body_bytes(
+        boundary,
+        issue_code,
+        filename,
+        "text/plain",
+        content.as_bytes(),
+    )
+}
+
+async fn insert_issues(pool: &sqlx::SqlitePool, issue_codes: &[&str]) {
+    for code in issue_codes {
+        sqlx::query("INSERT INTO issues (code, name) VALUES (?, ?)")
+            .bind(code)
+            .bind(code)
+            .execute(pool)
+            .await
+            .expect("insert issue");
+    }
+}
+
+async fn test_auth_cookie(pool: &sqlx::SqlitePool) -> Cookie<'static> {
+    let username = format!("smoke-{}", Uuid::new_v4().simple());
+    let user = match users::create_user(pool, &username, "test-password-hash")
+        .await
+        .expect("create smoke test user")
+    {
+        users::CreateUserOutcome::Created(user) => user,
+        users::CreateUserOutcome::DuplicateUsername => panic!("unexpected duplicate test user"),
+    };
+    sqlx::query("UPDATE issues SET owner_user_id = ? WHERE owner_user_id IS NULL")
+        .bind(&user.id)
+        .execute(pool)
+        .await
+        .expect("assign smoke issue ownership");
+    let token = generate_session_token();
+    sessions::create_session(
+        pool,
+        &user.id,
+        &hash_session_token(&token),
+        Utc::now() + Duration::hours(1),
+        None,
+        None,
+    )
+    .await
+    .expect("create smoke test session");
+    Cookie::new(SESSION_COOKIE_NAME, token)
+}
+
+fn multipart_body_bytes(
+    boundary: &str,
+    issue_code: &str,
+    filename: &str,
+    content_type: &str,
+    content: &[u8],
+) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(
+        format!(
+            "--{boundary}\r\n\
+Content-Disposition: form-data; name=\"issue_code\"\r\n\r\n\
+{issue_code}\r\n\
+--{boundary}\r\n\
+Content-Disposition: form-data; name=\"files\"; filename=\"{filename}\"\r\n\
+Content-Type: {content_type}\r\n\r\n"
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(content);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+    body
+}
+
+fn multipart_body_multi(boundary: &str, issue_code: &str, files: &[(&str, &str)]) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(
+        format!(
+            "--{boundary}\r\n\
+Content-Disposition: form-data; name=\"issue_code\"\r\n\r\n\
+{issue_code}\r\n"
+        )
+        .as_bytes(),
+    );
+    for (filename, content) in files {
+        body.extend_from_slice(
+            format!(
+                "--{boundary}\r\n\
+Content-Disposition: form-data; name=\"files\"; filename=\"{filename}\"\r\n\
+Content-Type: text/plain\r\n\r\n"
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(content.as_bytes());
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+    body
+}
+
+fn multipart_body_multi_bytes(
+    boundary: &str,
+    issue_code: &str,
+    files: &[(&str, &str, &[u8])],
+) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(
+        format!(
+            "--{boundary}\r\n\
+Content-Disposition: form-data; name=\"issue_code\"\r\n\r\n\
+{issue_code}\r\n"
+        )
+        .as_bytes(),
+    );
+    for (filename, content_type, content) in files {
+        body.extend_from_slice(
+            format!(
+                "--{boundary}\r\n\
+Content-Disposition: form-data; name=\"files\"; filename=\"{filename}\"\r\n\
+Content-Type: {content_type}\r\n\r\n"
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(content);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+    body
+}
+
+fn gzip_bytes(content: &str) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content.as_bytes()).expect("write gzip");
+    encoder.finish().expect("finish gzip")
+}
+
+fn tar_gz_bytes(path: &str, content: &str) -> Vec<u8> {
+    tar_gz_multi(&[(path, content)])
+}
+
+fn tar_gz_multi(files: &[(&str, &str)]) -> Vec<u8> {
+    let binary_files = files
+        .iter()
+        .map(|(path, content)| (*path, content.as_bytes()))
+        .collect::<Vec<_>>();
+    tar_gz_multi_bytes(&binary_files)
+}
+
+fn tar_gz_multi_bytes(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    {
+        let mut builder = tar::Builder::new(&mut encoder);
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, *path, *content)
+                .expect("append tar entry");
+        }
+        builder.finish().expect("finish tar");
+    }
+    encoder.finish().expect("finish tar.gz")
+}
+
+fn zip_bytes(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let cursor = Cursor::new(Vec::new());
+    let mut writer = zip::ZipWriter::new(cursor);
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (path, content) in files {
+        writer.start_file(*path, options).expect("start zip entry");
+        writer.write_all(content).expect("write zip entry");
+    }
+    writer.finish().expect("finish zip").into_inner()
+}
+
+/// Builds a stored ZIP entry protected with traditional PKWARE (ZipCrypto) encryption.
+fn zip_crypto_bytes(path: &str, content: &[u8], password: &str) -> Vec<u8> {
+    fn crc32_update(crc: u32, byte: u8) -> u32 {
+        let mut crc = crc ^ u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+        crc
+    }
+
+    let crc = !content
+        .iter()
+        .fold(u32::MAX, |crc, byte| crc32_update(crc, *byte));
+    let mut keys = [0x1234_5678_u32, 0x2345_6789, 0x3456_7890];
+    let update_keys = |keys: &mut [u32; 3], byte: u8| {
+        keys[0] = crc32_update(keys[0], byte);
+        keys[1] = keys[1]
+            .wrapping_add(keys[0] & 0xff)
+            .wrapping_mul(134_775_813)
+            .wrapping_add(1);
+        keys[2] = crc32_update(keys[2], (keys[1] >> 24) as u8);
+    };
+    for byte in password.bytes() {
+        update_keys(&mut keys, byte);
+    }
+    let mut header = [0x5a_u8; 12];
+    header[11] = (crc >> 24) as u8;
+    let mut encrypted = Vec::with_capacity(12 + content.len());
+    for byte in header.iter().chain(content) {
+        let temp = (keys[2] | 2) & 0xffff;
+        encrypted.push(byte ^ ((temp.wrapping_mul(temp ^ 1) >> 8) as u8));
+        update_keys(&mut keys, *byte);
+    }
+
+    let name = path.as_bytes();
+    let mut local = Vec::new();
+    local.extend_from_slice(&0x0403_4b50_u32.to_le_bytes());
+    local.extend_from_slice(&20_u16.to_le_bytes());
+    local.extend_from_slice(&1_u16.to_le_bytes());
+    local.extend_from_slice(&0_u16.to_le_bytes());
+    local.extend_from_slice(&[0; 4]);
+    local.extend_from_slice(&crc.to_le_bytes());
+    local.extend_from_slice(&(encrypted.len() as u32).to_le_bytes());
+    local.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    local.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    local.extend_from_slice(&0_u16.to_le_bytes());
+    local.extend_from_slice(name);
+    local.extend_from_slice(&encrypted);
+
+    let mut central = Vec::new();
+    central.extend_from_slice(&0x0201_4b50_u32.to_le_bytes());
+    central.extend_from_slice(&20_u16.to_le_bytes());
+    central.extend_from_slice(&20_u16.to_le_bytes());
+    central.extend_from_slice(&1_u16.to_le_bytes());
+    central.extend_from_slice(&0_u16.to_le_bytes());
+    central.extend_from_slice(&[0; 4]);
+    central.extend_from_slice(&crc.to_le_bytes());
+    central.extend_from_slice(&(encrypted.len() as u32).to_le_bytes());
+    central.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    central.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    central.extend_from_slice(&[0; 12]);
+    central.extend_from_slice(&0_u32.to_le_bytes());
+    central.extend_from_slice(name);
+
+    let mut archive = local;
+    let central_offset = archive.len() as u32;
+    archive.extend_from_slice(&central);
+    archive.extend_from_slice(&0x0605_4b50_u32.to_le_bytes());
+    archive.extend_from_slice(&[0; 4]);
+    archive.extend_from_slice(&1_u16.to_le_bytes());
+    archive.extend_from_slice(&1_u16.to_le_bytes());
+    archive.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&central_offset.to_le_bytes());
+    archive.extend_from_slice(&0_u16.to_le_bytes());
+    archive
+}
+
+fn sqlite_url(path: &Path) -> String {
+    format!("sqlite://{}", path.display().to_string().replace('\\', "/"))
+}
+
+struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    fn new(prefix: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("{prefix}-{}", Uuid::new_v4().simple()));
+        fs::create_dir_all(&path).expect("create temp test dir");
+        Self { path }
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+#[actix_web::test]
+async fn merged_timeline_interleaves_files_by_event_time_with_cursor_paging() {
+    let test_dir = TestDir::new("rain-merged-timeline");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["TIMELINE", "OTHERTL"]).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                AppLimits::default(),
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let uploads = [
+        (
+            "TIMELINE",
+            vec![(
+                "app.log",
+                "2026-03-01 10:00:00 INFO app start\n    caused by timeout\n2026-03-01 10:00:04 INFO app retry\n",
+            )],
+        ),
+        (
+            "TIMELINE",
+            vec![(
+                "db.log",
+                "db banner\n2026-03-01 10:00:02 WARN db slow\n2026-03-01 10:00:04 INFO db ok\n",
+            )],
+        ),
+        (
+            "OTHERTL",
+            vec![("other.log", "2026-03-01 09:00:00 INFO other\n")],
+        ),
+    ];
+    let mut hashes = Vec::new();
+    for (code, files) in &uploads {
+        let boundary = format!("rain-{}", Uuid::new_v4().simple());
+        let response = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri(&format!("/api/issues/{code}/uploads"))
+                .insert_header((
+                    "content-type",
+                    format!("multipart/form-data; boundary={boundary}"),
+                ))
+                .set_payload(multipart_body_multi(&boundary, code, files))
+                .cookie(auth_cookie.clone())
+                .to_request(),
+        )
+        .await;
+        let body: Value = test::read_body_json(response).await;
+        let hash = body["bundle_hash"]
+            .as_str()
+            .expect("bundle hash")
+            .to_string();
+        for _ in 0..100 {
+            let status: String = sqlx::query_scalar("SELECT status FROM bundles WHERE hash = ?")
+                .bind(&hash)
+                .fetch_one(&pool)
+                .await
+                .expect("bundle status");
+            if status == "READY" {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        hashes.push(hash);
+    }
+    let file_id = |name: &str| {
+        let pool = pool.clone();
+        let name = name.to_string();
+        async move {
+            sqlx::query_scalar::<_, i64>("SELECT id FROM files WHERE name = ?")
+                .bind(name)
+                .fetch_one(&pool)
+                .await
+                .expect("file id")
+        }
+    };
+    let app_log = file_id("app.log").await;
+    let db_log = file_id("db.log").await;
+    let other_log = file_id("other.log").await;
+    let timeline = |query: String| {
+        test::TestRequest::get()
+            .uri(&format!("/api/issues/TIMELINE/timeline?{query}"))
+            .to_request()
+    };
+
+    let response = test::call_service(&app, timeline(format!("files={app_log},{db_log}"))).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["sources"][0]["bundle_hash"], hashes[0]);
+    assert_eq!(body["sources"][1]["name"], "db.log");
+    let merged: Vec<(u64, u64, String)> = body["lines"]
+        .as_array()
+        .expect("lines")
+        .iter()
+        .map(|line| {
+            (
+                line["source"].as_u64().unwrap(),
+                line["line_number"].as_u64().unwrap(),
+                line["content"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        merged
+            .iter()
+            .map(|(source, line, _)| (*source, *line))
+            .collect::<Vec<_>>(),
+        vec![(1, 0), (0, 0), (0, 1), (1, 1), (0, 2), (1, 2)]
+    );
+    assert_eq!(body["lines"][0]["event_time"], Value::Null);
+    assert_eq!(body["lines"][2]["event_time"], "2026-03-01 10:00:00");
+    assert_eq!(body["lines"][2]["timed"], false);
+    assert_eq!(body["lines"][3]["file_id"], db_log.to_string());
+    assert_eq!(body["next_cursor"], Value::Null);
+
+    let mut paged = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let query = match &cursor {
+            Some(cursor) => format!("files={app_log},{db_log}&limit=4&cursor={cursor}"),
+            None => format!("files={app_log},{db_log}&limit=4"),
+        };
+        let response = test::call_service(&app, timeline(query)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = test::read_body_json(response).await;
+        for line in body["lines"].as_array().expect("lines") {
+            paged.push((
+                line["source"].as_u64().unwrap(),
+                line["line_number"].as_u64().unwrap(),
+                line["content"].as_str().unwrap().to_string(),
+            ));
+        }
+        match body["next_cursor"].as_str() {
+            Some(next) => cursor = Some(next.replace(':', "%3A").replace(',', "%2C")),
+            None => break,
+        }
+    }
+    assert_eq!(paged, merged);
+
+    let response = test::call_service(
+        &app,
+        timeline(format!("files={app_log},{db_log}&cursor=999%3A0%3A")),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = test::call_service(&app, timeline(format!("files={app_log},{other_log}"))).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = test::call_service(&app, timeline(format!("files={app_log},{app_log}"))).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn file_lines_jump_to_first_line_at_or_after_a_time() {
+    let test_dir = TestDir::new("rain-lines-at");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["LINESAT"]).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AppState::new(
+                pool.clone(),
+                data_root.clone(),
+                AppLimits::default(),
+            )))
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let base = chrono::NaiveDate::from_ymd_opt(2026, 3, 1)
+        .unwrap()
+        .and_hms_opt(10, 0, 0)
+        .unwrap();
+    let content: String = (0..900)
+        .map(|line| {
+            if line % 10 == 9 {
+                format!("    detail {line}\n")
+            } else {
+                let time = base + chrono::Duration::seconds(line);
+                format!("{} INFO tick {line}\n", time.format("%Y-%m-%d %H:%M:%S"))
+            }
+        })
+        .collect();
+    let boundary = format!("rain-{}", Uuid::new_v4().simple());
+    let response = test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/issues/LINESAT/uploads")
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(multipart_body_multi(
+                &boundary,
+                "LINESAT",
+                &[("ticks.log", content.as_str())],
+            ))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    let body: Value = test::read_body_json(response).await;
+    let hash = body["bundle_hash"]
+        .as_str()
+        .expect("bundle hash")
+        .to_string();
+    wait_for_issue_ready(&pool, "LINESAT").await;
+    let segments: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM log_segments")
+        .fetch_one(&pool)
+        .await
+        .expect("segment count");
+    assert!(segments > 2);
+    let file_id: i64 = sqlx::query_scalar("SELECT id FROM files WHERE name = 'ticks.log'")
+        .fetch_one(&pool)
+        .await
+        .expect("file id");
+    let lines = |query: &str| {
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/files/v1/{hash}/files/{file_id}/lines?limit=3&{query}"
+            ))
+            .to_request()
+    };
+
+    for (at, expected) in [
+        ("2026-03-01%2010:10:00", 600),
+        ("2026-03-01%2010:05:09", 310),
+        ("2026-03-01T10:05:08.500", 310),
+        ("2026-03-01%2009:00", 0),
+    ] {
+        let response = test::call_service(&app, lines(&format!("at={at}"))).await;
+        assert_eq!(response.status(), StatusCode::OK, "{at}");
+        let body: Value = test::read_body_json(response).await;
+        assert_eq!(body["start"], expected, "{at}");
+        assert_eq!(body["lines"][0]["line_number"], expected, "{at}");
+        assert_eq!(body["next_start"], expected + 3, "{at}");
+    }
+
+    let response = test::call_service(&app, lines("at=2026-03-01%2011:00:00")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["start"], 900);
+    assert_eq!(body["lines"].as_array().expect("lines").len(), 0);
+    assert_eq!(body["next_start"], Value::Null);
+
+    let response = test::call_service(
+        &app,
+        test::TestRequest::put()
+            .uri(&format!(
+                "/api/files/v1/{hash}/files/{file_id}/time-settings"
+            ))
+            .set_json(serde_json::json!({ "utc_offset_minutes": 0, "clock_skew_ms": 60_000 }))
+            .cookie(auth_cookie)
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    wait_for_event_time_rekey(&pool).await;
+    let response = test::call_service(&app, lines("at=2026-03-01%2010:10:00")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["start"], 540);
+
+    let response = test::call_service(&app, lines("at=yesterday")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = test::call_service(&app, lines("start=5&at=2026-03-01%2010:00:00")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn resumable_upload_accepts_chunks_at_offsets_and_queues_verified_file() {
+    use sha2::{Digest, Sha256};
+
+    let test_dir = TestDir::new("rain-resumable-upload");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["RESUME"]).await;
+    let mut limits = AppLimits::default();
+    limits.upload.max_tmp_bytes = 64;
+    let state = web::Data::new(AppState::new(pool.clone(), data_root.clone(), limits));
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let content = b"2026-03-01 10:00:00 INFO resumed upload\nsecond line\n";
+    let create = |body: Value| {
+        test::TestRequest::post()
+            .uri("/api/issues/RESUME/uploads/resumable")
+            .cookie(auth_cookie.clone())
+            .set_json(body)
+            .to_request()
+    };
+    let chunk = |id: &str, offset: usize, bytes: &[u8]| {
+        test::TestRequest::patch()
+            .uri(&format!("/api/uploads/resumable/{id}"))
+            .cookie(auth_cookie.clone())
+            .insert_header(("content-type", "application/offset+octet-stream"))
+            .insert_header(("Upload-Offset", offset.to_string()))
+            .set_payload(bytes.to_vec())
+            .to_request()
+    };
+    let progress = |id: &str| {
+        test::TestRequest::default()
+            .method(actix_web::http::Method::HEAD)
+            .uri(&format!("/api/uploads/resumable/{id}"))
+            .cookie(auth_cookie.clone())
+            .to_request()
+    };
+
+    let response = test::call_service(
+        &app,
+        create(serde_json::json!({
+            "filename": "service.log",
+            "size": content.len(),
+            "sha256": format!("{:x}", Sha256::digest(content)),
+        })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body: Value = test::read_body_json(response).await;
+    let id = body["upload_id"].as_str().expect("upload id").to_string();
+    assert_eq!(body["offset"], 0);
+
+    let response = test::call_service(&app, chunk(&id, 0, &content[..20])).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(response.headers().get("Upload-Offset").unwrap(), "20");
+    let response = test::call_service(&app, chunk(&id, 0, &content[..20])).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let response = test::call_service(&app, progress(&id)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("Upload-Offset").unwrap(), "20");
+    assert_eq!(
+        response.headers().get("Upload-Length").unwrap(),
+        content.len().to_string().as_str()
+    );
+    let response = test::call_service(
+        &app,
+        test::TestRequest::patch()
+            .uri(&format!("/api/uploads/resumable/{id}"))
+            .cookie(auth_cookie.clone())
+            .insert_header(("Upload-Offset", "20"))
+            .set_payload(content[20..].to_vec())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let response = test::call_service(&app, chunk(&id, 20, &content[20..])).await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let body: Value = test::read_body_json(response).await;
+    let hash = body["bundle_hash"].as_str().expect("bundle hash");
+    assert_eq!(body["total_bytes"], content.len());
+    wait_for_issue_ready(&pool, "RESUME").await;
+    let (name, file_id): (String, i64) = sqlx::query_as(
+        "SELECT f.name, f.id FROM files f JOIN bundles b ON b.id = f.bundle_id WHERE b.hash = ? AND f.is_dir = 0",
+    )
+    .bind(hash)
+    .fetch_one(&pool)
+    .await
+    .expect("uploaded file");
+    assert_eq!(name, "service.log");
+    let response = test::call_service(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!("/api/files/v1/{hash}/files/{file_id}/lines"))
+            .to_request(),
+    )
+    .await;
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["lines"][1]["content"], "second line");
+    let response = test::call_service(&app, progress(&id)).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        state
+            .upload
+            .tmp_bytes
+            .load(std::sync::atomic::Ordering::Acquire),
+        0
+    );
+
+    let response = test::call_service(
+        &app,
+        create(serde_json::json!({
+            "filename": "corrupt.log",
+            "size": 4,
+            "sha256": format!("{:x}", Sha256::digest(b"good")),
+        })),
+    )
+    .await;
+    let body: Value = test::read_body_json(response).await;
+    let corrupt = body["upload_id"].as_str().expect("upload id").to_string();
+    let response = test::call_service(&app, chunk(&corrupt, 0, b"bad!")).await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let response = test::call_service(&app, progress(&corrupt)).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let large = vec![b'x'; 100];
+    let response = test::call_service(
+        &app,
+        create(serde_json::json!({
+            "filename": "large.log",
+            "size": large.len(),
+            "sha256": format!("{:x}", Sha256::digest(&large)),
+        })),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // Each session holds its declared size, so two cannot share the budget.
+    let half = vec![b'y'; 40];
+    let create_half = || {
+        create(serde_json::json!({
+            "filename": "half.log",
+            "size": half.len(),
+            "sha256": format!("{:x}", Sha256::digest(&half)),
+        }))
+    };
+    let response = test::call_service(&app, create_half()).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body: Value = test::read_body_json(response).await;
+    let half_id = body["upload_id"].as_str().expect("upload id").to_string();
+    let response = test::call_service(&app, create_half()).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let response = test::call_service(
+        &app,
+        test::TestRequest::delete()
+            .uri(&format!("/api/uploads/resumable/{half_id}"))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(!data_root.join(".tmp").join(&half_id).exists());
+    assert_eq!(
+        state
+            .upload
+            .tmp_bytes
+            .load(std::sync::atomic::Ordering::Acquire),
+        0
+    );
+    let bundles: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM bundles")
+        .fetch_one(&pool)
+        .await
+        .expect("bundle count");
+    assert_eq!(bundles, 1);
+}
+
+#[actix_web::test]
+async fn appending_files_extends_a_ready_bundle_under_a_subdirectory() {
+    let test_dir = TestDir::new("rain-bundle-append");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    fs::create_dir_all(&data_root).expect("create data root");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["APPEND"]).await;
+    let limits = AppLimits {
+        issue_max_content_size: 256,
+        ..AppLimits::default()
+    };
+    let state = web::Data::new(AppState::new(pool.clone(), data_root.clone(), limits));
+    let app = test::init_service(
+        App::new()
+            .app_data(state.clone())
+            .configure(routes::register),
+    )
+    .await;
+    let auth_cookie = test_auth_cookie(&pool).await;
+    let base = "2026-03-01 10:00:00 INFO base line\n";
+    let boundary = format!("rain-{}", Uuid::new_v4().simple());
+    let upload: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/issues/APPEND/uploads")
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(multipart_body(&boundary, "APPEND", "base.log", base))
+            .cookie(auth_cookie.clone())
+            .to_request(),
+    )
+    .await;
+    let hash = upload["bundle_hash"]
+        .as_str()
+        .expect("bundle hash")
+        .to_string();
+    wait_for_issue_ready(&pool, "APPEND").await;
+    let append = |uri: String, body: Vec<u8>, boundary: &str| {
+        test::TestRequest::post()
+            .uri(&uri)
+            .insert_header((
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            ))
+            .set_payload(body)
+            .cookie(auth_cookie.clone())
+            .to_request()
+    };
+
+    let appended = "2026-03-01 10:00:05 INFO appended line\nsecond appended line\n";
+    let boundary = format!("rain-{}", Uuid::new_v4().simple());
+    let response = test::call_service(
+        &app,
+        append(
+            format!("/api/issues/APPEND/bundles/{hash}/files?subdirectory=node-2/logs"),
+            multipart_body_bytes(
+                &boundary,
+                "APPEND",
+                "node-2.tar.gz",
+                "application/gzip",
+                &tar_gz_bytes("nested/app.log", appended),
+            ),
+            &boundary,
+        ),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["file_count"], 1);
+    assert_eq!(body["added_content_bytes"], appended.len());
+    let logs_dir = body["directory_id"].as_i64().expect("directory id");
+
+    let (bundle_status, content_size): (String, i64) =
+        sqlx::query_as("SELECT status, content_size_bytes FROM bundles WHERE hash = ?")
+            .bind(&hash)
+            .fetch_one(&pool)
+            .await
+            .expect("bundle state");
+    assert_eq!(bundle_status, "READY");
+    assert_eq!(content_size as usize, base.len() + appended.len());
+    let (dir_name, dir_path, node_parent): (String, String, Option<i64>) = sqlx::query_as(
+        "SELECT d.name, d.path, p.parent_id FROM files d JOIN files p ON p.id = d.parent_id WHERE d.id = ?",
+    )
+    .bind(logs_dir)
+    .fetch_one(&pool)
+    .await
+    .expect("subdirectory node");
+    assert_eq!(dir_name, "logs");
+    assert_eq!(dir_path, format!("/{hash}/node-2/logs"));
+    assert_eq!(node_parent, None);
+    let (file_id, file_path): (i64, String) = sqlx::query_as(
+        "SELECT id, path FROM files WHERE name = 'app.log' AND bundle_id = (SELECT id FROM bundles WHERE hash = ?)",
+    )
+    .bind(&hash)
+    .fetch_one(&pool)
+    .await
+    .expect("appended file");
+    assert!(file_path.starts_with(&format!("/{hash}/node-2/logs/")));
+    assert!(file_path.ends_with("_extracted/nested/app.log"));
+    let body: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get()
+            .uri(&format!("/api/files/v1/{hash}/files/{file_id}/lines"))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(body["lines"][1]["content"], "second appended line");
+
+    let files_before: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM files")
+        .fetch_one(&pool)
+        .await
+        .expect("file count");
+    let boundary = format!("rain-{}", Uuid::new_v4().simple());
+    let response = test::call_service(
+        &app,
+        append(
+            format!("/api/issues/APPEND/bundles/{hash}/files?subdirectory=overflow"),
+            multipart_body(&boundary, "APPEND", "big.log", &"x".repeat(300)),
+            &boundary,
+        ),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["code"], "ISSUE_QUOTA_EXCEEDED");
+    let files_after: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM files")
+        .fetch_one(&pool)
+        .await
+        .expect("file count");
+    let (bundle_status, rolled_back_size): (String, i64) =
+        sqlx::query_as("SELECT status, content_size_bytes FROM bundles WHERE hash = ?")
+            .bind(&hash)
+            .fetch_one(&pool)
+            .await
+            .expect("bundle state");
+    assert_eq!(files_after, files_before);
+    assert_eq!(bundle_status, "READY");
+    assert_eq!(rolled_back_size, content_size);
+
+    let boundary = format!("rain-{}", Uuid::new_v4().simple());
+    let response = test::call_service(
+        &app,
+        append(
+            format!("/api/issues/APPEND/bundles/{hash}/files?subdirectory=../escape"),
+            multipart_body(&boundary, "APPEND", "extra.log", "extra\n"),
+            &boundary,
+        ),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = test::call_service(
+        &app,
+        append(
+            "/api/issues/APPEND/bundles/missing/files".into(),
+            multipart_body(&boundary, "APPEND", "extra.log", "extra\n"),
+            &boundary,
+        ),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        state
+            .upload
+            .tmp_bytes
+            .load(std::sync::atomic::Ordering::Acquire),
+        0
+    );
+    assert!(
+        fs::read_dir(data_root.join(".tmp"))
+            .map(|entries| entries.count() == 0)
+            .unwrap_or(true)
+    );
+
+    // A file cannot be deleted while an append to its bundle is in flight.
+    let bundle_id: String = sqlx::query_scalar("SELECT id FROM bundles WHERE hash = ?")
+        .bind(&hash)
+        .fetch_one(&pool)
+        .await
+        .expect("bundle id");
+    let delete_file = || {
+        test::TestRequest::delete()
+            .uri(&format!("/api/files/v1/{hash}/files/{file_id}"))
+            .cookie(auth_cookie.clone())
+            .to_request()
+    };
+    state
+        .upload
+        .appending
+        .lock()
+        .expect("append registry")
+        .insert(bundle_id.clone());
+    let response = test::call_service(&app, delete_file()).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["code"], "BUNDLE_APPEND_IN_PROGRESS");
+    let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM files WHERE id = ?")
+        .bind(file_id)
+        .fetch_one(&pool)
+        .await
+        .expect("file count");
+    assert_eq!(remaining, 1);
+    state
+        .upload
+        .appending
+        .lock()
+        .expect("append registry")
+        .remove(&bundle_id);
+    let response = test::call_service(&app, delete_file()).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(
+        state
+            .upload
+            .appending
+            .lock()
+            .expect("append registry")
+            .is_empty()
+    );
+}
+
+#[actix_web::test]
+async fn drop_folder_files_are_ingested_as_bundles_of_their_issue() {
+    let test_dir = TestDir::new("rain-drop-folder");
+    let db_url = sqlite_url(&test_dir.path.join("rain.db"));
+    let data_root = test_dir.path.join("uploads");
+    let drop_root = test_dir.path.join("drop");
+    fs::create_dir_all(&data_root).expect("create data root");
+    fs::create_dir_all(drop_root.join("dropin")).expect("create issue drop folder");
+    fs::create_dir_all(drop_root.join("UNKNOWN")).expect("create unknown drop folder");
+
+    let pool = db::init_pool(&db_url).expect("init sqlite pool");
+    db::prepare_schema(&pool, true)
+        .await
+        .expect("prepare schema");
+    insert_issues(&pool, &["DROPIN"]).await;
+    let limits = AppLimits {
+        issue_max_content_size: 1024,
+        ..AppLimits::default()
+    };
+    let state = AppState::new(pool.clone(), data_root.clone(), limits);
+    let config = backend::config::DropFolderConfig {
+        root: drop_root.clone(),
+        poll_interval: std::time::Duration::from_secs(30),
+        settle: std::time::Duration::ZERO,
+    };
+    let issue_dir = drop_root.join("dropin");
+    fs::write(
+        issue_dir.join("rig-1.log"),
+        "2026-03-01 10:00:00 INFO dropped line\n",
+    )
+    .expect("write dropped log");
+    fs::write(
+        issue_dir.join("rig-2.tar.gz"),
+        tar_gz_bytes("var/log/app.log", "archived line\n"),
+    )
+    .expect("write dropped archive");
+    fs::write(issue_dir.join("rig-3.log.part"), "still copying\n").expect("write partial");
+    fs::write(issue_dir.join("huge.log"), "x".repeat(4096)).expect("write oversized");
+    fs::write(drop_root.join("UNKNOWN").join("orphan.log"), "orphan\n").expect("write orphan");
+
+    let queued = backend::upload::drop_folder::ingest_drop_folder(&state, &config)
+        .await
+        .expect("scan drop folder");
+    assert_eq!(queued, 2);
+
+    let bundles: Vec<(String, String, Option<String>)> = sqlx::query_as(
+        "SELECT hash, name, source_path FROM bundles WHERE issue_code = 'DROPIN' ORDER BY name",
+    )
+    .fetch_all(&pool)
+    .await
+    .expect("dropped bundles");
+    assert_eq!(bundles.len(), 2);
+    for (hash, _, _) in &bundles {
+        wait_for_bundle_status(&pool, hash, "READY").await;
+    }
+    assert_eq!(bundles[0].1, "rig-1.log");
+    let ingested = issue_dir
+        .join(".ingested")
+        .join(format!("{}-rig-1.log", bundles[0].0));
+    assert_eq!(
+        bundles[0].2.as_deref(),
+        Some(ingested.display().to_string().as_str())
+    );
+    assert!(ingested.exists());
+    let archived: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM files f JOIN bundles b ON b.id = f.bundle_id WHERE b.hash = ? AND f.name = 'app.log'",
+    )
+    .bind(&bundles[1].0)
+    .fetch_one(&pool)
+    .await
+    .expect("extracted file");
+    assert_eq!(archived, 1);
+    assert!(!issue_dir.join("rig-1.log").exists());
+    assert!(issue_dir.join("rig-3.log.part").exists());
+    assert!(issue_dir.join(".failed").join("huge.log").exists());
+    assert!(drop_root.join("UNKNOWN").join("orphan.log").exists());
+
+    let queued = backend::upload::drop_folder::ingest_drop_folder(&state, &config)
+        .await
+        .expect("rescan drop folder");
+    assert_eq!(queued, 0);
 }
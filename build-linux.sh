@@ -24,12 +24,14 @@ cargo build --release --locked
 
 mkdir -p "$ROOT/release"
 cp "$ROOT/backend/target/release/backend" "$ROOT/release/rain"
+cp "$ROOT/backend/target/release/rain-cli" "$ROOT/release/rain-cli"
 cp "$ROOT/backend/.env.example" "$ROOT/release/.env"
 printf '%s\n' "$RAIN_RELEASE_VERSION" > "$ROOT/release/VERSION"
-chmod +x "$ROOT/release/rain"
+chmod +x "$ROOT/release/rain" "$ROOT/release/rain-cli"
 
 echo
 echo "Build completed. Keep both files together:"
 echo "$ROOT/release/rain"
 echo "$ROOT/release/.env"
 echo "$ROOT/release/VERSION"
+echo "Command-line client: $ROOT/release/rain-cli"
@@ -25,6 +25,7 @@ popd
 
 if not exist "%ROOT%\release" mkdir "%ROOT%\release"
 copy /Y "%ROOT%\backend\target\release\backend.exe" "%ROOT%\release\Rain.exe" >nul || exit /b 1
+copy /Y "%ROOT%\backend\target\release\rain-cli.exe" "%ROOT%\release\rain-cli.exe" >nul || exit /b 1
 copy /Y "%ROOT%\backend\.env.example" "%ROOT%\release\.env" >nul || exit /b 1
 > "%ROOT%\release\VERSION" echo %RAIN_RELEASE_VERSION%
 
@@ -33,3 +34,4 @@ echo Build completed. Keep both files together:
 echo %ROOT%\release\Rain.exe
 echo %ROOT%\release\.env
 echo %ROOT%\release\VERSION
+echo Command-line client: %ROOT%\release\rain-cli.exe
@@ -12,6 +12,8 @@ import type {
   TempResultPreviewResponse,
   UploadResponse,
   UploadTaskResponse,
+  ResumableUploadResponse,
+  AppendBundleFilesResponse,
   AuthMeResponse,
   Credentials,
   User,
@@ -225,9 +227,10 @@ export const rainApi = {
   fetchFileContent(bundleId: string, fileId: string) {
     return request<FileContentResponse>(`/api/files/v1/${encodePathSegment(bundleId)}/files/${encodePathSegment(fileId)}/content`);
   },
-  fetchFileLines(bundleId: string, fileId: string, options?: { start?: number; limit?: number }) {
+  fetchFileLines(bundleId: string, fileId: string, options?: { start?: number; at?: string; limit?: number }) {
     const params = new URLSearchParams();
     if (typeof options?.start === 'number') params.set('start', String(options.start));
+    if (options?.at) params.set('at', options.at);
     if (typeof options?.limit === 'number') params.set('limit', String(options.limit));
     const query = params.toString();
     return request<FileLinesResponse>(`/api/files/v1/${encodePathSegment(bundleId)}/files/${encodePathSegment(fileId)}/lines${query ? `?${query}` : ''}`);
@@ -286,13 +289,67 @@ export const rainApi = {
   deleteTempResult(id: string) {
     return request<void>(`/api/temp-results/${encodePathSegment(id)}`, { method: 'DELETE' });
   },
+  createResumableUpload(
+    issueCode: string,
+    payload: { filename: string; size: number; sha256: string; content_type?: string; archive_password?: string }
+  ) {
+    const normalizedIssueCode = normalizeIssueCode(issueCode);
+    return request<ResumableUploadResponse>(`/api/issues/${encodePathSegment(normalizedIssueCode)}/uploads/resumable`, {
+      method: 'POST',
+      body: JSON.stringify(payload)
+    });
+  },
+  async fetchResumableUploadOffset(uploadId: string) {
+    let response: Response;
+    try {
+      response = await fetch(`${API_BASE_URL}/api/uploads/resumable/${encodePathSegment(uploadId)}`, {
+        method: 'HEAD',
+        credentials: 'include'
+      });
+    } catch (error) {
+      throw new Error(normalizeApiError(error));
+    }
+    if (!response.ok) throw parseErrorResponse('', response.status);
+    return Number(response.headers.get('Upload-Offset') ?? 0);
+  },
+  /** Resolves with the upload task once the final chunk has been verified. */
+  appendResumableUpload(uploadId: string, offset: number, chunk: Blob) {
+    return request<UploadResponse | undefined>(`/api/uploads/resumable/${encodePathSegment(uploadId)}`, {
+      method: 'PATCH',
+      headers: { 'Content-Type': 'application/offset+octet-stream', 'Upload-Offset': String(offset) },
+      body: chunk
+    });
+  },
+  cancelResumableUpload(uploadId: string) {
+    return request<void>(`/api/uploads/resumable/${encodePathSegment(uploadId)}`, { method: 'DELETE' });
+  },
+  /** Adds files to a READY bundle; resolves once they are indexed. */
+  appendBundleFiles(issueCode: string, bundleHash: string, files: File[], options?: { subdirectory?: string; archivePassword?: string }) {
+    const normalizedIssueCode = normalizeIssueCode(issueCode);
+    const formData = new FormData();
+    if (options?.archivePassword) formData.append('archive_password', options.archivePassword);
+    files.forEach((file) => formData.append('files', file, file.name));
+    const params = new URLSearchParams();
+    if (options?.subdirectory) params.set('subdirectory', options.subdirectory);
+    const query = params.toString();
+    return request<AppendBundleFilesResponse>(
+      `/api/issues/${encodePathSegment(normalizedIssueCode)}/bundles/${encodePathSegment(bundleHash)}/files${query ? `?${query}` : ''}`,
+      { method: 'POST', body: formData }
+    );
+  },
   fetchUploadTask(taskId: string) {
     return request<UploadTaskResponse>(`/api/uploads/${encodePathSegment(taskId)}`);
   },
-  uploadLogs(issueCode: string, files: File[], onProgress?: (percent: number) => void) {
+  uploadLogs(
+    issueCode: string,
+    files: File[],
+    onProgress?: (percent: number) => void,
+    archivePassword?: string
+  ) {
     const normalizedIssueCode = normalizeIssueCode(issueCode);
     const formData = new FormData();
     formData.append('issue_code', normalizedIssueCode);
+    if (archivePassword) formData.append('archive_password', archivePassword);
     files.forEach((file) => formData.append('files', file, file.name));
     const path = `/api/issues/${encodePathSegment(normalizedIssueCode)}/uploads`;
 
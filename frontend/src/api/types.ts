@@ -113,13 +113,165 @@ export interface LogSearchHit {
   timeline?: string;
   offset?: number;
   line_number?: number;
+  line_end?: number;
   chunk_index?: number;
+  level?: string | null;
+  event_time?: string | null;
+  captures?: LogSearchCapture[] | null;
+  context_index?: number | null;
+}
+
+export interface LogSearchCapture {
+  index: number;
+  name?: string | null;
+  value?: string | null;
+}
+
+export interface LogSearchContext {
+  file_id: string;
+  start: number;
+  lines: FileLine[];
+  truncated: boolean;
+}
+
+export interface LogSearchTimeRange {
+  start: string;
+  end: string;
 }
 
 export interface LogSearchResponse {
   total: number;
   hits: LogSearchHit[];
   truncated: boolean;
+  level_counts?: Record<string, number>;
+  time_range?: LogSearchTimeRange | null;
+  untimed_hits?: number | null;
+  contexts?: LogSearchContext[] | null;
+  context_truncated?: boolean;
+}
+
+export interface LogHistogramSeries {
+  bundle_hash: string;
+  file_id?: string | null;
+  path?: string | null;
+  counts: number[];
+  total: number;
+}
+
+export interface LogHistogramResponse {
+  total: number;
+  bucket_ms?: number | null;
+  buckets: string[];
+  totals: number[];
+  series: LogHistogramSeries[];
+  series_truncated: boolean;
+  untimed_hits: number;
+}
+
+export interface LogExportRecord {
+  bundle_hash: string;
+  file_id: string;
+  path: string;
+  line_number?: number | null;
+  event_time?: string | null;
+  level?: string | null;
+  content: string;
+}
+
+export interface LogExportSummary {
+  summary: {
+    exported: number;
+    truncated: boolean;
+  };
+}
+
+export interface GlobalSearchBundle {
+  bundle_hash: string;
+  total: number;
+  hits: LogSearchHit[];
+}
+
+export interface GlobalSearchIssue {
+  issue_code: string;
+  issue_name: string;
+  owned: boolean;
+  total: number;
+  bundles: GlobalSearchBundle[];
+}
+
+export interface GlobalSearchResponse {
+  query: string;
+  saved_search_id?: string | null;
+  total: number;
+  issue_count: number;
+  issues: GlobalSearchIssue[];
+  truncated: boolean;
+  time_range?: LogSearchTimeRange | null;
+  untimed_hits?: number | null;
+}
+
+export interface LogTemplateExample {
+  bundle_hash: string;
+  file_id: string;
+  line_number?: number | null;
+  content: string;
+}
+
+export interface LogTemplateSummary {
+  key: string;
+  template: string;
+  line_count: number;
+  bundle_count: number;
+  levels: string[];
+  first_event_time?: string | null;
+  last_event_time?: string | null;
+  examples: LogTemplateExample[];
+}
+
+export interface LogTemplateListResponse {
+  templates: LogTemplateSummary[];
+  total_templates: number;
+  mined_bundles: number;
+  pending_bundles: number;
+  failed_bundles: number;
+}
+
+export interface LogTemplateLinesResponse {
+  key: string;
+  template: string;
+  lines: LogExportRecord[];
+  next_cursor?: string | null;
+}
+
+export interface TemplateDiffEntry {
+  base_key?: string | null;
+  target_key?: string | null;
+  template: string;
+  levels: string[];
+  base_count: number;
+  target_count: number;
+  ratio?: number | null;
+  examples: LogTemplateExample[];
+}
+
+export interface BundleTemplateDiffSide {
+  bundle_hash: string;
+  bundle_name: string;
+  line_count: number;
+  template_count: number;
+}
+
+export interface BundleTemplateDiffResponse {
+  base: BundleTemplateDiffSide;
+  target: BundleTemplateDiffSide;
+  min_ratio: number;
+  min_count: number;
+  only_in_base: TemplateDiffEntry[];
+  only_in_base_total: number;
+  only_in_target: TemplateDiffEntry[];
+  only_in_target_total: number;
+  changed: TemplateDiffEntry[];
+  changed_total: number;
 }
 
 export interface UploadResponse {
@@ -132,6 +284,22 @@ export interface UploadResponse {
   total_bytes: number;
 }
 
+export interface ResumableUploadResponse {
+  upload_id: string;
+  issue_code: string;
+  size: number;
+  offset: number;
+}
+
+export interface AppendBundleFilesResponse {
+  issue_code: string;
+  bundle_hash: string;
+  directory_id: number | null;
+  file_count: number;
+  total_bytes: number;
+  added_content_bytes: number;
+}
+
 export interface UploadTaskResponse {
   task_id: string;
   issue_code: string;
@@ -170,6 +338,71 @@ export interface FileLinesResponse {
   lines: FileLine[];
 }
 
+export type FileDiffRowKind = 'equal' | 'delete' | 'insert' | 'replace';
+
+export interface FileDiffLine {
+  line_number: number;
+  content: string;
+}
+
+export interface FileDiffRow {
+  kind: FileDiffRowKind;
+  left?: FileDiffLine | null;
+  right?: FileDiffLine | null;
+}
+
+export interface FileDiffSide {
+  bundle_hash: string;
+  file_id: string;
+  path: string;
+  line_count: number;
+}
+
+export interface FileDiffResponse {
+  left: FileDiffSide;
+  right: FileDiffSide;
+  ignore_timestamps: boolean;
+  ignore_numbers: boolean;
+  approximate: boolean;
+  identical: boolean;
+  summary: {
+    equal: number;
+    deleted: number;
+    inserted: number;
+    replaced: number;
+  };
+  total_rows: number;
+  start: number;
+  limit: number;
+  next_start?: number | null;
+  rows: FileDiffRow[];
+}
+
+export interface MergedTimelineSource {
+  bundle_hash: string;
+  file_id: string;
+  name: string;
+  path: string;
+  event_time_shift_ms: number;
+}
+
+export interface MergedTimelineLine {
+  source: number;
+  file_id: string;
+  line_number: number;
+  event_time?: string | null;
+  timed: boolean;
+  content: string;
+  truncated: boolean;
+}
+
+export interface MergedTimelineResponse {
+  sources: MergedTimelineSource[];
+  limit: number;
+  next_cursor?: string | null;
+  lines: MergedTimelineLine[];
+}
+
 export interface IssueLogSearchHit {
   file_id: string | number;
   path: string;
@@ -178,12 +411,20 @@ export interface IssueLogSearchHit {
   timeline?: string;
   line_end?: number | null;
   line_number?: number | null;
+  level?: string | null;
+  captures?: LogSearchCapture[] | null;
+  context_index?: number | null;
 }
 
 export interface IssueLogSearchResponse {
   total: number;
   hits: IssueLogSearchHit[];
   truncated: boolean;
+  level_counts?: Record<string, number>;
+  time_range?: LogSearchTimeRange | null;
+  untimed_hits?: number | null;
+  contexts?: LogSearchContext[] | null;
+  context_truncated?: boolean;
 }
 
 export interface TempResultInfo {
@@ -194,7 +194,9 @@ export function HomeView() {
               currentIssueCode={issues.currentIssueCode}
               canWrite={canWrite}
               fileInputRef={fileInputRef}
-              onFilesSelected={(files) => upload.performUpload(files).catch(() => undefined)}
+              onFilesSelected={(files, archivePassword) =>
+                upload.performUpload(files, archivePassword).catch(() => undefined)
+              }
               uploadDisabled={upload.uploadDisabled}
               uploadError={upload.uploadError}
               uploading={upload.uploading}
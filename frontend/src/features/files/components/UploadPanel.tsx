@@ -1,10 +1,10 @@
-import type { FormEvent, RefObject } from 'react';
+import { useState, type FormEvent, type RefObject } from 'react';
 
 type UploadPanelProps = {
   currentIssueCode: string;
   canWrite: boolean;
   fileInputRef: RefObject<HTMLInputElement>;
-  onFilesSelected: (files: File[]) => void;
+  onFilesSelected: (files: File[], archivePassword?: string) => void;
   uploadDisabled: boolean;
   uploadError: string | null;
   uploading: boolean;
@@ -21,9 +21,13 @@ export function UploadPanel({
   uploading,
   uploadingRef
 }: UploadPanelProps) {
+  const [archivePassword, setArchivePassword] = useState('');
   const handleUpload = (event: FormEvent<HTMLFormElement>) => {
     event.preventDefault();
   };
+  const submitFiles = (files: File[]) => {
+    onFilesSelected(files, archivePassword || undefined);
+  };
 
   return (
     <form onSubmit={handleUpload} className="space-y-3 p-4">
@@ -38,7 +42,7 @@ export function UploadPanel({
           if (!canWrite || uploadDisabled || uploadingRef.current) return;
           const files = event.target.files;
           if (files?.length) {
-            onFilesSelected(Array.from(files));
+            submitFiles(Array.from(files));
           }
           if (fileInputRef.current) {
             fileInputRef.current.value = '';
@@ -62,7 +66,7 @@ export function UploadPanel({
           event.stopPropagation();
           if (!canWrite || uploadDisabled || uploadingRef.current) return;
           if (event.dataTransfer.files.length) {
-            onFilesSelected(Array.from(event.dataTransfer.files));
+            submitFiles(Array.from(event.dataTransfer.files));
           }
         }}
       >
@@ -79,7 +83,7 @@ export function UploadPanel({
                   : '拖拽日志文件到这里，或点击选择文件'}
             </p>
             <p className="mt-1 text-xs text-slate-500">
-              支持 .log、.txt、.zip、.tar.gz、.tgz、.gz，单个文件最大 512 MB
+              支持 .log、.txt、.zip、.tar、.tar.gz、.tgz、.tar.bz2、.tar.xz、.tar.zst、.gz、.bz2、.xz、.zst，单个文件最大 512 MB
             </p>
           </div>
         </div>
@@ -95,6 +99,18 @@ export function UploadPanel({
           {uploading ? '处理中' : '选择文件'}
         </button>
       </div>
+      <label className="flex items-center gap-3 text-xs text-slate-500">
+        <span className="shrink-0">ZIP 解压密码</span>
+        <input
+          type="password"
+          autoComplete="off"
+          value={archivePassword}
+          disabled={uploadDisabled}
+          onChange={(event) => setArchivePassword(event.target.value)}
+          placeholder="仅加密压缩包需要填写，不会被保存"
+          className="min-w-0 flex-1 rounded-lg border border-slate-200 bg-white px-3 py-1.5 text-sm text-slate-900 outline-none transition focus:border-sky-400 disabled:opacity-60"
+        />
+      </label>
       {uploadError ? <p className="text-sm text-rose-600">{uploadError}</p> : null}
     </form>
   );
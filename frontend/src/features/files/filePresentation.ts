@@ -1,6 +1,6 @@
 import type { FileNode } from '../../api/types';
 
-const supportedArchivePattern = /\.(zip|tar\.gz|tgz|gz)$/i;
+const supportedArchivePattern = /\.(zip|tar|tar\.gz|tgz|tar\.bz2|tbz2?|tar\.xz|txz|tar\.zst|tzst|gz|bz2|xz|zst)$/i;
 
 type FileCapabilities = Pick<FileNode, 'is_dir' | 'name' | 'preview_kind'>;
 
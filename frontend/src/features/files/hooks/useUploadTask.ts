@@ -66,7 +66,7 @@ export function useUploadTask(options: {
   }, []);
 
   const performUpload = useCallback(
-    async (files: File[]) => {
+    async (files: File[], archivePassword?: string) => {
       if (uploadingRef.current) return;
       if (!currentIssueCode) {
         dispatch({ type: 'error', message: '请先选择或创建 Issue' });
@@ -85,9 +85,14 @@ export function useUploadTask(options: {
       });
 
       try {
-        await rainApi.uploadLogs(currentIssueCode, files, (progress) => {
-          dispatch({ type: 'upload-progress', progress });
-        });
+        await rainApi.uploadLogs(
+          currentIssueCode,
+          files,
+          (progress) => {
+            dispatch({ type: 'upload-progress', progress });
+          },
+          archivePassword
+        );
       } catch (error) {
         uploadingRef.current = false;
         dispatch(
@@ -175,6 +175,8 @@ export function LogsView({ activeBundle, recentBundles, onBundleSelected }: Logs
               <li key={`${hit.file_id}-${hit.offset ?? hit.path}`} className="rounded-lg border border-slate-200 bg-white p-4">
                 <p className="text-xs uppercase text-slate-500">
                   {hit.timeline ?? 'all'} · {hit.path} · 行 {hit.line_number ?? hit.offset ?? '-'} · 分块 {hit.chunk_index ?? '-'}
+                  {hit.level ? ` · ${hit.level}` : null}
+                  {hit.event_time ? ` · 事件时间 ${hit.event_time}` : null}
                 </p>
                 <pre className="mt-2 whitespace-pre-wrap text-sm text-slate-900">{hit.snippet}</pre>
               </li>